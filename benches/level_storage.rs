@@ -0,0 +1,91 @@
+use divan::{black_box, Bencher};
+use freya_ob::{
+    buffers::{
+        buffer::{OrderedBuffer, SearchStrategy},
+        soa_buffer::SoaBuffer,
+        storage::LevelStorage,
+    },
+    decimals::fixed_decimal::FixedDecimal,
+    level::Level,
+};
+
+const CAPACITY: usize = 300;
+
+fn main() {
+    divan::main();
+}
+
+// A full bid side, one tick apart
+fn filled<S: LevelStorage<FixedDecimal>>() -> S {
+    let mut storage = S::new(true);
+    for i in 0..CAPACITY {
+        let price = FixedDecimal::from_int(10_000 - i as i64);
+        storage.insert(i, Level::new(price, FixedDecimal::from_int(1 + (i % 7) as i64)));
+    }
+    storage
+}
+
+fn probes() -> Vec<FixedDecimal> {
+    (0..1_000).map(|i| FixedDecimal::from_int(10_000 - (i * 37 % (CAPACITY + 20)) as i64)).collect()
+}
+
+#[divan::bench(name = "find_index", types = [OrderedBuffer<CAPACITY, FixedDecimal>, SoaBuffer<CAPACITY, FixedDecimal>])]
+fn bench_find_index<S: LevelStorage<FixedDecimal>>(bencher: Bencher) {
+    let (storage, probes) = (filled::<S>(), probes());
+    bencher.bench_local(|| probes.iter().map(|&price| storage.find_index(black_box(price)).is_ok() as usize).sum::<usize>());
+}
+
+#[divan::bench(name = "depth_sum", types = [OrderedBuffer<CAPACITY, FixedDecimal>, SoaBuffer<CAPACITY, FixedDecimal>])]
+fn bench_depth_sum<S: LevelStorage<FixedDecimal>>(bencher: Bencher) {
+    let storage = filled::<S>();
+    let (mut prices, mut sizes) = (Vec::with_capacity(CAPACITY), Vec::with_capacity(CAPACITY));
+    bencher.bench_local(|| {
+        prices.clear();
+        sizes.clear();
+        storage.extend_depth(black_box(CAPACITY), &mut prices, &mut sizes);
+        sizes.iter().copied().sum::<FixedDecimal>()
+    });
+}
+
+#[divan::bench(name = "churn", types = [OrderedBuffer<CAPACITY, FixedDecimal>, SoaBuffer<CAPACITY, FixedDecimal>])]
+fn bench_churn<S: LevelStorage<FixedDecimal>>(bencher: Bencher) {
+    let probes = probes();
+    bencher.with_inputs(filled::<S>).bench_local_refs(|storage| {
+        for &price in &probes {
+            match storage.find_index(price) {
+                Ok(index) => {
+                    storage.remove(index);
+                }
+                Err(index) => {
+                    black_box(storage.insert(index, Level::new(price, FixedDecimal::from_int(1))));
+                }
+            }
+        }
+    });
+}
+
+// Bids at every tick, or spread unevenly with most of the book bunched near the touch
+fn ladder<const N: usize>(search: SearchStrategy, dense: bool) -> OrderedBuffer<N, FixedDecimal> {
+    let mut buffer = OrderedBuffer::new(true).with_search(search);
+    for i in 0..N as i64 {
+        let offset = if dense { i } else { i * i / 16 + i };
+        buffer.insert(i as usize, Level::new(FixedDecimal::from_int(100_000 - offset), FixedDecimal::from_int(1)));
+    }
+    buffer
+}
+
+const STRATEGIES: [SearchStrategy; 3] = [SearchStrategy::Binary, SearchStrategy::Interpolation, SearchStrategy::Adaptive];
+
+#[divan::bench(name = "search/dense", consts = [300, 1000], args = STRATEGIES)]
+fn bench_search_dense<const N: usize>(bencher: Bencher, search: SearchStrategy) {
+    let buffer = ladder::<N>(search, true);
+    let probes: Vec<_> = (0..1_000).map(|i| FixedDecimal::from_int(100_000 - (i * 37 % N) as i64)).collect();
+    bencher.bench_local(|| probes.iter().map(|&price| buffer.find_index(black_box(price)).is_ok() as usize).sum::<usize>());
+}
+
+#[divan::bench(name = "search/sparse", consts = [300, 1000], args = STRATEGIES)]
+fn bench_search_sparse<const N: usize>(bencher: Bencher, search: SearchStrategy) {
+    let buffer = ladder::<N>(search, false);
+    let probes: Vec<_> = (0..1_000).map(|i| buffer.get(i * 37 % N).map_or(FixedDecimal::ZERO, |level| level.price)).collect();
+    bencher.bench_local(|| probes.iter().map(|&price| buffer.find_index(black_box(price)).is_ok() as usize).sum::<usize>());
+}
@@ -8,6 +8,7 @@ use freya_ob::{
     event::Event,
     event_kind::EventKind,
     side::Side,
+    timestamp::Timestamp,
 };
 use rand::{distributions::Uniform, prelude::Distribution as _, rngs::StdRng, Rng as _, SeedableRng as _};
 use rust_decimal::{prelude::FromPrimitive as _, Decimal};
@@ -24,8 +25,8 @@ fn setup<T: OrderBook<Decimal> + Default>(skip: bool) -> (T, Vec<Event<Decimal>>
     for i in 0..500 {
         let price = Decimal::from_f64(1000.0 + (i as f64 * PI / 2.0).sin() * 10.0).unwrap();
         let size = Decimal::from_f64(100.0 + (i as f64 * PI / 4.0).sin() * 50.0).unwrap();
-        ob.process(Event::new(EventKind::L2, Side::Buy, price - Decimal::from(5), size, 0));
-        ob.process(Event::new(EventKind::L2, Side::Sell, price + Decimal::from(5), size, 0));
+        ob.process(Event::new(EventKind::L2, Side::Buy, price - Decimal::from(5), size, Timestamp::ZERO));
+        ob.process(Event::new(EventKind::L2, Side::Sell, price + Decimal::from(5), size, Timestamp::ZERO));
     }
 
     let records = if !skip {
@@ -52,7 +53,7 @@ fn setup<T: OrderBook<Decimal> + Default>(skip: bool) -> (T, Vec<Event<Decimal>>
                 70..=89 => EventKind::Trade, // 20% trades
                 _ => EventKind::BBO,         // 10% BBO updates
             };
-            records.push(Event::new(event_kind, side, price, size, i as i64));
+            records.push(Event::new(event_kind, side, price, size, Timestamp::from_nanos(i as i64)));
         }
 
         records
@@ -79,7 +80,7 @@ fn bench_array_l2_updates(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 size,
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -95,7 +96,7 @@ fn bench_btree_l2_updates(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 size,
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -111,7 +112,7 @@ fn bench_array_trades(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 size / Decimal::from(2),
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -127,7 +128,7 @@ fn bench_btree_trades(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 size / Decimal::from(2),
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -143,7 +144,13 @@ fn bench_array_mixed_updates(bencher: Bencher) {
                 1 => EventKind::Trade,
                 _ => EventKind::BBO,
             };
-            black_box(ob.process(Event::new(kind, if i % 2 == 0 { Side::Buy } else { Side::Sell }, price, size, i as i64)));
+            black_box(ob.process(Event::new(
+                kind,
+                if i % 2 == 0 { Side::Buy } else { Side::Sell },
+                price,
+                size,
+                Timestamp::from_nanos(i as i64),
+            )));
         }
     });
 }
@@ -158,7 +165,13 @@ fn bench_btree_mixed_updates(bencher: Bencher) {
                 1 => EventKind::Trade,
                 _ => EventKind::BBO,
             };
-            black_box(ob.process(Event::new(kind, if i % 2 == 0 { Side::Buy } else { Side::Sell }, price, size, i as i64)));
+            black_box(ob.process(Event::new(
+                kind,
+                if i % 2 == 0 { Side::Buy } else { Side::Sell },
+                price,
+                size,
+                Timestamp::from_nanos(i as i64),
+            )));
         }
     });
 }
@@ -173,7 +186,7 @@ fn bench_array_snapshot_updates(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 size,
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -189,7 +202,7 @@ fn bench_btree_snapshot_updates(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 size,
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -205,7 +218,7 @@ fn bench_array_rapid_updates(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 if i % 3 == 0 { Decimal::ZERO } else { size },
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -221,7 +234,7 @@ fn bench_btree_rapid_updates(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 if i % 3 == 0 { Decimal::ZERO } else { size },
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -241,7 +254,7 @@ fn bench_array_depth_maintenance(bencher: Bencher) {
                     if i % 2 == 0 { Side::Buy } else { Side::Sell },
                     price,
                     size,
-                    i as i64,
+                    Timestamp::from_nanos(i as i64),
                 )));
             }
         }
@@ -262,7 +275,7 @@ fn bench_btree_depth_maintenance(bencher: Bencher) {
                     if i % 2 == 0 { Side::Buy } else { Side::Sell },
                     price,
                     size,
-                    i as i64,
+                    Timestamp::from_nanos(i as i64),
                 )));
             }
         }
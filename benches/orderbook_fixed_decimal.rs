@@ -4,11 +4,16 @@ use std::f64::consts::PI;
 
 use divan::{black_box, Bencher};
 use freya_ob::{
-    books::{array_orderbook::ArrayOrderbook, btree_orderbook::BTreeOrderBook, interface::OrderBook},
+    books::{
+        array_orderbook::{ArrayOrderbook, GapOrderbook},
+        btree_orderbook::BTreeOrderBook,
+        interface::OrderBook,
+    },
     decimals::fixed_decimal::FixedDecimal,
     event::Event,
     event_kind::EventKind,
     side::Side,
+    timestamp::Timestamp,
 };
 use rand::{distributions::Uniform, prelude::Distribution as _, rngs::StdRng, Rng as _, SeedableRng as _};
 
@@ -24,8 +29,8 @@ fn setup<T: OrderBook<FixedDecimal> + Default>(skip: bool) -> (T, Vec<Event<Fixe
     for i in 0..500 {
         let price = FixedDecimal::from_f64(1000.0 + (i as f64 * PI / 2.0).sin() * 10.0);
         let size = FixedDecimal::from_f64(100.0 + (i as f64 * PI / 4.0).sin() * 50.0);
-        ob.process(Event::new(EventKind::L2, Side::Buy, price - FixedDecimal::from_int(5), size, 0));
-        ob.process(Event::new(EventKind::L2, Side::Sell, price + FixedDecimal::from_int(5), size, 0));
+        ob.process(Event::new(EventKind::L2, Side::Buy, price - FixedDecimal::from_int(5), size, Timestamp::ZERO));
+        ob.process(Event::new(EventKind::L2, Side::Sell, price + FixedDecimal::from_int(5), size, Timestamp::ZERO));
     }
 
     let records = if !skip {
@@ -52,7 +57,7 @@ fn setup<T: OrderBook<FixedDecimal> + Default>(skip: bool) -> (T, Vec<Event<Fixe
                 70..=89 => EventKind::Trade, // 20% trades
                 _ => EventKind::BBO,         // 10% BBO updates
             };
-            records.push(Event::new(event_kind, side, price, size, i as i64));
+            records.push(Event::new(event_kind, side, price, size, Timestamp::from_nanos(i as i64)));
         }
 
         records
@@ -79,7 +84,7 @@ fn bench_array_l2_updates(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 size,
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -95,7 +100,7 @@ fn bench_btree_l2_updates(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 size,
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -111,7 +116,7 @@ fn bench_array_trades(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 size / FixedDecimal::from_int(2),
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -127,7 +132,7 @@ fn bench_btree_trades(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 size / FixedDecimal::from_int(2),
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -143,7 +148,13 @@ fn bench_array_mixed_updates(bencher: Bencher) {
                 1 => EventKind::Trade,
                 _ => EventKind::BBO,
             };
-            black_box(ob.process(Event::new(kind, if i % 2 == 0 { Side::Buy } else { Side::Sell }, price, size, i as i64)));
+            black_box(ob.process(Event::new(
+                kind,
+                if i % 2 == 0 { Side::Buy } else { Side::Sell },
+                price,
+                size,
+                Timestamp::from_nanos(i as i64),
+            )));
         }
     });
 }
@@ -158,7 +169,13 @@ fn bench_btree_mixed_updates(bencher: Bencher) {
                 1 => EventKind::Trade,
                 _ => EventKind::BBO,
             };
-            black_box(ob.process(Event::new(kind, if i % 2 == 0 { Side::Buy } else { Side::Sell }, price, size, i as i64)));
+            black_box(ob.process(Event::new(
+                kind,
+                if i % 2 == 0 { Side::Buy } else { Side::Sell },
+                price,
+                size,
+                Timestamp::from_nanos(i as i64),
+            )));
         }
     });
 }
@@ -173,7 +190,7 @@ fn bench_array_snapshot_updates(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 size,
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -189,7 +206,7 @@ fn bench_btree_snapshot_updates(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 size,
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -205,7 +222,23 @@ fn bench_array_rapid_updates(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 if i % 3 == 0 { FixedDecimal::ZERO } else { size },
-                i as i64,
+                Timestamp::from_nanos(i as i64),
+            )));
+        }
+    });
+}
+
+#[divan::bench(name = "rapid_updates/gap")]
+fn bench_gap_rapid_updates(bencher: Bencher) {
+    bencher.with_inputs(|| setup::<GapOrderbook<300, FixedDecimal>>(true)).bench_refs(|(ob, _)| {
+        for i in 0..10_000 {
+            let (price, size) = generate_price_size(i);
+            black_box(ob.process(Event::new(
+                EventKind::L2,
+                if i % 2 == 0 { Side::Buy } else { Side::Sell },
+                price,
+                if i % 3 == 0 { FixedDecimal::ZERO } else { size },
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -221,7 +254,7 @@ fn bench_btree_rapid_updates(bencher: Bencher) {
                 if i % 2 == 0 { Side::Buy } else { Side::Sell },
                 price,
                 if i % 3 == 0 { FixedDecimal::ZERO } else { size },
-                i as i64,
+                Timestamp::from_nanos(i as i64),
             )));
         }
     });
@@ -237,7 +270,7 @@ fn bench_array_depth_maintenance(bencher: Bencher) {
                 let price = FixedDecimal::from_f64(base_price + j as f64);
                 let size = FixedDecimal::from_f64(100.0 + (j as f64 * PI / 4.0).sin() * 20.0);
                 let side = if i % 2 == 0 { Side::Buy } else { Side::Sell };
-                black_box(ob.process(Event::new(EventKind::L2, side, price, size, i as i64)));
+                black_box(ob.process(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(i as i64))));
             }
         }
     });
@@ -253,7 +286,7 @@ fn bench_btree_depth_maintenance(bencher: Bencher) {
                 let price = FixedDecimal::from_f64(base_price + j as f64);
                 let size = FixedDecimal::from_f64(100.0 + (j as f64 * PI / 4.0).sin() * 20.0);
                 let side = if i % 2 == 0 { Side::Buy } else { Side::Sell };
-                black_box(ob.process(Event::new(EventKind::L2, side, price, size, i as i64)));
+                black_box(ob.process(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(i as i64))));
             }
         }
     });
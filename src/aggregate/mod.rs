@@ -0,0 +1,209 @@
+//! Aggregation of the tape into bars and price buckets.
+//!
+//! A [`CandleBuilder`] folds trades into OHLCV [`Candle`]s in the book's own decimal type. Each
+//! [`CandleInterval`] decides where a candle ends: time candles cover aligned intervals, so every
+//! builder agrees on their boundaries, while volume and tick candles close on the trade that brings
+//! them to their size. Time intervals without trades produce no candle. Aggregations by price, such as
+//! the [volume profile](profile), group prices into buckets with [`bucket`].
+
+pub mod profile;
+
+use std::{
+    ops::{Add, Rem, Sub},
+    time::Duration,
+};
+
+use crate::{decimals::decimal_type::DecimalType, event::Event, event_kind::EventKind, timestamp::Timestamp};
+
+#[inline]
+#[must_use]
+/// Lower edge of the bucket holding `price`, buckets being `width` wide and starting at multiples of it.
+/// A width that is not positive leaves prices as they are.
+pub fn bucket<V>(price: V, width: V) -> V
+where
+    V: DecimalType + Copy + PartialOrd + Sub<Output = V> + Rem<Output = V>,
+{
+    if width <= V::ZERO {
+        return price;
+    }
+    let offset = price % width;
+    // The remainder takes the sign of the price, so a negative price rounds down a whole width further
+    if offset < V::ZERO {
+        price - offset - width
+    } else {
+        price - offset
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandleInterval<V: DecimalType> {
+    /// Intervals of this length from the epoch
+    Time(Duration),
+    /// Candles closing once they have traded at least this volume
+    Volume(V),
+    /// Candles of this many trades
+    Ticks(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candle<V: DecimalType> {
+    /// Start of the interval for a time candle, the first trade's timestamp otherwise
+    pub open_time: Timestamp,
+    /// Timestamp of the last trade
+    pub close_time: Timestamp,
+    pub open: V,
+    pub high: V,
+    pub low: V,
+    pub close: V,
+    pub volume: V,
+    pub trades: u64,
+}
+
+#[derive(Debug, Clone)]
+/// Builds candles from trades, see the [module documentation](self)
+pub struct CandleBuilder<V: DecimalType> {
+    interval: CandleInterval<V>,
+    current: Option<Candle<V>>,
+}
+
+impl<V> CandleBuilder<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V>,
+{
+    #[inline]
+    #[must_use]
+    pub const fn new(interval: CandleInterval<V>) -> Self {
+        Self { interval, current: None }
+    }
+
+    #[inline]
+    #[must_use]
+    /// The candle still open, `None` before the first trade or after one closed
+    pub const fn current(&self) -> Option<&Candle<V>> {
+        self.current.as_ref()
+    }
+
+    /// Record a trade, returning the candle it closed. A trade in a later time interval closes the open
+    /// candle and starts the next, a trade filling a volume or tick candle closes the candle it joined.
+    /// Other event kinds are ignored.
+    pub fn on_trade(&mut self, event: &Event<V>) -> Option<Candle<V>> {
+        if event.kind != EventKind::Trade {
+            return None;
+        }
+        let (price, size, ts) = (event.price, event.size, event.timestamp);
+        let mut closed = None;
+        if let (CandleInterval::Time(length), Some(open)) = (self.interval, &self.current) {
+            if ts >= open.open_time + length {
+                closed = self.current.take();
+            }
+        }
+        let open_time = match self.interval {
+            CandleInterval::Time(length) => {
+                let length = i64::try_from(length.as_nanos()).unwrap_or(i64::MAX).max(1);
+                Timestamp::from_nanos(ts.as_nanos().div_euclid(length) * length)
+            }
+            CandleInterval::Volume(_) | CandleInterval::Ticks(_) => ts,
+        };
+        let candle = self.current.get_or_insert(Candle {
+            open_time,
+            close_time: ts,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: V::ZERO,
+            trades: 0,
+        });
+        if price > candle.high {
+            candle.high = price;
+        }
+        if price < candle.low {
+            candle.low = price;
+        }
+        (candle.close, candle.close_time) = (price, ts);
+        candle.volume = candle.volume + size;
+        candle.trades += 1;
+        let full = match self.interval {
+            CandleInterval::Time(_) => false,
+            CandleInterval::Volume(volume) => candle.volume >= volume,
+            CandleInterval::Ticks(trades) => candle.trades >= trades,
+        };
+        if full {
+            return self.current.take();
+        }
+        closed
+    }
+
+    /// Close a time candle whose interval has ended by `now`, for quiet markets where no later trade
+    /// arrives to close it
+    pub fn on_time(&mut self, now: Timestamp) -> Option<Candle<V>> {
+        match (self.interval, &self.current) {
+            (CandleInterval::Time(length), Some(open)) if now >= open.open_time + length => self.current.take(),
+            _ => None,
+        }
+    }
+
+    /// Close the open candle whatever its interval, such as at the end of a session
+    pub fn flush(&mut self) -> Option<Candle<V>> {
+        self.current.take()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        aggregate::{Candle, CandleBuilder, CandleInterval},
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_builds_candles() {
+        let trade = |price, size, secs| Event::new(EventKind::Trade, Side::Sell, price, size, Timestamp::from_secs(secs));
+        let tape = [
+            trade(fixed!(100), fixed!(1), 61),
+            trade(fixed!(102), fixed!(3), 75),
+            trade(fixed!(99.5), fixed!(2), 110),
+            trade(fixed!(101), fixed!(4), 250),
+        ];
+
+        let mut minutes = CandleBuilder::new(CandleInterval::Time(Duration::from_secs(60)));
+        let closed = tape.iter().filter_map(|event| minutes.on_trade(event)).collect::<Vec<_>>();
+        let first = Candle {
+            open_time: Timestamp::from_secs(60),
+            close_time: Timestamp::from_secs(110),
+            open: fixed!(100),
+            high: fixed!(102),
+            low: fixed!(99.5),
+            close: fixed!(99.5),
+            volume: fixed!(6),
+            trades: 3,
+        };
+        // The quiet minutes between produce no candles
+        assert_eq!(closed, [first]);
+        assert_eq!(minutes.on_time(Timestamp::from_secs(299)), None);
+        assert_eq!(
+            minutes.on_time(Timestamp::from_secs(300)).map(|candle| (candle.open_time, candle.volume)),
+            Some((Timestamp::from_secs(240), fixed!(4)))
+        );
+        assert!(minutes
+            .on_trade(&Event::new(EventKind::L2, Side::Buy, fixed!(1), fixed!(1), Timestamp::from_nanos(400)))
+            .is_none());
+
+        let mut volume = CandleBuilder::new(CandleInterval::Volume(fixed!(4)));
+        let closed =
+            tape.iter().filter_map(|event| volume.on_trade(event)).map(|candle| (candle.open, candle.close, candle.volume));
+        assert_eq!(closed.collect::<Vec<_>>(), [(fixed!(100), fixed!(102), fixed!(4)), (fixed!(99.5), fixed!(101), fixed!(6))]);
+
+        let mut ticks = CandleBuilder::new(CandleInterval::Ticks(3));
+        assert_eq!(tape.iter().filter_map(|event| ticks.on_trade(event)).count(), 1);
+        assert_eq!(ticks.flush().map(|candle| (candle.open_time, candle.trades)), Some((Timestamp::from_secs(250), 1)));
+        assert!(ticks.current().is_none());
+    }
+}
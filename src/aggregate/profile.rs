@@ -0,0 +1,145 @@
+//! Traded volume by price.
+//!
+//! A [`VolumeProfile`] adds up the volume of each trade in its price [bucket](super::bucket) over a
+//! session. The point of control is the bucket that traded the most, and the value area the range of
+//! buckets around it holding a given share of the session's volume, grown one bucket at a time towards
+//! whichever neighbour traded more, the higher on a tie.
+
+use std::{
+    collections::BTreeMap,
+    ops::{Add, Bound, Mul, Rem, Sub},
+};
+
+use crate::{aggregate::bucket, decimals::decimal_type::DecimalType, event::Event, event_kind::EventKind};
+
+#[derive(Debug, Clone)]
+/// Volume traded in each price bucket over a session, see the [module documentation](self)
+pub struct VolumeProfile<V: DecimalType> {
+    width: V,
+    /// Volume by the lower edge of its bucket
+    volumes: BTreeMap<V, V>,
+    total: V,
+}
+
+impl<V> VolumeProfile<V>
+where
+    V: DecimalType + Copy + Ord + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Rem<Output = V>,
+{
+    #[inline]
+    #[must_use]
+    /// Buckets `width` wide, a tick size for a profile by price
+    pub const fn new(width: V) -> Self {
+        Self { width, volumes: BTreeMap::new(), total: V::ZERO }
+    }
+
+    /// Record a trade, other event kinds are ignored
+    pub fn on_trade(&mut self, event: &Event<V>) {
+        if event.kind != EventKind::Trade {
+            return;
+        }
+        let volume = self.volumes.entry(bucket(event.price, self.width)).or_insert(V::ZERO);
+        *volume = *volume + event.size;
+        self.total = self.total + event.size;
+    }
+
+    #[inline]
+    #[must_use]
+    /// Volume traded over the session
+    pub const fn total(&self) -> V {
+        self.total
+    }
+
+    #[inline]
+    #[must_use]
+    /// Volume traded in the bucket holding `price`
+    pub fn volume_at(&self, price: V) -> V {
+        self.volumes.get(&bucket(price, self.width)).copied().unwrap_or(V::ZERO)
+    }
+
+    #[inline]
+    /// Buckets that traded as `(lower edge, volume)`, lowest price first
+    pub fn levels(&self) -> impl DoubleEndedIterator<Item = (V, V)> + '_ {
+        self.volumes.iter().map(|(&price, &volume)| (price, volume))
+    }
+
+    #[must_use]
+    /// Lower edge of the bucket that traded the most, the lowest of those tied, `None` before any trade
+    pub fn point_of_control(&self) -> Option<V> {
+        self.levels()
+            .fold(None, |best: Option<(V, V)>, (price, volume)| match best {
+                Some((_, most)) if most >= volume => best,
+                _ => Some((price, volume)),
+            })
+            .map(|(price, _)| price)
+    }
+
+    #[must_use]
+    /// Lower edges of the lowest and highest buckets of the value area holding `share` of the volume,
+    /// such as `0.7` for the customary 70%, `None` before any trade
+    pub fn value_area(&self, share: V) -> Option<(V, V)> {
+        let control = self.point_of_control()?;
+        let target = self.total * share;
+        let (mut low, mut high) = (control, control);
+        let mut volume = self.volumes[&control];
+        while volume < target {
+            let below = self.volumes.range(..low).next_back();
+            let above = self.volumes.range((Bound::Excluded(high), Bound::Unbounded)).next();
+            match (below, above) {
+                (Some((&price, &below)), Some((_, &above))) if below > above => {
+                    (low, volume) = (price, volume + below);
+                }
+                (_, Some((&price, &above))) => (high, volume) = (price, volume + above),
+                (Some((&price, &below)), None) => (low, volume) = (price, volume + below),
+                (None, None) => break,
+            }
+        }
+        Some((low, high))
+    }
+
+    /// Start a new session
+    pub fn reset(&mut self) {
+        self.volumes.clear();
+        self.total = V::ZERO;
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        aggregate::{bucket, profile::VolumeProfile},
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_volume_profile() {
+        assert_eq!((bucket(fixed!(100.37), fixed!(0.25)), bucket(fixed!(-0.1), fixed!(0.25))), (fixed!(100.25), fixed!(-0.25)));
+
+        let mut profile = VolumeProfile::new(fixed!(0.5));
+        assert_eq!((profile.point_of_control(), profile.value_area(fixed!(0.7))), (None, None));
+        for (price, size) in [
+            (fixed!(99.1), fixed!(2)),
+            (fixed!(99.6), fixed!(5)),
+            (fixed!(100.2), fixed!(9)),
+            (fixed!(100.4), fixed!(3)),
+            (fixed!(100.7), fixed!(6)),
+            (fixed!(101.9), fixed!(4)),
+        ] {
+            profile.on_trade(&Event::new(EventKind::Trade, Side::Buy, price, size, Timestamp::from_nanos(1)));
+        }
+        profile.on_trade(&Event::new(EventKind::L2, Side::Buy, fixed!(100), fixed!(50), Timestamp::from_nanos(1)));
+        assert_eq!((profile.total(), profile.volume_at(fixed!(100.49))), (fixed!(29), fixed!(12)));
+        assert_eq!(profile.point_of_control(), Some(fixed!(100)));
+
+        // 23 of the 29 traded: 12 at the control, then 6 above and 5 below, skipping the empty 101
+        assert_eq!(profile.value_area(fixed!(0.7)), Some((fixed!(99.5), fixed!(100.5))));
+        assert_eq!(profile.value_area(fixed!(1)), Some((fixed!(99), fixed!(101.5))));
+
+        profile.reset();
+        assert_eq!(profile.levels().count(), 0);
+    }
+}
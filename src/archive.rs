@@ -0,0 +1,225 @@
+//! A local archive of book snapshots in an embedded key-value store.
+//!
+//! [`BookArchive`] keys each [`BookSnapshot`] by instrument and timestamp and stores it as a
+//! [delta recording](crate::formats::snapshots) keyframe. Keys are the instrument's UTF-8 bytes, a zero
+//! byte, then the timestamp big-endian with its sign bit flipped, the encoding raw decimals take to
+//! sort as bytes, so a store's byte order keeps each instrument's snapshots together and in time order
+//! and a time slice is a single range scan.
+//!
+//! Any ordered store can hold the archive through [`KvStore`]: `sled::Tree` and a `redb` table map onto
+//! it directly, and a `BTreeMap` serves tests and archives small enough for memory.
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//!
+//! use freya_ob::{archive::BookArchive, sync::BookSnapshot, timestamp::Timestamp};
+//!
+//! let mut archive = BookArchive::new(BTreeMap::new());
+//! for secs in [1, 2, 3] {
+//!     let timestamp = Timestamp::from_secs(secs);
+//!     archive.insert("BTC-USD", &BookSnapshot { bids: vec![], asks: vec![], timestamp, sequence_id: 0, version: 0 }).unwrap();
+//! }
+//! let slice = archive.range("BTC-USD", Timestamp::from_secs(2), Timestamp::from_secs(9)).unwrap();
+//! assert_eq!(slice.len(), 2);
+//! ```
+
+use std::{collections::BTreeMap, ops::Bound};
+
+use crate::{
+    decimals::fixed_decimal::FixedDecimal,
+    formats::{
+        snapshots::{SnapshotReplayer, SnapshotWriter},
+        FormatError,
+    },
+    sync::BookSnapshot,
+    timestamp::Timestamp,
+};
+
+/// A key and its value
+pub type Entry = (Vec<u8>, Vec<u8>);
+
+/// An ordered byte key-value store
+pub trait KvStore {
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<(), FormatError>;
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, FormatError>;
+    /// Entries with keys from `start` up to but excluding `end`, in key order
+    fn range(&self, start: &[u8], end: &[u8]) -> Result<Vec<Entry>, FormatError>;
+    /// The entry with the greatest key from `start` up to but excluding `end`
+    #[inline]
+    fn last_in(&self, start: &[u8], end: &[u8]) -> Result<Option<Entry>, FormatError> {
+        Ok(self.range(start, end)?.pop())
+    }
+}
+
+impl KvStore for BTreeMap<Vec<u8>, Vec<u8>> {
+    #[inline]
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<(), FormatError> {
+        BTreeMap::insert(self, key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    #[inline]
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, FormatError> {
+        Ok(BTreeMap::get(self, key).cloned())
+    }
+
+    fn range(&self, start: &[u8], end: &[u8]) -> Result<Vec<Entry>, FormatError> {
+        if start >= end {
+            return Ok(Vec::new());
+        }
+        Ok(BTreeMap::range::<[u8], _>(self, (Bound::Included(start), Bound::Excluded(end)))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    #[inline]
+    fn last_in(&self, start: &[u8], end: &[u8]) -> Result<Option<Entry>, FormatError> {
+        if start >= end {
+            return Ok(None);
+        }
+        Ok(BTreeMap::range::<[u8], _>(self, (Bound::Included(start), Bound::Excluded(end)))
+            .next_back()
+            .map(|(key, value)| (key.clone(), value.clone())))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Snapshots by instrument and timestamp, see the [module documentation](self)
+pub struct BookArchive<S: KvStore> {
+    store: S,
+}
+
+impl<S: KvStore> BookArchive<S> {
+    #[inline]
+    #[must_use]
+    pub const fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    /// Store `snapshot` under `instrument` at its timestamp, replacing any stored at the same time
+    ///
+    /// # Errors
+    /// If the instrument contains a zero byte or the store fails.
+    pub fn insert(&mut self, instrument: &str, snapshot: &BookSnapshot<FixedDecimal>) -> Result<(), FormatError> {
+        let mut writer = SnapshotWriter::new(Vec::new());
+        writer.write(snapshot)?;
+        self.store.insert(&key(instrument, snapshot.timestamp.as_nanos())?, &writer.into_inner())
+    }
+
+    /// The snapshot of `instrument` stored at exactly `timestamp`
+    ///
+    /// # Errors
+    /// If the store fails or holds a value that is not a snapshot.
+    pub fn get(&self, instrument: &str, timestamp: Timestamp) -> Result<Option<BookSnapshot<FixedDecimal>>, FormatError> {
+        self.store.get(&key(instrument, timestamp.as_nanos())?)?.map(|value| decode(&value)).transpose()
+    }
+
+    /// The latest snapshot of `instrument` at or before `timestamp`, the book as it stood then
+    ///
+    /// # Errors
+    /// If the store fails or holds a value that is not a snapshot.
+    pub fn latest_at(&self, instrument: &str, timestamp: Timestamp) -> Result<Option<BookSnapshot<FixedDecimal>>, FormatError> {
+        let (start, end) = (key(instrument, i64::MIN)?, end_key(instrument, timestamp.as_nanos())?);
+        self.store.last_in(&start, &end)?.map(|(_, value)| decode(&value)).transpose()
+    }
+
+    /// Snapshots of `instrument` from `from` up to but excluding `to`, in time order
+    ///
+    /// # Errors
+    /// If the store fails or holds a value that is not a snapshot.
+    pub fn range(
+        &self,
+        instrument: &str,
+        from: Timestamp,
+        to: Timestamp,
+    ) -> Result<Vec<BookSnapshot<FixedDecimal>>, FormatError> {
+        let (start, end) = (key(instrument, from.as_nanos())?, key(instrument, to.as_nanos())?);
+        self.store.range(&start, &end)?.iter().map(|(_, value)| decode(value)).collect()
+    }
+}
+
+/// Key of `instrument` at `nanos`
+fn key(instrument: &str, nanos: i64) -> Result<Vec<u8>, FormatError> {
+    if instrument.as_bytes().contains(&0) {
+        return Err(FormatError::Malformed { line: 0, reason: format!("instrument {instrument:?} contains a zero byte") });
+    }
+    let mut key = Vec::with_capacity(instrument.len() + 9);
+    key.extend_from_slice(instrument.as_bytes());
+    key.push(0);
+    key.extend_from_slice(&((nanos as u64) ^ (1 << 63)).to_be_bytes());
+    Ok(key)
+}
+
+/// First key after every key of `instrument` at or before `nanos`
+fn end_key(instrument: &str, nanos: i64) -> Result<Vec<u8>, FormatError> {
+    match nanos.checked_add(1) {
+        Some(next) => key(instrument, next),
+        None => {
+            let mut end = key(instrument, nanos)?;
+            end.truncate(instrument.len());
+            end.push(1);
+            Ok(end)
+        }
+    }
+}
+
+fn decode(value: &[u8]) -> Result<BookSnapshot<FixedDecimal>, FormatError> {
+    SnapshotReplayer::new(value)
+        .next()
+        .unwrap_or_else(|| Err(FormatError::Malformed { line: 0, reason: "empty value".to_owned() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        archive::BookArchive, decimals::fixed_decimal::FixedDecimal, fixed, level::Level, sync::BookSnapshot,
+        timestamp::Timestamp,
+    };
+
+    fn snapshot(nanos: i64, bid: FixedDecimal) -> BookSnapshot<FixedDecimal> {
+        let timestamp = Timestamp::from_nanos(nanos);
+        BookSnapshot { bids: vec![Level::new(bid, fixed!(1))], asks: vec![], timestamp, sequence_id: nanos as u64, version: 0 }
+    }
+
+    #[test]
+    fn test_archive_slices_by_time() {
+        let mut archive = BookArchive::new(BTreeMap::new());
+        // Negative timestamps sort before positive ones, and instruments sharing a prefix stay apart
+        for (instrument, nanos, bid) in [
+            ("ETH", 5, fixed!(10)),
+            ("ETH", -3, fixed!(9)),
+            ("ETH", 300, fixed!(11)),
+            ("ETH-PERP", 7, fixed!(50)),
+            ("BTC", 6, fixed!(70)),
+        ] {
+            archive.insert(instrument, &snapshot(nanos, bid)).unwrap();
+        }
+        let bids = |snapshots: Vec<BookSnapshot<FixedDecimal>>| snapshots.iter().map(|s| s.bids[0].price).collect::<Vec<_>>();
+        let all = archive.range("ETH", Timestamp::from_nanos(i64::MIN), Timestamp::from_nanos(i64::MAX)).unwrap();
+        assert_eq!(bids(all), [fixed!(9), fixed!(10), fixed!(11)]);
+        assert_eq!(
+            bids(archive.range("ETH", Timestamp::from_nanos(-3), Timestamp::from_nanos(300)).unwrap()),
+            [fixed!(9), fixed!(10)]
+        );
+
+        let at = |nanos| archive.latest_at("ETH", Timestamp::from_nanos(nanos)).unwrap().map(|s| s.bids[0].price);
+        assert_eq!((at(-4), at(4), at(5), at(i64::MAX)), (None, Some(fixed!(9)), Some(fixed!(10)), Some(fixed!(11))));
+        assert_eq!(archive.get("BTC", Timestamp::from_nanos(6)).unwrap().map(|s| s.sequence_id), Some(6));
+        assert!(archive.get("BTC", Timestamp::from_nanos(7)).unwrap().is_none());
+        assert!(archive.insert("BAD\0", &snapshot(1, fixed!(1))).is_err());
+    }
+}
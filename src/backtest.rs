@@ -0,0 +1,696 @@
+//! Backtesting a [`Strategy`] against recorded events.
+//!
+//! A [`Backtest`] applies each event of a source to its book and shows the strategy the result, trades
+//! through [`Strategy::on_trade`] and every other accepted event through [`Strategy::on_delta`]. Market
+//! orders the strategy queues on [`Orders`] are filled straight away against the book as it stands,
+//! with [`execute_market`], and the fills go to a paper trading [`Account`] marked to the book after
+//! every event. Fills do not take liquidity out of the recorded book.
+//!
+//! Post-only orders rest at their price behind the size the level displayed when they arrived, and a
+//! [`FillModel`], [`QueueReactive`] unless [`Backtest::with_fill_model`] says otherwise, decides how
+//! much of them each trade at the level fills. A trade through the order's price fills what is left of
+//! it. Post-only orders that would cross the book are dropped. [`Backtest::with_risk_checks`] vets each
+//! order as it reaches the book and tells the strategy about those turned away.
+//!
+//! Without latency the strategy decides on the book the moment each event happens and its orders fill
+//! at once, which flatters any strategy. [`Backtest::with_latency`] gives the strategy a book of its
+//! own that each event reaches only after a draw from the feed latency, and holds each order back for
+//! a draw from the order entry latency before filling it against the book as it stands by then.
+//!
+//! ```
+//! # #[cfg(feature = "fixed_decimal")] {
+//! use freya_ob::{
+//!     backtest::{Backtest, Orders, Strategy}, books::{btree_orderbook::BTreeOrderBook, delta::BookDelta,
+//!     interface::OrderBook}, decimals::fixed_decimal::FixedDecimal, event::Event, event_kind::EventKind, fixed,
+//!     formats::source::MemorySource, paper::Mark, side::Side, timestamp::Timestamp,
+//! };
+//!
+//! /// Buys one as soon as there is an ask
+//! struct BuyOnce(bool);
+//!
+//! impl Strategy<FixedDecimal> for BuyOnce {
+//!     fn on_delta<B: OrderBook<FixedDecimal>>(&mut self, _: &B, delta: &BookDelta<FixedDecimal>, orders: &mut Orders<FixedDecimal>) {
+//!         if !self.0 && delta.side == Side::Sell {
+//!             orders.market(Side::Buy, fixed!(1));
+//!             self.0 = true;
+//!         }
+//!     }
+//! }
+//!
+//! let events = vec![
+//!     Event::new(EventKind::L2, Side::Buy, fixed!(99), fixed!(5), Timestamp::from_nanos(1)),
+//!     Event::new(EventKind::L2, Side::Sell, fixed!(101), fixed!(5), Timestamp::from_nanos(2)),
+//!     Event::new(EventKind::L2, Side::Buy, fixed!(100), fixed!(5), Timestamp::from_nanos(3)),
+//! ];
+//! let mut backtest = Backtest::new(BTreeOrderBook::new(), BuyOnce(false), Mark::Mid);
+//! let report = backtest.run(MemorySource::new(events)).unwrap();
+//! assert_eq!((report.fills, report.account.position(), report.account.pnl()), (1, fixed!(1), fixed!(-0.5)));
+//! # }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    iter::Sum,
+    ops::{Add, ControlFlow, Div, Mul, Sub},
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    formats::source::{EventSource, SourceError},
+    metrics::MetricsCalculator,
+    paper::{Account, Fill, Mark},
+    risk::{RiskChecks, RiskRejection},
+    side::Side,
+    sim::{
+        execute_market,
+        fees::{FeeModel, Liquidity},
+        latency::LatencySampler,
+        queue::{FillModel, QueuePosition, QueueReactive},
+    },
+    timestamp::Timestamp,
+};
+
+pub trait Strategy<V: DecimalType> {
+    /// Called after the book applied any accepted event other than a trade
+    fn on_delta<B: OrderBook<V>>(&mut self, book: &B, delta: &BookDelta<V>, orders: &mut Orders<V>);
+    /// Called after the book applied a trade, reported on its resting side
+    #[inline]
+    fn on_trade<B: OrderBook<V>>(&mut self, _book: &B, _trade: &Event<V>, _orders: &mut Orders<V>) {}
+    /// Called with each fill of an order the strategy queued
+    #[inline]
+    fn on_fill(&mut self, _fill: &Fill<V>) {}
+    /// Called with each order the risk checks turned away as it reached the book
+    #[inline]
+    fn on_reject(&mut self, _order: &Order<V>, _reason: &RiskRejection<V>) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order<V: DecimalType> {
+    /// Take `size` from whatever the book offers
+    Market { side: Side, size: V },
+    /// Rest `size` at `price`, dropped if it would cross the book
+    Post { side: Side, price: V, size: V },
+    /// Cancel every resting order
+    CancelAll,
+}
+
+#[derive(Debug, Clone, Default)]
+/// Orders a [`Strategy`] queues, sent once its callback returns
+pub struct Orders<V: DecimalType> {
+    queued: Vec<Order<V>>,
+}
+
+impl<V: DecimalType> Orders<V> {
+    #[inline]
+    /// Buy or sell `size` at whatever the book offers
+    pub fn market(&mut self, side: Side, size: V) {
+        self.queued.push(Order::Market { side, size });
+    }
+
+    #[inline]
+    /// Buy or sell `size` resting at `price`
+    pub fn post(&mut self, side: Side, price: V, size: V) {
+        self.queued.push(Order::Post { side, price, size });
+    }
+
+    #[inline]
+    pub fn cancel_all(&mut self) {
+        self.queued.push(Order::CancelAll);
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn queued(&self) -> &[Order<V>] {
+        &self.queued
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A post-only order resting on the book
+pub struct RestingOrder<V: DecimalType> {
+    pub side: Side,
+    pub price: V,
+    pub queue: QueuePosition<V>,
+    /// Size the level displayed at the last update
+    displayed: V,
+}
+
+impl<V: DecimalType + Copy> RestingOrder<V> {
+    #[inline]
+    #[must_use]
+    /// An order that joined its level behind the size the level displayed, `queue.ahead`
+    pub const fn new(side: Side, price: V, queue: QueuePosition<V>) -> Self {
+        Self { side, price, queue, displayed: queue.ahead }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// How a [`Backtest::run`] went
+pub struct BacktestReport<V: DecimalType> {
+    /// Events read from the source
+    pub events: u64,
+    /// Orders the strategy sent
+    pub orders: u64,
+    /// Orders the risk checks turned away
+    pub rejected: u64,
+    pub fills: u64,
+    /// The account as the run left it, marked to the final book
+    pub account: Account<V>,
+}
+
+#[derive(Debug)]
+/// The strategy's late view of the market and the orders on their way to it
+struct Delayed<V: DecimalType, B> {
+    view: B,
+    feed: LatencySampler,
+    order_entry: LatencySampler,
+    /// Events on their way to the view, in the order they happened
+    deliveries: VecDeque<(Timestamp, Event<V>)>,
+    /// Orders on their way to the book, soonest first
+    in_flight: Vec<(Timestamp, Order<V>)>,
+}
+
+#[derive(Debug)]
+/// Runs a [`Strategy`] over events, see the [module documentation](self)
+pub struct Backtest<V: DecimalType, B, S> {
+    book: B,
+    strategy: S,
+    account: Account<V>,
+    fees: Option<Box<dyn FeeModel<V>>>,
+    fill_model: Box<dyn FillModel<V>>,
+    participation_limit: Option<V>,
+    risk: Option<RiskChecks<V>>,
+    orders: Orders<V>,
+    resting: Vec<RestingOrder<V>>,
+    delayed: Option<Delayed<V, B>>,
+    events: u64,
+    /// Orders sent so far
+    sent: u64,
+    rejected: u64,
+    fills: u64,
+}
+
+impl<V, B, S> Backtest<V, B, S>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V> + Sum,
+    B: OrderBook<V> + MetricsCalculator<V>,
+    S: Strategy<V>,
+{
+    #[must_use]
+    /// Backtest `strategy` on `book`, marking the account at `mark`
+    pub fn new(book: B, strategy: S, mark: Mark) -> Self {
+        Self {
+            book,
+            strategy,
+            account: Account::new(mark),
+            fees: None,
+            fill_model: Box::new(QueueReactive),
+            participation_limit: None,
+            risk: None,
+            orders: Orders { queued: Vec::new() },
+            resting: Vec::new(),
+            delayed: None,
+            events: 0,
+            sent: 0,
+            rejected: 0,
+            fills: 0,
+        }
+    }
+
+    #[must_use]
+    /// Charge every fill by `fees`, as a maker for resting orders and a taker otherwise
+    pub fn with_fees(mut self, fees: impl FeeModel<V> + 'static) -> Self {
+        self.fees = Some(Box::new(fees));
+        self
+    }
+
+    #[must_use]
+    /// Decide how resting orders fill with `model`
+    pub fn with_fill_model(mut self, model: impl FillModel<V> + 'static) -> Self {
+        self.fill_model = Box::new(model);
+        self
+    }
+
+    #[must_use]
+    /// Take at most `limit` of each level's size, see [`execute_market`]
+    pub const fn with_participation_limit(mut self, limit: V) -> Self {
+        self.participation_limit = Some(limit);
+        self
+    }
+
+    #[must_use]
+    /// Vet every order with `checks` as it reaches the book, against the book as it stands by then and
+    /// the orders resting on it
+    pub const fn with_risk_checks(mut self, checks: RiskChecks<V>) -> Self {
+        self.risk = Some(checks);
+        self
+    }
+
+    #[must_use]
+    /// Show the strategy `view`, an empty book each event reaches `feed` late, and fill its orders
+    /// `order_entry` after it sends them
+    pub fn with_latency(mut self, view: B, feed: LatencySampler, order_entry: LatencySampler) -> Self {
+        self.delayed = Some(Delayed { view, feed, order_entry, deliveries: VecDeque::new(), in_flight: Vec::new() });
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn book(&self) -> &B {
+        &self.book
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn account(&self) -> &Account<V> {
+        &self.account
+    }
+
+    #[inline]
+    #[must_use]
+    /// Post-only orders resting on the book, in the order they arrived
+    pub fn resting(&self) -> &[RestingOrder<V>] {
+        &self.resting
+    }
+
+    /// Apply one event, show the strategy the result and fill what it sends, or with latency, first
+    /// deliver the events and fill the orders due by the time it happened
+    pub fn step(&mut self, event: Event<V>) {
+        self.events += 1;
+        let Some(delayed) = self.delayed.as_mut() else {
+            let delta = self.book.process_delta(event);
+            self.match_resting(&event, delta.as_ref());
+            let Some(delta) = delta else {
+                return;
+            };
+            decide(&mut self.strategy, &self.book, &event, &delta, &mut self.orders);
+            for order in std::mem::take(&mut self.orders.queued) {
+                self.sent += 1;
+                self.send(order, event.timestamp);
+            }
+            self.account.mark_to(&mut self.book);
+            return;
+        };
+        let delivery = delayed.deliveries.back().map_or(Timestamp::ZERO, |&(at, _)| at);
+        let delivery = (event.timestamp + delayed.feed.sample()).max(delivery);
+        delayed.deliveries.push_back((delivery, event));
+        self.advance_to(Some(event.timestamp));
+        let delta = self.book.process_delta(event);
+        self.match_resting(&event, delta.as_ref());
+        if delta.is_some() {
+            self.account.mark_to(&mut self.book);
+        }
+    }
+
+    /// Step through every event of `source`, then [`flush`](Self::flush)
+    ///
+    /// # Errors
+    /// The first error the source yields, leaving the events before it applied.
+    pub fn run(&mut self, source: impl EventSource<V>) -> Result<BacktestReport<V>, SourceError> {
+        for event in source {
+            self.step(event?);
+        }
+        self.flush();
+        Ok(self.report())
+    }
+
+    /// Deliver the events and fill the orders still on their way
+    pub fn flush(&mut self) {
+        self.advance_to(None);
+    }
+
+    /// Deliver the events and fill the orders due no later than `until`, an order due at the same time as
+    /// a delivery going first as it was sent before
+    fn advance_to(&mut self, until: Option<Timestamp>) {
+        while let Some(delayed) = self.delayed.as_mut() {
+            let delivery = delayed.deliveries.front().map(|&(at, _)| at);
+            let (at, arrival) = match (delivery, delayed.in_flight.first().map(|&(at, _)| at)) {
+                (Some(delivery), Some(arrival)) if arrival <= delivery => (arrival, true),
+                (None, Some(arrival)) => (arrival, true),
+                (Some(delivery), _) => (delivery, false),
+                (None, None) => return,
+            };
+            if until.is_some_and(|until| at > until) {
+                return;
+            }
+            if arrival {
+                let (_, order) = delayed.in_flight.remove(0);
+                self.send(order, at);
+                self.account.mark_to(&mut self.book);
+                continue;
+            }
+            let (_, event) = delayed.deliveries.pop_front().expect("a delivery is due");
+            let Some(delta) = delayed.view.process_delta(event) else {
+                continue;
+            };
+            decide(&mut self.strategy, &delayed.view, &event, &delta, &mut self.orders);
+            for order in std::mem::take(&mut self.orders.queued) {
+                self.sent += 1;
+                let arrival = at + delayed.order_entry.sample();
+                let index = delayed.in_flight.partition_point(|&(queued, _)| queued <= arrival);
+                delayed.in_flight.insert(index, (arrival, order));
+            }
+        }
+    }
+
+    /// Act on `order` as it reaches the book at `at`
+    fn send(&mut self, order: Order<V>, at: Timestamp) {
+        if let Some(Err(reason)) = self.risk.map(|risk| risk.check(&self.book, &order, &self.resting)) {
+            self.rejected += 1;
+            self.strategy.on_reject(&order, &reason);
+            return;
+        }
+        match order {
+            Order::Market { side, size } => {
+                let mut execution = execute_market(&self.book, side, size, self.participation_limit).at(at);
+                if let Some(fees) = &self.fees {
+                    execution = execution.with_fees(fees.as_ref());
+                }
+                execution.fills.into_iter().for_each(|fill| self.on_fill(fill));
+            }
+            Order::Post { side, price, size } => {
+                let crosses = self.book.levels(side.opposite(), 1).first().is_some_and(|best| match side {
+                    Side::Buy => best.price <= price,
+                    Side::Sell => best.price >= price,
+                });
+                if crosses {
+                    return;
+                }
+                let mut displayed = V::ZERO;
+                self.book.walk_levels(side, &mut |level| {
+                    if level.price == price {
+                        displayed = level.size;
+                    }
+                    let reached = match side {
+                        Side::Buy => level.price <= price,
+                        Side::Sell => level.price >= price,
+                    };
+                    if reached {
+                        ControlFlow::Break(())
+                    } else {
+                        ControlFlow::Continue(())
+                    }
+                });
+                self.resting.push(RestingOrder::new(side, price, QueuePosition::new(displayed, size)));
+            }
+            Order::CancelAll => self.resting.clear(),
+        }
+    }
+
+    /// Fill resting orders from `event` as the book applied it, into `delta`
+    fn match_resting(&mut self, event: &Event<V>, delta: Option<&BookDelta<V>>) {
+        if self.resting.is_empty() {
+            return;
+        }
+        let mut fills = Vec::new();
+        for order in &mut self.resting {
+            if event.side != order.side {
+                continue;
+            }
+            let after = delta.filter(|delta| delta.price == order.price).map_or(order.displayed, |delta| delta.size);
+            let through = match order.side {
+                Side::Buy => event.price < order.price,
+                Side::Sell => event.price > order.price,
+            };
+            let filled = match event.kind {
+                EventKind::Trade if through => order.queue.remaining,
+                EventKind::Trade if event.price == order.price => self.fill_model.on_trade(&mut order.queue, event.size),
+                _ if event.price == order.price => {
+                    self.fill_model.on_update(&mut order.queue, order.displayed, after);
+                    V::ZERO
+                }
+                _ => V::ZERO,
+            };
+            order.displayed = after;
+            let filled = if filled < order.queue.remaining { filled } else { order.queue.remaining };
+            if filled > V::ZERO {
+                order.queue.remaining = order.queue.remaining - filled;
+                let fill = Fill::new(order.side, order.price, filled, event.timestamp);
+                fills.push(self.fees.as_ref().map_or(fill, |fees| fill.charged(fees.as_ref(), Liquidity::Maker)));
+            }
+        }
+        self.resting.retain(|order| order.queue.remaining > V::ZERO);
+        fills.into_iter().for_each(|fill| self.on_fill(fill));
+    }
+
+    fn on_fill(&mut self, fill: Fill<V>) {
+        self.fills += 1;
+        self.account.on_fill(fill);
+        self.strategy.on_fill(&fill);
+    }
+
+    #[must_use]
+    pub fn report(&self) -> BacktestReport<V> {
+        BacktestReport {
+            events: self.events,
+            orders: self.sent,
+            rejected: self.rejected,
+            fills: self.fills,
+            account: self.account,
+        }
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> (B, S) {
+        (self.book, self.strategy)
+    }
+}
+
+/// Show `strategy` the event `book` accepted
+fn decide<V: DecimalType, B: OrderBook<V>, S: Strategy<V>>(
+    strategy: &mut S,
+    book: &B,
+    event: &Event<V>,
+    delta: &BookDelta<V>,
+    orders: &mut Orders<V>,
+) {
+    if event.kind == EventKind::Trade {
+        strategy.on_trade(book, event, orders);
+    } else {
+        strategy.on_delta(book, delta, orders);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        backtest::{Backtest, Order, Orders, Strategy},
+        books::{btree_orderbook::BTreeOrderBook, delta::BookDelta, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::{source::MemorySource, FormatError},
+        paper::{Fill, Mark},
+        risk::RiskChecks,
+        side::Side,
+        sim::{
+            fees::BasisPoints,
+            latency::{LatencyModel, LatencySampler},
+        },
+        timestamp::Timestamp,
+    };
+
+    /// Follows trades: buys after a trade against the asks, sells after one against the bids
+    #[derive(Default)]
+    struct Momentum {
+        deltas: usize,
+        fills: Vec<Fill<FixedDecimal>>,
+    }
+
+    impl Strategy<FixedDecimal> for Momentum {
+        fn on_delta<B: OrderBook<FixedDecimal>>(&mut self, _: &B, _: &BookDelta<FixedDecimal>, _: &mut Orders<FixedDecimal>) {
+            self.deltas += 1;
+        }
+
+        fn on_trade<B: OrderBook<FixedDecimal>>(
+            &mut self,
+            book: &B,
+            trade: &Event<FixedDecimal>,
+            orders: &mut Orders<FixedDecimal>,
+        ) {
+            assert!(!book.levels(trade.side, 1).is_empty());
+            orders.market(trade.side.opposite(), fixed!(2));
+        }
+
+        fn on_fill(&mut self, fill: &Fill<FixedDecimal>) {
+            self.fills.push(*fill);
+        }
+    }
+
+    /// Buys one on the first ask it sees
+    #[derive(Default)]
+    struct Lift(bool);
+
+    impl Strategy<FixedDecimal> for Lift {
+        fn on_delta<B: OrderBook<FixedDecimal>>(
+            &mut self,
+            _: &B,
+            delta: &BookDelta<FixedDecimal>,
+            orders: &mut Orders<FixedDecimal>,
+        ) {
+            if !self.0 && delta.side == Side::Sell {
+                orders.market(Side::Buy, fixed!(1));
+                self.0 = true;
+            }
+        }
+    }
+
+    /// Sends the orders scripted for each timestamp it sees
+    #[derive(Default)]
+    struct Scripted {
+        script: Vec<(i64, Order<FixedDecimal>)>,
+        fills: Vec<Fill<FixedDecimal>>,
+    }
+
+    impl Scripted {
+        fn send(&mut self, at: i64, orders: &mut Orders<FixedDecimal>) {
+            for (_, order) in self.script.iter().filter(|&&(ts, _)| ts == at) {
+                match *order {
+                    Order::Market { side, size } => orders.market(side, size),
+                    Order::Post { side, price, size } => orders.post(side, price, size),
+                    Order::CancelAll => orders.cancel_all(),
+                }
+            }
+        }
+    }
+
+    impl Strategy<FixedDecimal> for Scripted {
+        fn on_delta<B: OrderBook<FixedDecimal>>(
+            &mut self,
+            _: &B,
+            delta: &BookDelta<FixedDecimal>,
+            orders: &mut Orders<FixedDecimal>,
+        ) {
+            self.send(delta.timestamp.as_nanos(), orders);
+        }
+
+        fn on_trade<B: OrderBook<FixedDecimal>>(
+            &mut self,
+            _: &B,
+            trade: &Event<FixedDecimal>,
+            orders: &mut Orders<FixedDecimal>,
+        ) {
+            self.send(trade.timestamp.as_nanos(), orders);
+        }
+
+        fn on_fill(&mut self, fill: &Fill<FixedDecimal>) {
+            self.fills.push(*fill);
+        }
+    }
+
+    fn l2(side: Side, price: FixedDecimal, size: FixedDecimal, ts: i64) -> Event<FixedDecimal> {
+        Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(ts))
+    }
+
+    #[test]
+    fn test_runs_strategy() {
+        let events = vec![
+            l2(Side::Buy, fixed!(99), fixed!(5), 1),
+            l2(Side::Sell, fixed!(101), fixed!(1), 2),
+            l2(Side::Sell, fixed!(102), fixed!(5), 3),
+            Event::new(EventKind::Trade, Side::Buy, fixed!(99), fixed!(1), Timestamp::from_nanos(4)),
+            l2(Side::Buy, fixed!(100), fixed!(5), 5),
+            Event::new(EventKind::Trade, Side::Sell, fixed!(101), fixed!(0.5), Timestamp::from_nanos(6)),
+        ];
+        let mut backtest = Backtest::new(BTreeOrderBook::new(), Momentum::default(), Mark::Mid)
+            .with_fees(BasisPoints::new(fixed!(0), fixed!(10)));
+        let report = backtest.run(MemorySource::new(events)).unwrap();
+        assert_eq!((report.events, report.orders, report.fills), (6, 2, 3));
+
+        // Sold 2 into the bids at 99, then bought 0.5 at 101 and 1.5 at 102
+        let account = report.account;
+        assert_eq!((account.position(), account.realized(), account.fees()), (fixed!(0), fixed!(-5.5), fixed!(0.4015)));
+        let (_, strategy) = backtest.into_inner();
+        assert_eq!((strategy.deltas, strategy.fills.len()), (4, 3));
+        assert_eq!(strategy.fills[0].timestamp, Timestamp::from_nanos(4));
+
+        let broken = std::iter::once(Err(FormatError::Io("gone".to_owned())));
+        let mut backtest = Backtest::new(BTreeOrderBook::new(), Momentum::default(), Mark::Mid);
+        assert!(backtest.run(broken).is_err());
+    }
+
+    #[test]
+    fn test_latency_delays_decisions_and_fills() {
+        let events = || {
+            MemorySource::new(vec![
+                l2(Side::Buy, fixed!(99), fixed!(5), 1),
+                l2(Side::Sell, fixed!(101), fixed!(5), 2),
+                l2(Side::Sell, fixed!(101), fixed!(0), 8),
+                l2(Side::Sell, fixed!(105), fixed!(5), 8),
+            ])
+        };
+        let fixed = |nanos| LatencySampler::new(LatencyModel::Fixed(Duration::from_nanos(nanos)), 0);
+        let mut instant = Backtest::new(BTreeOrderBook::new(), Lift::default(), Mark::Mid);
+        assert_eq!(instant.run(events()).unwrap().account.average_price(), fixed!(101));
+
+        // The ask reaches the strategy at 7 and its order the book at 12, after the ask moved up at 8
+        let mut delayed = Backtest::new(BTreeOrderBook::new(), Lift::default(), Mark::Mid).with_latency(
+            BTreeOrderBook::new(),
+            fixed(5),
+            fixed(5),
+        );
+        let report = delayed.run(events()).unwrap();
+        assert_eq!((report.orders, report.fills, report.account.average_price()), (1, 1, fixed!(105)));
+
+        // Orders still in flight when the source ends are filled by the flush
+        let mut late = Backtest::new(BTreeOrderBook::new(), Lift::default(), Mark::Mid).with_latency(
+            BTreeOrderBook::new(),
+            fixed(1),
+            fixed(100),
+        );
+        events().for_each(|event| late.step(event.unwrap()));
+        assert_eq!(late.report().fills, 0);
+        late.flush();
+        assert_eq!(late.report().account.average_price(), fixed!(105));
+    }
+
+    #[test]
+    fn test_fills_resting_orders_from_the_queue() {
+        let trade = |side, price, size, ts| Event::new(EventKind::Trade, side, price, size, Timestamp::from_nanos(ts));
+        let events = [
+            l2(Side::Buy, fixed!(100), fixed!(5), 1),
+            l2(Side::Sell, fixed!(101), fixed!(5), 2),
+            trade(Side::Buy, fixed!(100), fixed!(3), 3),
+            l2(Side::Buy, fixed!(100), fixed!(1), 4),
+            trade(Side::Buy, fixed!(100), fixed!(2), 5),
+            trade(Side::Buy, fixed!(99), fixed!(1), 6),
+        ];
+        let script = vec![
+            (1, Order::Post { side: Side::Buy, price: fixed!(100), size: fixed!(2) }),
+            (2, Order::Post { side: Side::Sell, price: fixed!(100), size: fixed!(1) }),
+            (2, Order::Post { side: Side::Sell, price: fixed!(103), size: fixed!(1) }),
+            (4, Order::Post { side: Side::Buy, price: fixed!(103), size: fixed!(1) }),
+            (6, Order::CancelAll),
+        ];
+        let mut backtest = Backtest::new(BTreeOrderBook::new(), Scripted { script, fills: Vec::new() }, Mark::Mid)
+            .with_fees(BasisPoints::new(fixed!(-1), fixed!(5)))
+            .with_risk_checks(RiskChecks::new().with_self_cross_prevention());
+        events[..3].iter().for_each(|&event| backtest.step(event));
+        // The sell at 100 would cross the buy and was rejected, the buy queues behind the 5 shown, 3 of them traded
+        let queues = backtest.resting().iter().map(|order| (order.price, order.queue.ahead)).collect::<Vec<_>>();
+        assert_eq!(queues, [(fixed!(100), fixed!(2)), (fixed!(103), fixed!(0))]);
+
+        // Half the level cancels, the next trade reaches the order and one through its price fills the rest
+        let report = backtest.run(MemorySource::new(events[3..].to_vec())).unwrap();
+        let (_, strategy) = backtest.into_inner();
+        let fills = strategy.fills.iter().map(|fill| (fill.price, fill.size, fill.timestamp.as_nanos())).collect::<Vec<_>>();
+        assert_eq!(fills, [(fixed!(100), fixed!(1), 5), (fixed!(100), fixed!(1), 6)]);
+        assert_eq!(
+            (report.orders, report.rejected, report.account.position(), report.account.fees()),
+            (5, 2, fixed!(2), fixed!(-0.02))
+        );
+    }
+}
@@ -1,17 +1,27 @@
 use std::{
+    fmt::{self, Display},
     iter::Sum,
-    ops::{Add, Div, Mul, Sub},
+    ops::{Add, ControlFlow, Div, Mul, Sub},
 };
 
 use crate::{
-    books::interface::OrderBook,
-    buffers::buffer::Buffer,
+    books::{delta::BookDelta, interface::OrderBook},
+    buffers::{
+        buffer::{OrderedBuffer, SearchStrategy},
+        dyn_buffer::DynBuffer,
+        gap_buffer::GapBuffer,
+        inline_buffer::InlineBuffer,
+        soa_buffer::SoaBuffer,
+        storage::LevelStorage,
+    },
     decimals::decimal_type::DecimalType,
     event::Event,
     event_kind::EventKind,
     level::Level,
-    metrics::{MetricsCalculator, OrderbookMetrics},
+    metrics::{MetricsCalculator, MetricsRequest, OrderbookMetrics},
+    render::Ladder,
     side::Side,
+    timestamp::Timestamp,
 };
 
 #[derive(Debug)]
@@ -25,21 +35,34 @@ use crate::{
 /// - `Sequence_id`: Stores the sequence ID of the last update.
 /// - `Has_moved`: A boolean flag indicating whether the order book has moved since the last update.
 ///
-pub struct ArrayOrderbook<const N: usize, V>
+pub struct ArrayOrderbook<const N: usize, V, S = OrderedBuffer<N, V>>
 where
     V: DecimalType + PartialOrd,
 {
     pub best_bid: Option<Level<V>>,
     pub best_ask: Option<Level<V>>,
-    pub bids: Buffer<N, V>,
-    pub asks: Buffer<N, V>,
-    pub ts: i64,
+    pub bids: S,
+    pub asks: S,
+    pub ts: Timestamp,
     pub sequence_id: u64,
     pub has_moved: bool,
 }
 
-impl<const N: usize, V> MetricsCalculator<V> for ArrayOrderbook<N, V>
+/// An [`ArrayOrderbook`] whose sides grow on the heap instead of holding a fixed `N` levels
+pub type DynOrderbook<V> = ArrayOrderbook<0, V, DynBuffer<V>>;
+
+/// An [`ArrayOrderbook`] whose sides keep their prices and sizes in separate arrays
+pub type SoaOrderbook<const N: usize, V> = ArrayOrderbook<N, V, SoaBuffer<N, V>>;
+
+/// An [`ArrayOrderbook`] whose sides keep their free slots next to the touch
+pub type GapOrderbook<const N: usize, V> = ArrayOrderbook<N, V, GapBuffer<N, V>>;
+
+/// An [`ArrayOrderbook`] holding its levels inline, for shallow books
+pub type InlineOrderbook<const N: usize, V> = ArrayOrderbook<N, V, InlineBuffer<N, V>>;
+
+impl<const N: usize, V, S> MetricsCalculator<V> for ArrayOrderbook<N, V, S>
 where
+    S: LevelStorage<V>,
     V: DecimalType + PartialOrd + Sub<Output = V> + Add<Output = V> + Mul<Output = V> + Div<Output = V> + Copy + Ord + Sum,
 {
     fn best_bid(&self) -> Option<Level<V>> {
@@ -49,45 +72,75 @@ where
     fn best_ask(&self) -> Option<Level<V>> {
         self.best_ask
     }
+
+    fn walk_levels(&self, side: Side, visit: &mut dyn FnMut(Level<V>) -> ControlFlow<()>) {
+        let buffer = if side.is_buy() { &self.bids } else { &self.asks };
+        let _ = buffer.iter().try_for_each(visit);
+    }
 }
 
-impl<const N: usize, V> OrderBook<V> for ArrayOrderbook<N, V>
+impl<const N: usize, V, S> OrderBook<V> for ArrayOrderbook<N, V, S>
 where
+    S: LevelStorage<V>,
     V: DecimalType + PartialOrd + Sub<Output = V> + Add<Output = V> + Mul<Output = V> + Div<Output = V> + Copy + Ord + Sum,
 {
     #[inline]
     /// Processes an event by updating the internal order book state based on the event kind.
     ///
-    /// - If the event is older than the current timestamp (`ts`), it will be ignored.
+    /// - If the event is older than the current timestamp (`ts`), or is a Level 3 order event, it will be ignored.
     /// - Updates the timestamp and handles the sequence ID to ensure the event is processed in the correct order.
+    ///   Snapshot and clear events are authoritative and are applied even when their sequence ID restarts lower.
     /// - Depending on the event kind:
     ///   - `Trade`: Calls `process_trade` to handle trade events and update bid/ask levels.
     ///   - `Instant`: Calls `process_bbo` to handle Best Bid/Offer events and adjust the order book accordingly.
     ///   - `L2`: Calls `process_lvl2` to handle Level 2 updates and maintain the depth of the order book.
+    ///   - `Snapshot`: Applied as a Level 2 update, the `Clear` leading the snapshot has already emptied the book.
+    ///   - `Clear` and `ClearSide`: Empty both sides, or the event's side, reporting it on the delta.
+    /// - Accepted events return the resulting size at their price level as a [`BookDelta`], along with any
+    ///   level a full side dropped to make room.
     ///
-    fn process(&mut self, event: Event<V>) {
+    fn process_delta(&mut self, event: Event<V>) -> Option<BookDelta<V>> {
         let ts = event.timestamp;
-        // Ignore old events
-        if ts < self.ts {
-            return;
+        // Ignore old events, and order level ones which a Level 2 book cannot place
+        if ts < self.ts || event.kind.is_order_level() {
+            return None;
         }
 
-        // Handle sequence_id (if its non-zero) and timestamp
+        // Handle sequence_id (if its non-zero) and timestamp, snapshots may reset the sequence
         if event.sequence_id == 0
             || self.sequence_id == 0
             || event.sequence_id == self.sequence_id
             || event.sequence_id > self.sequence_id
+            || matches!(event.kind, EventKind::Snapshot | EventKind::Clear)
         {
             self.ts = ts;
+            let (side, price, sequence_id) = (event.side, event.price, event.sequence_id);
+            let reset = event.kind == EventKind::Clear;
+            let cleared_side = event.kind == EventKind::ClearSide;
             if event.sequence_id != 0 {
                 self.sequence_id = event.sequence_id;
             }
-            match event.kind {
-                EventKind::Trade => self.process_trade(event),
+            let evicted = match event.kind {
+                EventKind::Trade => {
+                    self.process_trade(event);
+                    None
+                }
                 EventKind::BBO => self.process_bbo(event),
-                EventKind::L2 => self.process_lvl2(event),
-            }
+                EventKind::L2 | EventKind::Snapshot => self.process_lvl2(event),
+                EventKind::Clear => {
+                    self.clear();
+                    None
+                }
+                EventKind::ClearSide => {
+                    self.clear_side(side);
+                    None
+                }
+                EventKind::Add | EventKind::Cancel | EventKind::Modify | EventKind::Execute => unreachable!("filtered above"),
+            };
+            let size = self.size_at(side, price).unwrap_or(V::ZERO);
+            return Some(BookDelta { side, price, size, timestamp: ts, sequence_id, reset, cleared_side, evicted });
         }
+        None
     }
 
     #[inline]
@@ -103,76 +156,141 @@ where
     }
 
     #[inline]
-    #[must_use]
+    fn levels(&self, side: Side, depth: usize) -> Vec<Level<V>> {
+        let buffer = if side.is_buy() { &self.bids } else { &self.asks };
+        buffer.iter().take(depth).collect()
+    }
+
+    #[inline]
+    fn timestamp(&self) -> Timestamp {
+        self.ts
+    }
+
+    #[inline]
+    fn sequence_id(&self) -> u64 {
+        self.sequence_id
+    }
+
+    #[inline]
     /// Calculate various orderbook metrics up to a specified depth
     ///
     /// Returns a struct containing different market microstructure indicators
-    fn calculate_metrics(&self, depth: usize) -> OrderbookMetrics<V> {
+    fn calculate_metrics_with(&self, depth: usize, request: MetricsRequest) -> OrderbookMetrics<V> {
+        // Only the touch is needed unless a depth metric was requested
+        let depth = if request.needs_depth() { depth } else { 0 };
         let mut bid_sizes = Vec::with_capacity(depth);
         let mut ask_sizes = Vec::with_capacity(depth);
         let mut bid_prices = Vec::with_capacity(depth);
         let mut ask_prices = Vec::with_capacity(depth);
 
         // Collect bid and ask data up to specified depth
-        for i in 0..depth {
-            if i < self.bids.len {
-                unsafe {
-                    let level = self.bids.get_unchecked(i);
-                    bid_sizes.push(level.size);
-                    bid_prices.push(level.price);
-                }
-            }
-            if i < self.asks.len {
-                unsafe {
-                    let level = self.asks.get_unchecked(i);
-                    ask_sizes.push(level.size);
-                    ask_prices.push(level.price);
-                }
-            }
-        }
+        self.bids.extend_depth(depth, &mut bid_prices, &mut bid_sizes);
+        self.asks.extend_depth(depth, &mut ask_prices, &mut ask_sizes);
 
-        self.calculate_metrics_internal(bid_sizes, ask_sizes, bid_prices, ask_prices)
+        self.calculate_metrics_internal(request, bid_sizes, ask_sizes, bid_prices, ask_prices)
     }
 }
 
-#[cfg(feature = "rust_decimal")]
-impl Default for ArrayOrderbook<300, rust_decimal::Decimal> {
+impl<const N: usize, V> ArrayOrderbook<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
     #[inline]
     #[must_use]
-    fn default() -> Self {
-        Self::new()
+    /// Search both sides with `search` rather than the default binary search
+    pub fn with_search(mut self, search: SearchStrategy) -> Self {
+        self.bids.set_search(search);
+        self.asks.set_search(search);
+        self
+    }
+}
+
+/// The timestamp and sequence ID of the last update and a ladder of the populated levels, all of them
+/// unless the precision, as in `{:.5}`, limits each side to that many. Unlike the derived `Debug`, the
+/// free slots of the buffers are left out.
+impl<const N: usize, V, S> Display for ArrayOrderbook<N, V, S>
+where
+    S: LevelStorage<V>,
+    V: DecimalType
+        + Display
+        + PartialOrd
+        + Sub<Output = V>
+        + Add<Output = V>
+        + Mul<Output = V>
+        + Div<Output = V>
+        + Copy
+        + Ord
+        + Sum,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ts {} sequence {}", self.ts, self.sequence_id)?;
+        Ladder::new(f.precision().unwrap_or(usize::MAX)).with_bar_width(0).write(self, f)
     }
 }
 
-#[cfg(feature = "fixed_decimal")]
-impl Default for ArrayOrderbook<300, crate::decimals::fixed_decimal::FixedDecimal> {
+impl<const N: usize, V, S> Default for ArrayOrderbook<N, V, S>
+where
+    S: LevelStorage<V>,
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
     #[inline]
-    #[must_use]
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<const N: usize, V> ArrayOrderbook<N, V>
+impl<const N: usize, V, S> ArrayOrderbook<N, V, S>
 where
+    S: LevelStorage<V>,
     V: DecimalType + PartialOrd + Copy + Ord,
 {
     #[inline]
     #[must_use]
     pub fn new() -> Self {
-        Self {
-            best_bid: None,
-            best_ask: None,
-            bids: Buffer::new(true),
-            asks: Buffer::new(false),
-            ts: 0,
-            sequence_id: 0,
-            has_moved: false,
+        Self::with_storage(S::new(true), S::new(false))
+    }
+
+    #[inline]
+    #[must_use]
+    /// An empty book over the given storage, descending `bids` and ascending `asks`
+    pub fn with_storage(mut bids: S, mut asks: S) -> Self {
+        bids.clear();
+        asks.clear();
+        Self { best_bid: None, best_ask: None, bids, asks, ts: Timestamp::ZERO, sequence_id: 0, has_moved: false }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Size resting at `price` on one side, `None` when there is no such level
+    pub fn size_at(&self, side: Side, price: V) -> Option<V> {
+        let buffer = if side.is_buy() { &self.bids } else { &self.asks };
+        let index = buffer.find_index(price).ok()?;
+        buffer.get(index).map(|level| level.size)
+    }
+
+    #[inline]
+    /// Remove every level from both sides of the book, keeping the timestamp and sequence ID.
+    fn clear(&mut self) {
+        self.clear_side(Side::Buy);
+        self.clear_side(Side::Sell);
+    }
+
+    #[inline]
+    /// Remove every level from one side of the book
+    fn clear_side(&mut self, side: Side) {
+        if side.is_buy() {
+            self.bids.clear();
+            self.best_bid = None;
+        } else {
+            self.asks.clear();
+            self.best_ask = None;
         }
+        self.has_moved = true;
     }
 
     #[inline(always)]
-    fn process_lvl2(&mut self, event: Event<V>) {
+    /// Apply a Level 2 update, returning the price of any level dropped at capacity
+    fn process_lvl2(&mut self, event: Event<V>) -> Option<V> {
         let (buffer, best_price) = match event.side {
             Side::Buy => (&mut self.bids, &mut self.best_bid),
             Side::Sell => (&mut self.asks, &mut self.best_ask),
@@ -180,7 +298,7 @@ where
 
         // If the size is zero, remove the level
         if event.size == V::ZERO {
-            if let Ok(to_remove) = buffer.find_index(event.price, event.side.is_buy()) {
+            if let Ok(to_remove) = buffer.find_index(event.price) {
                 let removed = buffer.remove(to_remove);
                 if let Some(best) = *best_price {
                     if removed == best.price {
@@ -189,22 +307,25 @@ where
                     }
                 }
             }
-            return;
+            return None;
         }
 
         // If the size is non-zero, insert or modify the level
-        match buffer.find_index(event.price, event.side.is_buy()) {
+        match buffer.find_index(event.price) {
             Ok(to_modify) => {
                 buffer.modify(to_modify, event.size);
                 if to_modify == 0 {
                     *best_price = buffer.first();
                 }
+                None
             }
             Err(to_insert) => {
-                buffer.insert(to_insert, event.to_level());
+                let price = event.price;
+                let result = buffer.insert(to_insert, event.to_level());
                 if to_insert == 0 {
                     *best_price = buffer.first();
                 }
+                result.dropped(price)
             }
         }
     }
@@ -213,52 +334,29 @@ where
     /// Process a trade event. This function is responsible for updating the bid/ask
     /// buffer(s) and best bid/ask price(s) based on the trade event.
     ///
-    /// - If the trade event is a buy, it will decrement or remove the best bid level
-    /// - if the size of the trade is greater than or equal to the size of the level.
-    /// - If the trade event is a sell, it will decrement or remove the best ask level
-    /// - if the size of the trade is greater than or equal to the size of the level.
+    /// - If the trade event is a buy, it will take the trade size off the bid level at its price,
+    ///   removing the level if the trade is at least as large.
+    /// - If the trade event is a sell, it will do the same to the ask level at its price.
     ///
     /// If the level is removed, the best bid/ask price will be updated to the new
     /// best bid/ask price(s) in the buffer(s).
-    fn process_trade(&mut self, event: Event<V>) {
-        match event.side {
-            Side::Buy => {
-                if let Ok(index) = self.bids.find_index(event.price, true) {
-                    // SAFETY: index is valid from find_index
-                    unsafe {
-                        let level = self.bids.get_unchecked_mut(index);
-                        if event.size >= level.size {
-                            self.bids.remove(index);
-                            if index == 0 {
-                                self.best_bid = self.bids.first();
-                            }
-                        } else {
-                            self.bids.modify(index, event.size);
-                        }
-                    }
-                    if index == 0 {
-                        self.best_bid = self.bids.first();
-                    }
-                }
+    fn process_trade(&mut self, event: Event<V>)
+    where
+        V: Sub<Output = V>,
+    {
+        let (levels, best) = match event.side {
+            Side::Buy => (&mut self.bids, &mut self.best_bid),
+            Side::Sell => (&mut self.asks, &mut self.best_ask),
+        };
+        if let Ok(index) = levels.find_index(event.price) {
+            let level_size = levels.get(index).map_or(V::ZERO, |level| level.size);
+            if event.size >= level_size {
+                levels.remove(index);
+            } else {
+                levels.modify(index, level_size - event.size);
             }
-            Side::Sell => {
-                if let Ok(index) = self.asks.find_index(event.price, false) {
-                    // SAFETY: index is valid from find_index
-                    unsafe {
-                        let level = self.asks.get_unchecked_mut(index);
-                        if event.size >= level.size {
-                            self.asks.remove(index);
-                            if index == 0 {
-                                self.best_ask = self.asks.first();
-                            }
-                        } else {
-                            self.asks.modify(index, event.size);
-                        }
-                    }
-                    if index == 0 {
-                        self.best_ask = self.asks.first();
-                    }
-                }
+            if index == 0 {
+                *best = levels.first();
             }
         }
     }
@@ -274,8 +372,9 @@ where
     /// - If the BBO price level does not exist and the size is greater than zero, the level will be
     ///   inserted into the buffer.
     /// - The best bid/ask price will be updated to the new best bid/ask price(s) in the buffer(s).
+    /// - Returns the price of any level dropped because the side was full.
     ///
-    fn process_bbo(&mut self, event: Event<V>) {
+    fn process_bbo(&mut self, event: Event<V>) -> Option<V> {
         let (buffer, best_price) =
             if event.side.is_buy() { (&mut self.bids, &mut self.best_bid) } else { (&mut self.asks, &mut self.best_ask) };
 
@@ -288,18 +387,20 @@ where
         }
 
         // Handle the BBO price level
+        let (price, mut evicted) = (event.price, None);
         if event.size == V::ZERO {
-            if let Ok(index) = buffer.find_index(event.price, event.side.is_buy()) {
+            if let Ok(index) = buffer.find_index(event.price) {
                 buffer.remove(index);
             }
         } else {
-            match buffer.find_index(event.price, event.side.is_buy()) {
+            match buffer.find_index(event.price) {
                 Ok(index) => buffer.modify(index, event.size),
-                Err(index) => buffer.insert(index, event.to_level()),
+                Err(index) => evicted = buffer.insert(index, event.to_level()).dropped(price),
             }
         }
 
         *best_price = buffer.first();
+        evicted
     }
 }
 
@@ -317,7 +418,9 @@ mod test {
             interface::OrderBook as _,
         },
         event_kind::EventKind,
+        metrics::{MetricsCalculator as _, MetricsRequest},
         side::Side,
+        timestamp::Timestamp,
     };
 
     #[test]
@@ -329,40 +432,53 @@ mod test {
     /// 4. Updates the best bid to a worse price, verifies the orderbook state.
     fn bbo() {
         let mut lob = ArrayOrderbook::<3, Decimal>::new();
-        let bbo_bid = Event::new(EventKind::BBO, Side::Buy, dec!(100.0), dec!(1.), 10001);
-        let bbo_ask = Event::new(EventKind::BBO, Side::Sell, dec!(100.1), dec!(1.), 10001);
+        let bbo_bid = Event::new(EventKind::BBO, Side::Buy, dec!(100.0), dec!(1.), Timestamp::from_nanos(10001));
+        let bbo_ask = Event::new(EventKind::BBO, Side::Sell, dec!(100.1), dec!(1.), Timestamp::from_nanos(10001));
 
         lob.process(bbo_ask);
         lob.process(bbo_bid);
         insta::assert_debug_snapshot!(lob);
 
-        let bbo_bid = Event::new(EventKind::BBO, Side::Buy, dec!(100.05), dec!(1.), 10002);
+        let bbo_bid = Event::new(EventKind::BBO, Side::Buy, dec!(100.05), dec!(1.), Timestamp::from_nanos(10002));
         lob.process(bbo_bid);
         insta::assert_debug_snapshot!(lob);
 
-        let bbo_bid = Event::new(EventKind::BBO, Side::Buy, dec!(100.05), dec!(0.), 10003);
+        let bbo_bid = Event::new(EventKind::BBO, Side::Buy, dec!(100.05), dec!(0.), Timestamp::from_nanos(10003));
         lob.process(bbo_bid);
         insta::assert_debug_snapshot!(lob);
 
-        let bbo_bid = Event::new(EventKind::BBO, Side::Buy, dec!(100.04), dec!(0.), 10004);
+        let bbo_bid = Event::new(EventKind::BBO, Side::Buy, dec!(100.04), dec!(0.), Timestamp::from_nanos(10004));
         lob.process(bbo_bid);
         insta::assert_debug_snapshot!(lob);
     }
 
+    #[test]
+    /// Display shows only the populated levels of a deep buffer, the precision limiting each side
+    fn display() {
+        let mut lob = ArrayOrderbook::<300, Decimal>::new();
+        for (side, price, size) in
+            [(Side::Buy, dec!(100.0), dec!(2)), (Side::Buy, dec!(99.5), dec!(10)), (Side::Sell, dec!(100.1), dec!(1.5))]
+        {
+            lob.process(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(10001)).with_sequence_id(7));
+        }
+        insta::assert_snapshot!(format!("{lob}"));
+        insta::assert_snapshot!(format!("{lob:.1}"));
+    }
+
     #[test]
     /// Test that a trade event is correctly processed. Given a BBO of 100.0 / 100.1, with a quantity
     /// of 2.0 and 1.1 respectively, process a buy trade of 1.0 and a sell trade of 1.0. Verify
     /// that the orderbook is updated to reflect the new quantities.
     fn trade() {
         let mut lob = ArrayOrderbook::<3, Decimal>::new();
-        let bbo_bid = Event::new(EventKind::BBO, Side::Buy, dec!(100.0), dec!(2.), 10001);
-        let bbo_ask = Event::new(EventKind::BBO, Side::Sell, dec!(100.1), dec!(1.1), 10001);
+        let bbo_bid = Event::new(EventKind::BBO, Side::Buy, dec!(100.0), dec!(2.), Timestamp::from_nanos(10001));
+        let bbo_ask = Event::new(EventKind::BBO, Side::Sell, dec!(100.1), dec!(1.1), Timestamp::from_nanos(10001));
 
         lob.process(bbo_ask);
         lob.process(bbo_bid);
 
-        let bid_trade = Event::new(EventKind::Trade, Side::Buy, dec!(100.0), dec!(1.), 10002);
-        let ask_trade = Event::new(EventKind::Trade, Side::Sell, dec!(100.1), dec!(1.), 10002);
+        let bid_trade = Event::new(EventKind::Trade, Side::Buy, dec!(100.0), dec!(1.), Timestamp::from_nanos(10002));
+        let ask_trade = Event::new(EventKind::Trade, Side::Sell, dec!(100.1), dec!(1.), Timestamp::from_nanos(10002));
         lob.process(bid_trade);
         lob.process(ask_trade);
         insta::assert_debug_snapshot!(lob);
@@ -376,10 +492,10 @@ mod test {
     /// that removes a level.
     fn lv2() {
         let mut lob = ArrayOrderbook::<3, Decimal>::new();
-        let event1 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(2.), 10001);
-        let event2 = Event::new(EventKind::L2, Side::Buy, dec!(100.1), dec!(1.1), 10001);
-        let event3 = Event::new(EventKind::L2, Side::Buy, dec!(100.2), dec!(0.1), 10001);
-        let event4 = Event::new(EventKind::Trade, Side::Buy, dec!(100.0), dec!(1.), 10001);
+        let event1 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(2.), Timestamp::from_nanos(10001));
+        let event2 = Event::new(EventKind::L2, Side::Buy, dec!(100.1), dec!(1.1), Timestamp::from_nanos(10001));
+        let event3 = Event::new(EventKind::L2, Side::Buy, dec!(100.2), dec!(0.1), Timestamp::from_nanos(10001));
+        let event4 = Event::new(EventKind::Trade, Side::Buy, dec!(100.0), dec!(1.), Timestamp::from_nanos(10001));
 
         lob.process(event1);
         lob.process(event2);
@@ -388,7 +504,7 @@ mod test {
 
         insta::assert_debug_snapshot!(lob);
 
-        let bbo_bid = Event::new(EventKind::BBO, Side::Buy, dec!(100.0), dec!(2.), 10002);
+        let bbo_bid = Event::new(EventKind::BBO, Side::Buy, dec!(100.0), dec!(2.), Timestamp::from_nanos(10002));
 
         lob.process(bbo_bid);
 
@@ -400,14 +516,14 @@ mod test {
         let mut lob = ArrayOrderbook::<5, Decimal>::new();
         // Add some sample orders
         let events = vec![
-            Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(1.0), 1),
-            Event::new(EventKind::L2, Side::Buy, dec!(99.0), dec!(2.0), 1),
-            Event::new(EventKind::L2, Side::Sell, dec!(101.0), dec!(1.5), 1),
-            Event::new(EventKind::L2, Side::Sell, dec!(102.0), dec!(1.0), 1),
-            Event::new(EventKind::L2, Side::Buy, dec!(95.0), dec!(1.0), 1),
-            Event::new(EventKind::L2, Side::Buy, dec!(98.0), dec!(2.0), 1),
-            Event::new(EventKind::L2, Side::Sell, dec!(102.21), dec!(1.5), 1),
-            Event::new(EventKind::L2, Side::Sell, dec!(104.1), dec!(1.0), 1),
+            Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(1.0), Timestamp::from_nanos(1)),
+            Event::new(EventKind::L2, Side::Buy, dec!(99.0), dec!(2.0), Timestamp::from_nanos(1)),
+            Event::new(EventKind::L2, Side::Sell, dec!(101.0), dec!(1.5), Timestamp::from_nanos(1)),
+            Event::new(EventKind::L2, Side::Sell, dec!(102.0), dec!(1.0), Timestamp::from_nanos(1)),
+            Event::new(EventKind::L2, Side::Buy, dec!(95.0), dec!(1.0), Timestamp::from_nanos(1)),
+            Event::new(EventKind::L2, Side::Buy, dec!(98.0), dec!(2.0), Timestamp::from_nanos(1)),
+            Event::new(EventKind::L2, Side::Sell, dec!(102.21), dec!(1.5), Timestamp::from_nanos(1)),
+            Event::new(EventKind::L2, Side::Sell, dec!(104.1), dec!(1.0), Timestamp::from_nanos(1)),
         ];
         for event in events {
             lob.process(event);
@@ -415,6 +531,56 @@ mod test {
         insta::assert_debug_snapshot!(lob.calculate_metrics(5));
     }
 
+    #[test]
+    fn test_liquidity_within_bps() {
+        let mut lob = ArrayOrderbook::<5, Decimal>::new();
+        assert!(lob.liquidity_within_bps(dec!(100)).is_none());
+        for (side, price, size) in [
+            (Side::Buy, dec!(99.5), dec!(1)),
+            (Side::Buy, dec!(99.0), dec!(2)),
+            (Side::Buy, dec!(98.0), dec!(4)),
+            (Side::Sell, dec!(100.5), dec!(3)),
+            (Side::Sell, dec!(101.5), dec!(5)),
+        ] {
+            lob.process(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(1)));
+        }
+        // 100 bps of the 100 mid spans 99 to 101
+        let liquidity = lob.liquidity_within_bps(dec!(100)).unwrap();
+        assert_eq!((liquidity.bid_size, liquidity.ask_size), (dec!(3), dec!(3)));
+        assert_eq!((liquidity.bid_notional, liquidity.ask_notional), (dec!(297.5), dec!(301.5)));
+    }
+
+    #[test]
+    fn test_impact_curve() {
+        let mut lob = ArrayOrderbook::<5, Decimal>::new();
+        for (side, price, size) in
+            [(Side::Buy, dec!(99), dec!(10)), (Side::Sell, dec!(101), dec!(1)), (Side::Sell, dec!(102), dec!(1))]
+        {
+            lob.process(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(1)));
+        }
+        let curve = lob.impact_curve(Side::Buy, &[dec!(50.5), dec!(152), dec!(1000)]).unwrap();
+        assert_eq!((curve[0].size, curve[0].average_price, curve[0].slippage), (dec!(0.5), dec!(101), dec!(1)));
+        assert_eq!((curve[1].filled, curve[1].size), (dec!(152), dec!(1.5)));
+        // The book runs out before the last notional is filled
+        assert_eq!((curve[2].filled, curve[2].size, curve[2].average_price), (dec!(203), dec!(2), dec!(101.5)));
+
+        let curve = lob.impact_curve(Side::Sell, &[dec!(99)]).unwrap();
+        assert_eq!((curve[0].size, curve[0].slippage), (dec!(1), dec!(1)));
+    }
+
+    #[test]
+    fn test_selected_metrics() {
+        let mut lob = ArrayOrderbook::<5, Decimal>::new();
+        lob.process(Event::new(EventKind::L2, Side::Buy, dec!(99), dec!(101), Timestamp::from_nanos(1)));
+        lob.process(Event::new(EventKind::L2, Side::Sell, dec!(101), dec!(33), Timestamp::from_nanos(1)));
+        let metrics = lob.calculate_metrics_with(5, MetricsRequest::new().spread());
+        assert_eq!((metrics.spread, metrics.mid_price, metrics.quote_imbalance), (Some(dec!(2)), None, None));
+
+        let metrics = lob.calculate_metrics_with(5, MetricsRequest::new().imbalance().mid_price());
+        assert_eq!((metrics.spread, metrics.mid_price, metrics.quote_imbalance), (None, Some(dec!(100)), Some(dec!(0.5))));
+        assert_eq!(metrics.vwap_bid, None);
+    }
+
     fn sin_generation_fn(base_price: f64, i: usize) -> Decimal {
         let value = (i as f64 * PI / 8.0).sin().mul_add(50.0, base_price);
         Decimal::from_f64(value).unwrap()
@@ -429,11 +595,11 @@ mod test {
             // Buy side
             let price = sin_generation_fn(base_price - 1., i);
             let size = sin_generation_fn(100.0, i);
-            ob.process(Event::new(EventKind::BBO, Side::Buy, price, size, 0));
+            ob.process(Event::new(EventKind::BBO, Side::Buy, price, size, Timestamp::ZERO));
             // Sell side
             let size = sin_generation_fn(100.0, i);
             let price = sin_generation_fn(base_price + 1., i);
-            ob.process(Event::new(EventKind::BBO, Side::Sell, price, size, 0));
+            ob.process(Event::new(EventKind::BBO, Side::Sell, price, size, Timestamp::ZERO));
             // Assert
             insta::assert_debug_snapshot!(ob);
         }
@@ -446,15 +612,15 @@ mod test {
         let mut lob = ArrayOrderbook::<5, Decimal>::new();
         // Add levels
         let events = vec![
-            Event::new(EventKind::L2, Side::Buy, dec!(100.), dec!(1.), 1),
-            Event::new(EventKind::L2, Side::Buy, dec!(99.), dec!(2.), 2),
+            Event::new(EventKind::L2, Side::Buy, dec!(100.), dec!(1.), Timestamp::from_nanos(1)),
+            Event::new(EventKind::L2, Side::Buy, dec!(99.), dec!(2.), Timestamp::from_nanos(2)),
         ];
         for event in events {
             lob.process(event);
         }
         // Remove first level by setting size to 0
-        lob.process(Event::new(EventKind::L2, Side::Buy, dec!(100.), Decimal::ZERO, 2));
-        assert_eq!(lob.bids.len, 1);
+        lob.process(Event::new(EventKind::L2, Side::Buy, dec!(100.), Decimal::ZERO, Timestamp::from_nanos(2)));
+        assert_eq!(lob.bids.len(), 1);
         unsafe {
             assert_eq!(lob.bids.get_unchecked(0).price, dec!(99.));
         }
@@ -464,16 +630,29 @@ mod test {
     fn test_quote_imbalance() {
         let mut lob = ArrayOrderbook::<5, Decimal>::new();
         // Add equal bid and ask volumes
-        lob.process(Event::new(EventKind::L2, Side::Buy, dec!(100.), dec!(1.), 1));
-        lob.process(Event::new(EventKind::L2, Side::Sell, dec!(101.), dec!(1.), 2));
+        lob.process(Event::new(EventKind::L2, Side::Buy, dec!(100.), dec!(1.), Timestamp::from_nanos(1)));
+        lob.process(Event::new(EventKind::L2, Side::Sell, dec!(101.), dec!(1.), Timestamp::from_nanos(2)));
         let metrics = lob.calculate_metrics(5);
         insta::assert_debug_snapshot!(metrics);
 
         // Add more bid volume
-        lob.process(Event::new(EventKind::L2, Side::Buy, dec!(99.), dec!(2.), 3));
+        lob.process(Event::new(EventKind::L2, Side::Buy, dec!(99.), dec!(2.), Timestamp::from_nanos(3)));
         let metrics = lob.calculate_metrics(5);
         insta::assert_debug_snapshot!(metrics);
     }
+
+    #[test]
+    fn test_metrics_without_market() {
+        let mut lob = ArrayOrderbook::<5, Decimal>::new();
+        let metrics = lob.calculate_metrics(5);
+        assert_eq!((metrics.mid_price, metrics.spread, metrics.quote_imbalance), (None, None, None));
+
+        // A one-sided book is fully imbalanced and has a VWAP for the populated side
+        lob.process(Event::new(EventKind::L2, Side::Buy, dec!(100.), dec!(1.), Timestamp::from_nanos(1)));
+        let metrics = lob.calculate_metrics(5);
+        assert_eq!((metrics.spread, metrics.spread_percentage, metrics.quote_imbalance), (None, None, Some(dec!(1))));
+        assert_eq!((metrics.vwap_bid, metrics.vwap_ask), (Some(dec!(100.)), None));
+    }
 }
 
 #[cfg(test)]
@@ -487,6 +666,7 @@ mod sequence_tests {
         event::Event,
         event_kind::EventKind,
         side::Side,
+        timestamp::Timestamp,
     };
 
     #[test]
@@ -495,16 +675,17 @@ mod sequence_tests {
     fn test_sequence_order() {
         let mut ob = ArrayOrderbook::<5, Decimal>::new();
         // Process initial state
-        let event1 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(1.0), 0).with_sequence_id(1);
+        let event1 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(1.0), Timestamp::ZERO).with_sequence_id(1);
         ob.process(event1);
         assert_eq!(ob.sequence_id, 1);
         // Process event with higher sequence - should update
-        let event2 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(2.0), 1).with_sequence_id(2);
+        let event2 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(2.0), Timestamp::from_nanos(1)).with_sequence_id(2);
         ob.process(event2);
         assert_eq!(ob.sequence_id, 2);
         assert_eq!(ob.best_bid().unwrap().size, dec!(2.0));
         // Process older event - should be ignored
-        let old_event = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(0.5), 2).with_sequence_id(1);
+        let old_event =
+            Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(0.5), Timestamp::from_nanos(2)).with_sequence_id(1);
         ob.process(old_event);
         assert_eq!(ob.sequence_id, 2);
         assert_eq!(ob.best_bid().unwrap().size, dec!(2.0));
@@ -515,11 +696,11 @@ mod sequence_tests {
     fn test_zero_sequence() {
         let mut ob = ArrayOrderbook::<5, Decimal>::new();
         // Set initial state with non-zero sequence
-        let event1 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(1.0), 0).with_sequence_id(5);
+        let event1 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(1.0), Timestamp::ZERO).with_sequence_id(5);
         ob.process(event1);
         assert_eq!(ob.sequence_id, 5);
         // Event with sequence_id 0 should still be processed
-        let event2 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(2.0), 1).with_sequence_id(0);
+        let event2 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(2.0), Timestamp::from_nanos(1)).with_sequence_id(0);
         ob.process(event2);
         assert_eq!(ob.sequence_id, 5); // Sequence ID shouldn't change
         assert_eq!(ob.best_bid().unwrap().size, dec!(2.0)); // But state should update
@@ -530,15 +711,17 @@ mod sequence_tests {
     fn test_sequence_reset() {
         let mut ob = ArrayOrderbook::<5, Decimal>::new();
         // Initial state
-        let event1 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(1.0), 0).with_sequence_id(1);
+        let event1 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(1.0), Timestamp::ZERO).with_sequence_id(1);
         ob.process(event1);
         // Jump to much higher sequence (simulating reset/reconnect)
-        let event2 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(2.0), 1).with_sequence_id(1000);
+        let event2 =
+            Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(2.0), Timestamp::from_nanos(1)).with_sequence_id(1000);
         ob.process(event2);
         assert_eq!(ob.sequence_id, 1000);
         assert_eq!(ob.best_bid().unwrap().size, dec!(2.0));
         // Ensure we continue processing higher sequences
-        let event3 = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(3.0), 2).with_sequence_id(1001);
+        let event3 =
+            Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(3.0), Timestamp::from_nanos(2)).with_sequence_id(1001);
         ob.process(event3);
         assert_eq!(ob.sequence_id, 1001);
         assert_eq!(ob.best_bid().unwrap().size, dec!(3.0));
@@ -549,21 +732,21 @@ mod sequence_tests {
     fn test_mixed_event_types() {
         let mut ob = ArrayOrderbook::<5, Decimal>::new();
         // Set up initial state with L2 update
-        let l2_event = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(2.0), 0).with_sequence_id(1);
+        let l2_event = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(2.0), Timestamp::ZERO).with_sequence_id(1);
         ob.process(l2_event);
         // Process a trade
-        let trade = Event::new(EventKind::Trade, Side::Buy, dec!(100.0), dec!(1.0), 1).with_sequence_id(2);
+        let trade = Event::new(EventKind::Trade, Side::Buy, dec!(100.0), dec!(1.0), Timestamp::from_nanos(1)).with_sequence_id(2);
         ob.process(trade);
         assert_eq!(ob.best_bid().unwrap().size, dec!(1.0));
         // Process a BBO update
-        let bbo = Event::new(EventKind::BBO, Side::Buy, dec!(101.0), dec!(1.5), 2).with_sequence_id(3);
+        let bbo = Event::new(EventKind::BBO, Side::Buy, dec!(101.0), dec!(1.5), Timestamp::from_nanos(2)).with_sequence_id(3);
         ob.process(bbo);
         assert_eq!(ob.best_bid().unwrap().price, dec!(101.0));
         assert_eq!(ob.best_bid().unwrap().size, dec!(1.5));
         // Try to process old events of each type - should all be ignored
-        let old_l2 = Event::new(EventKind::L2, Side::Buy, dec!(99.0), dec!(1.0), 0).with_sequence_id(2);
-        let old_trade = Event::new(EventKind::Trade, Side::Buy, dec!(101.0), dec!(0.5), 0).with_sequence_id(2);
-        let old_bbo = Event::new(EventKind::BBO, Side::Buy, dec!(102.0), dec!(2.0), 0).with_sequence_id(1);
+        let old_l2 = Event::new(EventKind::L2, Side::Buy, dec!(99.0), dec!(1.0), Timestamp::ZERO).with_sequence_id(2);
+        let old_trade = Event::new(EventKind::Trade, Side::Buy, dec!(101.0), dec!(0.5), Timestamp::ZERO).with_sequence_id(2);
+        let old_bbo = Event::new(EventKind::BBO, Side::Buy, dec!(102.0), dec!(2.0), Timestamp::ZERO).with_sequence_id(1);
         // Process old events
         ob.process(old_l2);
         ob.process(old_trade);
@@ -573,4 +756,100 @@ mod sequence_tests {
         assert_eq!(ob.best_bid().unwrap().price, dec!(101.0));
         assert_eq!(ob.best_bid().unwrap().size, dec!(1.5));
     }
+
+    #[test]
+    /// Test that accepted events report the size left at their level and ignored events report nothing
+    fn test_process_delta() {
+        let mut ob = ArrayOrderbook::<5, Decimal>::new();
+        assert!(ob.process_delta(Event::clear(Timestamp::from_nanos(1)).with_sequence_id(1)).unwrap().reset);
+        let snapshot =
+            Event::new(EventKind::Snapshot, Side::Buy, dec!(100.0), dec!(2.0), Timestamp::from_nanos(1)).with_sequence_id(1);
+        let delta = ob.process_delta(snapshot).unwrap();
+        assert!(!delta.reset);
+        assert_eq!((delta.side, delta.price, delta.size, delta.sequence_id), (Side::Buy, dec!(100.0), dec!(2.0), 1));
+
+        let trade = Event::new(EventKind::Trade, Side::Buy, dec!(100.0), dec!(2.0), Timestamp::from_nanos(2)).with_sequence_id(2);
+        let delta = ob.process_delta(trade).unwrap();
+        assert!(delta.is_removal() && !delta.reset);
+
+        let stale = Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(1.0), Timestamp::from_nanos(1)).with_sequence_id(3);
+        assert!(ob.process_delta(stale).is_none());
+    }
+
+    #[test]
+    /// Test that only the leading clear replaces the book, however the snapshot's sequence IDs run
+    fn test_resnapshot() {
+        let mut ob = ArrayOrderbook::<5, Decimal>::new();
+        let snapshot = |price, ts| {
+            [
+                Event::clear(Timestamp::from_nanos(ts)).with_sequence_id(7),
+                Event::new(EventKind::Snapshot, Side::Buy, price, dec!(1.0), Timestamp::from_nanos(ts)).with_sequence_id(7),
+            ]
+        };
+        snapshot(dec!(100.0), 1).into_iter().for_each(|event| ob.process(event));
+        // A second snapshot straight after the first, at the same sequence ID
+        snapshot(dec!(99.0), 2).into_iter().for_each(|event| ob.process(event));
+        assert_eq!(ob.levels(Side::Buy, 5).iter().map(|level| level.price).collect::<Vec<_>>(), [dec!(99.0)]);
+
+        // Snapshot levels of a new sequence ID within one snapshot keep what came before
+        let event = Event::new(EventKind::Snapshot, Side::Buy, dec!(98.0), dec!(1.0), Timestamp::from_nanos(2));
+        ob.process(event.with_sequence_id(8));
+        assert_eq!(ob.bids.len(), 2);
+    }
+
+    #[test]
+    /// Test that a full side reports the level it dropped
+    fn test_capacity_eviction() {
+        let mut ob = ArrayOrderbook::<2, Decimal>::new();
+        for (price, ts) in [(dec!(100.0), 1), (dec!(99.0), 2)] {
+            let delta =
+                ob.process_delta(Event::new(EventKind::L2, Side::Buy, price, dec!(1.0), Timestamp::from_nanos(ts))).unwrap();
+            assert_eq!(delta.evicted, None);
+        }
+        let delta =
+            ob.process_delta(Event::new(EventKind::L2, Side::Buy, dec!(99.5), dec!(1.0), Timestamp::from_nanos(3))).unwrap();
+        assert_eq!((delta.size, delta.evicted), (dec!(1.0), Some(dec!(99.0))));
+
+        // Worse than every level of the full side, so the level itself is not kept
+        let delta =
+            ob.process_delta(Event::new(EventKind::L2, Side::Buy, dec!(98.0), dec!(1.0), Timestamp::from_nanos(4))).unwrap();
+        assert_eq!((delta.size, delta.evicted), (Decimal::ZERO, Some(dec!(98.0))));
+    }
+
+    #[test]
+    /// Test that order level events leave a Level 2 book and its clocks untouched
+    fn test_ignores_order_events() {
+        let mut ob = ArrayOrderbook::<5, Decimal>::new();
+        ob.process(Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(1.0), Timestamp::from_nanos(1)).with_sequence_id(1));
+        for kind in [EventKind::Add, EventKind::Cancel, EventKind::Modify, EventKind::Execute] {
+            let event = Event::new(kind, Side::Buy, dec!(100.0), dec!(5.0), Timestamp::from_nanos(2))
+                .with_sequence_id(2)
+                .with_order_id(42);
+            assert!(ob.process_delta(event).is_none());
+        }
+        assert_eq!((ob.ts.as_nanos(), ob.sequence_id, ob.best_bid().map(|level| level.size)), (1, 1, Some(dec!(1.0))));
+    }
+
+    #[test]
+    /// Test that clear events empty one side or the whole book and say so on the delta
+    fn test_clear() {
+        let mut ob = ArrayOrderbook::<5, Decimal>::new();
+        ob.process(Event::new(EventKind::L2, Side::Buy, dec!(100.0), dec!(1.0), Timestamp::from_nanos(1)));
+        ob.process(Event::new(EventKind::L2, Side::Sell, dec!(101.0), dec!(1.0), Timestamp::from_nanos(1)));
+
+        let delta = ob
+            .process_delta(Event::new(EventKind::ClearSide, Side::Sell, Decimal::ZERO, Decimal::ZERO, Timestamp::from_nanos(2)))
+            .unwrap();
+        assert!(delta.cleared_side && !delta.reset && delta.is_removal());
+        assert!(ob.best_ask().is_none());
+        assert_eq!(ob.best_bid().map(|level| level.price), Some(dec!(100.0)));
+
+        ob.process(Event::new(EventKind::L2, Side::Sell, dec!(102.0), dec!(1.0), Timestamp::from_nanos(3)));
+        let delta = ob
+            .process_delta(Event::new(EventKind::Clear, Side::Buy, Decimal::ZERO, Decimal::ZERO, Timestamp::from_nanos(4)))
+            .unwrap();
+        assert!(delta.reset && !delta.cleared_side);
+        assert!(ob.best_bid().is_none() && ob.best_ask().is_none());
+        assert!(ob.levels(Side::Sell, 5).is_empty());
+    }
 }
@@ -1,18 +1,20 @@
 use std::{
     collections::BTreeMap,
-    fmt::Debug,
+    fmt::{self, Debug, Display},
     iter::Sum,
-    ops::{Add, Div, Mul, Sub, SubAssign},
+    ops::{Add, ControlFlow, Div, Mul, Sub, SubAssign},
 };
 
 use crate::{
-    books::interface::OrderBook,
+    books::{delta::BookDelta, interface::OrderBook},
     decimals::decimal_type::DecimalType,
     event::Event,
     event_kind::EventKind,
     level::Level,
-    metrics::{MetricsCalculator, OrderbookMetrics},
+    metrics::{MetricsCalculator, MetricsRequest, OrderbookMetrics},
+    render::Ladder,
     side::Side,
+    timestamp::Timestamp,
 };
 
 #[derive(Debug)]
@@ -24,7 +26,7 @@ where
     best_ask: Option<Level<V>>,
     bids: BTreeMap<V, V>,
     asks: BTreeMap<V, V>,
-    ts: i64,
+    ts: Timestamp,
     sequence_id: u64,
 }
 
@@ -43,25 +45,41 @@ where
         + Div<Output = V>
         + Sum,
 {
-    fn process(&mut self, event: Event<V>) {
+    fn process_delta(&mut self, event: Event<V>) -> Option<BookDelta<V>> {
         let ts = event.timestamp;
-        if ts < self.ts {
-            return;
+        if ts < self.ts || event.kind.is_order_level() {
+            return None;
         }
 
         if event.sequence_id == 0
             || self.sequence_id == 0
             || event.sequence_id == self.sequence_id
             || event.sequence_id > self.sequence_id
+            || matches!(event.kind, EventKind::Snapshot | EventKind::Clear)
         {
             self.ts = ts;
+            let (side, price, sequence_id) = (event.side, event.price, event.sequence_id);
+            let reset = event.kind == EventKind::Clear;
+            let cleared_side = event.kind == EventKind::ClearSide;
 
             match event.kind {
                 EventKind::Trade => self.process_trade(event),
                 EventKind::BBO => self.process_bbo(event),
-                EventKind::L2 => self.process_l2(event),
+                EventKind::L2 | EventKind::Snapshot => self.process_l2(event),
+                EventKind::Clear => {
+                    self.sequence_id = event.sequence_id;
+                    self.clear();
+                }
+                EventKind::ClearSide => {
+                    self.sequence_id = event.sequence_id;
+                    self.clear_side(side);
+                }
+                EventKind::Add | EventKind::Cancel | EventKind::Modify | EventKind::Execute => unreachable!("filtered above"),
             }
+            let size = self.size_at(side, price).unwrap_or(V::ZERO);
+            return Some(BookDelta { side, price, size, timestamp: ts, sequence_id, reset, cleared_side, evicted: None });
         }
+        None
     }
 
     fn best_bid(&mut self) -> Option<Level<V>> {
@@ -72,7 +90,24 @@ where
         self.best_ask
     }
 
-    fn calculate_metrics(&self, depth: usize) -> OrderbookMetrics<V> {
+    fn levels(&self, side: Side, depth: usize) -> Vec<Level<V>> {
+        match side {
+            Side::Buy => self.bids.iter().rev().take(depth).map(|(&price, &size)| Level::new(price, size)).collect(),
+            Side::Sell => self.asks.iter().take(depth).map(|(&price, &size)| Level::new(price, size)).collect(),
+        }
+    }
+
+    fn timestamp(&self) -> Timestamp {
+        self.ts
+    }
+
+    fn sequence_id(&self) -> u64 {
+        self.sequence_id
+    }
+
+    fn calculate_metrics_with(&self, depth: usize, request: MetricsRequest) -> OrderbookMetrics<V> {
+        // Only the touch is needed unless a depth metric was requested
+        let depth = if request.needs_depth() { depth } else { 0 };
         let mut bid_sizes = Vec::with_capacity(depth);
         let mut ask_sizes = Vec::with_capacity(depth);
         let mut bid_prices = Vec::with_capacity(depth);
@@ -90,7 +125,7 @@ where
             ask_prices.push(*price);
         }
 
-        self.calculate_metrics_internal(bid_sizes, ask_sizes, bid_prices, ask_prices)
+        self.calculate_metrics_internal(request, bid_sizes, ask_sizes, bid_prices, ask_prices)
     }
 }
 
@@ -115,20 +150,43 @@ where
     fn best_ask(&self) -> Option<Level<V>> {
         self.best_ask
     }
+
+    fn walk_levels(&self, side: Side, visit: &mut dyn FnMut(Level<V>) -> ControlFlow<()>) {
+        let mut visit = |(&price, &size): (&V, &V)| visit(Level::new(price, size));
+        let _ = match side {
+            Side::Buy => self.bids.iter().rev().try_for_each(&mut visit),
+            Side::Sell => self.asks.iter().try_for_each(&mut visit),
+        };
+    }
 }
 
-#[cfg(feature = "rust_decimal")]
-impl Default for BTreeOrderBook<rust_decimal::Decimal> {
-    #[inline]
-    fn default() -> Self {
-        Self::new()
+/// The timestamp and sequence ID of the last update and a ladder of the populated levels, all of them
+/// unless the precision, as in `{:.5}`, limits each side to that many
+impl<V> Display for BTreeOrderBook<V>
+where
+    V: Debug
+        + Display
+        + Ord
+        + Copy
+        + DecimalType
+        + SubAssign
+        + Sub<Output = V>
+        + Add<Output = V>
+        + Mul<Output = V>
+        + Div<Output = V>
+        + Sum,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ts {} sequence {}", self.ts, self.sequence_id)?;
+        Ladder::new(f.precision().unwrap_or(usize::MAX)).with_bar_width(0).write(self, f)
     }
 }
 
-#[cfg(feature = "fixed_decimal")]
-impl Default for BTreeOrderBook<crate::decimals::fixed_decimal::FixedDecimal> {
+impl<V> Default for BTreeOrderBook<V>
+where
+    V: Debug + DecimalType + SubAssign + PartialEq + PartialOrd + Ord + Copy,
+{
     #[inline]
-    #[must_use]
     fn default() -> Self {
         Self::new()
     }
@@ -139,7 +197,29 @@ where
     V: Debug + DecimalType + SubAssign + PartialEq + PartialOrd + Ord + Copy,
 {
     pub fn new() -> Self {
-        Self { best_bid: None, best_ask: None, bids: BTreeMap::new(), asks: BTreeMap::new(), ts: 0, sequence_id: 0 }
+        Self { best_bid: None, best_ask: None, bids: BTreeMap::new(), asks: BTreeMap::new(), ts: Timestamp::ZERO, sequence_id: 0 }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Size resting at `price` on one side, `None` when there is no such level
+    pub fn size_at(&self, side: Side, price: V) -> Option<V> {
+        let book = if side.is_buy() { &self.bids } else { &self.asks };
+        book.get(&price).copied()
+    }
+
+    fn clear(&mut self) {
+        self.clear_side(Side::Buy);
+        self.clear_side(Side::Sell);
+    }
+
+    fn clear_side(&mut self, side: Side) {
+        let (book, best_price) = match side {
+            Side::Buy => (&mut self.bids, &mut self.best_bid),
+            Side::Sell => (&mut self.asks, &mut self.best_ask),
+        };
+        book.clear();
+        *best_price = None;
     }
 
     fn process_l2(&mut self, event: Event<V>) {
@@ -0,0 +1,32 @@
+use crate::{decimals::decimal_type::DecimalType, side::Side, timestamp::Timestamp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The level an accepted event left behind, as returned by [`OrderBook::process_delta`].
+///
+/// `size` is the absolute size resting at `price` after the event, zero when the level was removed.
+/// Levels a BBO event removes ahead of its price are implied rather than reported one by one.
+///
+/// [`OrderBook::process_delta`]: crate::books::interface::OrderBook::process_delta
+pub struct BookDelta<V: DecimalType> {
+    pub side: Side,
+    pub price: V,
+    pub size: V,
+    pub timestamp: Timestamp,
+    pub sequence_id: u64,
+    /// Both sides were emptied before the level was applied, as at the start of a snapshot or on a
+    /// [`Clear`](crate::event_kind::EventKind::Clear)
+    pub reset: bool,
+    /// `side` alone was emptied, by a [`ClearSide`](crate::event_kind::EventKind::ClearSide)
+    pub cleared_side: bool,
+    /// Price of a level the side dropped for lack of capacity, either its worst level pushed out by this
+    /// one or this level itself when it fell beyond the capacity
+    pub evicted: Option<V>,
+}
+
+impl<V: DecimalType + PartialEq> BookDelta<V> {
+    #[inline(always)]
+    #[must_use]
+    pub fn is_removal(&self) -> bool {
+        self.size == V::ZERO
+    }
+}
@@ -0,0 +1,113 @@
+//! Stable hashing of book state.
+//!
+//! [`StateHasher`] is 64-bit FNV-1a fed with the decimal text of each price and size, so the hash of a
+//! book depends only on what it holds, not on the decimal backend's memory layout, the platform's
+//! endianness or the process. [`OrderBook::state_hash`](crate::books::interface::OrderBook::state_hash)
+//! hashes the populated levels of both sides, best price first, then the timestamp and sequence ID of
+//! the last update. Backends that print equal values differently, as `rust_decimal` does for `1.0` and
+//! `1.00`, hash them differently.
+
+use std::fmt::{self, Display, Write};
+
+use crate::{decimals::decimal_type::DecimalType, level::Level, side::Side, timestamp::Timestamp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateHasher(u64);
+
+impl StateHasher {
+    const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01B3;
+
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    #[inline]
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// Hash the levels of one side, tagged with the side and separated so no two different ladders
+    /// feed the same bytes
+    pub fn levels<V: DecimalType + Display>(&mut self, side: Side, levels: &[Level<V>]) {
+        self.update(if side.is_buy() { b"B" } else { b"S" });
+        for level in levels {
+            write!(self, "{}:{};", level.price, level.size).expect("hashing text cannot fail");
+        }
+    }
+
+    #[inline]
+    pub fn last_update(&mut self, timestamp: Timestamp, sequence_id: u64) {
+        self.update(&timestamp.as_nanos().to_le_bytes());
+        self.update(&sequence_id.to_le_bytes());
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for StateHasher {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for StateHasher {
+    #[inline]
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.update(text.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, btree_orderbook::BTreeOrderBook, hash::StateHasher, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_state_hash() {
+        let mut empty = StateHasher::new();
+        empty.update(b"");
+        assert_eq!(empty.finish(), 0xCBF2_9CE4_8422_2325);
+        empty.update(b"a");
+        assert_eq!(empty.finish(), 0xAF63_DC4C_8601_EC8C);
+
+        let events = [
+            Event::new(EventKind::L2, Side::Buy, fixed!(99.5), fixed!(3), Timestamp::from_nanos(10)).with_sequence_id(1),
+            Event::new(EventKind::L2, Side::Sell, fixed!(100), fixed!(2), Timestamp::from_nanos(11)).with_sequence_id(2),
+            Event::new(EventKind::L2, Side::Buy, fixed!(99), fixed!(7), Timestamp::from_nanos(12)).with_sequence_id(3),
+        ];
+        let mut btree = BTreeOrderBook::<FixedDecimal>::new();
+        let mut array = ArrayOrderbook::<300, FixedDecimal>::new();
+        for event in events {
+            btree.process(event);
+            array.process(event);
+        }
+        assert_eq!(btree.state_hash(), array.state_hash());
+        assert_ne!(btree.state_hash(), BTreeOrderBook::<FixedDecimal>::new().state_hash());
+
+        // The same levels a step later differ by the last update alone
+        let before = btree.state_hash();
+        btree.process(Event::new(EventKind::L2, Side::Buy, fixed!(99), fixed!(7), Timestamp::from_nanos(13)).with_sequence_id(4));
+        assert_ne!(btree.state_hash(), before);
+        array.process(Event::new(EventKind::L2, Side::Buy, fixed!(99), fixed!(7), Timestamp::from_nanos(13)).with_sequence_id(4));
+        assert_eq!(btree.state_hash(), array.state_hash());
+    }
+}
@@ -1,12 +1,66 @@
-use crate::{decimals::decimal_type::DecimalType, event::Event, level::Level, metrics::OrderbookMetrics};
+use std::fmt::Display;
+
+use crate::{
+    books::{delta::BookDelta, hash::StateHasher},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    level::Level,
+    metrics::{MetricsRequest, OrderbookMetrics},
+    side::Side,
+    timestamp::Timestamp,
+    validation::{EventError, Validated},
+};
 
 pub trait OrderBook<V: DecimalType> {
     /// Process an incoming event
-    fn process(&mut self, event: Event<V>);
+    #[inline]
+    fn process(&mut self, event: Event<V>) {
+        self.process_delta(event);
+    }
+    /// Process an incoming event, returning the level it changed or `None` when it was ignored
+    fn process_delta(&mut self, event: Event<V>) -> Option<BookDelta<V>>;
+    /// Process an event that already passed validation
+    #[inline]
+    fn process_validated(&mut self, event: Validated<V>) -> Option<BookDelta<V>> {
+        self.process_delta(event.into_inner())
+    }
+    /// Validate an event and process it, leaving the book untouched when it is rejected
+    #[inline]
+    fn process_checked(&mut self, event: Event<V>) -> Result<Option<BookDelta<V>>, EventError>
+    where
+        V: PartialOrd,
+    {
+        Ok(self.process_validated(Validated::new(event)?))
+    }
     /// Get the current best bid
     fn best_bid(&mut self) -> Option<Level<V>>;
     /// Get the current best ask
     fn best_ask(&mut self) -> Option<Level<V>>;
+    /// Collect up to `depth` populated levels of one side, best price first
+    fn levels(&self, side: Side, depth: usize) -> Vec<Level<V>>;
+    /// Timestamp of the last update applied
+    fn timestamp(&self) -> Timestamp;
+    /// Sequence ID of the last update applied
+    fn sequence_id(&self) -> u64;
+    /// Stable 64-bit hash of the populated levels and the last update, equal across runs, platforms and
+    /// book implementations holding the same state, see [`StateHasher`]
+    #[must_use]
+    fn state_hash(&self) -> u64
+    where
+        V: Display,
+    {
+        let mut hasher = StateHasher::new();
+        for side in [Side::Buy, Side::Sell] {
+            hasher.levels(side, &self.levels(side, usize::MAX));
+        }
+        hasher.last_update(self.timestamp(), self.sequence_id());
+        hasher.finish()
+    }
     /// Calculate orderbook metrics up to specified depth
-    fn calculate_metrics(&self, depth: usize) -> OrderbookMetrics<V>;
+    #[inline]
+    fn calculate_metrics(&self, depth: usize) -> OrderbookMetrics<V> {
+        self.calculate_metrics_with(depth, MetricsRequest::all())
+    }
+    /// Calculate only the requested metrics up to specified depth, leaving the others as `None`
+    fn calculate_metrics_with(&self, depth: usize, request: MetricsRequest) -> OrderbookMetrics<V>;
 }
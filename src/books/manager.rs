@@ -0,0 +1,606 @@
+use std::{
+    collections::HashMap,
+    iter::Sum,
+    marker::PhantomData,
+    ops::Add,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, SyncSender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    formats::source::{EventSource, SourceError},
+    instrument::{InstrId, SymbolTable},
+    metrics::OrderbookMetrics,
+    pipeline::{LatencyHistogram, LatencyRecorder},
+};
+
+#[derive(Debug)]
+/// Owns one book per symbol, creating books on first use.
+///
+/// Symbols are interned into [`InstrId`]s and the books indexed by them, so a stream carrying many
+/// instruments is routed with [`Self::route`] without hashing a string per event. IDs stay assigned
+/// after a book is removed.
+pub struct BookManager<V, B>
+where
+    V: DecimalType,
+    B: OrderBook<V>,
+{
+    books: Vec<Option<B>>,
+    symbols: SymbolTable,
+    _decimal: PhantomData<V>,
+}
+
+impl<V, B> Default for BookManager<V, B>
+where
+    V: DecimalType,
+    B: OrderBook<V>,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V, B> BookManager<V, B>
+where
+    V: DecimalType,
+    B: OrderBook<V>,
+{
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_symbols(SymbolTable::new())
+    }
+
+    #[inline]
+    #[must_use]
+    /// A manager routing by the IDs of an existing table, such as the one a decoder interned into
+    pub fn with_symbols(symbols: SymbolTable) -> Self {
+        Self { books: Vec::new(), symbols, _decimal: PhantomData }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn symbol_table(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    #[inline]
+    /// The ID events for `symbol` should carry, assigning one if it is new
+    pub fn intern(&mut self, symbol: &str) -> InstrId {
+        self.symbols.intern(symbol)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn book(&self, symbol: &str) -> Option<&B> {
+        self.symbols.get(symbol).and_then(|id| self.book_by_id(id))
+    }
+
+    #[inline]
+    pub fn book_mut(&mut self, symbol: &str) -> Option<&mut B> {
+        self.symbols.get(symbol).and_then(|id| self.book_by_id_mut(id))
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn book_by_id(&self, id: InstrId) -> Option<&B> {
+        self.books.get(id.index()).and_then(Option::as_ref)
+    }
+
+    #[inline]
+    pub fn book_by_id_mut(&mut self, id: InstrId) -> Option<&mut B> {
+        self.books.get_mut(id.index()).and_then(Option::as_mut)
+    }
+
+    /// Register a book for `symbol`, returning the one it replaces
+    pub fn insert(&mut self, symbol: impl Into<String>, book: B) -> Option<B> {
+        let id = self.symbols.intern(&symbol.into());
+        self.slot(id).replace(book)
+    }
+
+    #[inline]
+    pub fn remove(&mut self, symbol: &str) -> Option<B> {
+        let id = self.symbols.get(symbol)?;
+        self.books.get_mut(id.index()).and_then(Option::take)
+    }
+
+    #[inline]
+    /// Symbols that have a book
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.symbols.iter().filter(|&(id, _)| self.book_by_id(id).is_some()).map(|(_, symbol)| symbol)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.books.iter().flatten().count()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    fn slot(&mut self, id: InstrId) -> &mut Option<B> {
+        if self.books.len() <= id.index() {
+            self.books.resize_with(id.index() + 1, || None);
+        }
+        &mut self.books[id.index()]
+    }
+}
+
+impl<V, B> BookManager<V, B>
+where
+    V: DecimalType + Send,
+    B: OrderBook<V> + Sync,
+{
+    /// Metrics up to `depth` of every book, keyed by symbol. The books are split across the available
+    /// cores, so a large universe is swept in a fraction of the serial time.
+    pub fn calculate_metrics_all(&self, depth: usize) -> HashMap<String, OrderbookMetrics<V>> {
+        let books = self.books.iter().enumerate().filter_map(|(index, book)| Some((InstrId(index as u32), book.as_ref()?)));
+        let books = books.collect::<Vec<_>>();
+        let workers = thread::available_parallelism().map_or(1, usize::from).min(books.len()).max(1);
+        let sweep =
+            |chunk: &[(InstrId, &B)]| chunk.iter().map(|&(id, book)| (id, book.calculate_metrics(depth))).collect::<Vec<_>>();
+        let metrics = if workers == 1 {
+            sweep(&books)
+        } else {
+            thread::scope(|scope| {
+                let chunks = books.chunks(books.len().div_ceil(workers)).map(|chunk| scope.spawn(move || sweep(chunk)));
+                chunks
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .flat_map(|worker| worker.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                    .collect()
+            })
+        };
+        metrics.into_iter().filter_map(|(id, metrics)| Some((self.symbols.name(id)?.to_owned(), metrics))).collect()
+    }
+}
+
+impl<V, B> BookManager<V, B>
+where
+    V: DecimalType,
+    B: OrderBook<V> + Default,
+{
+    #[inline]
+    /// Get the book for `symbol`, creating an empty one if it is not managed yet
+    pub fn book_or_default(&mut self, symbol: &str) -> &mut B {
+        let id = self.symbols.intern(symbol);
+        self.slot(id).get_or_insert_with(B::default)
+    }
+
+    #[inline]
+    /// Apply `event` to the book of its instrument, creating the book if the instrument is interned but
+    /// has none yet. Events for IDs the table never assigned are ignored.
+    pub fn route(&mut self, event: Event<V>) -> Option<BookDelta<V>> {
+        if event.instrument.index() >= self.symbols.len() {
+            return None;
+        }
+        self.slot(event.instrument).get_or_insert_with(B::default).process_delta(event)
+    }
+
+    /// Route every event of `source`, returning how many were read. Stops at the first error, leaving
+    /// the events before it applied.
+    pub fn replay(&mut self, source: impl EventSource<V>) -> Result<usize, SourceError> {
+        let mut count = 0;
+        for event in source {
+            self.route(event?);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl<V, B> BookManager<V, B>
+where
+    V: DecimalType + Send + 'static,
+    B: OrderBook<V> + Default + Send + 'static,
+{
+    /// Hand the books to `shards` worker threads, instrument `id` going to shard `id % shards`, each
+    /// queueing up to `capacity` events. Symbols must be interned before sharding.
+    pub fn into_sharded(self, shards: usize, capacity: usize) -> ShardedBookManager<V, B> {
+        self.spawn_shards(shards.max(1), capacity)
+    }
+
+    #[cfg(all(feature = "affinity", target_os = "linux"))]
+    /// Like [`into_sharded`](Self::into_sharded) with a shard per entry of `cores`, each pinned to
+    /// that CPU, see [`pin_thread`](crate::pipeline::pin_thread). A shard that cannot be pinned runs
+    /// unpinned, see [`ShardedBookManager::pin_errors`].
+    pub fn into_pinned_shards(self, cores: &[usize], capacity: usize) -> ShardedBookManager<V, B> {
+        let mut sharded = self.spawn_shards(cores.len().max(1), capacity);
+        for (shard, &core) in sharded.shards.iter_mut().zip(cores) {
+            shard.pin_error = crate::pipeline::pin_thread(&shard.handle, core).err();
+        }
+        sharded
+    }
+
+    fn spawn_shards(self, shards: usize, capacity: usize) -> ShardedBookManager<V, B> {
+        let mut parts = (0..shards).map(|_| Self::with_symbols(self.symbols.clone())).collect::<Vec<_>>();
+        for (index, book) in self.books.into_iter().enumerate() {
+            if let Some(book) = book {
+                *parts[index % shards].slot(InstrId(index as u32)) = Some(book);
+            }
+        }
+        let shards = parts
+            .into_iter()
+            .map(|mut manager| {
+                let (sender, receiver) = mpsc::sync_channel::<ShardMessage<V, B>>(capacity);
+                let counters = Arc::new(ShardCounters::default());
+                let shard_counters = Arc::clone(&counters);
+                let handle = thread::spawn(move || {
+                    for message in receiver {
+                        match message {
+                            ShardMessage::Event(event) => {
+                                shard_counters.events.fetch_add(1, Ordering::Relaxed);
+                                let start = Instant::now();
+                                let applied = manager.route(event).is_some();
+                                shard_counters.latency.record(start.elapsed());
+                                // Pairs with the load in `stats`, so a reader seeing this event applied also sees it received
+                                shard_counters.applied.fetch_add(u64::from(applied), Ordering::Release);
+                            }
+                            ShardMessage::Query(query) => query(&manager),
+                        }
+                    }
+                    manager
+                });
+                Shard {
+                    sender,
+                    counters,
+                    handle,
+                    #[cfg(all(feature = "affinity", target_os = "linux"))]
+                    pin_error: None,
+                }
+            })
+            .collect();
+        ShardedBookManager { symbols: self.symbols, shards }
+    }
+}
+
+#[cfg(feature = "feeds")]
+impl<V, B> BookManager<V, B>
+where
+    V: DecimalType,
+    B: OrderBook<V> + Default,
+{
+    /// Parse a raw venue message with `adapter` and apply the resulting events to the adapter's book.
+    ///
+    /// Returns the number of events applied. Errors leave earlier events of the message applied, and
+    /// [`ExchangeAdapter::needs_snapshot`] tells whether the subscription must be restarted. While it
+    /// does, the book is cleared rather than left with levels from before the gap, and updates are
+    /// refused with [`FeedError::MissingSnapshot`] until the venue sends a snapshot.
+    ///
+    /// [`ExchangeAdapter::needs_snapshot`]: crate::feeds::adapter::ExchangeAdapter::needs_snapshot
+    /// [`FeedError::MissingSnapshot`]: crate::feeds::FeedError::MissingSnapshot
+    pub fn feed<A>(&mut self, adapter: &mut A, message: &[u8]) -> Result<usize, crate::feeds::FeedError>
+    where
+        A: crate::feeds::adapter::ExchangeAdapter<V> + ?Sized,
+    {
+        let events = adapter.parse_message(message).inspect_err(|_| self.resync(adapter))?;
+        if adapter.needs_snapshot() && !events.is_empty() {
+            self.resync(adapter);
+            return Err(crate::feeds::FeedError::MissingSnapshot);
+        }
+        let count = events.len();
+        let book = self.book_or_default(adapter.symbol());
+        for event in events {
+            book.process(event);
+        }
+        adapter.verify_book(book).inspect_err(|_| self.resync(adapter))?;
+        Ok(count)
+    }
+
+    /// Clear the book of `adapter` when the adapter lost sync with the venue
+    fn resync<A>(&mut self, adapter: &A)
+    where
+        A: crate::feeds::adapter::ExchangeAdapter<V> + ?Sized,
+    {
+        if !adapter.needs_snapshot() {
+            return;
+        }
+        if let Some(book) = self.book_mut(adapter.symbol()) {
+            let ts = book.timestamp();
+            book.process(Event::clear(ts));
+        }
+    }
+}
+
+type Query<V, B> = Box<dyn FnOnce(&BookManager<V, B>) + Send>;
+
+enum ShardMessage<V: DecimalType, B: OrderBook<V>> {
+    Event(Event<V>),
+    Query(Query<V, B>),
+}
+
+#[derive(Debug, Default)]
+struct ShardCounters {
+    events: AtomicU64,
+    applied: AtomicU64,
+    latency: LatencyRecorder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ShardStats {
+    /// Events the shard received
+    pub events: u64,
+    /// Events a book accepted
+    pub applied: u64,
+}
+
+impl ShardStats {
+    #[inline]
+    #[must_use]
+    /// Events the books ignored as stale, out of sequence or not applicable
+    pub const fn ignored(&self) -> u64 {
+        self.events.saturating_sub(self.applied)
+    }
+}
+
+impl Add for ShardStats {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, other: Self) -> Self {
+        Self { events: self.events + other.events, applied: self.applied + other.applied }
+    }
+}
+
+impl Sum for ShardStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+struct Shard<V: DecimalType, B: OrderBook<V>> {
+    sender: SyncSender<ShardMessage<V, B>>,
+    counters: Arc<ShardCounters>,
+    handle: JoinHandle<BookManager<V, B>>,
+    /// Why the shard could not be pinned to its core
+    #[cfg(all(feature = "affinity", target_os = "linux"))]
+    pin_error: Option<std::io::Error>,
+}
+
+/// A [`BookManager`] whose books are spread over worker threads, see [`BookManager::into_sharded`].
+///
+/// Events are queued to the shard owning their instrument and applied in the order they were routed,
+/// so books on different shards update in parallel while each book sees its events in order.
+pub struct ShardedBookManager<V: DecimalType, B: OrderBook<V>> {
+    symbols: SymbolTable,
+    shards: Vec<Shard<V, B>>,
+}
+
+impl<V, B> ShardedBookManager<V, B>
+where
+    V: DecimalType + Send + 'static,
+    B: OrderBook<V> + Default + Send + 'static,
+{
+    #[inline]
+    #[must_use]
+    pub const fn symbol_table(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    #[cfg(all(feature = "affinity", target_os = "linux"))]
+    #[must_use]
+    /// Shards running unpinned despite [`BookManager::into_pinned_shards`], by index, with the reason
+    pub fn pin_errors(&self) -> Vec<(usize, &std::io::Error)> {
+        self.shards.iter().enumerate().filter_map(|(index, shard)| Some((index, shard.pin_error.as_ref()?))).collect()
+    }
+
+    #[inline]
+    fn shard(&self, id: InstrId) -> &Shard<V, B> {
+        &self.shards[id.index() % self.shards.len()]
+    }
+
+    /// Queue `event` to the shard of its instrument, waiting while that queue is full. Returns false,
+    /// dropping the event, for IDs the table never assigned.
+    ///
+    /// # Panics
+    /// If the shard's thread panicked applying an earlier event.
+    pub fn route(&self, event: Event<V>) -> bool {
+        if event.instrument.index() >= self.symbols.len() {
+            return false;
+        }
+        self.shard(event.instrument).sender.send(ShardMessage::Event(event)).expect("shard thread panicked");
+        true
+    }
+
+    /// Route every event of `source`, returning how many were read. Stops at the first error, leaving
+    /// the events before it routed.
+    pub fn replay(&self, source: impl EventSource<V>) -> Result<usize, SourceError> {
+        let mut count = 0;
+        for event in source {
+            self.route(event?);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Run `read` on the book of `symbol` on its shard, once the events routed before have been applied.
+    /// Returns `None` when the symbol has no book.
+    ///
+    /// # Panics
+    /// If the shard's thread panicked applying an earlier event.
+    pub fn query<R: Send + 'static>(&self, symbol: &str, read: impl FnOnce(&B) -> R + Send + 'static) -> Option<R> {
+        let id = self.symbols.get(symbol)?;
+        let (reply, answer) = mpsc::sync_channel(1);
+        let query = Box::new(move |manager: &BookManager<V, B>| {
+            let _ = reply.send(manager.book_by_id(id).map(read));
+        });
+        self.shard(id).sender.send(ShardMessage::Query(query)).expect("shard thread panicked");
+        answer.recv().expect("shard thread panicked")
+    }
+
+    #[must_use]
+    /// Counts of each shard so far
+    pub fn stats(&self) -> Vec<ShardStats> {
+        let stats = |counters: &ShardCounters| {
+            // Applied before received, so the worker cannot apply an event the snapshot has not counted
+            let applied = counters.applied.load(Ordering::Acquire);
+            ShardStats { events: counters.events.load(Ordering::Relaxed), applied }
+        };
+        self.shards.iter().map(|shard| stats(&shard.counters)).collect()
+    }
+
+    #[must_use]
+    /// Time each shard has taken to apply each event so far
+    pub fn latencies(&self) -> Vec<LatencyHistogram> {
+        self.shards.iter().map(|shard| shard.counters.latency.histogram()).collect()
+    }
+
+    #[must_use]
+    /// Counts of every shard added together
+    pub fn total_stats(&self) -> ShardStats {
+        self.stats().into_iter().sum()
+    }
+
+    #[must_use]
+    /// Wait for every shard to apply its queue, then gather the books back into one manager
+    pub fn into_manager(self) -> BookManager<V, B> {
+        let mut manager = BookManager::with_symbols(self.symbols);
+        for Shard { sender, handle, .. } in self.shards {
+            drop(sender);
+            let shard = handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            for (index, book) in shard.books.into_iter().enumerate() {
+                if let Some(book) = book {
+                    *manager.slot(InstrId(index as u32)) = Some(book);
+                }
+            }
+        }
+        manager
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{
+            array_orderbook::ArrayOrderbook,
+            interface::OrderBook,
+            manager::{BookManager, ShardStats},
+        },
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        instrument::InstrId,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_routes_by_instrument() {
+        let mut manager = BookManager::<FixedDecimal, ArrayOrderbook<8, FixedDecimal>>::new();
+        let (btc, eth) = (manager.intern("BTC-USD"), manager.intern("ETH-USD"));
+        let bid = |price, instrument| {
+            Event::new(EventKind::L2, Side::Buy, price, fixed!(1), Timestamp::from_nanos(1)).with_instrument(instrument)
+        };
+        assert!(manager.route(bid(fixed!(100), btc)).is_some());
+        assert!(manager.route(bid(fixed!(20), eth)).is_some());
+        assert!(manager.route(bid(fixed!(5), InstrId(9))).is_none());
+
+        assert_eq!(manager.len(), 2);
+        assert_eq!(manager.book_mut("ETH-USD").and_then(|book| book.best_bid()).map(|level| level.price), Some(fixed!(20)));
+        assert!(manager.remove("BTC-USD").is_some());
+        assert_eq!(manager.symbols().collect::<Vec<_>>(), ["ETH-USD"]);
+        // The ID outlives its book
+        assert_eq!(manager.intern("BTC-USD"), btc);
+    }
+
+    #[test]
+    fn test_metrics_all() {
+        let mut manager = BookManager::<FixedDecimal, ArrayOrderbook<8, FixedDecimal>>::new();
+        for i in 1..=40 {
+            let book = manager.book_or_default(&format!("SYM{i}"));
+            book.process(Event::new(EventKind::L2, Side::Buy, FixedDecimal::from_int(i), fixed!(1), Timestamp::from_nanos(1)));
+            book.process(Event::new(
+                EventKind::L2,
+                Side::Sell,
+                FixedDecimal::from_int(i + 2),
+                fixed!(1),
+                Timestamp::from_nanos(1),
+            ));
+        }
+        manager.remove("SYM7");
+        let metrics = manager.calculate_metrics_all(5);
+        assert_eq!(metrics.len(), 39);
+        assert!(!metrics.contains_key("SYM7"));
+        assert_eq!(metrics["SYM31"].mid_price, Some(fixed!(32)));
+        assert!(BookManager::<FixedDecimal, ArrayOrderbook<8, FixedDecimal>>::new().calculate_metrics_all(5).is_empty());
+    }
+
+    #[test]
+    fn test_sharded() {
+        let mut manager = BookManager::<FixedDecimal, ArrayOrderbook<8, FixedDecimal>>::new();
+        let ids = (0..10).map(|i| manager.intern(&format!("SYM{i}"))).collect::<Vec<_>>();
+        let sharded = manager.into_sharded(3, 16);
+        for round in 1..=50 {
+            for &id in &ids {
+                let price = FixedDecimal::from_int(i64::from(id.0) * 100 + round);
+                assert!(sharded.route(
+                    Event::new(EventKind::L2, Side::Buy, price, fixed!(1), Timestamp::from_nanos(round)).with_instrument(id)
+                ));
+            }
+        }
+        // Stale, so ignored by the book
+        sharded.route(Event::new(EventKind::L2, Side::Buy, fixed!(1), fixed!(1), Timestamp::ZERO).with_instrument(ids[4]));
+        assert!(!sharded.route(
+            Event::new(EventKind::L2, Side::Buy, fixed!(1), fixed!(1), Timestamp::from_nanos(60)).with_instrument(InstrId(99))
+        ));
+
+        let best = sharded.query("SYM4", |book| book.levels(Side::Buy, 1)[0].price);
+        assert_eq!(best, Some(fixed!(450)));
+        assert_eq!(sharded.query("SYM99", |_| ()), None);
+        let stats = sharded.stats();
+        assert_eq!(stats.iter().map(|shard| shard.events).collect::<Vec<_>>(), [200, 151, 150]);
+        assert_eq!((sharded.total_stats().applied, sharded.total_stats().ignored()), (500, 1));
+        assert_eq!(sharded.latencies().iter().map(|latency| latency.count()).sum::<u64>(), 501);
+        assert_eq!(ShardStats { events: 1, applied: 2 }.ignored(), 0);
+
+        let mut manager = sharded.into_manager();
+        assert_eq!(manager.len(), 10);
+        assert_eq!(manager.book_mut("SYM9").and_then(|book| book.best_bid()).map(|level| level.price), Some(fixed!(950)));
+    }
+
+    #[cfg(all(feature = "affinity", target_os = "linux"))]
+    #[test]
+    fn test_pinned_shards() {
+        // The core the test runs on is one the process may use
+        let core = unsafe { libc::sched_getcpu() } as usize;
+        let mut manager = BookManager::<FixedDecimal, ArrayOrderbook<8, FixedDecimal>>::new();
+        let btc = manager.intern("BTC-USD");
+        let sharded = manager.into_pinned_shards(&[core, core], 16);
+        assert_eq!(sharded.shards(), 2);
+        assert!(sharded.pin_errors().is_empty());
+        sharded
+            .route(Event::new(EventKind::L2, Side::Sell, fixed!(101), fixed!(1), Timestamp::from_nanos(1)).with_instrument(btc));
+        assert_eq!(sharded.query("BTC-USD", |book| book.levels(Side::Sell, 1).len()), Some(1));
+
+        // A core beyond the CPU set leaves its shard running unpinned, with the books still in reach
+        let sharded = sharded.into_manager().into_pinned_shards(&[core, 1 << 20], 16);
+        let errors = sharded.pin_errors();
+        assert_eq!(
+            errors.iter().map(|(shard, err)| (*shard, err.kind())).collect::<Vec<_>>(),
+            [(1, std::io::ErrorKind::InvalidInput)]
+        );
+        assert_eq!(sharded.query("BTC-USD", |book| book.levels(Side::Sell, 1).len()), Some(1));
+    }
+}
@@ -1,3 +1,6 @@
 pub mod array_orderbook;
 pub mod btree_orderbook;
+pub mod delta;
+pub mod hash;
 pub mod interface;
+pub mod manager;
@@ -1,102 +1,343 @@
-use std::{cmp::Ordering, mem::MaybeUninit, ptr};
+use std::{cmp::Ordering, ops::Range};
+#[cfg(not(feature = "safe"))]
+use std::{mem::MaybeUninit, ptr};
 
-use crate::{decimals::decimal_type::DecimalType, level::Level};
+use crate::{
+    buffers::storage::{InsertResult, LevelStorage},
+    decimals::decimal_type::DecimalType,
+    level::Level,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Occupancy counters of an [`OrderedBuffer`] since it was created, for sizing its capacity
+pub struct BufferStats {
+    /// Most levels held at once
+    pub high_watermark: usize,
+    /// Levels stored by inserts and bulk loads
+    pub inserts: u64,
+    /// Levels taken out by [`OrderedBuffer::remove`]
+    pub removes: u64,
+    /// Levels lost for lack of capacity, pushed off the end or never stored
+    pub dropped: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// How an [`OrderedBuffer`] searches its levels for a price
+pub enum SearchStrategy {
+    #[default]
+    /// Binary search, branchless once the buffer is large enough to benefit
+    Binary,
+    /// Guess the position from where the price falls between the neighbouring levels, which suits
+    /// ladders quoted at most ticks. Finishes with a binary search when the guesses miss.
+    Interpolation,
+    /// Interpolation once the buffer holds enough levels to repay it and they look evenly spaced,
+    /// binary search otherwise
+    Adaptive,
+}
 
 #[derive(Debug, Clone)]
-pub struct Buffer<const N: usize, V: DecimalType> {
+/// A fixed-capacity run of levels sorted best price first, descending for bids and ascending for asks.
+///
+/// Slots past `len` hold a bound sentinel, the lowest price for a descending buffer and the highest
+/// for an ascending one, so that the worst end of the buffer always compares as beyond any real price.
+pub struct OrderedBuffer<const N: usize, V: DecimalType> {
     buf: Box<[Level<V>; N]>,
-    limit: V,
+    descending: bool,
     /// Track actual number of valid levels
-    pub len: usize,
+    len: usize,
     /// Cache the first level for fast access
     cached_first: Option<Level<V>>,
+    stats: BufferStats,
+    search: SearchStrategy,
 }
 
-impl<const N: usize, V> Buffer<N, V>
+impl<const N: usize, V> OrderedBuffer<N, V>
 where
     V: DecimalType + PartialOrd + Copy + Ord,
 {
+    const ADAPTIVE_INTERPOLATION_LEN: usize = 128;
+    const MAX_INTERPOLATION_PROBES: usize = 3;
+    const MIN_INTERPOLATION_SPAN: usize = 8;
+    const UNIFORM_TOLERANCE: f64 = 0.1;
+
     #[inline]
     #[must_use]
-    pub fn new(is_bid: bool) -> Self {
+    /// A buffer for one side of the book, `descending` for bids
+    pub fn new(descending: bool) -> Self {
+        let bound = Level::bound(descending);
+        // Built on the heap, a large array would not fit on the stack
+        #[cfg(feature = "safe")]
+        let buf = vec![bound; N].into_boxed_slice().try_into().unwrap_or_else(|_| unreachable!("the vector holds N levels"));
+        #[cfg(not(feature = "safe"))]
         let buf = unsafe {
             let mut buf = Box::new(MaybeUninit::<[Level<V>; N]>::uninit());
-            let bound = Level::bound(is_bid);
             for i in 0..N {
                 ptr::addr_of_mut!((*buf.as_mut_ptr())[i]).write(bound);
             }
             buf.assume_init()
         };
 
-        Self { buf, limit: if is_bid { V::MIN } else { V::MAX }, len: 0, cached_first: None }
+        Self { buf, descending, len: 0, cached_first: None, stats: BufferStats::default(), search: SearchStrategy::Binary }
+    }
+
+    #[inline]
+    // An empty buffer over a recycled backing array, see `BufferPool`
+    pub(crate) fn with_storage(mut buf: Box<[Level<V>; N]>, descending: bool) -> Self {
+        buf.fill(Level::bound(descending));
+        Self { buf, descending, len: 0, cached_first: None, stats: BufferStats::default(), search: SearchStrategy::Binary }
+    }
+
+    #[inline]
+    pub(crate) fn into_storage(self) -> Box<[Level<V>; N]> {
+        self.buf
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn is_descending(&self) -> bool {
+        self.descending
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn with_search(mut self, search: SearchStrategy) -> Self {
+        self.search = search;
+        self
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn search(&self) -> SearchStrategy {
+        self.search
+    }
+
+    #[inline]
+    pub fn set_search(&mut self, search: SearchStrategy) {
+        self.search = search;
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn stats(&self) -> BufferStats {
+        self.stats
     }
 
     #[inline(always)]
-    unsafe fn invalidate_cache(&mut self) {
+    fn limit(&self) -> V {
+        if self.descending {
+            V::MIN
+        } else {
+            V::MAX
+        }
+    }
+
+    // Whether `a.cmp(b)` returning `ordering` puts `a` ahead of `b` in this buffer's order
+    #[inline(always)]
+    fn is_ahead(&self, ordering: Ordering) -> bool {
+        if self.descending {
+            ordering == Ordering::Greater
+        } else {
+            ordering == Ordering::Less
+        }
+    }
+
+    #[inline(always)]
+    fn invalidate_cache(&mut self) {
         self.cached_first = if self.len > 0 {
-            let first = self.get_unchecked(0);
-            (first.price != self.limit).then_some(*first)
+            let first = self.at(0);
+            (first.price != self.limit()).then_some(*first)
         } else {
             None
         };
     }
 
+    // The slot at `index`, which every caller keeps below `N`. Bounds-checked under the `safe` feature.
+    #[inline(always)]
+    fn at(&self, index: usize) -> &Level<V> {
+        #[cfg(feature = "safe")]
+        return &self.buf[index];
+        #[cfg(not(feature = "safe"))]
+        unsafe {
+            self.buf.get_unchecked(index)
+        }
+    }
+
+    #[inline(always)]
+    fn at_mut(&mut self, index: usize) -> &mut Level<V> {
+        #[cfg(feature = "safe")]
+        return &mut self.buf[index];
+        #[cfg(not(feature = "safe"))]
+        unsafe {
+            self.buf.get_unchecked_mut(index)
+        }
+    }
+
+    // Move the slots of `source` to start at `destination`, which may overlap
+    #[inline(always)]
+    fn shift(&mut self, source: Range<usize>, destination: usize) {
+        #[cfg(feature = "safe")]
+        self.buf.copy_within(source, destination);
+        #[cfg(not(feature = "safe"))]
+        unsafe {
+            ptr::copy(self.buf.as_ptr().add(source.start), self.buf.as_mut_ptr().add(destination), source.len());
+        }
+    }
+
     #[inline(always)]
     #[must_use]
     /// # Safety
     /// `index` must be less than `self.len`
     pub unsafe fn get_unchecked(&self, index: usize) -> &Level<V> {
-        self.buf.get_unchecked(index)
+        self.at(index)
     }
 
     #[inline(always)]
     /// # Safety
     /// `index` must be less than `self.len`
     pub unsafe fn get_unchecked_mut(&mut self, index: usize) -> &mut Level<V> {
-        self.buf.get_unchecked_mut(index)
+        self.at_mut(index)
+    }
+
+    #[inline(always)]
+    #[must_use]
+    /// The populated levels, best price first
+    pub fn as_slice(&self) -> &[Level<V>] {
+        &self.buf[..self.len]
+    }
+
+    #[inline(always)]
+    #[must_use]
+    /// The populated levels within `range`, clamped to the populated levels so it never panics
+    pub fn range(&self, range: Range<usize>) -> &[Level<V>] {
+        let end = range.end.min(self.len);
+        &self.buf[range.start.min(end)..end]
+    }
+
+    #[inline(always)]
+    #[must_use]
+    /// The best `n` levels, or all of them when there are fewer
+    pub fn top(&self, n: usize) -> &[Level<V>] {
+        &self.buf[..n.min(self.len)]
+    }
+
+    #[inline(always)]
+    #[must_use]
+    /// The level at `index`, `None` past the populated levels
+    pub fn get(&self, index: usize) -> Option<&Level<V>> {
+        self.as_slice().get(index)
     }
 
     #[inline(always)]
+    #[must_use]
+    /// The worst populated level
+    pub fn last(&self) -> Option<&Level<V>> {
+        self.as_slice().last()
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> std::slice::Iter<'_, Level<V>> {
+        self.as_slice().iter()
+    }
+
+    #[inline(always)]
+    /// Mutable access to the populated levels. Prices must keep their order, and a change to the first
+    /// level is only reflected by [`Self::first`] after the next insert, remove or modify.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Level<V>> {
+        self.buf[..self.len].iter_mut()
+    }
+
+    #[inline]
+    /// Take every level out best price first, leaving the buffer empty once the iterator is dropped
+    pub fn drain(&mut self) -> Drain<'_, N, V> {
+        Drain { buffer: self, index: 0 }
+    }
+
+    #[inline]
+    /// Remove every level, restoring the bound sentinels
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    #[inline]
+    /// Keep the best `len` levels and drop the rest, a no-op when there are fewer
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        self.buf[len..self.len].fill(Level::bound(self.descending));
+        self.len = len;
+        self.invalidate_cache();
+    }
+
+    #[inline(always)]
+    /// Append `levels` verbatim after the current ones. The caller must pass them in this buffer's
+    /// order and beyond its worst price, see [`Self::bulk_load`] otherwise.
     pub fn bulk_insert(&mut self, levels: &[Level<V>]) {
         let available_space = N - self.len;
         let insert_count = levels.len().min(available_space);
 
+        self.stats.dropped += (levels.len() - insert_count) as u64;
         if insert_count > 0 {
-            unsafe {
-                std::ptr::copy_nonoverlapping(levels.as_ptr(), self.buf.as_mut_ptr().add(self.len), insert_count);
-                self.len += insert_count;
-                self.invalidate_cache();
-            }
+            self.buf[self.len..self.len + insert_count].copy_from_slice(&levels[..insert_count]);
+            self.len += insert_count;
+            self.invalidate_cache();
+            self.record_insert(insert_count);
         }
     }
 
+    /// Replace the contents with `levels` in any order, such as those of a snapshot.
+    ///
+    /// The levels are sorted into this buffer's order, a price given more than once keeps its last
+    /// level, and only the best `N` are kept.
+    pub fn bulk_load(&mut self, levels: &[Level<V>]) {
+        // Reversed so the stable sort and dedup keep the latest level of each price
+        let mut sorted: Vec<Level<V>> = levels.iter().rev().copied().collect();
+        if self.descending {
+            sorted.sort_by_key(|level| std::cmp::Reverse(level.price));
+        } else {
+            sorted.sort_by_key(|level| level.price);
+        }
+        sorted.dedup_by_key(|level| level.price);
+        self.stats.dropped += sorted.len().saturating_sub(N) as u64;
+        sorted.truncate(N);
+
+        self.buf[..sorted.len()].copy_from_slice(&sorted);
+        self.buf[sorted.len()..self.len.max(sorted.len())].fill(Level::bound(self.descending));
+        self.len = sorted.len();
+        self.invalidate_cache();
+        self.record_insert(sorted.len());
+    }
+
     #[inline(always)]
-    pub fn find_index(&self, price: V, is_bid: bool) -> Result<usize, usize> {
+    fn record_insert(&mut self, count: usize) {
+        self.stats.inserts += count as u64;
+        self.stats.high_watermark = self.stats.high_watermark.max(self.len);
+    }
+
+    #[inline(always)]
+    /// Position of `price`, or `Err` with the index it would be inserted at
+    pub fn find_index(&self, price: V) -> Result<usize, usize> {
         // Fast path for empty buffer
         if self.len == 0 {
             return Err(0);
         }
         // Fast path for beyond bounds
-        unsafe {
-            if is_bid {
-                if price > self.get_unchecked(0).price {
-                    return Err(0);
-                }
-                if price < self.get_unchecked(self.len - 1).price {
-                    return Err(self.len);
-                }
-            } else {
-                if price < self.get_unchecked(0).price {
-                    return Err(0);
-                }
-                if price > self.get_unchecked(self.len - 1).price {
-                    return Err(self.len);
-                }
+        if self.is_ahead(price.cmp(&self.at(0).price)) {
+            return Err(0);
+        }
+        if self.is_ahead(self.at(self.len - 1).price.cmp(&price)) {
+            return Err(self.len);
+        }
+        match self.search {
+            SearchStrategy::Interpolation => return self.interpolation_search(price),
+            SearchStrategy::Adaptive if self.len >= Self::ADAPTIVE_INTERPOLATION_LEN && self.looks_uniform() => {
+                return self.interpolation_search(price)
             }
+            _ => {}
         }
         // Use SIMD-friendly binary search for larger ranges
         if self.len >= 32 {
-            return self.branchless_binary_search(price, is_bid);
+            return self.branchless_binary_search(price, 0, self.len);
         }
         // Regular binary search for small ranges
         let mut left = 0;
@@ -104,43 +345,67 @@ where
 
         while left < right {
             let mid = left + (right - left) / 2;
-            unsafe {
-                let level_price = self.get_unchecked(mid).price;
-                match price.cmp(&level_price) {
-                    Ordering::Equal => return Ok(mid),
-                    Ordering::Greater if is_bid => right = mid,
-                    Ordering::Less if !is_bid => right = mid,
-                    Ordering::Less | Ordering::Greater => left = mid + 1,
-                }
+            match price.cmp(&self.at(mid).price) {
+                Ordering::Equal => return Ok(mid),
+                ordering if self.is_ahead(ordering) => right = mid,
+                _ => left = mid + 1,
             }
         }
 
         Err(left)
     }
 
+    // Whether the middle level sits near halfway between the first and last prices, as it does when
+    // most ticks are quoted
     #[inline(always)]
-    fn branchless_binary_search(&self, price: V, is_bid: bool) -> Result<usize, usize> {
-        let mut size = self.len;
-        let mut left = 0;
+    fn looks_uniform(&self) -> bool {
+        let (first, middle, last) =
+            (self.at(0).price.to_f64(), self.at(self.len / 2).price.to_f64(), self.at(self.len - 1).price.to_f64());
+        ((middle - first) / (last - first) - 0.5).abs() < Self::UNIFORM_TOLERANCE
+    }
+
+    // The price lies between the first and last levels, as checked by `find_index`
+    #[inline(always)]
+    fn interpolation_search(&self, price: V) -> Result<usize, usize> {
+        let target = price.to_f64();
+        let (mut left, mut right) = (0, self.len - 1);
+        for _ in 0..Self::MAX_INTERPOLATION_PROBES {
+            if right - left < Self::MIN_INTERPOLATION_SPAN {
+                break;
+            }
+            let (low, high) = (self.at(left).price.to_f64(), self.at(right).price.to_f64());
+            let fraction = (target - low) / (high - low);
+            // Also rejects the NaN of two levels converting to the same value
+            if !(0.0..=1.0).contains(&fraction) {
+                break;
+            }
+            let mid = left + ((right - left) as f64 * fraction) as usize;
+            match price.cmp(&self.at(mid).price) {
+                Ordering::Equal => return Ok(mid),
+                ordering if self.is_ahead(ordering) => right = mid,
+                _ => left = mid,
+            }
+        }
+        self.branchless_binary_search(price, left, right - left + 1)
+    }
 
+    #[inline(always)]
+    // Search the `size` levels from `left`, the first of which must not sort behind the price
+    fn branchless_binary_search(&self, price: V, mut left: usize, mut size: usize) -> Result<usize, usize> {
         while size > 1 {
             let half = size / 2;
             let mid = left + half;
 
-            unsafe {
-                let level_price = self.get_unchecked(mid).price;
-                let cmp = price.cmp(&level_price);
-                left = if (is_bid && cmp == Ordering::Less) || (!is_bid && cmp == Ordering::Greater) { mid } else { left };
-                size -= half;
-            }
+            // Move right while the level at mid sorts ahead of or at the price
+            let ordering = self.at(mid).price.cmp(&price);
+            left = if ordering == Ordering::Equal || self.is_ahead(ordering) { mid } else { left };
+            size -= half;
         }
 
-        unsafe {
-            if self.get_unchecked(left).price == price {
-                Ok(left)
-            } else {
-                Err(left + 1)
-            }
+        match self.at(left).price {
+            level_price if level_price == price => Ok(left),
+            level_price if self.is_ahead(price.cmp(&level_price)) => Err(left),
+            _ => Err(left + 1),
         }
     }
 
@@ -150,34 +415,47 @@ where
             return;
         }
 
-        unsafe {
-            if start >= self.len - 1 {
-                *self.get_unchecked_mut(self.len - 1) = Level::bound(self.limit == V::MIN);
-                self.len -= 1;
-                self.invalidate_cache();
-                return;
-            }
-            // Use ptr::copy for better performance
-            ptr::copy(self.buf.as_ptr().add(start + 1), self.buf.as_mut_ptr().add(start), self.len - start - 1);
-            *self.get_unchecked_mut(self.len - 1) = Level::bound(self.limit == V::MIN);
+        if start >= self.len - 1 {
+            *self.at_mut(self.len - 1) = Level::bound(self.descending);
             self.len -= 1;
+            self.invalidate_cache();
+            return;
+        }
+        self.shift(start + 1..self.len, start);
+        *self.at_mut(self.len - 1) = Level::bound(self.descending);
+        self.len -= 1;
 
-            if start == 0 {
-                self.invalidate_cache();
-            }
+        if start == 0 {
+            self.invalidate_cache();
         }
     }
 
     #[inline(always)]
-    pub fn remove(&mut self, index: usize) -> V {
-        unsafe {
-            let is_min = self.limit == V::MIN;
-            let level = self.get_unchecked_mut(index);
-            let removed = level.price;
-            *level = Level::bound(is_min);
-            self.move_back(index);
-            removed
+    #[must_use]
+    /// Number of levels held
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline(always)]
+    /// Remove the level at `index`, returning its price, or `None` when `index` is past the last level
+    pub fn remove(&mut self, index: usize) -> Option<V> {
+        if index >= self.len {
+            return None;
         }
+        let bound = Level::bound(self.descending);
+        let level = self.at_mut(index);
+        let removed = level.price;
+        *level = bound;
+        self.move_back(index);
+        self.stats.removes += 1;
+        Some(removed)
     }
 
     #[inline(always)]
@@ -187,46 +465,411 @@ where
     }
 
     #[inline(always)]
-    pub fn insert(&mut self, index: usize, level: Level<V>) {
-        if index >= N {
-            return;
+    /// Insert `level` at `index`, dropping the worst level when the buffer is full. Indices at or past
+    /// the capacity are ignored.
+    pub fn insert(&mut self, index: usize, level: Level<V>) -> InsertResult<V> {
+        if index >= N || index > self.len {
+            self.stats.dropped += 1;
+            return InsertResult::RejectedBeyondCapacity;
         }
 
-        unsafe {
-            match index {
-                // Fast path for empty buffer or append
-                i if i == self.len => {
-                    *self.get_unchecked_mut(self.len) = level;
-                    self.len += 1;
-                    self.invalidate_cache();
-                }
-                // Fast path for insert at beginning
-                0 => {
-                    std::ptr::copy(self.buf.as_ptr(), self.buf.as_mut_ptr().add(1), self.len);
-                    *self.get_unchecked_mut(0) = level;
-                    self.len = (self.len + 1).min(N);
-                    self.invalidate_cache();
-                }
-                // Regular insert
-                _ => {
-                    std::ptr::copy(self.buf.as_ptr().add(index), self.buf.as_mut_ptr().add(index + 1), self.len - index);
-                    *self.get_unchecked_mut(index) = level;
-                    self.len = (self.len + 1).min(N);
-                    if index == 0 {
-                        self.invalidate_cache();
-                    }
-                }
-            }
+        let result = if self.len == N { InsertResult::ReplacedTail(*self.at(N - 1)) } else { InsertResult::Inserted };
+        // A full buffer shifts one level fewer, the worst level falls off the end
+        let shifted = (self.len - index).min(N - 1 - index);
+        self.shift(index..index + shifted, index + 1);
+        *self.at_mut(index) = level;
+        self.len = (self.len + 1).min(N);
+        if index == 0 {
+            self.invalidate_cache();
         }
+        self.stats.dropped += u64::from(matches!(result, InsertResult::ReplacedTail(_)));
+        self.record_insert(1);
+        result
     }
 
     #[inline(always)]
-    pub fn modify(&mut self, index: usize, size: V) {
-        debug_assert!(index < self.len, "index out of bounds");
-        unsafe {
-            self.get_unchecked_mut(index).size = size;
-            if index == 0 {
-                self.invalidate_cache();
+    /// Replace the size of the level at `index`, returning the previous size, or `None` when `index` is
+    /// past the last level
+    pub fn modify(&mut self, index: usize, size: V) -> Option<V> {
+        if index >= self.len {
+            return None;
+        }
+        let previous = std::mem::replace(&mut self.at_mut(index).size, size);
+        if index == 0 {
+            self.invalidate_cache();
+        }
+        Some(previous)
+    }
+}
+
+impl<const N: usize, V> LevelStorage<V> for OrderedBuffer<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    #[inline]
+    fn new(descending: bool) -> Self {
+        Self::new(descending)
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    #[inline(always)]
+    fn get(&self, index: usize) -> Option<Level<V>> {
+        self.get(index).copied()
+    }
+
+    #[inline(always)]
+    fn find_index(&self, price: V) -> Result<usize, usize> {
+        self.find_index(price)
+    }
+
+    #[inline(always)]
+    fn insert(&mut self, index: usize, level: Level<V>) -> InsertResult<V> {
+        self.insert(index, level)
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, index: usize) -> V {
+        self.remove(index).unwrap_or_else(|| panic!("index {index} out of bounds"))
+    }
+
+    #[inline(always)]
+    fn modify(&mut self, index: usize, size: V) {
+        if self.modify(index, size).is_none() {
+            panic!("index {index} out of bounds");
+        }
+    }
+
+    #[inline(always)]
+    fn first(&self) -> Option<Level<V>> {
+        self.first()
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.clear();
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len);
+    }
+
+    #[inline]
+    fn iter(&self) -> impl Iterator<Item = Level<V>> + '_ {
+        self.iter().copied()
+    }
+}
+
+impl<'a, const N: usize, V> IntoIterator for &'a OrderedBuffer<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    type Item = &'a Level<V>;
+    type IntoIter = std::slice::Iter<'a, Level<V>>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<const N: usize, V> IntoIterator for OrderedBuffer<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    type Item = Level<V>;
+    type IntoIter = IntoIter<N, V>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { buffer: self, index: 0 }
+    }
+}
+
+#[derive(Debug)]
+/// The levels of an [`OrderedBuffer`] by value, best price first
+pub struct IntoIter<const N: usize, V: DecimalType> {
+    buffer: OrderedBuffer<N, V>,
+    index: usize,
+}
+
+impl<const N: usize, V> Iterator for IntoIter<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    type Item = Level<V>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let level = self.buffer.get(self.index).copied()?;
+        self.index += 1;
+        Some(level)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.buffer.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<const N: usize, V> ExactSizeIterator for IntoIter<N, V> where V: DecimalType + PartialOrd + Copy + Ord {}
+
+#[derive(Debug)]
+/// Levels taken out of an [`OrderedBuffer`] by [`OrderedBuffer::drain`]
+pub struct Drain<'a, const N: usize, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    buffer: &'a mut OrderedBuffer<N, V>,
+    index: usize,
+}
+
+impl<const N: usize, V> Iterator for Drain<'_, N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    type Item = Level<V>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let level = self.buffer.get(self.index).copied()?;
+        self.index += 1;
+        Some(level)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.buffer.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<const N: usize, V> ExactSizeIterator for Drain<'_, N, V> where V: DecimalType + PartialOrd + Copy + Ord {}
+
+impl<const N: usize, V> Drop for Drain<'_, N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    // Levels left unread are dropped along with the rest
+    fn drop(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        buffers::{
+            buffer::{BufferStats, OrderedBuffer, SearchStrategy},
+            dyn_buffer::DynBuffer,
+            storage::{InsertResult, LevelStorage},
+        },
+        decimals::fixed_decimal::FixedDecimal,
+        fixed,
+        level::Level,
+    };
+
+    fn prices<const N: usize>(buffer: &OrderedBuffer<N, FixedDecimal>) -> Vec<FixedDecimal> {
+        buffer.iter().map(|level| level.price).collect()
+    }
+
+    // Insert through the search, as the books do
+    fn upsert<const N: usize>(buffer: &mut OrderedBuffer<N, FixedDecimal>, price: FixedDecimal) {
+        match buffer.find_index(price) {
+            Ok(index) => {
+                buffer.modify(index, fixed!(2));
+            }
+            Err(index) => {
+                buffer.insert(index, Level::new(price, fixed!(1)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_basic_operations() {
+        let mut buffer = OrderedBuffer::<10, FixedDecimal>::new(false);
+
+        buffer.insert(0, Level::new(FixedDecimal::from_int(10), FixedDecimal::from_int(1)));
+        buffer.insert(1, Level::new(FixedDecimal::from_int(90), FixedDecimal::from_int(2)));
+        buffer.insert(2, Level::new(FixedDecimal::from_int(80), FixedDecimal::from_int(3)));
+        assert_eq!(prices(&buffer), vec![fixed!(10), fixed!(90), fixed!(80)]);
+        assert_eq!(buffer.first().map(|level| level.price), Some(fixed!(10)));
+
+        let removed = buffer.remove(1);
+        assert_eq!(removed, Some(FixedDecimal::from_int(90)));
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(prices(&buffer), vec![fixed!(10), fixed!(80)]);
+        // Past the last level, including the sentinel slots below the capacity
+        assert_eq!((buffer.remove(2), buffer.modify(2, fixed!(1))), (None, None));
+        assert_eq!((buffer.modify(1, fixed!(5)), buffer.len()), (Some(fixed!(3)), 2));
+    }
+
+    #[test]
+    fn test_both_directions() {
+        // Enough levels to take the branchless search
+        let inputs: Vec<_> = (0..40).map(|i| FixedDecimal::from_int((i * 7) % 40 + 100)).collect();
+        for descending in [true, false] {
+            let mut buffer = OrderedBuffer::<64, FixedDecimal>::new(descending);
+            for &price in &inputs {
+                upsert(&mut buffer, price);
+            }
+            let mut expected = inputs.clone();
+            expected.sort();
+            if descending {
+                expected.reverse();
+            }
+            assert_eq!(prices(&buffer), expected);
+            for (index, &price) in expected.iter().enumerate() {
+                assert_eq!(buffer.find_index(price), Ok(index));
+            }
+            let (best, worst) = (expected[0], expected[39]);
+            let (ahead, behind) =
+                if descending { (best + fixed!(1), worst - fixed!(1)) } else { (best - fixed!(1), worst + fixed!(1)) };
+            assert_eq!((buffer.find_index(ahead), buffer.find_index(behind)), (Err(0), Err(40)));
+            assert_eq!(buffer.find_index(fixed!(120.5)), Err(if descending { 19 } else { 21 }));
+        }
+    }
+
+    #[test]
+    fn test_full_buffer_drops_worst() {
+        let mut buffer = OrderedBuffer::<3, FixedDecimal>::new(true);
+        for price in [fixed!(100), fixed!(99), fixed!(98), fixed!(101)] {
+            upsert(&mut buffer, price);
+        }
+        assert_eq!(prices(&buffer), vec![fixed!(101), fixed!(100), fixed!(99)]);
+        let result = buffer.insert(2, Level::new(fixed!(99.5), fixed!(1)));
+        assert!(matches!(result, InsertResult::ReplacedTail(level) if level.price == fixed!(99)));
+        assert_eq!(prices(&buffer), vec![fixed!(101), fixed!(100), fixed!(99.5)]);
+        // Past the worst level of a full buffer
+        let result = buffer.insert(3, Level::new(fixed!(97), fixed!(1)));
+        assert!(matches!(result, InsertResult::RejectedBeyondCapacity));
+        assert_eq!(buffer.len(), 3);
+    }
+
+    #[test]
+    fn test_safe_access() {
+        let mut buffer = OrderedBuffer::<4, _>::new(true);
+        assert!(buffer.get(0).is_none() && buffer.last().is_none() && buffer.as_slice().is_empty());
+
+        buffer.insert(0, Level::new(fixed!(100), fixed!(1)));
+        buffer.insert(1, Level::new(fixed!(99), fixed!(2)));
+        let price_size = |level: Option<&Level<_>>| level.map(|level| (level.price, level.size));
+        assert_eq!(price_size(buffer.get(1)), Some((fixed!(99), fixed!(2))));
+        // The sentinels past the populated levels are not exposed
+        assert!(buffer.get(2).is_none());
+        assert_eq!(price_size(buffer.last()), Some((fixed!(99), fixed!(2))));
+        assert_eq!(buffer.top(1).len(), 1);
+        assert_eq!(buffer.top(10).len(), 2);
+        assert_eq!(buffer.range(1..10).iter().map(|level| level.price).collect::<Vec<_>>(), [fixed!(99)]);
+        assert!(buffer.range(5..8).is_empty());
+
+        buffer.iter_mut().for_each(|level| level.size = level.size + fixed!(1));
+        let sizes: Vec<_> = buffer.iter().map(|level| level.size).collect();
+        assert_eq!(sizes, vec![fixed!(2), fixed!(3)]);
+    }
+
+    #[test]
+    fn test_clear_and_truncate() {
+        let mut buffer = OrderedBuffer::<4, _>::new(false);
+        for (index, price) in [fixed!(100), fixed!(101), fixed!(102)].into_iter().enumerate() {
+            buffer.insert(index, Level::new(price, fixed!(1)));
+        }
+        buffer.truncate(5);
+        assert_eq!(buffer.len(), 3);
+        buffer.truncate(1);
+        assert_eq!((buffer.len(), buffer.first().map(|level| level.price)), (1, Some(fixed!(100))));
+        // Searches past the kept levels see the sentinels again
+        assert_eq!(buffer.find_index(fixed!(101)), Err(1));
+
+        buffer.clear();
+        assert!(buffer.first().is_none() && buffer.as_slice().is_empty());
+        buffer.insert(0, Level::new(fixed!(103), fixed!(2)));
+        assert_eq!(buffer.first().map(|level| level.price), Some(fixed!(103)));
+    }
+
+    #[test]
+    fn test_bulk_load() {
+        let mut buffer = OrderedBuffer::<3, _>::new(true);
+        for (index, price) in [fixed!(90), fixed!(89), fixed!(88)].into_iter().enumerate() {
+            buffer.insert(index, Level::new(price, fixed!(1)));
+        }
+        let snapshot = [
+            Level::new(fixed!(99), fixed!(1)),
+            Level::new(fixed!(101), fixed!(2)),
+            Level::new(fixed!(98), fixed!(3)),
+            Level::new(fixed!(99), fixed!(4)),
+            Level::new(fixed!(100), fixed!(5)),
+        ];
+        buffer.bulk_load(&snapshot);
+        let levels: Vec<_> = buffer.iter().map(|level| (level.price, level.size)).collect();
+        assert_eq!(levels, [(fixed!(101), fixed!(2)), (fixed!(100), fixed!(5)), (fixed!(99), fixed!(4))]);
+        assert_eq!(buffer.first().map(|level| level.price), Some(fixed!(101)));
+        assert_eq!(buffer.find_index(fixed!(100)), Ok(1));
+
+        buffer.bulk_load(&snapshot[..1]);
+        assert_eq!((buffer.len(), buffer.find_index(fixed!(98))), (1, Err(1)));
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut buffer = OrderedBuffer::<2, FixedDecimal>::new(false);
+        for price in [fixed!(100), fixed!(101), fixed!(99), fixed!(102)] {
+            upsert(&mut buffer, price);
+        }
+        buffer.remove(0);
+        buffer.bulk_load(&[Level::new(fixed!(1), fixed!(1)), Level::new(fixed!(2), fixed!(1)), Level::new(fixed!(3), fixed!(1))]);
+        let expected = BufferStats { high_watermark: 2, inserts: 5, removes: 1, dropped: 3 };
+        assert_eq!(buffer.stats(), expected);
+    }
+
+    #[test]
+    fn test_iterators_and_drain() {
+        let mut buffer = OrderedBuffer::<4, FixedDecimal>::new(true);
+        for price in [fixed!(100), fixed!(99), fixed!(98)] {
+            upsert(&mut buffer, price);
+        }
+        let by_ref: Vec<_> = (&buffer).into_iter().map(|level| level.price).collect();
+        assert_eq!(by_ref, [fixed!(100), fixed!(99), fixed!(98)]);
+
+        // Migrate the levels into another storage
+        let mut drain = buffer.drain();
+        assert_eq!(drain.len(), 3);
+        let mut migrated = DynBuffer::new(true);
+        for (index, level) in drain.by_ref().enumerate() {
+            migrated.insert(index, level);
+        }
+        drop(drain);
+        assert!(buffer.first().is_none() && buffer.as_slice().is_empty());
+        assert_eq!(migrated.len(), 3);
+
+        upsert(&mut buffer, fixed!(97));
+        let mut owned = buffer.into_iter();
+        assert_eq!((owned.len(), owned.next().map(|level| level.price), owned.len()), (1, Some(fixed!(97)), 0));
+    }
+
+    #[test]
+    fn test_search_strategies_agree() {
+        for descending in [true, false] {
+            let mut buffers = [SearchStrategy::Binary, SearchStrategy::Interpolation, SearchStrategy::Adaptive]
+                .map(|search| OrderedBuffer::<300, FixedDecimal>::new(descending).with_search(search));
+            // A dense ladder with a few gaps and one outlier
+            for buffer in &mut buffers {
+                for tick in (0..250).filter(|tick| tick % 17 != 3) {
+                    upsert(buffer, FixedDecimal::from_int(10_000 + tick));
+                }
+                upsert(buffer, FixedDecimal::from_int(50_000));
+            }
+            for tick in -5..260 {
+                let price = FixedDecimal::from_int(10_000 + tick);
+                let [binary, interpolation, adaptive] = buffers.each_ref().map(|buffer| buffer.find_index(price));
+                assert_eq!(binary, interpolation, "{price:?}");
+                assert_eq!(binary, adaptive, "{price:?}");
             }
         }
     }
@@ -0,0 +1,167 @@
+use crate::{
+    buffers::storage::{InsertResult, LevelStorage},
+    decimals::decimal_type::DecimalType,
+    level::Level,
+};
+
+#[derive(Debug, Clone)]
+/// A growable run of levels sorted best price first, for instruments whose depth is not known up front.
+///
+/// Unlike [`OrderedBuffer`](crate::buffers::buffer::OrderedBuffer) it never drops levels and keeps no
+/// sentinels, at the cost of a heap allocation that grows with the book.
+pub struct DynBuffer<V: DecimalType> {
+    levels: Vec<Level<V>>,
+    descending: bool,
+}
+
+impl<V> DynBuffer<V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    #[inline]
+    #[must_use]
+    /// A buffer for one side of the book, `descending` for bids
+    pub const fn new(descending: bool) -> Self {
+        Self { levels: Vec::new(), descending }
+    }
+
+    #[inline]
+    #[must_use]
+    /// A buffer with room for `capacity` levels before it reallocates
+    pub fn with_capacity(descending: bool, capacity: usize) -> Self {
+        Self { levels: Vec::with_capacity(capacity), descending }
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn is_descending(&self) -> bool {
+        self.descending
+    }
+}
+
+impl<V> LevelStorage<V> for DynBuffer<V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    #[inline]
+    fn new(descending: bool) -> Self {
+        Self::new(descending)
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    #[inline(always)]
+    fn get(&self, index: usize) -> Option<Level<V>> {
+        self.levels.get(index).copied()
+    }
+
+    #[inline(always)]
+    fn find_index(&self, price: V) -> Result<usize, usize> {
+        let descending = self.descending;
+        self.levels.binary_search_by(|level| {
+            let ordering = level.price.cmp(&price);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        })
+    }
+
+    #[inline(always)]
+    fn insert(&mut self, index: usize, level: Level<V>) -> InsertResult<V> {
+        if index > self.levels.len() {
+            return InsertResult::RejectedBeyondCapacity;
+        }
+        self.levels.insert(index, level);
+        InsertResult::Inserted
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, index: usize) -> V {
+        self.levels.remove(index).price
+    }
+
+    #[inline(always)]
+    fn modify(&mut self, index: usize, size: V) {
+        debug_assert!(index < self.levels.len(), "index out of bounds");
+        if let Some(level) = self.levels.get_mut(index) {
+            level.size = size;
+        }
+    }
+
+    #[inline(always)]
+    fn first(&self) -> Option<Level<V>> {
+        self.levels.first().copied()
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.levels.clear();
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        self.levels.truncate(len);
+    }
+
+    #[inline]
+    fn iter(&self) -> impl Iterator<Item = Level<V>> + '_ {
+        self.levels.iter().copied()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, array_orderbook::DynOrderbook, interface::OrderBook},
+        buffers::{dyn_buffer::DynBuffer, storage::LevelStorage},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        level::Level,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_sorted_both_directions() {
+        for descending in [true, false] {
+            let mut buffer = DynBuffer::<FixedDecimal>::new(descending);
+            for price in [fixed!(101), fixed!(99), fixed!(100)] {
+                let index = buffer.find_index(price).unwrap_err();
+                buffer.insert(index, Level::new(price, fixed!(1)));
+            }
+            let prices: Vec<_> = buffer.iter().map(|level| level.price).collect();
+            let expected =
+                if descending { [fixed!(101), fixed!(100), fixed!(99)] } else { [fixed!(99), fixed!(100), fixed!(101)] };
+            assert_eq!(prices, expected);
+            assert_eq!(buffer.find_index(fixed!(100)), Ok(1));
+            assert_eq!(buffer.remove(0), expected[0]);
+            assert_eq!(buffer.first().map(|level| level.price), Some(fixed!(100)));
+        }
+    }
+
+    #[test]
+    fn test_book_matches_fixed_capacity() {
+        let mut fixed_book = ArrayOrderbook::<64, FixedDecimal>::new();
+        let mut dyn_book = DynOrderbook::<FixedDecimal>::new();
+        for i in 0..40 {
+            let (side, price) = if i % 2 == 0 { (Side::Buy, 100 - i) } else { (Side::Sell, 100 + i) };
+            let size = if i % 7 == 0 { fixed!(0) } else { FixedDecimal::from_int(i + 1) };
+            let event = || Event::new(EventKind::L2, side, FixedDecimal::from_int(price), size, Timestamp::from_nanos(i));
+            fixed_book.process(event());
+            dyn_book.process(event());
+        }
+        for side in [Side::Buy, Side::Sell] {
+            let levels = |book: Vec<Level<FixedDecimal>>| book.iter().map(|level| (level.price, level.size)).collect::<Vec<_>>();
+            assert_eq!(levels(fixed_book.levels(side, 64)), levels(dyn_book.levels(side, 64)));
+        }
+        assert_eq!(dyn_book.bids.len() + dyn_book.asks.len(), 34);
+    }
+}
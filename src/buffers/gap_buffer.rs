@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+
+use crate::{
+    buffers::storage::{InsertResult, LevelStorage},
+    decimals::decimal_type::DecimalType,
+    level::Level,
+};
+
+#[derive(Debug, Clone)]
+/// A fixed-capacity run of levels sorted best price first, whose free slots sit just ahead of the
+/// best level.
+///
+/// The levels live in a ring, so the gap of unused slots wraps around from behind the worst level to
+/// ahead of the best one. An insert or remove moves only the levels between it and the nearer end,
+/// which for the churn near the touch is a handful rather than the whole side as in an
+/// [`OrderedBuffer`](crate::buffers::buffer::OrderedBuffer), and dropping the worst level of a full
+/// buffer moves nothing.
+pub struct GapBuffer<const N: usize, V: DecimalType> {
+    levels: VecDeque<Level<V>>,
+    descending: bool,
+}
+
+impl<const N: usize, V> GapBuffer<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    #[inline]
+    #[must_use]
+    /// A buffer for one side of the book, `descending` for bids
+    pub fn new(descending: bool) -> Self {
+        Self { levels: VecDeque::with_capacity(N), descending }
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn is_descending(&self) -> bool {
+        self.descending
+    }
+}
+
+impl<const N: usize, V> LevelStorage<V> for GapBuffer<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    #[inline]
+    fn new(descending: bool) -> Self {
+        Self::new(descending)
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    #[inline(always)]
+    fn get(&self, index: usize) -> Option<Level<V>> {
+        self.levels.get(index).copied()
+    }
+
+    #[inline(always)]
+    fn find_index(&self, price: V) -> Result<usize, usize> {
+        let descending = self.descending;
+        self.levels.binary_search_by(|level| {
+            let ordering = level.price.cmp(&price);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        })
+    }
+
+    #[inline(always)]
+    /// Insert `level` at `index`, dropping the worst level when the buffer is full. Indices at or past
+    /// the capacity are ignored.
+    fn insert(&mut self, index: usize, level: Level<V>) -> InsertResult<V> {
+        if index >= N || index > self.levels.len() {
+            return InsertResult::RejectedBeyondCapacity;
+        }
+        let result = if self.levels.len() == N {
+            InsertResult::ReplacedTail(self.levels.pop_back().expect("a full buffer is not empty"))
+        } else {
+            InsertResult::Inserted
+        };
+        self.levels.insert(index, level);
+        result
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, index: usize) -> V {
+        self.levels.remove(index).expect("index out of bounds").price
+    }
+
+    #[inline(always)]
+    fn modify(&mut self, index: usize, size: V) {
+        debug_assert!(index < self.levels.len(), "index out of bounds");
+        if let Some(level) = self.levels.get_mut(index) {
+            level.size = size;
+        }
+    }
+
+    #[inline(always)]
+    fn first(&self) -> Option<Level<V>> {
+        self.levels.front().copied()
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.levels.clear();
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        self.levels.truncate(len);
+    }
+
+    #[inline]
+    fn iter(&self) -> impl Iterator<Item = Level<V>> + '_ {
+        self.levels.iter().copied()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{
+            array_orderbook::{ArrayOrderbook, GapOrderbook},
+            interface::OrderBook,
+        },
+        buffers::{
+            gap_buffer::GapBuffer,
+            storage::{InsertResult, LevelStorage},
+        },
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        level::Level,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_full_buffer_drops_worst() {
+        let mut buffer = GapBuffer::<3, FixedDecimal>::new(false);
+        for price in [fixed!(100), fixed!(102), fixed!(101)] {
+            let index = buffer.find_index(price).unwrap_err();
+            assert!(matches!(buffer.insert(index, Level::new(price, fixed!(1))), InsertResult::Inserted));
+        }
+        let result = buffer.insert(0, Level::new(fixed!(99), fixed!(1)));
+        assert!(matches!(result, InsertResult::ReplacedTail(level) if level.price == fixed!(102)));
+        assert!(matches!(buffer.insert(3, Level::new(fixed!(103), fixed!(1))), InsertResult::RejectedBeyondCapacity));
+        let prices: Vec<_> = buffer.iter().map(|level| level.price).collect();
+        assert_eq!(prices, [fixed!(99), fixed!(100), fixed!(101)]);
+        assert_eq!(buffer.remove(0), fixed!(99));
+        assert_eq!(buffer.first().map(|level| level.price), Some(fixed!(100)));
+    }
+
+    #[test]
+    fn test_book_matches_ordered_buffer() {
+        let mut ordered_book = ArrayOrderbook::<16, FixedDecimal>::new();
+        let mut gap_book = GapOrderbook::<16, FixedDecimal>::new();
+        for i in 0..80 {
+            let (side, price) = if i % 2 == 0 { (Side::Buy, 100 - i % 20) } else { (Side::Sell, 101 + i % 20) };
+            let size = if i % 3 == 0 { fixed!(0) } else { FixedDecimal::from_int(i) };
+            let event = || Event::new(EventKind::L2, side, FixedDecimal::from_int(price), size, Timestamp::from_nanos(i));
+            ordered_book.process(event());
+            gap_book.process(event());
+        }
+        for side in [Side::Buy, Side::Sell] {
+            let levels = |book: Vec<Level<FixedDecimal>>| book.iter().map(|level| (level.price, level.size)).collect::<Vec<_>>();
+            assert_eq!(levels(ordered_book.levels(side, 16)), levels(gap_book.levels(side, 16)));
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{
+    buffers::storage::{InsertResult, LevelStorage},
+    decimals::decimal_type::DecimalType,
+    level::Level,
+};
+
+#[derive(Debug, Clone)]
+/// A fixed-capacity run of levels sorted best price first, held inline rather than behind a `Box`.
+///
+/// For a shallow book, such as a handful of levels around the touch, the levels share an allocation
+/// with the book itself, which can live on the stack. A large `N` makes the book that much larger to
+/// move, so deep books are better served by an [`OrderedBuffer`](crate::buffers::buffer::OrderedBuffer).
+pub struct InlineBuffer<const N: usize, V: DecimalType> {
+    levels: [Level<V>; N],
+    descending: bool,
+    len: usize,
+}
+
+impl<const N: usize, V> InlineBuffer<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    #[inline]
+    #[must_use]
+    /// A buffer for one side of the book, `descending` for bids
+    pub fn new(descending: bool) -> Self {
+        Self { levels: [Level::bound(descending); N], descending, len: 0 }
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn is_descending(&self) -> bool {
+        self.descending
+    }
+
+    #[inline(always)]
+    #[must_use]
+    /// The populated levels, best price first
+    pub fn as_slice(&self) -> &[Level<V>] {
+        &self.levels[..self.len]
+    }
+}
+
+impl<const N: usize, V> LevelStorage<V> for InlineBuffer<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    #[inline]
+    fn new(descending: bool) -> Self {
+        Self::new(descending)
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    fn get(&self, index: usize) -> Option<Level<V>> {
+        self.as_slice().get(index).copied()
+    }
+
+    #[inline(always)]
+    fn find_index(&self, price: V) -> Result<usize, usize> {
+        let descending = self.descending;
+        self.as_slice().binary_search_by(|level| {
+            let ordering = level.price.cmp(&price);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        })
+    }
+
+    #[inline(always)]
+    /// Insert `level` at `index`, dropping the worst level when the buffer is full. Indices at or past
+    /// the capacity are ignored.
+    fn insert(&mut self, index: usize, level: Level<V>) -> InsertResult<V> {
+        if index >= N || index > self.len {
+            return InsertResult::RejectedBeyondCapacity;
+        }
+        let result = if self.len == N { InsertResult::ReplacedTail(self.levels[N - 1]) } else { InsertResult::Inserted };
+        // A full buffer shifts one level fewer, the worst level falls off the end
+        self.levels.copy_within(index..self.len.min(N - 1), index + 1);
+        self.levels[index] = level;
+        self.len = (self.len + 1).min(N);
+        result
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, index: usize) -> V {
+        debug_assert!(index < self.len, "index out of bounds");
+        let removed = self.levels[index].price;
+        self.levels.copy_within(index + 1..self.len, index);
+        self.len -= 1;
+        removed
+    }
+
+    #[inline(always)]
+    fn modify(&mut self, index: usize, size: V) {
+        debug_assert!(index < self.len, "index out of bounds");
+        if index < self.len {
+            self.levels[index].size = size;
+        }
+    }
+
+    #[inline(always)]
+    fn first(&self) -> Option<Level<V>> {
+        self.get(0)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+
+    #[inline]
+    fn iter(&self) -> impl Iterator<Item = Level<V>> + '_ {
+        self.as_slice().iter().copied()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::mem::size_of;
+
+    use crate::{
+        books::{
+            array_orderbook::{ArrayOrderbook, InlineOrderbook},
+            interface::OrderBook,
+        },
+        buffers::inline_buffer::InlineBuffer,
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        level::Level,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_levels_are_inline() {
+        assert!(size_of::<InlineBuffer<10, FixedDecimal>>() >= 10 * size_of::<Level<FixedDecimal>>());
+    }
+
+    #[test]
+    fn test_book_matches_ordered_buffer() {
+        let mut ordered_book = ArrayOrderbook::<10, FixedDecimal>::new();
+        let mut inline_book = InlineOrderbook::<10, FixedDecimal>::new();
+        for i in 0..60 {
+            let (side, price) = if i % 2 == 0 { (Side::Buy, 100 - i % 14) } else { (Side::Sell, 101 + i % 14) };
+            let size = if i % 4 == 0 { FixedDecimal::ZERO } else { FixedDecimal::from_int(i) };
+            let event = || Event::new(EventKind::L2, side, FixedDecimal::from_int(price), size, Timestamp::from_nanos(i));
+            ordered_book.process(event());
+            inline_book.process(event());
+        }
+        for side in [Side::Buy, Side::Sell] {
+            let levels = |book: Vec<Level<FixedDecimal>>| book.iter().map(|level| (level.price, level.size)).collect::<Vec<_>>();
+            assert_eq!(levels(ordered_book.levels(side, 10)), levels(inline_book.levels(side, 10)));
+        }
+    }
+}
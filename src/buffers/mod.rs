@@ -1,2 +1,7 @@
 pub mod buffer;
-pub mod reversed_vec;
+pub mod dyn_buffer;
+pub mod gap_buffer;
+pub mod inline_buffer;
+pub mod pool;
+pub mod soa_buffer;
+pub mod storage;
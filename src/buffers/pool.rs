@@ -0,0 +1,156 @@
+//! Recycling of [`OrderedBuffer`] backing arrays.
+//!
+//! Every `OrderedBuffer` owns a boxed array of `N` levels, so a process that creates and drops books
+//! for thousands of symbols churns the global allocator with large, identically sized blocks.
+//! [`BufferPool`] keeps the arrays of retired buffers on a free list and hands them out again, and can
+//! pre-warm that list at startup so no allocation happens once the feed is live.
+
+use std::{
+    iter::Sum,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use crate::{
+    books::{array_orderbook::ArrayOrderbook, manager::BookManager},
+    buffers::buffer::OrderedBuffer,
+    decimals::decimal_type::DecimalType,
+    level::Level,
+};
+
+#[derive(Debug)]
+pub struct BufferPool<const N: usize, V: DecimalType> {
+    free: Vec<Box<[Level<V>; N]>>,
+    allocated: usize,
+}
+
+impl<const N: usize, V> BufferPool<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { free: Vec::new(), allocated: 0 }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Backing arrays waiting to be reused
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Backing arrays this pool has allocated, in use or not
+    pub const fn allocated(&self) -> usize {
+        self.allocated
+    }
+
+    /// Allocate `count` more backing arrays up front, a book taking one for each side
+    pub fn prewarm(&mut self, count: usize) {
+        self.free.reserve(count);
+        for _ in 0..count {
+            self.free.push(OrderedBuffer::<N, V>::new(false).into_storage());
+        }
+        self.allocated += count;
+    }
+
+    /// An empty buffer, reusing a recycled backing array when there is one
+    pub fn take(&mut self, descending: bool) -> OrderedBuffer<N, V> {
+        match self.free.pop() {
+            Some(storage) => OrderedBuffer::with_storage(storage, descending),
+            None => {
+                self.allocated += 1;
+                OrderedBuffer::new(descending)
+            }
+        }
+    }
+
+    #[inline]
+    /// Hand the backing array of `buffer` back for reuse
+    pub fn recycle(&mut self, buffer: OrderedBuffer<N, V>) {
+        self.free.push(buffer.into_storage());
+    }
+
+    #[inline]
+    /// An empty book over pooled buffers
+    pub fn take_book(&mut self) -> ArrayOrderbook<N, V> {
+        ArrayOrderbook::with_storage(self.take(true), self.take(false))
+    }
+
+    #[inline]
+    /// Hand the buffers of `book` back for reuse
+    pub fn recycle_book(&mut self, book: ArrayOrderbook<N, V>) {
+        self.recycle(book.bids);
+        self.recycle(book.asks);
+    }
+}
+
+impl<const N: usize, V> Default for BufferPool<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, V> BookManager<V, ArrayOrderbook<N, V>>
+where
+    V: DecimalType + PartialOrd + Sub<Output = V> + Add<Output = V> + Mul<Output = V> + Div<Output = V> + Copy + Ord + Sum,
+{
+    /// Get the book for `symbol`, creating an empty one over buffers from `pool` if it is not managed yet
+    pub fn book_from_pool(&mut self, symbol: &str, pool: &mut BufferPool<N, V>) -> &mut ArrayOrderbook<N, V> {
+        if self.book(symbol).is_none() {
+            self.insert(symbol, pool.take_book());
+        }
+        self.book_mut(symbol).expect("book was just inserted")
+    }
+
+    /// Stop managing `symbol` and return its buffers to `pool`, `false` when it was not managed
+    pub fn recycle(&mut self, symbol: &str, pool: &mut BufferPool<N, V>) -> bool {
+        self.remove(symbol).map(|book| pool.recycle_book(book)).is_some()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, interface::OrderBook, manager::BookManager},
+        buffers::pool::BufferPool,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_recycles_buffers() {
+        let mut pool = BufferPool::<8, _>::new();
+        pool.prewarm(4);
+        let mut manager = BookManager::<_, ArrayOrderbook<8, _>>::new();
+        let book = manager.book_from_pool("BTC", &mut pool);
+        book.process(Event::new(EventKind::L2, Side::Buy, fixed!(100), fixed!(1), Timestamp::from_nanos(1)));
+        book.process(Event::new(EventKind::L2, Side::Sell, fixed!(101), fixed!(1), Timestamp::from_nanos(1)));
+        manager.book_from_pool("ETH", &mut pool);
+        assert_eq!((pool.available(), pool.allocated()), (0, 4));
+
+        assert!(manager.recycle("BTC", &mut pool) && !manager.recycle("BTC", &mut pool));
+        assert_eq!(pool.available(), 2);
+
+        // A recycled buffer starts empty in the direction it is taken for
+        let book = manager.book_from_pool("SOL", &mut pool);
+        assert!(book.best_bid().is_none() && book.levels(Side::Sell, 8).is_empty());
+        book.process(Event::new(EventKind::L2, Side::Buy, fixed!(99), fixed!(1), Timestamp::from_nanos(1)));
+        book.process(Event::new(EventKind::L2, Side::Buy, fixed!(100), fixed!(1), Timestamp::from_nanos(2)));
+        assert_eq!(book.best_bid().map(|level| level.price), Some(fixed!(100)));
+        assert_eq!((pool.available(), pool.allocated()), (0, 4));
+
+        manager.book_from_pool("XRP", &mut pool);
+        assert_eq!(pool.allocated(), 6);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{
+    buffers::storage::{InsertResult, LevelStorage},
+    decimals::decimal_type::DecimalType,
+    level::Level,
+};
+
+#[derive(Debug, Clone)]
+/// A fixed-capacity run of levels sorted best price first, with the prices and sizes held in separate
+/// contiguous arrays.
+///
+/// A search only touches the price array, so a cache line holds twice as many candidates as it does
+/// in an [`OrderedBuffer`](crate::buffers::buffer::OrderedBuffer), and sums over the sizes run over a
+/// plain slice the compiler can vectorise. Like the `OrderedBuffer` it drops its worst level when an
+/// insert arrives full.
+pub struct SoaBuffer<const N: usize, V: DecimalType> {
+    prices: Box<[V]>,
+    sizes: Box<[V]>,
+    descending: bool,
+    len: usize,
+}
+
+impl<const N: usize, V> SoaBuffer<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    #[inline]
+    #[must_use]
+    /// A buffer for one side of the book, `descending` for bids
+    pub fn new(descending: bool) -> Self {
+        Self { prices: vec![V::ZERO; N].into_boxed_slice(), sizes: vec![V::ZERO; N].into_boxed_slice(), descending, len: 0 }
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn is_descending(&self) -> bool {
+        self.descending
+    }
+
+    #[inline(always)]
+    #[must_use]
+    /// Prices of the populated levels, best first
+    pub fn prices(&self) -> &[V] {
+        &self.prices[..self.len]
+    }
+
+    #[inline(always)]
+    #[must_use]
+    /// Sizes of the populated levels, in the same order as [`Self::prices`]
+    pub fn sizes(&self) -> &[V] {
+        &self.sizes[..self.len]
+    }
+}
+
+impl<const N: usize, V> LevelStorage<V> for SoaBuffer<N, V>
+where
+    V: DecimalType + PartialOrd + Copy + Ord,
+{
+    #[inline]
+    fn new(descending: bool) -> Self {
+        Self::new(descending)
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    fn get(&self, index: usize) -> Option<Level<V>> {
+        (index < self.len).then(|| Level::new(self.prices[index], self.sizes[index]))
+    }
+
+    #[inline(always)]
+    fn find_index(&self, price: V) -> Result<usize, usize> {
+        let descending = self.descending;
+        self.prices().binary_search_by(|level_price| {
+            let ordering = level_price.cmp(&price);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        })
+    }
+
+    #[inline(always)]
+    /// Insert `level` at `index`, dropping the worst level when the buffer is full. Indices at or past
+    /// the capacity are ignored.
+    fn insert(&mut self, index: usize, level: Level<V>) -> InsertResult<V> {
+        if index >= N || index > self.len {
+            return InsertResult::RejectedBeyondCapacity;
+        }
+        let result = if self.len == N {
+            InsertResult::ReplacedTail(Level::new(self.prices[N - 1], self.sizes[N - 1]))
+        } else {
+            InsertResult::Inserted
+        };
+        // A full buffer shifts one level fewer, the worst level falls off the end
+        let end = self.len.min(N - 1);
+        self.prices.copy_within(index..end, index + 1);
+        self.sizes.copy_within(index..end, index + 1);
+        self.prices[index] = level.price;
+        self.sizes[index] = level.size;
+        self.len = (self.len + 1).min(N);
+        result
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, index: usize) -> V {
+        debug_assert!(index < self.len, "index out of bounds");
+        let removed = self.prices[index];
+        self.prices.copy_within(index + 1..self.len, index);
+        self.sizes.copy_within(index + 1..self.len, index);
+        self.len -= 1;
+        removed
+    }
+
+    #[inline(always)]
+    fn modify(&mut self, index: usize, size: V) {
+        debug_assert!(index < self.len, "index out of bounds");
+        if index < self.len {
+            self.sizes[index] = size;
+        }
+    }
+
+    #[inline(always)]
+    fn first(&self) -> Option<Level<V>> {
+        self.get(0)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+
+    #[inline]
+    fn iter(&self) -> impl Iterator<Item = Level<V>> + '_ {
+        self.prices().iter().zip(self.sizes()).map(|(&price, &size)| Level::new(price, size))
+    }
+
+    #[inline]
+    fn extend_depth(&self, depth: usize, prices: &mut Vec<V>, sizes: &mut Vec<V>) {
+        let depth = depth.min(self.len);
+        prices.extend_from_slice(&self.prices[..depth]);
+        sizes.extend_from_slice(&self.sizes[..depth]);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{
+            array_orderbook::{ArrayOrderbook, SoaOrderbook},
+            interface::OrderBook,
+        },
+        buffers::{soa_buffer::SoaBuffer, storage::LevelStorage},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        level::Level,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_full_buffer_drops_worst() {
+        let mut buffer = SoaBuffer::<3, FixedDecimal>::new(true);
+        for price in [fixed!(100), fixed!(98), fixed!(99), fixed!(101)] {
+            let index = buffer.find_index(price).unwrap_err();
+            buffer.insert(index, Level::new(price, price - fixed!(90)));
+        }
+        assert_eq!(buffer.prices(), [fixed!(101), fixed!(100), fixed!(99)]);
+        assert_eq!(buffer.sizes(), [fixed!(11), fixed!(10), fixed!(9)]);
+        // Beyond the worst level of a full buffer
+        buffer.insert(3, Level::new(fixed!(97), fixed!(1)));
+        assert_eq!(buffer.remove(1), fixed!(100));
+        assert_eq!(buffer.last().map(|level| (level.price, level.size)), Some((fixed!(99), fixed!(9))));
+    }
+
+    #[test]
+    fn test_book_matches_array_of_structs() {
+        let mut aos_book = ArrayOrderbook::<16, FixedDecimal>::new();
+        let mut soa_book = SoaOrderbook::<16, FixedDecimal>::new();
+        for i in 0..60 {
+            let (side, price) = if i % 2 == 0 { (Side::Buy, 100 - i % 24) } else { (Side::Sell, 100 + i % 24) };
+            let size = if i % 5 == 0 { fixed!(0) } else { FixedDecimal::from_int(i) };
+            let event = || Event::new(EventKind::L2, side, FixedDecimal::from_int(price), size, Timestamp::from_nanos(i));
+            aos_book.process(event());
+            soa_book.process(event());
+        }
+        for side in [Side::Buy, Side::Sell] {
+            let levels = |book: Vec<Level<FixedDecimal>>| book.iter().map(|level| (level.price, level.size)).collect::<Vec<_>>();
+            assert_eq!(levels(aos_book.levels(side, 16)), levels(soa_book.levels(side, 16)));
+        }
+        let (aos, soa) = (aos_book.calculate_metrics(10), soa_book.calculate_metrics(10));
+        assert_eq!((aos.quote_imbalance, aos.vwap_bid, aos.vwap_ask), (soa.quote_imbalance, soa.vwap_bid, soa.vwap_ask));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{decimals::decimal_type::DecimalType, level::Level};
+
+#[derive(Debug, Clone, Copy)]
+/// What [`LevelStorage::insert`] did with a level
+pub enum InsertResult<V: DecimalType> {
+    Inserted,
+    /// The storage was full, so its worst level was dropped to make room
+    ReplacedTail(Level<V>),
+    /// The index was at or past the capacity, so the level was not stored
+    RejectedBeyondCapacity,
+}
+
+impl<V: DecimalType + Copy> InsertResult<V> {
+    #[inline(always)]
+    #[must_use]
+    /// Price of the level that is no longer stored after inserting one at `price`, if any
+    pub fn dropped(self, price: V) -> Option<V> {
+        match self {
+            Self::Inserted => None,
+            Self::ReplacedTail(level) => Some(level.price),
+            Self::RejectedBeyondCapacity => Some(price),
+        }
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn is_inserted(&self) -> bool {
+        !matches!(self, Self::RejectedBeyondCapacity)
+    }
+}
+
+/// Sorted storage for the levels of one side of a book, best price first.
+///
+/// The Array-based Orderbook runs the same algorithms over any implementation, a fixed-capacity
+/// [`OrderedBuffer`](crate::buffers::buffer::OrderedBuffer), the growable
+/// [`DynBuffer`](crate::buffers::dyn_buffer::DynBuffer), the structure-of-arrays
+/// [`SoaBuffer`](crate::buffers::soa_buffer::SoaBuffer), the ring-backed
+/// [`GapBuffer`](crate::buffers::gap_buffer::GapBuffer) or the unboxed
+/// [`InlineBuffer`](crate::buffers::inline_buffer::InlineBuffer). Levels are handed out by value so that an
+/// implementation need not keep its prices and sizes side by side.
+pub trait LevelStorage<V: DecimalType> {
+    /// Empty storage for one side, `descending` for bids
+    fn new(descending: bool) -> Self;
+    fn len(&self) -> usize;
+    /// The level at `index`, counting from the best price
+    fn get(&self, index: usize) -> Option<Level<V>>;
+    /// Position of `price`, or `Err` with the index it would be inserted at
+    fn find_index(&self, price: V) -> Result<usize, usize>;
+    /// Insert `level` at `index`, which may drop the worst level when the storage is bounded
+    fn insert(&mut self, index: usize, level: Level<V>) -> InsertResult<V>;
+    /// Remove the level at `index`, returning its price
+    fn remove(&mut self, index: usize) -> V;
+    /// Replace the size of the level at `index`
+    fn modify(&mut self, index: usize, size: V);
+    fn first(&self) -> Option<Level<V>>;
+    fn clear(&mut self);
+    /// Keep the best `len` levels and drop the rest
+    fn truncate(&mut self, len: usize);
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline(always)]
+    fn last(&self) -> Option<Level<V>> {
+        self.len().checked_sub(1).and_then(|index| self.get(index))
+    }
+
+    #[inline]
+    /// The levels, best price first
+    fn iter(&self) -> impl Iterator<Item = Level<V>> + '_ {
+        (0..self.len()).map_while(|index| self.get(index))
+    }
+
+    #[inline]
+    /// Append the prices and sizes of the best `depth` levels
+    fn extend_depth(&self, depth: usize, prices: &mut Vec<V>, sizes: &mut Vec<V>) {
+        for level in self.iter().take(depth) {
+            prices.push(level.price);
+            sizes.push(level.size);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Differential testing of book implementations.
+//!
+//! A [`Consistency`] runner feeds every event to several books at once and, after each one, compares
+//! them against the first: the timestamp and sequence ID of the last update, then the populated
+//! levels of each side, best price first. The first [`Mismatch`] names the event, the book and what
+//! differs, so drift between implementations shows up as soon as a recording exercises it.
+//!
+//! ```
+//! # #[cfg(feature = "fixed_decimal")] {
+//! use freya_ob::{
+//!     books::{array_orderbook::ArrayOrderbook, btree_orderbook::BTreeOrderBook}, consistency::Consistency,
+//!     decimals::fixed_decimal::FixedDecimal, event::Event, event_kind::EventKind, fixed, formats::source::MemorySource,
+//!     side::Side, timestamp::Timestamp,
+//! };
+//!
+//! let mut runner = Consistency::new()
+//!     .with_book("btree", BTreeOrderBook::<FixedDecimal>::new())
+//!     .with_book("array", ArrayOrderbook::<64, FixedDecimal>::new());
+//! let events = vec![Event::new(EventKind::L2, Side::Buy, fixed!(99), fixed!(3), Timestamp::from_nanos(1))];
+//! assert!(runner.run(MemorySource::new(events)).unwrap().is_none());
+//! # }
+//! ```
+
+use std::fmt::{self, Debug, Display};
+
+use crate::{
+    books::interface::OrderBook,
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    formats::source::{EventSource, SourceError},
+    level::Level,
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy)]
+/// What differs between a book and the reference, the first book of the runner
+pub enum Divergence<V: DecimalType> {
+    Timestamp {
+        expected: Timestamp,
+        actual: Timestamp,
+    },
+    SequenceId {
+        expected: u64,
+        actual: u64,
+    },
+    /// The level `depth` from the touch, `0` for the best price, `None` past the end of a side
+    Level {
+        side: Side,
+        depth: usize,
+        expected: Option<Level<V>>,
+        actual: Option<Level<V>>,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+/// The first divergence a [`Consistency`] runner found
+pub struct Mismatch<V: DecimalType> {
+    /// Index of the event after which the books differ
+    pub event: usize,
+    pub reference: &'static str,
+    pub book: &'static str,
+    pub divergence: Divergence<V>,
+}
+
+impl<V: DecimalType + Display> Display for Mismatch<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "after event {} {} differs from {}: ", self.event, self.book, self.reference)?;
+        let level = |level: &Option<Level<V>>| {
+            level.as_ref().map_or_else(|| "nothing".to_owned(), |level| format!("{}@{}", level.price, level.size))
+        };
+        match &self.divergence {
+            Divergence::Timestamp { expected, actual } => write!(f, "timestamp {actual}, expected {expected}"),
+            Divergence::SequenceId { expected, actual } => write!(f, "sequence ID {actual}, expected {expected}"),
+            Divergence::Level { side, depth, expected, actual } => {
+                write!(f, "{side:?} level {depth} is {}, expected {}", level(actual), level(expected))
+            }
+        }
+    }
+}
+
+/// Feeds the same events to several books and reports the first divergence, see the
+/// [module documentation](self)
+pub struct Consistency<V: DecimalType> {
+    books: Vec<(&'static str, Box<dyn OrderBook<V>>)>,
+    depth: usize,
+    events: usize,
+}
+
+impl<V: DecimalType> Debug for Consistency<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = self.books.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        f.debug_struct("Consistency").field("books", &names).field("depth", &self.depth).field("events", &self.events).finish()
+    }
+}
+
+impl<V: DecimalType> Default for Consistency<V> {
+    #[inline]
+    fn default() -> Self {
+        Self { books: Vec::new(), depth: usize::MAX, events: 0 }
+    }
+}
+
+impl<V: DecimalType + Copy + PartialEq> Consistency<V> {
+    #[inline]
+    #[must_use]
+    /// No books yet, the first one added is the reference the others are compared against
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_book(mut self, name: &'static str, book: impl OrderBook<V> + 'static) -> Self {
+        self.books.push((name, Box::new(book)));
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// Compare only the top `depth` levels of each side, for books that keep a bounded number of
+    /// levels
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// Events fed so far
+    pub const fn events(&self) -> usize {
+        self.events
+    }
+
+    /// Feed `event` to every book and compare them
+    ///
+    /// # Errors
+    /// The first way a book differs from the reference after the event.
+    pub fn process(&mut self, event: Event<V>) -> Result<(), Mismatch<V>> {
+        for (_, book) in &mut self.books {
+            book.process(event);
+        }
+        let index = self.events;
+        self.events += 1;
+        let Some(((reference, expected), others)) = self.books.split_first() else {
+            return Ok(());
+        };
+        for (name, book) in others {
+            if let Some(divergence) = self.compare(expected.as_ref(), book.as_ref()) {
+                return Err(Mismatch { event: index, reference, book: name, divergence });
+            }
+        }
+        Ok(())
+    }
+
+    /// Feed every event of `source`, stopping at the first mismatch
+    ///
+    /// # Errors
+    /// The first error reading `source`.
+    pub fn run(&mut self, source: impl EventSource<V>) -> Result<Option<Mismatch<V>>, SourceError> {
+        for event in source {
+            if let Err(mismatch) = self.process(event?) {
+                return Ok(Some(mismatch));
+            }
+        }
+        Ok(None)
+    }
+
+    fn compare(&self, expected: &dyn OrderBook<V>, actual: &dyn OrderBook<V>) -> Option<Divergence<V>> {
+        if expected.timestamp() != actual.timestamp() {
+            return Some(Divergence::Timestamp { expected: expected.timestamp(), actual: actual.timestamp() });
+        }
+        if expected.sequence_id() != actual.sequence_id() {
+            return Some(Divergence::SequenceId { expected: expected.sequence_id(), actual: actual.sequence_id() });
+        }
+        [Side::Buy, Side::Sell].into_iter().find_map(|side| {
+            let (expected, actual) = (expected.levels(side, self.depth), actual.levels(side, self.depth));
+            (0..expected.len().max(actual.len())).find_map(|depth| {
+                let (expected, actual) = (expected.get(depth).copied(), actual.get(depth).copied());
+                let same = match (expected, actual) {
+                    (Some(expected), Some(actual)) => expected.price == actual.price && expected.size == actual.size,
+                    _ => false,
+                };
+                (!same).then_some(Divergence::Level { side, depth, expected, actual })
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, btree_orderbook::BTreeOrderBook},
+        consistency::{Consistency, Divergence},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::source::MemorySource,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn runner(levels: usize) -> Consistency<FixedDecimal> {
+        Consistency::new()
+            .with_book("btree", BTreeOrderBook::<FixedDecimal>::new())
+            .with_book("array", ArrayOrderbook::<4, FixedDecimal>::new())
+            .with_depth(levels)
+    }
+
+    #[test]
+    fn test_reports_first_divergence() {
+        let l2 =
+            |side, price, size, seq| Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(1)).with_sequence_id(seq);
+        let mut events = vec![
+            l2(Side::Buy, fixed!(99), fixed!(3), 1),
+            l2(Side::Sell, fixed!(101), fixed!(2), 2),
+            l2(Side::Buy, fixed!(98), fixed!(5), 3),
+            l2(Side::Sell, fixed!(101), fixed!(0), 4),
+        ];
+        let mut agreeing = runner(usize::MAX);
+        assert!(agreeing.run(MemorySource::new(events.clone())).unwrap().is_none());
+        assert_eq!(agreeing.events(), 4);
+
+        // Both books take a partial fill off the level and remove one that is filled completely
+        let trade = |price, size| Event::new(EventKind::Trade, Side::Buy, price, size, Timestamp::from_nanos(2));
+        let trades = [trade(fixed!(98), fixed!(2)), trade(fixed!(99), fixed!(3))];
+        let traded = events.iter().copied().chain(trades).collect();
+        assert!(runner(usize::MAX).run(MemorySource::new(traded)).unwrap().is_none());
+
+        // Past its four levels the array book drops the worst bid, which only a deeper comparison sees
+        for (price, seq) in [(fixed!(97), 5), (fixed!(96), 6), (fixed!(95), 7)] {
+            events.push(l2(Side::Buy, price, fixed!(1), seq));
+        }
+        assert!(runner(4).run(MemorySource::new(events.clone())).unwrap().is_none());
+        let mismatch = runner(usize::MAX).run(MemorySource::new(events.clone())).unwrap().unwrap();
+        assert_eq!((mismatch.event, mismatch.book), (6, "array"));
+        assert!(matches!(mismatch.divergence, Divergence::Level { side: Side::Buy, depth: 4, expected: Some(_), actual: None }));
+
+        // A level update without a sequence ID leaves the array book's sequence alone but resets the BTree's
+        events.truncate(4);
+        events.push(l2(Side::Sell, fixed!(102), fixed!(4), 0));
+        let mismatch = runner(usize::MAX).run(MemorySource::new(events)).unwrap().unwrap();
+        assert_eq!(mismatch.event, 4);
+        assert!(matches!(mismatch.divergence, Divergence::SequenceId { expected: 0, actual: 4 }));
+        assert_eq!(mismatch.to_string(), "after event 4 array differs from btree: sequence ID 4, expected 0");
+    }
+}
@@ -5,6 +5,12 @@ pub trait DecimalType {
     const MAX: Self;
     const MIN: Self;
     const ONE_HUNDRED: Self;
+
+    /// Build a value from an integer mantissa and a number of decimal places, `mantissa * 10^-scale`
+    fn from_scaled(mantissa: i64, scale: u32) -> Self;
+
+    /// The nearest `f64`, for estimates such as where a price falls between two others
+    fn to_f64(self) -> f64;
 }
 
 #[cfg(feature = "rust_decimal")]
@@ -16,4 +22,14 @@ impl DecimalType for rust_decimal::Decimal {
     const MAX: Self = rust_decimal::Decimal::MAX;
     const MIN: Self = rust_decimal::Decimal::MIN;
     const ONE_HUNDRED: Self = rust_decimal::Decimal::ONE_HUNDRED;
+
+    #[inline(always)]
+    fn from_scaled(mantissa: i64, scale: u32) -> Self {
+        rust_decimal::Decimal::new(mantissa, scale)
+    }
+
+    #[inline(always)]
+    fn to_f64(self) -> f64 {
+        rust_decimal::prelude::ToPrimitive::to_f64(&self).unwrap_or(f64::NAN)
+    }
 }
@@ -1,7 +1,6 @@
 use std::{
     fmt,
     iter::Sum,
-    mem::transmute,
     ops::{Add, Div, Mul, Rem, Sub, SubAssign},
     str::FromStr,
 };
@@ -66,6 +65,21 @@ impl DecimalType for FixedDecimal {
     const MAX: Self = Self::MAX;
     const MIN: Self = Self::MIN;
     const ONE_HUNDRED: Self = Self::ONE_HUNDRED;
+
+    #[inline(always)]
+    fn from_scaled(mantissa: i64, scale: u32) -> Self {
+        let target = Self::SCALE as u32;
+        if scale <= target {
+            Self { raw: mantissa.saturating_mul(Self::power_of_ten(target - scale)) }
+        } else {
+            Self { raw: mantissa / Self::power_of_ten(scale - target) }
+        }
+    }
+
+    #[inline(always)]
+    fn to_f64(self) -> f64 {
+        Self::to_f64(self)
+    }
 }
 
 impl FixedDecimal {
@@ -142,7 +156,7 @@ impl FixedDecimal {
 
     #[inline(always)]
     pub fn from_f64(value: f64) -> Self {
-        let bits: u64 = unsafe { transmute(value) };
+        let bits: u64 = value.to_bits();
         let exp = ((bits >> 52) & 0x7FF) as i32 - 1023;
 
         if exp == 1024 {
@@ -854,7 +868,7 @@ impl<'de> serde::Deserialize<'de> for FixedDecimal {
 mod tests {
     use std::str::FromStr as _;
 
-    use crate::decimals::fixed_decimal::FixedDecimal;
+    use crate::decimals::{decimal_type::DecimalType as _, fixed_decimal::FixedDecimal};
 
     #[test]
     fn test_basic_remainder() {
@@ -932,6 +946,13 @@ mod tests {
         assert_eq!(FixedDecimal::from_str("-0.1").unwrap().to_string(), "-0.1");
     }
 
+    #[test]
+    fn test_from_scaled() {
+        assert_eq!(FixedDecimal::from_scaled(1_234_500, 4).to_string(), "123.45");
+        assert_eq!(FixedDecimal::from_scaled(-75, 0).to_string(), "-75");
+        assert_eq!(FixedDecimal::from_scaled(1_234_567_891_234_567, 15).to_string(), "1.2345678912345");
+    }
+
     #[test]
     fn test_error_cases() {
         assert!(FixedDecimal::from_str("").is_err());
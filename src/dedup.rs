@@ -0,0 +1,215 @@
+//! Suppression of replayed messages.
+//!
+//! Aggregator feeds and reconnect overlaps resend messages already delivered, and the books accept an
+//! event whose sequence ID equals the last one so snapshot runs and trades sharing an ID apply. A
+//! resent trade is then applied twice. [`Deduper`] sits in front of a book or a source and drops an
+//! event identical to one of the last few it passed: the same sequence ID, instrument, order ID, kind,
+//! side, price and size, so distinct orders or instruments sharing a packet's sequence ID all pass.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    formats::source::SourceError,
+    instrument::InstrId,
+    level::Level,
+    metrics::{MetricsRequest, OrderbookMetrics},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+type Key<V> = (u64, InstrId, u64, EventKind, Side, V, V);
+
+#[derive(Debug, Clone)]
+/// Drops exact duplicates among the last `window` events passed, events without a sequence ID always
+/// pass as nothing tells a resend from a repeat
+pub struct Deduper<V: DecimalType, T> {
+    inner: T,
+    window: usize,
+    recent: VecDeque<Key<V>>,
+    /// Number of keys in `recent` per sequence ID, so only events sharing an ID are compared
+    sequence_ids: HashMap<u64, usize>,
+    dropped: u64,
+}
+
+impl<V: DecimalType + Copy + PartialEq, T> Deduper<V, T> {
+    #[inline]
+    #[must_use]
+    pub fn new(inner: T, window: usize) -> Self {
+        Self {
+            inner,
+            window: window.max(1),
+            recent: VecDeque::with_capacity(window.max(1)),
+            sequence_ids: HashMap::new(),
+            dropped: 0,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    #[inline]
+    #[must_use]
+    /// Events dropped as duplicates so far
+    pub const fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Whether `event` repeats one in the window, remembering it when it does not
+    fn is_duplicate(&mut self, event: &Event<V>) -> bool {
+        if event.sequence_id == 0 {
+            return false;
+        }
+        let key = (event.sequence_id, event.instrument, event.order_id, event.kind, event.side, event.price, event.size);
+        if self.sequence_ids.contains_key(&key.0) && self.recent.contains(&key) {
+            self.dropped += 1;
+            return true;
+        }
+        if self.recent.len() == self.window {
+            if let Some((sequence_id, ..)) = self.recent.pop_front() {
+                if let Some(count) = self.sequence_ids.get_mut(&sequence_id) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.sequence_ids.remove(&sequence_id);
+                    }
+                }
+            }
+        }
+        *self.sequence_ids.entry(key.0).or_default() += 1;
+        self.recent.push_back(key);
+        false
+    }
+}
+
+impl<V: DecimalType + Copy + PartialEq, B: OrderBook<V>> OrderBook<V> for Deduper<V, B> {
+    #[inline]
+    fn process_delta(&mut self, event: Event<V>) -> Option<BookDelta<V>> {
+        if self.is_duplicate(&event) {
+            return None;
+        }
+        self.inner.process_delta(event)
+    }
+
+    #[inline]
+    fn best_bid(&mut self) -> Option<Level<V>> {
+        self.inner.best_bid()
+    }
+
+    #[inline]
+    fn best_ask(&mut self) -> Option<Level<V>> {
+        self.inner.best_ask()
+    }
+
+    #[inline]
+    fn levels(&self, side: Side, depth: usize) -> Vec<Level<V>> {
+        self.inner.levels(side, depth)
+    }
+
+    #[inline]
+    fn timestamp(&self) -> Timestamp {
+        self.inner.timestamp()
+    }
+
+    #[inline]
+    fn sequence_id(&self) -> u64 {
+        self.inner.sequence_id()
+    }
+
+    #[inline]
+    fn calculate_metrics_with(&self, depth: usize, request: MetricsRequest) -> OrderbookMetrics<V> {
+        self.inner.calculate_metrics_with(depth, request)
+    }
+}
+
+impl<V, S> Iterator for Deduper<V, S>
+where
+    V: DecimalType + Copy + PartialEq,
+    S: Iterator<Item = Result<Event<V>, SourceError>>,
+{
+    type Item = Result<Event<V>, SourceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.inner.next()? {
+                Ok(event) if self.is_duplicate(&event) => continue,
+                event => return Some(event),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        dedup::Deduper,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::source::MemorySource,
+        instrument::InstrId,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn event(kind: EventKind, size: FixedDecimal, sequence_id: u64) -> Event<FixedDecimal> {
+        Event::new(kind, Side::Sell, fixed!(101), size, Timestamp::from_nanos(1)).with_sequence_id(sequence_id)
+    }
+
+    #[test]
+    fn test_drops_resent_trade() {
+        let mut book = Deduper::new(BTreeOrderBook::new(), 16);
+        book.process(event(EventKind::L2, fixed!(5), 1));
+        book.process(event(EventKind::Trade, fixed!(2), 2));
+        // A reconnect replays the trade, then the feed moves on
+        book.process(event(EventKind::Trade, fixed!(2), 2));
+        book.process(event(EventKind::Trade, fixed!(1), 2));
+        assert_eq!(book.best_ask().map(|level| level.size), Some(fixed!(2)));
+        assert_eq!(book.dropped(), 1);
+    }
+
+    #[test]
+    fn test_window() {
+        let events = vec![
+            event(EventKind::L2, fixed!(1), 1),
+            event(EventKind::L2, fixed!(2), 2),
+            event(EventKind::L2, fixed!(1), 1),
+            event(EventKind::L2, fixed!(3), 3),
+            // Fell out of a window of two
+            event(EventKind::L2, fixed!(1), 1),
+            event(EventKind::L2, fixed!(1), 0),
+            event(EventKind::L2, fixed!(1), 0),
+        ];
+        let mut source = Deduper::new(MemorySource::new(events), 2);
+        let sizes: Vec<_> = source.by_ref().map(|event| event.unwrap().size).collect();
+        assert_eq!(sizes, [fixed!(1), fixed!(2), fixed!(3), fixed!(1), fixed!(1), fixed!(1)]);
+        assert_eq!(source.dropped(), 1);
+    }
+
+    #[test]
+    fn test_keeps_orders_sharing_a_sequence_id() {
+        let add = |order_id| {
+            Event::new(EventKind::Add, Side::Buy, fixed!(100), fixed!(1), Timestamp::from_nanos(1))
+                .with_sequence_id(7)
+                .with_order_id(order_id)
+        };
+        let events = vec![add(1), add(2), add(1), add(2).with_instrument(InstrId(3))];
+        let mut source = Deduper::new(MemorySource::new(events), 16);
+        let order_ids: Vec<_> = source.by_ref().map(|event| event.unwrap().order_id).collect();
+        assert_eq!((order_ids, source.dropped()), (vec![1, 2, 2], 1));
+    }
+}
@@ -1,20 +1,81 @@
-use crate::{decimals::decimal_type::DecimalType, event_kind::EventKind, level::Level, side::Side};
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event_kind::EventKind,
+    instrument::InstrId,
+    level::Level,
+    side::{Aggressor, Side},
+    timestamp::Timestamp,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+/// A single market data update. Build one with [`Event::new`] or [`Event::builder`], fields added later
+/// arrive with defaults so existing callers and serialised events keep working.
 pub struct Event<V: DecimalType> {
     pub kind: EventKind,
     pub side: Side,
     pub price: V,
     pub size: V,
-    pub timestamp: i64,
+    /// Exchange timestamp
+    pub timestamp: Timestamp,
+    /// When the event was received locally, zero when not recorded
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub local_timestamp: Timestamp,
+    #[cfg_attr(feature = "serde", serde(default))]
     pub sequence_id: u64,
+    /// Instrument the event belongs to, for streams that carry more than one
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub instrument: InstrId,
+    /// Order the event refers to, 0 for events that are not order level
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub order_id: u64,
+    /// Queue priority assigned by the venue where it publishes one, lower first
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub priority: Option<u64>,
+    /// Side that took liquidity on a trade, while `side` is the resting side the book applies it to
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub aggressor: Aggressor,
 }
 
 impl<V: DecimalType> Event<V> {
     #[inline(always)]
     #[must_use]
-    pub const fn new(kind: EventKind, side: Side, price: V, size: V, timestamp: i64) -> Self {
-        Self { kind, side, price, size, timestamp, sequence_id: 0 }
+    /// An event at `timestamp`, with every optional field left at its default
+    pub fn new(kind: EventKind, side: Side, price: V, size: V, timestamp: Timestamp) -> Self {
+        Self {
+            kind,
+            side,
+            price,
+            size,
+            timestamp,
+            local_timestamp: Timestamp::ZERO,
+            sequence_id: 0,
+            instrument: InstrId(0),
+            order_id: 0,
+            priority: None,
+            aggressor: Aggressor::Unknown,
+        }
+    }
+
+    #[inline(always)]
+    #[must_use]
+    /// A trade reported by the side of its aggressor, applied to the opposite side of the book
+    pub fn trade(aggressor: Side, price: V, size: V, timestamp: Timestamp) -> Self {
+        Self::new(EventKind::Trade, aggressor.opposite(), price, size, timestamp).with_aggressor(aggressor.into())
+    }
+
+    #[inline(always)]
+    #[must_use]
+    /// A [`Clear`](EventKind::Clear) of both sides, as leads a snapshot
+    pub fn clear(timestamp: Timestamp) -> Self {
+        Self::new(EventKind::Clear, Side::Buy, V::ZERO, V::ZERO, timestamp)
+    }
+
+    #[inline(always)]
+    /// Start an event of `kind` on `side`, every other field defaults to zero or `None`
+    pub fn builder(kind: EventKind, side: Side) -> EventBuilder<V> {
+        EventBuilder { event: Self::new(kind, side, V::ZERO, V::ZERO, Timestamp::ZERO) }
     }
 
     #[inline(always)]
@@ -23,9 +84,169 @@ impl<V: DecimalType> Event<V> {
         Self { sequence_id, ..self }
     }
 
+    #[inline(always)]
+    #[must_use]
+    pub fn with_instrument(self, instrument: InstrId) -> Self {
+        Self { instrument, ..self }
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub fn with_order_id(self, order_id: u64) -> Self {
+        Self { order_id, ..self }
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub fn with_priority(self, priority: u64) -> Self {
+        Self { priority: Some(priority), ..self }
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub fn with_aggressor(self, aggressor: Aggressor) -> Self {
+        Self { aggressor, ..self }
+    }
+
+    #[inline(always)]
+    #[must_use]
+    /// Side that took liquidity, taken as the opposite of the resting side when the venue did not say
+    pub const fn taker_side(&self) -> Side {
+        match self.aggressor.side() {
+            Some(side) => side,
+            None => self.side.opposite(),
+        }
+    }
+
     #[inline(always)]
     #[must_use]
     pub fn to_level(self) -> Level<V> {
         Level { price: self.price, size: self.size }
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+#[must_use]
+/// Builds an [`Event`] field by field, see [`Event::builder`]
+pub struct EventBuilder<V: DecimalType> {
+    event: Event<V>,
+}
+
+impl<V: DecimalType> EventBuilder<V> {
+    #[inline(always)]
+    pub fn price(mut self, price: V) -> Self {
+        self.event.price = price;
+        self
+    }
+
+    #[inline(always)]
+    pub fn size(mut self, size: V) -> Self {
+        self.event.size = size;
+        self
+    }
+
+    #[inline(always)]
+    pub fn exchange_ts(mut self, timestamp: Timestamp) -> Self {
+        self.event.timestamp = timestamp;
+        self
+    }
+
+    #[inline(always)]
+    pub fn local_ts(mut self, timestamp: Timestamp) -> Self {
+        self.event.local_timestamp = timestamp;
+        self
+    }
+
+    #[inline(always)]
+    pub const fn sequence(mut self, sequence_id: u64) -> Self {
+        self.event.sequence_id = sequence_id;
+        self
+    }
+
+    #[inline(always)]
+    pub const fn instrument(mut self, instrument: InstrId) -> Self {
+        self.event.instrument = instrument;
+        self
+    }
+
+    #[inline(always)]
+    pub const fn order_id(mut self, order_id: u64) -> Self {
+        self.event.order_id = order_id;
+        self
+    }
+
+    #[inline(always)]
+    pub const fn priority(mut self, priority: u64) -> Self {
+        self.event.priority = Some(priority);
+        self
+    }
+
+    #[inline(always)]
+    pub const fn aggressor(mut self, aggressor: Aggressor) -> Self {
+        self.event.aggressor = aggressor;
+        self
+    }
+
+    #[inline(always)]
+    pub fn build(self) -> Event<V> {
+        self.event
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        side::{Aggressor, Side},
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_builder() {
+        let event = Event::builder(EventKind::Add, Side::Sell)
+            .price(fixed!(100.5))
+            .size(fixed!(2))
+            .exchange_ts(Timestamp::from_nanos(10))
+            .local_ts(Timestamp::from_nanos(12))
+            .sequence(3)
+            .order_id(42)
+            .build();
+        let expected = Event::new(EventKind::Add, Side::Sell, fixed!(100.5), fixed!(2), Timestamp::from_nanos(10))
+            .with_sequence_id(3)
+            .with_order_id(42);
+        assert_eq!(event, Event { local_timestamp: Timestamp::from_nanos(12), ..expected });
+        assert_eq!(
+            Event::builder(EventKind::Trade, Side::Buy).build(),
+            Event::new(EventKind::Trade, Side::Buy, fixed!(0), fixed!(0), Timestamp::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_trade_aggressor() {
+        let trade = Event::trade(Side::Buy, fixed!(101), fixed!(1), Timestamp::from_nanos(5));
+        assert_eq!((trade.side, trade.aggressor, trade.taker_side()), (Side::Sell, Aggressor::Buy, Side::Buy));
+        // Without an aggressor the resting side is all there is to go on
+        let trade = Event::new(EventKind::Trade, Side::Buy, fixed!(100), fixed!(1), Timestamp::from_nanos(5));
+        assert_eq!((trade.aggressor, trade.taker_side()), (Aggressor::Unknown, Side::Sell));
+        assert_eq!(Aggressor::Sell.resting_side(), Some(Side::Buy));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_roundtrip() {
+        use crate::decimals::fixed_decimal::FixedDecimal;
+
+        let event = Event::new(EventKind::L2, Side::Buy, fixed!(100.5), fixed!(2), Timestamp::from_nanos(1_700_000_000))
+            .with_sequence_id(7);
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(serde_json::from_str::<Event<FixedDecimal>>(&json).unwrap(), event);
+
+        // Fields missing from older payloads take their defaults
+        let json = r#"{"kind": "Trade", "side": "sell", "price": "101", "size": 3, "timestamp": 5}"#;
+        let event = serde_json::from_str::<Event<FixedDecimal>>(json).unwrap();
+        assert_eq!(event, Event::new(EventKind::Trade, Side::Sell, fixed!(101), fixed!(3), Timestamp::from_nanos(5)));
+    }
+}
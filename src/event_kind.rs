@@ -1,9 +1,56 @@
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EventKind {
-    /// Trade events
+    /// Trade events, applied to the resting `side` whatever `aggressor` says
     Trade,
     /// Best Bid/Offer events
     BBO,
     /// Level 2 events (prices and sizes)
     L2,
+    /// Level 2 events belonging to a full book snapshot, applied like `L2`. Sources lead each snapshot
+    /// with a `Clear` so it replaces the book rather than merging into it.
+    Snapshot,
+    /// Level 3: order `order_id` joins the book at `price` for `size`
+    Add,
+    /// Level 3: `size` of order `order_id` is cancelled, leaving the book when nothing remains
+    Cancel,
+    /// Level 3: order `order_id` now rests at `price` for `size`
+    Modify,
+    /// Level 3: `size` of resting order `order_id` trades at `price`
+    Execute,
+    /// Both sides of the book are emptied, as on an auction, halt or reconnect reset
+    Clear,
+    /// The `side` of the book is emptied
+    ClearSide,
+}
+
+impl EventKind {
+    /// Every kind, in declaration order so `kind as usize` indexes it
+    pub const ALL: [Self; 10] = [
+        Self::Trade,
+        Self::BBO,
+        Self::L2,
+        Self::Snapshot,
+        Self::Add,
+        Self::Cancel,
+        Self::Modify,
+        Self::Execute,
+        Self::Clear,
+        Self::ClearSide,
+    ];
+
+    #[inline(always)]
+    #[must_use]
+    /// Whether the event refers to a single order rather than a price level. The Level 2 books ignore
+    /// these events.
+    pub const fn is_order_level(self) -> bool {
+        matches!(self, Self::Add | Self::Cancel | Self::Modify | Self::Execute)
+    }
+
+    #[inline(always)]
+    #[must_use]
+    /// Whether the event empties the book or one side of it
+    pub const fn is_clear(self) -> bool {
+        matches!(self, Self::Clear | Self::ClearSide)
+    }
 }
@@ -0,0 +1,248 @@
+//! A common interface over the venue normalizers, so books can be fed from any of them uniformly.
+
+use std::{collections::HashMap, str::FromStr};
+
+use crate::{
+    books::interface::OrderBook,
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    feeds::{
+        bybit::BybitNormalizer, checksum, coinbase::CoinbaseNormalizer, deribit::DeribitNormalizer, kraken::KrakenNormalizer,
+        okx::OkxNormalizer, FeedError,
+    },
+};
+
+/// Normalizes the messages of one venue subscription (a single symbol's book channel).
+pub trait ExchangeAdapter<V: DecimalType> {
+    /// Parse a raw message into events, an empty result for messages that carry no book data
+    ///
+    /// A `Vec` rather than an inline small vector: snapshots run to hundreds of levels, so an inline
+    /// buffer sized for updates would spill to the heap on exactly the messages that matter, and the
+    /// crate keeps its dependencies to what the books need.
+    fn parse_message(&mut self, message: &[u8]) -> Result<Vec<Event<V>>, FeedError>;
+    /// Whether updates cannot be trusted until a (re)snapshot has been received
+    fn needs_snapshot(&self) -> bool;
+    /// Canonical symbol of the book the subscription feeds
+    fn symbol(&self) -> &str;
+    /// Check the book after the events of the last message were applied, for venues publishing a checksum
+    fn verify_book(&mut self, _book: &dyn OrderBook<V>) -> Result<(), FeedError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+/// Two-way mapping between venue symbols (`XBT/USD`, `BTC-USDT`) and canonical ones (`BTC-USD`)
+pub struct SymbolMap {
+    to_canonical: HashMap<String, String>,
+    to_venue: HashMap<String, String>,
+}
+
+impl SymbolMap {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn insert(&mut self, venue: impl Into<String>, canonical: impl Into<String>) {
+        let (venue, canonical) = (venue.into(), canonical.into());
+        self.to_venue.insert(canonical.clone(), venue.clone());
+        self.to_canonical.insert(venue, canonical);
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn canonical<'a>(&'a self, venue: &'a str) -> &'a str {
+        self.to_canonical.get(venue).map_or(venue, String::as_str)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn venue<'a>(&'a self, canonical: &'a str) -> &'a str {
+        self.to_venue.get(canonical).map_or(canonical, String::as_str)
+    }
+}
+
+#[derive(Debug)]
+/// Binds a venue normalizer to the canonical symbol of the book it feeds and tracks whether the
+/// subscription is in sync.
+pub struct VenueAdapter<N> {
+    symbol: String,
+    normalizer: N,
+    synced: bool,
+    /// Checksum published with the last message, for venues that send one
+    checksum: Option<u32>,
+}
+
+impl<N: Default> VenueAdapter<N> {
+    #[inline]
+    #[must_use]
+    pub fn new(symbol: impl Into<String>) -> Self {
+        Self { symbol: symbol.into(), normalizer: N::default(), synced: false, checksum: None }
+    }
+}
+
+impl<N> VenueAdapter<N> {
+    #[inline]
+    #[must_use]
+    pub const fn normalizer(&self) -> &N {
+        &self.normalizer
+    }
+
+    /// Track sync state from the outcome of normalizing one message
+    fn track<V: DecimalType>(&mut self, result: Result<Vec<Event<V>>, FeedError>) -> Result<Vec<Event<V>>, FeedError> {
+        match &result {
+            // Snapshots are led by a clear, which is all there is of the snapshot of an empty book
+            Ok(events) if events.first().is_some_and(|e| e.kind == EventKind::Clear) => self.synced = true,
+            Ok(_) => {}
+            Err(err) => self.track_error(err),
+        }
+        result
+    }
+
+    #[inline]
+    fn track_error(&mut self, err: &FeedError) {
+        if matches!(err, FeedError::SequenceGap { .. } | FeedError::MissingSnapshot | FeedError::ChecksumMismatch { .. }) {
+            self.synced = false;
+        }
+    }
+}
+
+#[inline]
+fn as_str(message: &[u8]) -> Result<&str, FeedError> {
+    std::str::from_utf8(message).map_err(|err| FeedError::Malformed(err.to_string()))
+}
+
+macro_rules! impl_json_adapter {
+    ($($normalizer:ty),*) => {
+        $(
+            impl<V: DecimalType + FromStr> ExchangeAdapter<V> for VenueAdapter<$normalizer> {
+                fn parse_message(&mut self, message: &[u8]) -> Result<Vec<Event<V>>, FeedError> {
+                    let result = as_str(message).and_then(|message| self.normalizer.normalize(message));
+                    self.track(result)
+                }
+
+                fn needs_snapshot(&self) -> bool {
+                    !self.synced
+                }
+
+                fn symbol(&self) -> &str {
+                    &self.symbol
+                }
+            }
+        )*
+    };
+}
+
+impl_json_adapter!(CoinbaseNormalizer, BybitNormalizer, OkxNormalizer, DeribitNormalizer);
+
+impl<V: DecimalType + FromStr + std::fmt::Display> ExchangeAdapter<V> for VenueAdapter<KrakenNormalizer> {
+    fn parse_message(&mut self, message: &[u8]) -> Result<Vec<Event<V>>, FeedError> {
+        let parsed = as_str(message).and_then(|message| self.normalizer.normalize(message));
+        let result = parsed.map(|parsed| {
+            self.checksum = parsed.checksum;
+            parsed.events
+        });
+        self.track(result)
+    }
+
+    fn needs_snapshot(&self) -> bool {
+        !self.synced
+    }
+
+    fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    fn verify_book(&mut self, book: &dyn OrderBook<V>) -> Result<(), FeedError> {
+        let Some(expected) = self.checksum.take() else {
+            return Ok(());
+        };
+        let computed = self.normalizer.checksum(book);
+        checksum::verify(expected, computed).inspect_err(|err| self.track_error(err))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, interface::OrderBook as _, manager::BookManager},
+        decimals::fixed_decimal::FixedDecimal,
+        feeds::{
+            adapter::{ExchangeAdapter, SymbolMap, VenueAdapter},
+            coinbase::CoinbaseNormalizer,
+            kraken::KrakenNormalizer,
+            okx::OkxNormalizer,
+            FeedError,
+        },
+        fixed,
+    };
+
+    #[test]
+    fn test_symbol_map() {
+        let mut symbols = SymbolMap::new();
+        symbols.insert("XBT/USD", "BTC-USD");
+        assert_eq!(symbols.canonical("XBT/USD"), "BTC-USD");
+        assert_eq!(symbols.venue("BTC-USD"), "XBT/USD");
+        assert_eq!(symbols.canonical("ETH-USD"), "ETH-USD");
+    }
+
+    #[test]
+    fn test_heterogeneous_adapters() {
+        let mut symbols = SymbolMap::new();
+        symbols.insert("XBT/USD", "BTC-USD");
+        let mut manager = BookManager::<FixedDecimal, ArrayOrderbook<10, FixedDecimal>>::new();
+        let mut adapters: Vec<Box<dyn ExchangeAdapter<FixedDecimal>>> = vec![
+            Box::new(VenueAdapter::<CoinbaseNormalizer>::new("ETH-USD")),
+            Box::new(VenueAdapter::<KrakenNormalizer>::new(symbols.canonical("XBT/USD"))),
+        ];
+        assert!(adapters.iter().all(|adapter| adapter.needs_snapshot()));
+
+        let coinbase = br#"{"type":"snapshot","product_id":"ETH-USD","bids":[["1800.10","2"]],"asks":[["1800.20","3"]]}"#;
+        let kraken = br#"[0,{"as":[["5541.30000","2.50700000","1534614248.123678"]],"bs":[["5541.20000","1.52900000","1534614248.765567"]]},"book-10","XBT/USD"]"#;
+        assert_eq!(manager.feed(adapters[0].as_mut(), coinbase), Ok(3));
+        assert_eq!(manager.feed(adapters[1].as_mut(), kraken), Ok(3));
+        assert!(adapters.iter().all(|adapter| !adapter.needs_snapshot()));
+
+        assert_eq!(manager.len(), 2);
+        assert_eq!(manager.book_mut("ETH-USD").unwrap().best_bid().unwrap().price, fixed!(1800.1));
+        assert_eq!(manager.book_mut("BTC-USD").unwrap().best_ask().unwrap().price, fixed!(5541.3));
+
+        let bad_checksum = br#"[0,{"a":[["5541.30000","1.00000000","1534614249.000000"]],"c":"1"},"book-10","XBT/USD"]"#;
+        assert!(matches!(manager.feed(adapters[1].as_mut(), bad_checksum), Err(FeedError::ChecksumMismatch { .. })));
+        assert!(adapters[1].needs_snapshot());
+        // The book no longer matches the venue, so it is cleared and updates wait for the resnapshot
+        assert!(manager.book_mut("BTC-USD").unwrap().best_ask().is_none());
+        let update = br#"[0,{"b":[["5541.10000","1.00000000","1534614250.000000"]],"c":"0"},"book-10","XBT/USD"]"#;
+        assert_eq!(manager.feed(adapters[1].as_mut(), update), Err(FeedError::MissingSnapshot));
+        assert!(manager.book_mut("BTC-USD").unwrap().best_bid().is_none());
+        let resnapshot = br#"[0,{"as":[["5541.40000","1.00000000","1534614251.000000"]],"bs":[["5541.20000","1.00000000","1534614251.000000"]]},"book-10","XBT/USD"]"#;
+        assert_eq!(manager.feed(adapters[1].as_mut(), resnapshot), Ok(3));
+        assert_eq!(manager.book_mut("BTC-USD").unwrap().best_ask().unwrap().price, fixed!(5541.4));
+    }
+
+    #[test]
+    fn test_empty_snapshot_syncs() {
+        let mut manager = BookManager::<FixedDecimal, ArrayOrderbook<10, FixedDecimal>>::new();
+        let mut adapter = VenueAdapter::<CoinbaseNormalizer>::new("BTC-USD");
+        let snapshot = br#"{"type":"snapshot","product_id":"BTC-USD","bids":[],"asks":[]}"#;
+        assert_eq!(manager.feed(&mut adapter, snapshot), Ok(1));
+        assert!(!ExchangeAdapter::<FixedDecimal>::needs_snapshot(&adapter));
+        let update =
+            br#"{"type":"l2update","product_id":"BTC-USD","time":"2019-08-14T20:42:27.265Z","changes":[["buy","10101.8","1"]]}"#;
+        assert_eq!(manager.feed(&mut adapter, update), Ok(1));
+        assert_eq!(manager.book_mut("BTC-USD").unwrap().best_bid().unwrap().price, fixed!(10101.8));
+    }
+
+    #[test]
+    fn test_gap_requires_snapshot() {
+        let mut adapter = VenueAdapter::<OkxNormalizer>::new("BTC-USDT");
+        let update = br#"{"action":"update","data":[{"asks":[],"bids":[],"ts":"1","checksum":0,"prevSeqId":1,"seqId":2}]}"#;
+        let result: Result<Vec<_>, _> = ExchangeAdapter::<FixedDecimal>::parse_message(&mut adapter, update);
+        assert_eq!(result.unwrap_err(), FeedError::MissingSnapshot);
+        assert!(ExchangeAdapter::<FixedDecimal>::needs_snapshot(&adapter));
+    }
+}
@@ -0,0 +1,155 @@
+//! Normalizer for the Bybit v5 `orderbook.{depth}.{symbol}` topic.
+//!
+//! The topic sends a `snapshot` on subscription and `delta` messages afterwards, each carrying
+//! absolute sizes (zero removes the level). Per the venue documentation a new snapshot must replace
+//! the local book, and a `delta` with update ID `u == 1` is a service restart to be treated as one.
+//! The update ID `u` becomes the event `sequence_id`; the cross sequence `seq` is kept on the
+//! normalizer for comparing against other topics of the same symbol.
+
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    feeds::{parse_decimal, FeedError},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Deserialize)]
+struct Message<'a> {
+    #[serde(rename = "type")]
+    kind: Option<&'a str>,
+    /// Millisecond timestamp the message was generated
+    ts: Option<i64>,
+    #[serde(borrow)]
+    data: Option<Data<'a>>,
+}
+
+#[derive(Deserialize)]
+struct Data<'a> {
+    #[serde(borrow)]
+    b: Vec<(&'a str, &'a str)>,
+    #[serde(borrow)]
+    a: Vec<(&'a str, &'a str)>,
+    u: u64,
+    seq: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct BybitNormalizer {
+    cross_sequence: u64,
+}
+
+impl BybitNormalizer {
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { cross_sequence: 0 }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Cross sequence (`seq`) of the last book message
+    pub const fn cross_sequence(&self) -> u64 {
+        self.cross_sequence
+    }
+
+    /// Translate a raw orderbook topic message into crate events.
+    ///
+    /// Snapshots (and restart deltas with `u == 1`) produce an [`EventKind::Clear`] then
+    /// [`EventKind::Snapshot`] events so they replace the book, deltas produce [`EventKind::L2`] events. Timestamps
+    /// are converted to nanoseconds. Operation responses such as subscription acks yield no events.
+    pub fn normalize<V>(&mut self, message: &str) -> Result<Vec<Event<V>>, FeedError>
+    where
+        V: DecimalType + FromStr,
+    {
+        let message = serde_json::from_str::<Message>(message)?;
+        let (Some(kind), Some(data)) = (message.kind, message.data) else {
+            return Ok(Vec::new());
+        };
+        let kind = match kind {
+            "snapshot" => EventKind::Snapshot,
+            "delta" if data.u == 1 => EventKind::Snapshot,
+            "delta" => EventKind::L2,
+            other => return Err(FeedError::Malformed(format!("unknown message type {other}"))),
+        };
+        let ts = Timestamp::from_millis(message.ts.unwrap_or_default());
+        self.cross_sequence = data.seq;
+
+        let mut events = Vec::with_capacity(1 + data.b.len() + data.a.len());
+        if kind == EventKind::Snapshot {
+            events.push(Event::clear(ts).with_sequence_id(data.u));
+        }
+        for (side, levels) in [(Side::Buy, data.b), (Side::Sell, data.a)] {
+            for (price, size) in levels {
+                let event = Event::new(kind, side, parse_decimal(price)?, parse_decimal(size)?, ts);
+                events.push(event.with_sequence_id(data.u));
+            }
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, interface::OrderBook as _},
+        decimals::fixed_decimal::FixedDecimal,
+        event_kind::EventKind,
+        feeds::bybit::BybitNormalizer,
+        fixed,
+    };
+
+    const SNAPSHOT: &str = r#"{"topic":"orderbook.50.BTCUSDT","type":"snapshot","ts":1672304484978,"data":{"s":"BTCUSDT","b":[["16493.50","0.006"],["16493.00","0.100"]],"a":[["16611.00","0.029"],["16612.00","0.213"]],"u":18521288,"seq":7961638724},"cts":1672304484976}"#;
+    const DELTA: &str = r#"{"topic":"orderbook.50.BTCUSDT","type":"delta","ts":1687940967466,"data":{"s":"BTCUSDT","b":[["16493.50","0"]],"a":[["16611.00","0.5"]],"u":18521289,"seq":7961638725},"cts":1687940967464}"#;
+    const RESTART: &str = r#"{"topic":"orderbook.50.BTCUSDT","type":"delta","ts":1687940967500,"data":{"s":"BTCUSDT","b":[["16400.00","1"]],"a":[["16700.00","2"]],"u":1,"seq":7961638800},"cts":1687940967498}"#;
+
+    #[test]
+    fn test_snapshot_and_delta() {
+        let mut normalizer = BybitNormalizer::new();
+        let mut lob = ArrayOrderbook::<10, FixedDecimal>::new();
+
+        let events = normalizer.normalize::<FixedDecimal>(SNAPSHOT).unwrap();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0].kind, EventKind::Clear);
+        assert!(events[1..].iter().all(|e| e.kind == EventKind::Snapshot && e.sequence_id == 18521288));
+        assert_eq!(events[0].timestamp.as_nanos(), 1_672_304_484_978_000_000);
+        assert_eq!(normalizer.cross_sequence(), 7961638724);
+        events.into_iter().for_each(|e| lob.process(e));
+
+        let events = normalizer.normalize::<FixedDecimal>(DELTA).unwrap();
+        assert!(events.iter().all(|e| e.kind == EventKind::L2));
+        events.into_iter().for_each(|e| lob.process(e));
+        assert_eq!(lob.sequence_id, 18521289);
+        assert_eq!(lob.best_bid().unwrap().price, fixed!(16493));
+        assert_eq!(lob.best_ask().unwrap().size, fixed!(0.5));
+    }
+
+    #[test]
+    fn test_restart_delta_clears_book() {
+        let mut normalizer = BybitNormalizer::new();
+        let mut lob = ArrayOrderbook::<10, FixedDecimal>::new();
+        normalizer.normalize::<FixedDecimal>(SNAPSHOT).unwrap().into_iter().for_each(|e| lob.process(e));
+        normalizer.normalize::<FixedDecimal>(DELTA).unwrap().into_iter().for_each(|e| lob.process(e));
+
+        let events = normalizer.normalize::<FixedDecimal>(RESTART).unwrap();
+        assert_eq!(events[0].kind, EventKind::Clear);
+        assert!(events[1..].iter().all(|e| e.kind == EventKind::Snapshot));
+        events.into_iter().for_each(|e| lob.process(e));
+        assert_eq!(lob.sequence_id, 1);
+        assert_eq!((lob.bids.len(), lob.asks.len()), (1, 1));
+        assert_eq!(lob.best_bid().unwrap().price, fixed!(16400));
+        assert_eq!(lob.best_ask().unwrap().price, fixed!(16700));
+    }
+
+    #[test]
+    fn test_operation_response() {
+        let ack = r#"{"success":true,"ret_msg":"","conn_id":"abc","op":"subscribe"}"#;
+        assert!(BybitNormalizer::new().normalize::<FixedDecimal>(ack).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+//! Book checksum verification shared by venues that publish a CRC32 of their top levels.
+
+use crate::feeds::FeedError;
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+#[derive(Debug, Clone, Copy)]
+/// Incremental CRC32 (IEEE 802.3) hasher, so checksum input never has to be concatenated up front
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc32 {
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { state: u32::MAX }
+    }
+
+    #[inline]
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state = CRC32_TABLE[((self.state ^ byte as u32) & 0xFF) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn finish(self) -> u32 {
+        !self.state
+    }
+}
+
+#[inline]
+#[must_use]
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut hasher = Crc32::new();
+    hasher.update(bytes);
+    hasher.finish()
+}
+
+#[inline]
+/// Compare a venue-published checksum with the one computed from the local book.
+///
+/// A mismatch means the local book has diverged from the venue and should be resubscribed.
+pub fn verify(expected: u32, computed: u32) -> Result<(), FeedError> {
+    if expected == computed {
+        Ok(())
+    } else {
+        Err(FeedError::ChecksumMismatch { expected, computed })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::feeds::{
+        checksum::{crc32, verify, Crc32},
+        FeedError,
+    };
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let mut hasher = Crc32::new();
+        hasher.update(b"1234");
+        hasher.update(b"56789");
+        assert_eq!(hasher.finish(), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_verify() {
+        assert_eq!(verify(7, 7), Ok(()));
+        assert_eq!(verify(7, 8), Err(FeedError::ChecksumMismatch { expected: 7, computed: 8 }));
+    }
+}
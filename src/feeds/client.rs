@@ -0,0 +1,355 @@
+//! Connection driver for live venue feeds.
+//!
+//! [`FeedClient`] owns the subscription life cycle: connecting, sending subscription requests,
+//! answering pings, and reconnecting with a resubscription (and therefore a fresh snapshot)
+//! whenever the connection drops or the adapter loses sync. The first reconnect is immediate, and
+//! later consecutive ones back off exponentially with jitter, so clients do not retry a venue that is
+//! down in lockstep. The socket itself is abstracted behind [`Transport`], so any websocket
+//! implementation (tungstenite, a vendor SDK, or a recorded session in tests) can be plugged in
+//! without this crate depending on an async runtime.
+//! Enabled by the `ws` feature.
+
+use std::{collections::hash_map::RandomState, fmt, hash::BuildHasher, sync::mpsc::Sender, thread, time::Duration};
+
+use crate::{
+    books::interface::OrderBook,
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    feeds::{adapter::ExchangeAdapter, FeedError},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close,
+}
+
+/// A message-oriented connection to a venue.
+pub trait Transport {
+    /// (Re)open the connection, dropping any previous one
+    fn connect(&mut self) -> Result<(), String>;
+    fn send(&mut self, frame: Frame) -> Result<(), String>;
+    /// Block until the next frame arrives
+    fn recv(&mut self) -> Result<Frame, String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientError {
+    Transport(String),
+    Feed(FeedError),
+    /// The connection could not be re-established within the configured attempts
+    ReconnectsExhausted,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(reason) => write!(f, "transport error: {reason}"),
+            Self::Feed(err) => write!(f, "feed error: {err}"),
+            Self::ReconnectsExhausted => write!(f, "reconnect attempts exhausted"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Poll {
+    /// A book message was normalized into this many events
+    Events(usize),
+    /// A control frame (ping/pong) or a message without book data was handled
+    Idle,
+    /// The connection was re-established and the subscriptions resent
+    Reconnected,
+}
+
+#[derive(Debug)]
+pub struct FeedClient<T, A> {
+    transport: T,
+    adapter: A,
+    subscriptions: Vec<String>,
+    max_reconnects: usize,
+    reconnects: usize,
+    /// Wait before the second consecutive reconnect, doubling for each one after up to `max_backoff`
+    backoff: Duration,
+    max_backoff: Duration,
+    connected: bool,
+}
+
+impl<T: Transport, A> FeedClient<T, A> {
+    #[inline]
+    #[must_use]
+    pub fn new(transport: T, adapter: A) -> Self {
+        Self {
+            transport,
+            adapter,
+            subscriptions: Vec::new(),
+            max_reconnects: 5,
+            reconnects: 0,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            connected: false,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Add a subscription request sent after every (re)connect
+    pub fn with_subscription(mut self, request: impl Into<String>) -> Self {
+        self.subscriptions.push(request.into());
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// Consecutive reconnect attempts allowed before giving up, reset once events flow again
+    pub fn with_max_reconnects(self, max_reconnects: usize) -> Self {
+        Self { max_reconnects, ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Wait `initial` before the second consecutive reconnect and double it for each one after, up to
+    /// `max`, 100 ms up to 10 s by default. Each wait is drawn at random from its upper half.
+    pub fn with_backoff(self, initial: Duration, max: Duration) -> Self {
+        Self { backoff: initial, max_backoff: max, ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn adapter(&self) -> &A {
+        &self.adapter
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Open the connection and send the subscription requests
+    pub fn connect(&mut self) -> Result<(), ClientError> {
+        self.transport.connect().map_err(ClientError::Transport)?;
+        for request in &self.subscriptions {
+            self.transport.send(Frame::Text(request.clone())).map_err(ClientError::Transport)?;
+        }
+        self.connected = true;
+        Ok(())
+    }
+
+    fn reconnect(&mut self) -> Result<Poll, ClientError> {
+        self.connected = false;
+        while self.reconnects < self.max_reconnects {
+            thread::sleep(self.next_backoff());
+            self.reconnects += 1;
+            if self.connect().is_ok() {
+                return Ok(Poll::Reconnected);
+            }
+        }
+        Err(ClientError::ReconnectsExhausted)
+    }
+
+    /// Wait before the next reconnect attempt, none for the first after events last flowed
+    fn next_backoff(&self) -> Duration {
+        let Some(doublings) = self.reconnects.checked_sub(1) else {
+            return Duration::ZERO;
+        };
+        let ceiling = self.backoff.saturating_mul(1 << doublings.min(31)).min(self.max_backoff);
+        let half = ceiling / 2;
+        let nanos = u64::try_from(half.as_nanos()).unwrap_or(u64::MAX);
+        half + Duration::from_nanos(RandomState::new().hash_one(self.reconnects) % nanos.saturating_add(1))
+    }
+
+    /// Receive the next data payload, answering control frames and reconnecting on connection loss
+    fn next_payload(&mut self) -> Result<Vec<u8>, Result<Poll, ClientError>> {
+        if !self.connected {
+            return Err(self.reconnect());
+        }
+        let payload = match self.transport.recv() {
+            Ok(Frame::Text(text)) => text.into_bytes(),
+            Ok(Frame::Binary(bytes)) => bytes,
+            Ok(Frame::Ping(payload)) => {
+                return Err(match self.transport.send(Frame::Pong(payload)) {
+                    Ok(()) => Ok(Poll::Idle),
+                    Err(_) => self.reconnect(),
+                });
+            }
+            Ok(Frame::Pong(_)) => return Err(Ok(Poll::Idle)),
+            Ok(Frame::Close) | Err(_) => return Err(self.reconnect()),
+        };
+        Ok(payload)
+    }
+
+    /// Resubscribe when a feed error left the adapter out of sync
+    fn settle<V>(&mut self, result: Result<usize, FeedError>) -> Result<Poll, ClientError>
+    where
+        V: DecimalType,
+        A: ExchangeAdapter<V>,
+    {
+        match result {
+            Ok(0) => Ok(Poll::Idle),
+            Ok(count) => {
+                self.reconnects = 0;
+                Ok(Poll::Events(count))
+            }
+            Err(_) if self.adapter.needs_snapshot() => self.reconnect(),
+            Err(err) => Err(ClientError::Feed(err)),
+        }
+    }
+
+    /// Receive and handle a single frame, applying any normalized events to `book`.
+    ///
+    /// Closed connections, transport failures and feed errors that leave the adapter out of sync
+    /// (sequence gaps, checksum mismatches) trigger a reconnect so the venue resends a snapshot.
+    /// Other feed errors, such as a single malformed message, are returned without reconnecting.
+    pub fn poll<V, B>(&mut self, book: &mut B) -> Result<Poll, ClientError>
+    where
+        V: DecimalType,
+        A: ExchangeAdapter<V>,
+        B: OrderBook<V>,
+    {
+        let payload = match self.next_payload() {
+            Ok(payload) => payload,
+            Err(handled) => return handled,
+        };
+        let result = self.adapter.parse_message(&payload).and_then(|events| {
+            let count = events.len();
+            events.into_iter().for_each(|event| book.process(event));
+            self.adapter.verify_book(book).map(|()| count)
+        });
+        self.settle(result)
+    }
+
+    /// Like [`FeedClient::poll`], but forwards the events to another thread which maintains the
+    /// books itself. Venue checksums cannot be verified this way. Events sent after the receiver
+    /// was dropped are discarded.
+    pub fn poll_channel<V>(&mut self, sender: &Sender<Event<V>>) -> Result<Poll, ClientError>
+    where
+        V: DecimalType,
+        A: ExchangeAdapter<V>,
+    {
+        let payload = match self.next_payload() {
+            Ok(payload) => payload,
+            Err(handled) => return handled,
+        };
+        let result = self.adapter.parse_message(&payload).map(|events| {
+            let count = events.len();
+            events.into_iter().for_each(|event| _ = sender.send(event));
+            count
+        });
+        self.settle(result)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::{collections::VecDeque, sync::mpsc, time::Duration};
+
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, interface::OrderBook as _},
+        decimals::fixed_decimal::FixedDecimal,
+        event_kind::EventKind,
+        feeds::{
+            adapter::VenueAdapter,
+            client::{ClientError, FeedClient, Frame, Poll, Transport},
+            okx::OkxNormalizer,
+        },
+        fixed,
+    };
+
+    #[derive(Default)]
+    struct MockTransport {
+        sessions: VecDeque<VecDeque<Frame>>,
+        current: VecDeque<Frame>,
+        sent: Vec<Frame>,
+        connects: usize,
+    }
+
+    impl Transport for MockTransport {
+        fn connect(&mut self) -> Result<(), String> {
+            self.connects += 1;
+            self.current = self.sessions.pop_front().ok_or("refused")?;
+            Ok(())
+        }
+
+        fn send(&mut self, frame: Frame) -> Result<(), String> {
+            self.sent.push(frame);
+            Ok(())
+        }
+
+        fn recv(&mut self) -> Result<Frame, String> {
+            self.current.pop_front().ok_or_else(|| "connection reset".to_owned())
+        }
+    }
+
+    const SUBSCRIBE: &str = r#"{"op":"subscribe","args":[{"channel":"books","instId":"BTC-USDT"}]}"#;
+
+    fn book(action: &str, prev: i64, seq: i64, bid: &str) -> Frame {
+        Frame::Text(format!(
+            r#"{{"action":"{action}","data":[{{"asks":[],"bids":[["{bid}","1","0","1"]],"ts":"{seq}","checksum":0,"prevSeqId":{prev},"seqId":{seq}}}]}}"#
+        ))
+    }
+
+    #[test]
+    fn test_ping_gap_and_resnapshot() {
+        let transport = MockTransport {
+            sessions: VecDeque::from([
+                VecDeque::from([book("snapshot", -1, 10, "100"), Frame::Ping(vec![1]), book("update", 12, 13, "101")]),
+                VecDeque::from([book("snapshot", -1, 20, "99")]),
+            ]),
+            ..Default::default()
+        };
+        let mut client = FeedClient::new(transport, VenueAdapter::<OkxNormalizer>::new("BTC-USDT"))
+            .with_subscription(SUBSCRIBE)
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(2));
+        let mut lob = ArrayOrderbook::<10, FixedDecimal>::new();
+
+        client.connect().unwrap();
+        assert_eq!(client.poll(&mut lob), Ok(Poll::Events(2)));
+        assert_eq!(client.poll(&mut lob), Ok(Poll::Idle));
+        assert_eq!(client.transport().sent.last(), Some(&Frame::Pong(vec![1])));
+
+        // The update skips sequence 11, so the client resubscribes and the new snapshot replaces the book
+        assert_eq!(client.poll(&mut lob), Ok(Poll::Reconnected));
+        assert_eq!(client.poll(&mut lob), Ok(Poll::Events(2)));
+        assert_eq!(lob.best_bid().unwrap().price, fixed!(99));
+        assert_eq!(client.transport().connects, 2);
+        assert_eq!(client.transport().sent.iter().filter(|f| **f == Frame::Text(SUBSCRIBE.into())).count(), 2);
+
+        // The venue drops the connection and refuses new ones
+        assert_eq!(client.poll(&mut lob), Err(ClientError::ReconnectsExhausted));
+        assert_eq!(client.transport().connects, 7);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let mut client = FeedClient::new(MockTransport::default(), VenueAdapter::<OkxNormalizer>::new("BTC-USDT"))
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(500));
+        let mut waits = Vec::new();
+        for reconnects in 0..6 {
+            client.reconnects = reconnects;
+            waits.push(client.next_backoff());
+        }
+        assert_eq!(waits[0], Duration::ZERO);
+        // Drawn from the upper half of 100, 200, 400, then the 500 cap
+        for (wait, ceiling) in waits[1..].iter().zip([100, 200, 400, 500, 500]) {
+            assert!((Duration::from_millis(ceiling / 2)..=Duration::from_millis(ceiling)).contains(wait), "{wait:?}");
+        }
+    }
+
+    #[test]
+    fn test_channel_sink() {
+        let transport =
+            MockTransport { sessions: VecDeque::from([VecDeque::from([book("snapshot", -1, 1, "100")])]), ..Default::default() };
+        let mut client = FeedClient::new(transport, VenueAdapter::<OkxNormalizer>::new("BTC-USDT"));
+        let (tx, rx) = mpsc::channel();
+
+        assert_eq!(client.poll_channel::<FixedDecimal>(&tx), Ok(Poll::Reconnected));
+        assert_eq!(client.poll_channel::<FixedDecimal>(&tx), Ok(Poll::Events(2)));
+        assert_eq!(rx.try_iter().map(|event| event.kind).collect::<Vec<_>>(), [EventKind::Clear, EventKind::Snapshot]);
+    }
+}
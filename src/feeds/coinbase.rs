@@ -0,0 +1,151 @@
+//! Normalizer for the Coinbase Exchange `level2` channel.
+//!
+//! The channel sends a `snapshot` of the full book after subscribing, followed by `l2update`
+//! messages. Every change carries the new absolute size resting at that price (not a delta),
+//! with a size of zero removing the level, which maps directly onto [`EventKind::L2`].
+
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    feeds::{parse_decimal, parse_rfc3339_nanos, FeedError},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message<'a> {
+    Snapshot {
+        #[serde(borrow)]
+        bids: Vec<(&'a str, &'a str)>,
+        #[serde(borrow)]
+        asks: Vec<(&'a str, &'a str)>,
+    },
+    L2update {
+        #[serde(borrow)]
+        time: &'a str,
+        #[serde(borrow)]
+        changes: Vec<(Side, &'a str, &'a str)>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Default)]
+pub struct CoinbaseNormalizer {
+    /// Timestamp of the last `l2update`, snapshots carry no time so they are stamped with it
+    last_ts: Timestamp,
+}
+
+impl CoinbaseNormalizer {
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { last_ts: Timestamp::ZERO }
+    }
+
+    /// Translate a raw `level2` message into crate events.
+    ///
+    /// - `snapshot`: an [`EventKind::Clear`] then one [`EventKind::Snapshot`] event per level, replacing the book.
+    /// - `l2update`: one [`EventKind::L2`] event per change, timestamped in nanoseconds.
+    /// - Any other message type (subscriptions, heartbeats) yields no events.
+    pub fn normalize<V>(&mut self, message: &str) -> Result<Vec<Event<V>>, FeedError>
+    where
+        V: DecimalType + FromStr,
+    {
+        match serde_json::from_str::<Message>(message)? {
+            Message::Snapshot { bids, asks } => {
+                let mut events = Vec::with_capacity(1 + bids.len() + asks.len());
+                events.push(Event::clear(self.last_ts));
+                for (side, levels) in [(Side::Buy, bids), (Side::Sell, asks)] {
+                    for (price, size) in levels {
+                        let (price, size) = (parse_decimal(price)?, parse_decimal(size)?);
+                        events.push(Event::new(EventKind::Snapshot, side, price, size, self.last_ts));
+                    }
+                }
+                Ok(events)
+            }
+            Message::L2update { time, changes } => {
+                self.last_ts = Timestamp::from_nanos(parse_rfc3339_nanos(time)?);
+                changes
+                    .into_iter()
+                    .map(|(side, price, size)| {
+                        Ok(Event::new(EventKind::L2, side, parse_decimal(price)?, parse_decimal(size)?, self.last_ts))
+                    })
+                    .collect()
+            }
+            Message::Other => Ok(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, interface::OrderBook as _},
+        decimals::fixed_decimal::FixedDecimal,
+        event_kind::EventKind,
+        feeds::{coinbase::CoinbaseNormalizer, FeedError},
+        fixed,
+        side::Side,
+    };
+
+    const SNAPSHOT: &str = r#"{"type":"snapshot","product_id":"BTC-USD","bids":[["10101.10","0.45054140"],["10101.00","1.5"]],"asks":[["10102.55","0.57753524"]]}"#;
+    const UPDATE: &str = r#"{"type":"l2update","product_id":"BTC-USD","time":"2019-08-14T20:42:27.265Z","changes":[["buy","10101.80000000","0.162567"],["sell","10102.55","0.00000000"]]}"#;
+
+    #[test]
+    fn test_snapshot() {
+        let mut normalizer = CoinbaseNormalizer::new();
+        let events = normalizer.normalize::<FixedDecimal>(SNAPSHOT).unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].kind, EventKind::Clear);
+        assert!(events[1..].iter().all(|e| e.kind == EventKind::Snapshot));
+        assert_eq!(events[1].side, Side::Buy);
+        assert_eq!(events[1].price, fixed!(10101.10));
+        assert_eq!(events[3].side, Side::Sell);
+        assert_eq!(events[3].size, fixed!(0.57753524));
+    }
+
+    #[test]
+    fn test_update_applies_absolute_sizes() {
+        let mut normalizer = CoinbaseNormalizer::new();
+        let mut lob = ArrayOrderbook::<10, FixedDecimal>::new();
+        for event in normalizer.normalize(SNAPSHOT).unwrap() {
+            lob.process(event);
+        }
+        let events = normalizer.normalize::<FixedDecimal>(UPDATE).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].timestamp.as_nanos(), 1_565_815_347_265_000_000);
+        for event in events {
+            lob.process(event);
+        }
+        assert_eq!(lob.best_bid().unwrap().price, fixed!(10101.8));
+        assert_eq!(lob.best_bid().unwrap().size, fixed!(0.162567));
+        assert!(lob.best_ask().is_none());
+
+        // A resnapshot replaces the book instead of being merged into it
+        for event in normalizer.normalize(SNAPSHOT).unwrap() {
+            lob.process(event);
+        }
+        assert_eq!(lob.best_bid().unwrap().price, fixed!(10101.10));
+        assert_eq!(lob.bids.len(), 2);
+        assert_eq!(lob.best_ask().unwrap().price, fixed!(10102.55));
+    }
+
+    #[test]
+    fn test_ignored_and_invalid_messages() {
+        let mut normalizer = CoinbaseNormalizer::new();
+        let heartbeat = r#"{"type":"heartbeat","sequence":90,"last_trade_id":20}"#;
+        assert!(normalizer.normalize::<FixedDecimal>(heartbeat).unwrap().is_empty());
+
+        let bad_decimal = r#"{"type":"snapshot","bids":[["abc","1"]],"asks":[]}"#;
+        assert_eq!(normalizer.normalize::<FixedDecimal>(bad_decimal).unwrap_err(), FeedError::InvalidDecimal("abc".into()));
+        assert!(matches!(normalizer.normalize::<FixedDecimal>("not json"), Err(FeedError::Malformed(_))));
+    }
+}
@@ -0,0 +1,208 @@
+//! Normalizer for the Deribit `book.{instrument}.{interval}` channel.
+//!
+//! Levels arrive as `[action, price, amount]` with `action` one of `new`, `change` or `delete`,
+//! where `new` and `change` carry the absolute amount resting at the price. Notifications chain
+//! through `change_id` and `prev_change_id`, checked with a [`SequenceTracker`], and `change_id`
+//! becomes the event `sequence_id`.
+
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    feeds::{parse_decimal, sequence::SequenceTracker, FeedError},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Deserialize)]
+struct Message<'a> {
+    #[serde(borrow)]
+    params: Option<Params<'a>>,
+}
+
+#[derive(Deserialize)]
+struct Params<'a> {
+    #[serde(borrow, default)]
+    channel: &'a str,
+    #[serde(borrow)]
+    data: Option<Book<'a>>,
+}
+
+#[derive(Deserialize)]
+struct Book<'a> {
+    #[serde(rename = "type")]
+    kind: Option<&'a str>,
+    /// Millisecond timestamp
+    timestamp: i64,
+    change_id: u64,
+    prev_change_id: Option<u64>,
+    /// `[action, price, amount]`, or `[price, amount]` on grouped channels, read once the type is
+    /// known. Prices and amounts are kept as written, since a parsed float would print as `1e-7`.
+    #[serde(borrow)]
+    bids: Vec<&'a RawValue>,
+    #[serde(borrow)]
+    asks: Vec<&'a RawValue>,
+}
+
+#[derive(Debug, Default)]
+pub struct DeribitNormalizer {
+    sequence: SequenceTracker,
+}
+
+impl DeribitNormalizer {
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { sequence: SequenceTracker::new() }
+    }
+
+    /// Translate a raw `book` subscription notification into crate events.
+    ///
+    /// - `snapshot`: an [`EventKind::Clear`] then [`EventKind::Snapshot`] events, restarting the change ID chain.
+    /// - `change`: [`EventKind::L2`] events with `delete` actions mapped onto a zero size, or
+    ///   [`FeedError::SequenceGap`] when `prev_change_id` does not match the last `change_id`.
+    /// - Grouped channels (`book.{instrument}.{group}.{depth}.{interval}`) carry no type and send the
+    ///   top of the book as `[price, amount]` pairs each time, so they are handled like `snapshot`.
+    ///
+    /// Timestamps are converted to nanoseconds. Other JSON-RPC messages and channels yield no events.
+    pub fn normalize<V>(&mut self, message: &str) -> Result<Vec<Event<V>>, FeedError>
+    where
+        V: DecimalType + FromStr,
+    {
+        let Some(Params { channel, data: Some(book) }) = serde_json::from_str::<Message>(message)?.params else {
+            return Ok(Vec::new());
+        };
+        if !channel.starts_with("book.") {
+            return Ok(Vec::new());
+        }
+
+        // Grouped book channels omit the type and always carry a full book
+        let kind = match (book.kind, book.prev_change_id) {
+            (Some("snapshot") | None, _) => {
+                self.sequence.reset(book.change_id);
+                EventKind::Snapshot
+            }
+            (Some("change"), Some(previous)) => {
+                self.sequence.advance(previous, book.change_id)?;
+                EventKind::L2
+            }
+            (Some("change"), None) => return Err(FeedError::Malformed("change without prev_change_id".to_owned())),
+            (Some(other), _) => return Err(FeedError::Malformed(format!("unexpected book type {other}"))),
+        };
+
+        let ts = Timestamp::from_millis(book.timestamp);
+        let mut events = Vec::with_capacity(1 + book.bids.len() + book.asks.len());
+        if kind == EventKind::Snapshot {
+            events.push(Event::clear(ts).with_sequence_id(book.change_id));
+        }
+        for (side, levels) in [(Side::Buy, book.bids), (Side::Sell, book.asks)] {
+            for level in levels {
+                let (action, price, amount) = if book.kind.is_none() {
+                    let (price, amount) = serde_json::from_str::<(&RawValue, &RawValue)>(level.get())?;
+                    ("new", price, amount)
+                } else {
+                    serde_json::from_str::<(&str, &RawValue, &RawValue)>(level.get())?
+                };
+                let size = match action {
+                    "new" | "change" => parse_decimal(amount.get())?,
+                    "delete" => V::ZERO,
+                    other => return Err(FeedError::Malformed(format!("unknown level action {other}"))),
+                };
+                let event = Event::new(kind, side, parse_decimal(price.get())?, size, ts);
+                events.push(event.with_sequence_id(book.change_id));
+            }
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::str::FromStr as _;
+
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, interface::OrderBook as _},
+        decimals::fixed_decimal::FixedDecimal,
+        event_kind::EventKind,
+        feeds::{deribit::DeribitNormalizer, FeedError},
+        fixed,
+    };
+
+    const SNAPSHOT: &str = r#"{"jsonrpc":"2.0","method":"subscription","params":{"channel":"book.BTC-PERPETUAL.100ms","data":{"type":"snapshot","timestamp":1554373962454,"instrument_name":"BTC-PERPETUAL","change_id":297217,"bids":[["new",5042.34,30],["new",5041.94,20]],"asks":[["new",5042.64,40],["new",5043.3,40]]}}}"#;
+    const CHANGE: &str = r#"{"jsonrpc":"2.0","method":"subscription","params":{"channel":"book.BTC-PERPETUAL.100ms","data":{"type":"change","timestamp":1554373962554,"prev_change_id":297217,"instrument_name":"BTC-PERPETUAL","change_id":297218,"bids":[["delete",5042.34,0],["change",5041.94,25.5]],"asks":[["new",5042.5,10]]}}}"#;
+
+    #[test]
+    fn test_snapshot_and_change() {
+        let mut normalizer = DeribitNormalizer::new();
+        let mut lob = ArrayOrderbook::<10, FixedDecimal>::new();
+
+        let events = normalizer.normalize::<FixedDecimal>(SNAPSHOT).unwrap();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0].kind, EventKind::Clear);
+        assert!(events[1..].iter().all(|e| e.kind == EventKind::Snapshot && e.sequence_id == 297217));
+        events.into_iter().for_each(|e| lob.process(e));
+
+        let events = normalizer.normalize::<FixedDecimal>(CHANGE).unwrap();
+        assert_eq!(events[0].size, fixed!(0));
+        events.into_iter().for_each(|e| lob.process(e));
+        assert_eq!(lob.sequence_id, 297218);
+        assert_eq!(lob.best_bid().unwrap().price, fixed!(5041.94 f64));
+        assert_eq!(lob.best_bid().unwrap().size, fixed!(25.5));
+        assert_eq!(lob.best_ask().unwrap().price, fixed!(5042.5));
+    }
+
+    #[test]
+    fn test_grouped_book() {
+        const GROUPED: &str = r#"{"jsonrpc":"2.0","method":"subscription","params":{"channel":"book.BTC-PERPETUAL.none.10.100ms","data":{"timestamp":1554375447971,"instrument_name":"BTC-PERPETUAL","change_id":297219,"bids":[[5042.5,16.5],[5042,45]],"asks":[[5043,10]]}}}"#;
+        let mut normalizer = DeribitNormalizer::new();
+        let events = normalizer.normalize::<FixedDecimal>(GROUPED).unwrap();
+        assert_eq!(events.iter().map(|e| e.kind).collect::<Vec<_>>()[..2], [EventKind::Clear, EventKind::Snapshot]);
+        let mut lob = ArrayOrderbook::<10, FixedDecimal>::new();
+        events.into_iter().for_each(|e| lob.process(e));
+        assert_eq!((lob.best_bid().unwrap().price, lob.best_bid().unwrap().size), (fixed!(5042.5), fixed!(16.5)));
+        assert_eq!(lob.best_ask().unwrap().price, fixed!(5043));
+        assert_eq!(lob.sequence_id, 297219);
+    }
+
+    #[test]
+    fn test_change_gap() {
+        let mut normalizer = DeribitNormalizer::new();
+        assert_eq!(normalizer.normalize::<FixedDecimal>(CHANGE).unwrap_err(), FeedError::MissingSnapshot);
+        normalizer.normalize::<FixedDecimal>(SNAPSHOT).unwrap();
+        normalizer.normalize::<FixedDecimal>(CHANGE).unwrap();
+        assert_eq!(
+            normalizer.normalize::<FixedDecimal>(CHANGE).unwrap_err(),
+            FeedError::SequenceGap { expected: 297218, received: 297217 }
+        );
+        let unchained = CHANGE.replace(r#""prev_change_id":297217,"#, "");
+        assert_eq!(
+            normalizer.normalize::<FixedDecimal>(&unchained).unwrap_err(),
+            FeedError::Malformed("change without prev_change_id".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_levels_as_written() {
+        let mut normalizer = DeribitNormalizer::new();
+        normalizer.normalize::<FixedDecimal>(SNAPSHOT).unwrap();
+        // Read as a float and printed back, the amount would become 1e-7
+        let tiny = CHANGE.replace("25.5", "0.0000001");
+        let events = normalizer.normalize::<FixedDecimal>(&tiny).unwrap();
+        assert_eq!(events[1].size, FixedDecimal::from_str("0.0000001").unwrap());
+    }
+
+    #[test]
+    fn test_other_messages() {
+        let mut normalizer = DeribitNormalizer::new();
+        let response = r#"{"jsonrpc":"2.0","id":42,"result":["book.BTC-PERPETUAL.100ms"]}"#;
+        assert!(normalizer.normalize::<FixedDecimal>(response).unwrap().is_empty());
+        let heartbeat = r#"{"jsonrpc":"2.0","method":"heartbeat","params":{"type":"test_request"}}"#;
+        assert!(normalizer.normalize::<FixedDecimal>(heartbeat).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+//! Normalizer for the Kraken (websocket v1) `book-N` channel.
+//!
+//! Payloads are arrays of `[channel_id, {...}, ("book-N"), pair]` where the object carries either
+//! `as`/`bs` snapshot levels or `a`/`b` updates with absolute sizes. Updates may carry a `c`
+//! field with the CRC32 of the top ten levels on each side, which [`KrakenNormalizer::apply`]
+//! checks against the local book.
+
+use std::{fmt::Display, str::FromStr};
+
+use serde_json::Value;
+
+use crate::{
+    books::interface::OrderBook,
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    feeds::{checksum, parse_decimal, FeedError},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+/// Number of levels per side covered by the Kraken checksum
+const CHECKSUM_DEPTH: usize = 10;
+
+#[derive(Debug)]
+pub struct KrakenMessage<V: DecimalType> {
+    pub events: Vec<Event<V>>,
+    /// Checksum published with the update, if any
+    pub checksum: Option<u32>,
+}
+
+#[derive(Debug, Default)]
+pub struct KrakenNormalizer {
+    /// Decimal places of the pair's prices, learnt from the first level seen
+    price_precision: Option<usize>,
+    /// Decimal places of the pair's volumes, learnt from the first level seen
+    size_precision: Option<usize>,
+}
+
+impl KrakenNormalizer {
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { price_precision: None, size_precision: None }
+    }
+
+    /// Translate a raw `book-N` payload into crate events.
+    ///
+    /// All events of a payload share its latest level timestamp (in nanoseconds), as Kraken stamps
+    /// each level with the time it last changed and those are not ordered within a message.
+    /// Snapshot levels (`as` and `bs`) follow an [`EventKind::Clear`] so they replace the book. Non-book
+    /// payloads such as heartbeats or subscription statuses yield no events.
+    pub fn normalize<V>(&mut self, message: &str) -> Result<KrakenMessage<V>, FeedError>
+    where
+        V: DecimalType + FromStr,
+    {
+        let mut parsed = KrakenMessage { events: Vec::new(), checksum: None };
+        let Value::Array(items) = serde_json::from_str::<Value>(message)? else {
+            return Ok(parsed);
+        };
+
+        let mut timestamp = 0;
+        for item in items.iter().skip(1) {
+            let Value::Object(fields) = item else { continue };
+            for (key, value) in fields {
+                let (kind, side) = match key.as_str() {
+                    "as" => (EventKind::Snapshot, Side::Sell),
+                    "bs" => (EventKind::Snapshot, Side::Buy),
+                    "a" => (EventKind::L2, Side::Sell),
+                    "b" => (EventKind::L2, Side::Buy),
+                    "c" => {
+                        let checksum = value.as_str().and_then(|c| c.parse::<u32>().ok());
+                        parsed.checksum = Some(checksum.ok_or_else(|| FeedError::Malformed(format!("checksum {value}")))?);
+                        continue;
+                    }
+                    _ => continue,
+                };
+                let Value::Array(levels) = value else {
+                    return Err(FeedError::Malformed(format!("levels under {key}")));
+                };
+                for level in levels {
+                    let (price, size, ts) = match level.as_array().map(Vec::as_slice) {
+                        Some([Value::String(price), Value::String(size), Value::String(ts), ..]) => (price, size, ts),
+                        _ => return Err(FeedError::Malformed(format!("level {level}"))),
+                    };
+                    self.price_precision.get_or_insert_with(|| fraction_digits(price));
+                    self.size_precision.get_or_insert_with(|| fraction_digits(size));
+                    timestamp = timestamp.max(parse_seconds_nanos(ts)?);
+                    parsed.events.push(Event::new(kind, side, parse_decimal(price)?, parse_decimal(size)?, Timestamp::ZERO));
+                }
+            }
+        }
+
+        if parsed.events.iter().any(|event| event.kind == EventKind::Snapshot) {
+            parsed.events.insert(0, Event::clear(Timestamp::ZERO));
+        }
+        for event in &mut parsed.events {
+            event.timestamp = Timestamp::from_nanos(timestamp);
+        }
+        Ok(parsed)
+    }
+
+    /// Normalize a payload, process its events into `book` and verify the published checksum.
+    ///
+    /// Returns [`FeedError::ChecksumMismatch`] when the book has diverged from the venue, in which
+    /// case the channel should be resubscribed to receive a fresh snapshot.
+    pub fn apply<V, B>(&mut self, book: &mut B, message: &str) -> Result<(), FeedError>
+    where
+        V: DecimalType + FromStr + Display,
+        B: OrderBook<V>,
+    {
+        let parsed = self.normalize(message)?;
+        for event in parsed.events {
+            book.process(event);
+        }
+        match parsed.checksum {
+            Some(expected) => checksum::verify(expected, self.checksum(book)),
+            None => Ok(()),
+        }
+    }
+
+    /// Compute the Kraken checksum of the book's top ten asks (ascending) followed by its top ten
+    /// bids (descending), each price and volume formatted at the pair's precision with the decimal
+    /// point and leading zeros removed.
+    pub fn checksum<V, B>(&self, book: &B) -> u32
+    where
+        V: DecimalType + Display,
+        B: OrderBook<V> + ?Sized,
+    {
+        let mut hasher = checksum::Crc32::new();
+        for side in [Side::Sell, Side::Buy] {
+            for level in book.levels(side, CHECKSUM_DEPTH) {
+                hasher.update(checksum_token(&level.price, self.price_precision.unwrap_or(0)).as_bytes());
+                hasher.update(checksum_token(&level.size, self.size_precision.unwrap_or(0)).as_bytes());
+            }
+        }
+        hasher.finish()
+    }
+}
+
+#[inline]
+fn fraction_digits(value: &str) -> usize {
+    value.split_once('.').map_or(0, |(_, fraction)| fraction.len())
+}
+
+/// Format `value` with exactly `precision` decimal places, then drop the point and leading zeros
+fn checksum_token<V: Display>(value: &V, precision: usize) -> String {
+    let formatted = value.to_string();
+    let (whole, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+    let mut token = String::with_capacity(whole.len() + precision);
+    token.push_str(whole);
+    token.extend(fraction.chars().chain(std::iter::repeat('0')).take(precision));
+    token.trim_start_matches('0').to_owned()
+}
+
+/// Parse a `seconds.micros` string into nanoseconds since the Unix epoch
+fn parse_seconds_nanos(value: &str) -> Result<i64, FeedError> {
+    let invalid = || FeedError::InvalidTimestamp(value.to_owned());
+    let (seconds, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let seconds = seconds.parse::<i64>().map_err(|_| invalid())?;
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        format!("{:0<9}", &fraction[..fraction.len().min(9)]).parse::<i64>().map_err(|_| invalid())?
+    };
+    Ok(seconds * 1_000_000_000 + nanos)
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, btree_orderbook::BTreeOrderBook, interface::OrderBook as _},
+        decimals::fixed_decimal::FixedDecimal,
+        event_kind::EventKind,
+        feeds::{
+            checksum::crc32,
+            kraken::{checksum_token, parse_seconds_nanos, KrakenNormalizer},
+            FeedError,
+        },
+        fixed,
+    };
+
+    const SNAPSHOT: &str = r#"[0,{"as":[["5541.30000","2.50700000","1534614248.123678"],["5541.80000","0.33000000","1534614098.345543"]],"bs":[["5541.20000","1.52900000","1534614248.765567"],["5539.90000","0.30000000","1534614241.769870"]]},"book-10","XBT/USD"]"#;
+
+    #[test]
+    fn test_snapshot() {
+        let mut normalizer = KrakenNormalizer::new();
+        let parsed = normalizer.normalize::<FixedDecimal>(SNAPSHOT).unwrap();
+        assert_eq!(parsed.events.len(), 5);
+        assert_eq!(parsed.checksum, None);
+        assert_eq!(parsed.events[0].kind, EventKind::Clear);
+        assert!(parsed.events[1..].iter().all(|e| e.kind == EventKind::Snapshot));
+        assert!(parsed.events.iter().all(|e| e.timestamp.as_nanos() == 1_534_614_248_765_567_000));
+        assert!(normalizer.normalize::<FixedDecimal>(r#"{"event":"heartbeat"}"#).unwrap().events.is_empty());
+    }
+
+    #[test]
+    fn test_checksum_token() {
+        assert_eq!(checksum_token(&fixed!(5541.3), 5), "554130000");
+        assert_eq!(checksum_token(&fixed!(0.05005), 8), "5005000");
+        assert_eq!(checksum_token(&fixed!(2), 3), "2000");
+        assert_eq!(parse_seconds_nanos("1534614248.123678"), Ok(1_534_614_248_123_678_000));
+    }
+
+    #[test]
+    fn test_apply_verifies_checksum() {
+        let mut normalizer = KrakenNormalizer::new();
+        let mut lob = ArrayOrderbook::<10, FixedDecimal>::new();
+        normalizer.apply(&mut lob, SNAPSHOT).unwrap();
+
+        // Book after the update below: asks 5541.3 @ 2.507, 5541.8 @ 0.33 and bids 5541.2 @ 1.0, 5539.9 @ 0.3
+        let expected =
+            crc32(concat!("554130000250700000", "55418000033000000", "554120000100000000", "55399000030000000").as_bytes());
+        let update =
+            format!(r#"[0,{{"b":[["5541.20000","1.00000000","1534614250.000000"]],"c":"{expected}"}},"book-10","XBT/USD"]"#);
+        normalizer.apply(&mut lob, &update).unwrap();
+        assert_eq!(lob.best_bid().unwrap().size, fixed!(1));
+
+        let mut btree = BTreeOrderBook::<FixedDecimal>::new();
+        normalizer.apply(&mut btree, SNAPSHOT).unwrap();
+        normalizer.apply(&mut btree, &update).unwrap();
+
+        let stale = r#"[0,{"a":[["5541.80000","0.00000000","1534614251.000000"]],"c":"12345"},"book-10","XBT/USD"]"#;
+        assert_eq!(
+            normalizer.apply(&mut lob, stale),
+            Err(FeedError::ChecksumMismatch { expected: 12345, computed: normalizer.checksum(&lob) })
+        );
+    }
+}
@@ -0,0 +1,353 @@
+//! Reader for LOBSTER `message_*.csv` and `orderbook_*.csv` files.
+//!
+//! The message file is order-by-order: every row carries the time in seconds after midnight, the
+//! event type, order ID, size, price (in units of 1/10000) and direction. [`parse_message`] decodes a
+//! row into a [`LobsterMessage`], and [`LobsterReplay`] aggregates the messages into Level 2 events
+//! with the total size resting at the affected price. [`LobsterMessage::to_event`] instead keeps a
+//! message order level.
+//!
+//! Row `i` of the orderbook file is the top of the book after message `i`, laid out as
+//! `ask price, ask size, bid price, bid size` per level. [`validate`] replays a message file into a
+//! book and compares it against this ground truth after every message. Orders resting beyond the
+//! recorded depth when the files start are unknown, so books deeper than that depth may diverge.
+
+use std::{collections::HashMap, io::BufRead};
+
+use crate::{
+    books::interface::OrderBook, decimals::decimal_type::DecimalType, event::Event, event_kind::EventKind, feeds::FeedError,
+    level::Level, side::Side, timestamp::Timestamp,
+};
+
+/// Decimal places implied by LOBSTER prices
+pub const PRICE_SCALE: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    /// 1: a new limit order
+    Submission,
+    /// 2: part of an order is cancelled
+    Cancellation,
+    /// 3: the remainder of an order is cancelled
+    Deletion,
+    /// 4: a visible order is executed
+    Execution,
+    /// 5: a hidden order is executed, it was never on the visible book
+    HiddenExecution,
+    /// 6: an auction cross trade
+    Cross,
+    /// 7: a trading halt, quote or resumption indicator
+    Halt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LobsterMessage {
+    /// Nanoseconds after midnight
+    pub time: i64,
+    pub kind: MessageType,
+    pub order_id: u64,
+    pub size: u64,
+    /// Price in units of 1/10000
+    pub price: i64,
+    pub side: Side,
+}
+
+impl LobsterMessage {
+    /// The message as an order level event, `None` for messages that do not touch a visible order
+    pub fn to_event<V: DecimalType>(&self) -> Option<Event<V>> {
+        let kind = match self.kind {
+            MessageType::Submission => EventKind::Add,
+            MessageType::Cancellation | MessageType::Deletion => EventKind::Cancel,
+            MessageType::Execution => EventKind::Execute,
+            MessageType::HiddenExecution | MessageType::Cross | MessageType::Halt => return None,
+        };
+        Some(level_event(kind, self.side, self.price, self.size, self.time).with_order_id(self.order_id))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Populated levels of an orderbook file row, best first, as `(price, size)`
+pub struct OrderbookRow {
+    pub asks: Vec<(i64, u64)>,
+    pub bids: Vec<(i64, u64)>,
+}
+
+#[inline]
+fn field<'a>(fields: &mut impl Iterator<Item = &'a str>, name: &str) -> Result<&'a str, FeedError> {
+    fields.next().map(str::trim).ok_or_else(|| FeedError::Malformed(format!("missing {name}")))
+}
+
+#[inline]
+fn number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, FeedError> {
+    value.parse().map_err(|_| FeedError::Malformed(format!("invalid {name} {value}")))
+}
+
+/// Parse `seconds.fraction` after midnight into nanoseconds
+fn parse_time(value: &str) -> Result<i64, FeedError> {
+    let invalid = || FeedError::InvalidTimestamp(value.to_owned());
+    let (seconds, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let seconds = seconds.parse::<i64>().map_err(|_| invalid())?;
+    let digits = &fraction[..fraction.len().min(9)];
+    let nanos = if digits.is_empty() { 0 } else { format!("{digits:0<9}").parse::<i64>().map_err(|_| invalid())? };
+    Ok(seconds * 1_000_000_000 + nanos)
+}
+
+/// Parse a message file row
+pub fn parse_message(row: &str) -> Result<LobsterMessage, FeedError> {
+    let mut fields = row.split(',');
+    let time = parse_time(field(&mut fields, "time")?)?;
+    let kind = match field(&mut fields, "type")? {
+        "1" => MessageType::Submission,
+        "2" => MessageType::Cancellation,
+        "3" => MessageType::Deletion,
+        "4" => MessageType::Execution,
+        "5" => MessageType::HiddenExecution,
+        "6" => MessageType::Cross,
+        "7" => MessageType::Halt,
+        other => return Err(FeedError::Malformed(format!("unknown event type {other}"))),
+    };
+    let order_id = number(field(&mut fields, "order ID")?, "order ID")?;
+    let size = number(field(&mut fields, "size")?, "size")?;
+    let price = number(field(&mut fields, "price")?, "price")?;
+    let side = match field(&mut fields, "direction")? {
+        "1" => Side::Buy,
+        "-1" => Side::Sell,
+        other => return Err(FeedError::Malformed(format!("unknown direction {other}"))),
+    };
+    Ok(LobsterMessage { time, kind, order_id, size, price, side })
+}
+
+/// Parse an orderbook file row, dropping the dummy entries of empty levels
+pub fn parse_orderbook(row: &str) -> Result<OrderbookRow, FeedError> {
+    let values = row.split(',').map(|value| number::<i64>(value.trim(), "level")).collect::<Result<Vec<_>, _>>()?;
+    if values.len() % 4 != 0 {
+        return Err(FeedError::Malformed(format!("{} values do not make up whole levels", values.len())));
+    }
+    let mut book = OrderbookRow::default();
+    for level in values.chunks_exact(4) {
+        if level[1] > 0 {
+            book.asks.push((level[0], level[1] as u64));
+        }
+        if level[3] > 0 {
+            book.bids.push((level[2], level[3] as u64));
+        }
+    }
+    Ok(book)
+}
+
+#[inline]
+fn level_event<V: DecimalType>(kind: EventKind, side: Side, price: i64, size: u64, ts: i64) -> Event<V> {
+    Event::new(kind, side, V::from_scaled(price, PRICE_SCALE), V::from_scaled(size as i64, 0), Timestamp::from_nanos(ts))
+}
+
+#[derive(Debug, Default)]
+/// Aggregates LOBSTER messages into per-price Level 2 events.
+pub struct LobsterReplay {
+    levels: HashMap<(Side, i64), u64>,
+}
+
+impl LobsterReplay {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the known levels with an orderbook row, returning it as an [`EventKind::Clear`] then
+    /// [`EventKind::Snapshot`] events
+    pub fn seed<V: DecimalType>(&mut self, row: &OrderbookRow, ts: i64) -> Vec<Event<V>> {
+        self.levels.clear();
+        let mut events = Vec::with_capacity(1 + row.asks.len() + row.bids.len());
+        events.push(Event::clear(Timestamp::from_nanos(ts)));
+        for (side, levels) in [(Side::Buy, &row.bids), (Side::Sell, &row.asks)] {
+            for &(price, size) in levels {
+                self.levels.insert((side, price), size);
+                events.push(level_event(EventKind::Snapshot, side, price, size, ts));
+            }
+        }
+        events
+    }
+
+    /// Apply a message, returning the Level 2 event for the affected price if the visible book changed
+    pub fn apply<V: DecimalType>(&mut self, message: &LobsterMessage) -> Option<Event<V>> {
+        let key = (message.side, message.price);
+        let size = match message.kind {
+            MessageType::Submission => {
+                let size = self.levels.entry(key).or_default();
+                *size += message.size;
+                *size
+            }
+            MessageType::Cancellation | MessageType::Deletion | MessageType::Execution => {
+                let size = self.levels.entry(key).or_default();
+                *size = size.saturating_sub(message.size);
+                let size = *size;
+                if size == 0 {
+                    self.levels.remove(&key);
+                }
+                size
+            }
+            MessageType::HiddenExecution | MessageType::Cross | MessageType::Halt => return None,
+        };
+        Some(level_event(EventKind::L2, message.side, message.price, size, message.time))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    Feed(FeedError),
+    /// The book differs from the orderbook file after the message on `row` (1-based)
+    Mismatch {
+        row: usize,
+        side: Side,
+    },
+    /// One file has more rows than the other
+    LengthMismatch,
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Feed(err) => write!(f, "{err}"),
+            Self::Mismatch { row, side } => write!(f, "{} side differs from the orderbook file on row {row}", side.as_ref()),
+            Self::LengthMismatch => write!(f, "message and orderbook files have different lengths"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl From<FeedError> for ValidationError {
+    fn from(err: FeedError) -> Self {
+        Self::Feed(err)
+    }
+}
+
+#[inline]
+fn io_error(err: std::io::Error) -> ValidationError {
+    ValidationError::Feed(FeedError::Malformed(err.to_string()))
+}
+
+/// Replay a message file into `book`, checking the top `depth` levels against the orderbook file
+/// after every message. The book is seeded from the first orderbook row, as the state before the
+/// first message is not recorded. Returns the number of rows checked.
+pub fn validate<V, B>(
+    book: &mut B,
+    messages: impl BufRead,
+    orderbook: impl BufRead,
+    depth: usize,
+) -> Result<usize, ValidationError>
+where
+    V: DecimalType + PartialEq,
+    B: OrderBook<V> + ?Sized,
+{
+    let mut replay = LobsterReplay::new();
+    let (mut messages, mut orderbook) = (messages.lines(), orderbook.lines());
+    let mut checked = 0;
+    for row in 0.. {
+        let (message, expected) = match (messages.next(), orderbook.next()) {
+            (Some(message), Some(expected)) => (message, expected),
+            (None, None) => break,
+            _ => return Err(ValidationError::LengthMismatch),
+        };
+        let message = parse_message(&message.map_err(io_error)?)?;
+        let expected = parse_orderbook(&expected.map_err(io_error)?)?;
+        if row == 0 {
+            replay.seed::<V>(&expected, message.time).into_iter().for_each(|event| book.process(event));
+            continue;
+        }
+        if let Some(event) = replay.apply(&message) {
+            book.process(event);
+        }
+
+        for (side, levels) in [(Side::Buy, &expected.bids), (Side::Sell, &expected.asks)] {
+            let levels = levels.iter().take(depth).map(|&(price, size)| {
+                let event = level_event::<V>(EventKind::L2, side, price, size, 0);
+                (event.price, event.size)
+            });
+            let actual = book.levels(side, depth).into_iter().map(|Level { price, size }| (price, size));
+            if !levels.eq(actual) {
+                return Err(ValidationError::Mismatch { row: row + 1, side });
+            }
+        }
+        checked += 1;
+    }
+    Ok(checked)
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, btree_orderbook::BTreeOrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        feeds::lobster::{parse_message, parse_orderbook, validate, LobsterMessage, MessageType, ValidationError},
+        fixed,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    const MESSAGES: &str = "34200.004241176,1,16113575,18,5853300,1
+34200.005347323,1,16113584,18,5853200,1
+34200.011856228,1,16120456,18,5859100,-1
+34200.013994580,3,16120456,18,5859100,-1
+34200.019375000,4,16113575,10,5853300,1
+34200.020000000,5,0,7,5855000,-1
+";
+    const ORDERBOOK: &str = "9999999999,0,5853300,18,9999999999,0,-9999999999,0
+9999999999,0,5853300,18,9999999999,0,5853200,18
+5859100,18,5853300,18,9999999999,0,5853200,18
+9999999999,0,5853300,18,9999999999,0,5853200,18
+9999999999,0,5853300,8,9999999999,0,5853200,18
+9999999999,0,5853300,8,9999999999,0,5853200,18
+";
+
+    #[test]
+    fn test_parse_rows() {
+        assert_eq!(
+            parse_message("34200.004241176,1,16113575,18,5853300,1").unwrap(),
+            LobsterMessage {
+                time: 34_200_004_241_176,
+                kind: MessageType::Submission,
+                order_id: 16113575,
+                size: 18,
+                price: 5853300,
+                side: Side::Buy
+            }
+        );
+        let row = parse_orderbook("5859100,18,5853300,18,9999999999,0,5853200,5").unwrap();
+        assert_eq!((row.asks, row.bids), (vec![(5859100, 18)], vec![(5853300, 18), (5853200, 5)]));
+        assert!(parse_message("34200.1,8,1,1,1,1").is_err());
+    }
+
+    #[test]
+    fn test_order_events() {
+        let submission = parse_message("34200.004241176,1,16113575,18,5853300,1").unwrap();
+        let expected =
+            Event::new(EventKind::Add, Side::Buy, fixed!(585.33), fixed!(18), Timestamp::from_nanos(34_200_004_241_176))
+                .with_order_id(16113575);
+        assert_eq!(submission.to_event::<FixedDecimal>(), Some(expected));
+        let deletion = parse_message("34200.1,3,16113575,18,5853300,1").unwrap();
+        assert_eq!(deletion.to_event::<FixedDecimal>().map(|event| event.kind), Some(EventKind::Cancel));
+        assert!(parse_message("34200.1,5,0,10,5853300,-1").unwrap().to_event::<FixedDecimal>().is_none());
+    }
+
+    #[test]
+    fn test_validate_against_orderbook_file() {
+        let mut array = ArrayOrderbook::<10, FixedDecimal>::new();
+        assert_eq!(validate(&mut array, MESSAGES.as_bytes(), ORDERBOOK.as_bytes(), 2), Ok(5));
+        let mut btree = BTreeOrderBook::<FixedDecimal>::new();
+        assert_eq!(validate(&mut btree, MESSAGES.as_bytes(), ORDERBOOK.as_bytes(), 2), Ok(5));
+
+        let wrong = ORDERBOOK.replacen("5853300,8", "5853300,9", 1);
+        let mut lob = BTreeOrderBook::<FixedDecimal>::new();
+        assert_eq!(
+            validate(&mut lob, MESSAGES.as_bytes(), wrong.as_bytes(), 2),
+            Err(ValidationError::Mismatch { row: 5, side: Side::Buy })
+        );
+        // Drop the last orderbook row
+        let truncated = ORDERBOOK.trim_end().rsplit_once('\n').unwrap().0;
+        let mut lob = BTreeOrderBook::<FixedDecimal>::new();
+        assert_eq!(validate(&mut lob, MESSAGES.as_bytes(), truncated.as_bytes(), 2), Err(ValidationError::LengthMismatch));
+    }
+}
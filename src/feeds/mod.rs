@@ -0,0 +1,137 @@
+pub mod adapter;
+pub mod bybit;
+pub mod checksum;
+#[cfg(feature = "ws")]
+pub mod client;
+pub mod coinbase;
+pub mod deribit;
+pub mod kraken;
+pub mod lobster;
+pub mod okx;
+pub mod sequence;
+pub mod tardis;
+
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeedError {
+    /// The payload is not valid JSON or does not match the venue schema
+    Malformed(String),
+    /// A price or size could not be parsed into the decimal backend
+    InvalidDecimal(String),
+    /// A timestamp could not be parsed
+    InvalidTimestamp(String),
+    /// The venue checksum does not match the local book, which must be resubscribed
+    ChecksumMismatch { expected: u32, computed: u32 },
+    /// An update does not follow on from the previous one, the book must be resnapshotted
+    SequenceGap { expected: u64, received: u64 },
+    /// An update arrived before the snapshot it builds on
+    MissingSnapshot,
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(reason) => write!(f, "malformed message: {reason}"),
+            Self::InvalidDecimal(value) => write!(f, "invalid decimal: {value}"),
+            Self::InvalidTimestamp(value) => write!(f, "invalid timestamp: {value}"),
+            Self::ChecksumMismatch { expected, computed } => {
+                write!(f, "checksum mismatch (expected {expected}, computed {computed}), resubscribe to rebuild the book")
+            }
+            Self::SequenceGap { expected, received } => {
+                write!(f, "sequence gap (expected {expected}, received {received}), resubscribe to rebuild the book")
+            }
+            Self::MissingSnapshot => write!(f, "update received before a snapshot"),
+        }
+    }
+}
+
+impl std::error::Error for FeedError {}
+
+impl From<serde_json::Error> for FeedError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Malformed(err.to_string())
+    }
+}
+
+#[inline]
+/// Parse a venue decimal string into the chosen decimal backend
+pub(crate) fn parse_decimal<V: FromStr>(value: &str) -> Result<V, FeedError> {
+    V::from_str(value).map_err(|_| FeedError::InvalidDecimal(value.to_owned()))
+}
+
+/// Parse an RFC 3339 timestamp (`2019-08-14T20:42:27.265Z`) into nanoseconds since the Unix epoch
+pub(crate) fn parse_rfc3339_nanos(value: &str) -> Result<i64, FeedError> {
+    let invalid = || FeedError::InvalidTimestamp(value.to_owned());
+    let bytes = value.as_bytes();
+    if bytes.len() < 20 || bytes[4] != b'-' || bytes[7] != b'-' || bytes[13] != b':' || bytes[16] != b':' {
+        return Err(invalid());
+    }
+    let number = |range: std::ops::Range<usize>| value.get(range).and_then(|s| s.parse::<i64>().ok()).ok_or_else(invalid);
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+    // Optional fractional seconds, truncated to nanosecond precision
+    let mut idx = 19;
+    let mut nanos = 0;
+    if bytes[idx] == b'.' {
+        idx += 1;
+        let start = idx;
+        while idx < bytes.len() && bytes[idx].is_ascii_digit() {
+            idx += 1;
+        }
+        let digits = &value[start..idx];
+        if digits.is_empty() {
+            return Err(invalid());
+        }
+        let padded = format!("{:0<9}", &digits[..digits.len().min(9)]);
+        nanos = padded.parse::<i64>().map_err(|_| invalid())?;
+    }
+
+    // Timezone designator
+    let offset_seconds = match value.get(idx..) {
+        Some("Z") | Some("z") => 0,
+        Some(tz) if tz.len() == 6 && (tz.starts_with('+') || tz.starts_with('-')) => {
+            let field = |range: std::ops::Range<usize>| tz.get(range).and_then(|s| s.parse::<i64>().ok()).ok_or_else(invalid);
+            let (hours, minutes) = (field(1..3)?, field(4..6)?);
+            let offset = hours * 3600 + minutes * 60;
+            if tz.starts_with('-') {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => return Err(invalid()),
+    };
+
+    let days = days_from_civil(year, month, day);
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset_seconds;
+    Ok(seconds * 1_000_000_000 + nanos)
+}
+
+#[inline]
+/// Days since 1970-01-01 for a proleptic Gregorian date
+const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::feeds::{parse_rfc3339_nanos, FeedError};
+
+    #[test]
+    fn test_parse_rfc3339_nanos() {
+        assert_eq!(parse_rfc3339_nanos("1970-01-01T00:00:00Z"), Ok(0));
+        assert_eq!(parse_rfc3339_nanos("2019-08-14T20:42:27.265Z"), Ok(1_565_815_347_265_000_000));
+        assert_eq!(parse_rfc3339_nanos("2019-08-14T20:42:27.265123456789Z"), Ok(1_565_815_347_265_123_456));
+        assert_eq!(parse_rfc3339_nanos("2019-08-14T22:42:27+02:00"), Ok(1_565_815_347_000_000_000));
+        assert!(matches!(parse_rfc3339_nanos("2019-08-14"), Err(FeedError::InvalidTimestamp(_))));
+        assert!(matches!(parse_rfc3339_nanos("2019-08-14T20:42:27.Z"), Err(FeedError::InvalidTimestamp(_))));
+        assert!(matches!(parse_rfc3339_nanos("2019-08-14T20:42:27+0é:0"), Err(FeedError::InvalidTimestamp(_))));
+    }
+}
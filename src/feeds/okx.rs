@@ -0,0 +1,159 @@
+//! Normalizer for the OKX v5 `books` and `books-l2-tbt` channels.
+//!
+//! Both channels push an initial `snapshot` followed by incremental `update` actions with absolute
+//! sizes. Every message carries `seqId` and `prevSeqId`: an update is only valid when its
+//! `prevSeqId` equals the `seqId` of the previous message, which is checked with a
+//! [`SequenceTracker`]. `seqId` becomes the event `sequence_id`.
+
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    feeds::{parse_decimal, sequence::SequenceTracker, FeedError},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Deserialize)]
+struct Message<'a> {
+    action: Option<&'a str>,
+    #[serde(borrow, default)]
+    data: Vec<Book<'a>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Book<'a> {
+    /// `[price, size, deprecated, order count]`
+    #[serde(borrow)]
+    asks: Vec<(&'a str, &'a str, &'a str, &'a str)>,
+    #[serde(borrow)]
+    bids: Vec<(&'a str, &'a str, &'a str, &'a str)>,
+    /// Millisecond timestamp as a string
+    ts: &'a str,
+    seq_id: i64,
+    prev_seq_id: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct OkxNormalizer {
+    sequence: SequenceTracker,
+}
+
+impl OkxNormalizer {
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { sequence: SequenceTracker::new() }
+    }
+
+    /// Translate a raw book channel message into crate events.
+    ///
+    /// - `snapshot`: an [`EventKind::Clear`] then [`EventKind::Snapshot`] events, restarting the sequence chain.
+    /// - `update`: [`EventKind::L2`] events, or [`FeedError::SequenceGap`] when `prevSeqId` does not
+    ///   follow on from the last message. After a gap updates are rejected with
+    ///   [`FeedError::MissingSnapshot`] until the channel is resubscribed.
+    ///
+    /// Timestamps are converted to nanoseconds. Event and error pushes yield no events.
+    pub fn normalize<V>(&mut self, message: &str) -> Result<Vec<Event<V>>, FeedError>
+    where
+        V: DecimalType + FromStr,
+    {
+        let message = serde_json::from_str::<Message>(message)?;
+        let kind = match message.action {
+            Some("snapshot") => EventKind::Snapshot,
+            Some("update") => EventKind::L2,
+            Some(other) => return Err(FeedError::Malformed(format!("unknown action {other}"))),
+            None => return Ok(Vec::new()),
+        };
+
+        let mut events = Vec::new();
+        for book in message.data {
+            let sequence = u64::try_from(book.seq_id).map_err(|_| FeedError::Malformed(format!("seqId {}", book.seq_id)))?;
+            if kind == EventKind::Snapshot {
+                self.sequence.reset(sequence);
+            } else {
+                // A negative previous sequence only appears on snapshots, so it can never continue the chain
+                self.sequence.advance(u64::try_from(book.prev_seq_id).unwrap_or(u64::MAX), sequence)?;
+            }
+
+            let ts = book.ts.parse::<i64>().map_err(|_| FeedError::InvalidTimestamp(book.ts.to_owned()))?;
+            let ts = Timestamp::from_millis(ts);
+            if kind == EventKind::Snapshot {
+                events.push(Event::clear(ts).with_sequence_id(sequence));
+            }
+            for (side, levels) in [(Side::Buy, book.bids), (Side::Sell, book.asks)] {
+                for (price, size, _, _) in levels {
+                    let event = Event::new(kind, side, parse_decimal(price)?, parse_decimal(size)?, ts);
+                    events.push(event.with_sequence_id(sequence));
+                }
+            }
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook as _},
+        decimals::fixed_decimal::FixedDecimal,
+        event_kind::EventKind,
+        feeds::{okx::OkxNormalizer, FeedError},
+        fixed,
+    };
+
+    const SNAPSHOT: &str = r#"{"arg":{"channel":"books","instId":"BTC-USDT"},"action":"snapshot","data":[{"asks":[["8476.98","415","0","13"],["8477","7","0","2"]],"bids":[["8476.97","256","0","12"]],"ts":"1597026383085","checksum":-855196043,"prevSeqId":-1,"seqId":123456}]}"#;
+
+    fn update(prev: u64, seq: u64) -> String {
+        format!(
+            r#"{{"arg":{{"channel":"books","instId":"BTC-USDT"}},"action":"update","data":[{{"asks":[["8476.98","0","0","0"]],"bids":[["8476.90","3","0","1"]],"ts":"1597026383185","checksum":123,"prevSeqId":{prev},"seqId":{seq}}}]}}"#
+        )
+    }
+
+    #[test]
+    fn test_snapshot_then_update() {
+        let mut normalizer = OkxNormalizer::new();
+        let mut lob = BTreeOrderBook::<FixedDecimal>::new();
+
+        let events = normalizer.normalize::<FixedDecimal>(SNAPSHOT).unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].kind, EventKind::Clear);
+        assert!(events[1..].iter().all(|e| e.kind == EventKind::Snapshot && e.sequence_id == 123456));
+        assert_eq!(events[0].timestamp.as_nanos(), 1_597_026_383_085_000_000);
+        events.into_iter().for_each(|e| lob.process(e));
+
+        let events = normalizer.normalize::<FixedDecimal>(&update(123456, 123457)).unwrap();
+        assert!(events.iter().all(|e| e.kind == EventKind::L2 && e.sequence_id == 123457));
+        events.into_iter().for_each(|e| lob.process(e));
+        assert_eq!(lob.best_ask().unwrap().price, fixed!(8477));
+        assert_eq!(lob.best_bid().unwrap().price, fixed!(8476.97));
+    }
+
+    #[test]
+    fn test_sequence_gap() {
+        let mut normalizer = OkxNormalizer::new();
+        assert_eq!(normalizer.normalize::<FixedDecimal>(&update(1, 2)).unwrap_err(), FeedError::MissingSnapshot);
+
+        normalizer.normalize::<FixedDecimal>(SNAPSHOT).unwrap();
+        assert_eq!(
+            normalizer.normalize::<FixedDecimal>(&update(123458, 123459)).unwrap_err(),
+            FeedError::SequenceGap { expected: 123456, received: 123458 }
+        );
+        assert_eq!(normalizer.normalize::<FixedDecimal>(&update(123459, 123460)).unwrap_err(), FeedError::MissingSnapshot);
+
+        normalizer.normalize::<FixedDecimal>(SNAPSHOT).unwrap();
+        assert!(normalizer.normalize::<FixedDecimal>(&update(123456, 123457)).is_ok());
+    }
+
+    #[test]
+    fn test_event_push() {
+        let subscribe = r#"{"event":"subscribe","arg":{"channel":"books","instId":"BTC-USDT"},"connId":"a4d3ae55"}"#;
+        assert!(OkxNormalizer::new().normalize::<FixedDecimal>(subscribe).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+//! Gap detection for venues that chain each update to the previous one with a sequence pair.
+
+use crate::feeds::FeedError;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SequenceTracker {
+    last: Option<u64>,
+}
+
+impl SequenceTracker {
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { last: None }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Sequence of the last accepted message, `None` until a snapshot has been seen
+    pub const fn last(&self) -> Option<u64> {
+        self.last
+    }
+
+    #[inline]
+    /// Start a new chain from a snapshot
+    pub fn reset(&mut self, sequence: u64) {
+        self.last = Some(sequence);
+    }
+
+    #[inline]
+    /// Forget the chain, e.g. after a gap, so that only a new snapshot is accepted
+    pub fn invalidate(&mut self) {
+        self.last = None;
+    }
+
+    #[inline]
+    /// Accept an update whose `previous` sequence must equal the last accepted one.
+    ///
+    /// On a gap the tracker is invalidated, so every following update is rejected with
+    /// [`FeedError::MissingSnapshot`] until [`SequenceTracker::reset`] is called again.
+    pub fn advance(&mut self, previous: u64, sequence: u64) -> Result<(), FeedError> {
+        match self.last {
+            Some(last) if last == previous => {
+                self.last = Some(sequence);
+                Ok(())
+            }
+            Some(last) => {
+                self.invalidate();
+                Err(FeedError::SequenceGap { expected: last, received: previous })
+            }
+            None => Err(FeedError::MissingSnapshot),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::feeds::{sequence::SequenceTracker, FeedError};
+
+    #[test]
+    fn test_sequence_tracker() {
+        let mut tracker = SequenceTracker::new();
+        assert_eq!(tracker.advance(1, 2), Err(FeedError::MissingSnapshot));
+
+        tracker.reset(10);
+        assert_eq!(tracker.advance(10, 11), Ok(()));
+        assert_eq!(tracker.advance(11, 15), Ok(()));
+        assert_eq!(tracker.last(), Some(15));
+
+        assert_eq!(tracker.advance(16, 17), Err(FeedError::SequenceGap { expected: 15, received: 16 }));
+        assert_eq!(tracker.advance(17, 18), Err(FeedError::MissingSnapshot));
+        tracker.reset(20);
+        assert_eq!(tracker.advance(20, 21), Ok(()));
+    }
+}
@@ -0,0 +1,345 @@
+//! Reader for Tardis.dev historical datasets.
+//!
+//! [`TardisReader`] accepts the downloadable CSV datasets `incremental_book_L2` and `trades`, whose
+//! header row identifies the dataset, as well as the normalized NDJSON `book_change` and `trade`
+//! messages of tardis-machine. Datasets are distributed gzip-compressed, wrap the file in a
+//! decompressing reader (e.g. `flate2::read::GzDecoder` inside a `BufReader`) before handing it over.
+//!
+//! - Book rows become [`EventKind::Snapshot`] events while `is_snapshot` is set, [`EventKind::L2`]
+//!   events otherwise. Amounts are absolute, zero removes the level. Each snapshot is led by an
+//!   [`EventKind::Clear`]: in CSV, where a snapshot row follows an update row, and in NDJSON ahead of
+//!   every snapshot message.
+//! - Trades become [`EventKind::Trade`] events on the resting side with the reported aggressor, so a
+//!   `buy` trade consumes asks. Trades with an `unknown` aggressor side are skipped.
+//!
+//! Exchange timestamps (microseconds in CSV, RFC 3339 in NDJSON) are converted to nanoseconds.
+//! [`merge`] interleaves a book and a trades reader by timestamp.
+
+use std::{
+    collections::VecDeque,
+    io::{BufRead, Lines},
+    iter::Peekable,
+    str::FromStr,
+};
+
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    feeds::{parse_decimal, parse_rfc3339_nanos, FeedError},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Columns {
+    timestamp: usize,
+    side: usize,
+    price: usize,
+    amount: usize,
+    /// Only present in book datasets
+    is_snapshot: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Csv(Columns),
+    Ndjson,
+}
+
+/// A tardis-machine message, flat rather than an internally tagged enum since those buffer their
+/// fields and cannot hand out the raw text of a number
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Message<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    #[serde(default)]
+    is_snapshot: bool,
+    #[serde(borrow, default)]
+    bids: Vec<Change<'a>>,
+    #[serde(borrow, default)]
+    asks: Vec<Change<'a>>,
+    #[serde(borrow)]
+    side: Option<&'a str>,
+    /// Prices and amounts are kept as written, since a parsed float would print as `1e-7`
+    #[serde(borrow)]
+    price: Option<&'a RawValue>,
+    #[serde(borrow)]
+    amount: Option<&'a RawValue>,
+    #[serde(borrow)]
+    timestamp: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct Change<'a> {
+    #[serde(borrow)]
+    price: &'a RawValue,
+    #[serde(borrow)]
+    amount: &'a RawValue,
+}
+
+#[derive(Debug)]
+pub struct TardisReader<R, V: DecimalType> {
+    lines: Lines<R>,
+    format: Option<Format>,
+    line: usize,
+    pending: VecDeque<Event<V>>,
+    /// Whether the last CSV book row belonged to a snapshot
+    in_snapshot: bool,
+}
+
+impl<R: BufRead, V: DecimalType + FromStr> TardisReader<R, V> {
+    #[inline]
+    #[must_use]
+    pub fn new(reader: R) -> Self {
+        Self { lines: reader.lines(), format: None, line: 0, pending: VecDeque::new(), in_snapshot: false }
+    }
+
+    fn malformed(&self, reason: impl std::fmt::Display) -> FeedError {
+        FeedError::Malformed(format!("line {}: {reason}", self.line))
+    }
+
+    /// Identify the dataset from its CSV header
+    fn read_header(&self, header: &str) -> Result<Format, FeedError> {
+        let names = header.split(',').collect::<Vec<_>>();
+        let column =
+            |name: &str| names.iter().position(|&n| n == name).ok_or_else(|| self.malformed(format!("missing {name} column")));
+        Ok(Format::Csv(Columns {
+            timestamp: column("timestamp")?,
+            side: column("side")?,
+            price: column("price")?,
+            amount: column("amount")?,
+            is_snapshot: column("is_snapshot").ok(),
+        }))
+    }
+
+    fn read_csv(&mut self, columns: Columns, row: &str) -> Result<(), FeedError> {
+        let fields = row.split(',').collect::<Vec<_>>();
+        let field = |index: usize| fields.get(index).copied().ok_or_else(|| self.malformed("missing field"));
+
+        let ts = field(columns.timestamp)?;
+        let ts = Timestamp::from_micros(ts.parse::<i64>().map_err(|_| FeedError::InvalidTimestamp(ts.to_owned()))?);
+        let (price, amount) = (parse_decimal(field(columns.price)?)?, parse_decimal(field(columns.amount)?)?);
+        let event = match (columns.is_snapshot, field(columns.side)?) {
+            (Some(snapshot), side) => {
+                let is_snapshot = field(snapshot)? == "true";
+                if is_snapshot && !self.in_snapshot {
+                    self.pending.push_back(Event::clear(ts));
+                }
+                self.in_snapshot = is_snapshot;
+                let kind = if is_snapshot { EventKind::Snapshot } else { EventKind::L2 };
+                Event::new(kind, self.book_side(side)?, price, amount, ts)
+            }
+            (None, side) => match self.aggressor(side)? {
+                Some(aggressor) => Event::trade(aggressor, price, amount, ts),
+                None => return Ok(()),
+            },
+        };
+        self.pending.push_back(event);
+        Ok(())
+    }
+
+    fn read_ndjson(&mut self, line: &str) -> Result<(), FeedError> {
+        let message = serde_json::from_str::<Message>(line).map_err(|err| self.malformed(err))?;
+        let timestamp = || {
+            let timestamp = message.timestamp.ok_or_else(|| self.malformed("missing timestamp"))?;
+            Ok::<_, FeedError>(Timestamp::from_nanos(parse_rfc3339_nanos(timestamp)?))
+        };
+        match message.kind {
+            "book_change" => {
+                let ts = timestamp()?;
+                let kind = if message.is_snapshot { EventKind::Snapshot } else { EventKind::L2 };
+                if message.is_snapshot {
+                    self.pending.push_back(Event::clear(ts));
+                }
+                for (side, changes) in [(Side::Buy, &message.bids), (Side::Sell, &message.asks)] {
+                    for change in changes {
+                        let (price, amount) = (parse_decimal(change.price.get())?, parse_decimal(change.amount.get())?);
+                        self.pending.push_back(Event::new(kind, side, price, amount, ts));
+                    }
+                }
+            }
+            "trade" => {
+                let side = message.side.ok_or_else(|| self.malformed("missing side"))?;
+                if let Some(aggressor) = self.aggressor(side)? {
+                    let number = |value: Option<&RawValue>, name| {
+                        parse_decimal(value.ok_or_else(|| self.malformed(format!("missing {name}")))?.get())
+                    };
+                    let (price, amount) = (number(message.price, "price")?, number(message.amount, "amount")?);
+                    self.pending.push_back(Event::trade(aggressor, price, amount, timestamp()?));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn book_side(&self, side: &str) -> Result<Side, FeedError> {
+        match side {
+            "bid" => Ok(Side::Buy),
+            "ask" => Ok(Side::Sell),
+            other => Err(self.malformed(format!("unknown side {other}"))),
+        }
+    }
+
+    /// Side of the book a trade executed against, from the aggressor side
+    fn aggressor(&self, side: &str) -> Result<Option<Side>, FeedError> {
+        match side {
+            "buy" => Ok(Some(Side::Buy)),
+            "sell" => Ok(Some(Side::Sell)),
+            "unknown" => Ok(None),
+            other => Err(self.malformed(format!("unknown side {other}"))),
+        }
+    }
+}
+
+impl<R: BufRead, V: DecimalType + FromStr> Iterator for TardisReader<R, V> {
+    type Item = Result<Event<V>, FeedError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(FeedError::Malformed(err.to_string()))),
+            };
+            self.line += 1;
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+
+            let result = match self.format {
+                Some(Format::Csv(columns)) => self.read_csv(columns, line),
+                Some(Format::Ndjson) => self.read_ndjson(line),
+                None if line.starts_with('{') => {
+                    self.format = Some(Format::Ndjson);
+                    self.read_ndjson(line)
+                }
+                None => self.read_header(line).map(|format| self.format = Some(format)),
+            };
+            if let Err(err) = result {
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+/// Iterator interleaving two event streams by timestamp, see [`merge`]
+pub struct Merged<A: Iterator, B: Iterator> {
+    first: Peekable<A>,
+    second: Peekable<B>,
+}
+
+/// Interleave two timestamp-ordered event streams, e.g. a book and a trades dataset.
+///
+/// On equal timestamps the event of `first` comes first. Errors are passed through as they are met.
+pub fn merge<V, A, B>(first: A, second: B) -> Merged<A::IntoIter, B::IntoIter>
+where
+    V: DecimalType,
+    A: IntoIterator<Item = Result<Event<V>, FeedError>>,
+    B: IntoIterator<Item = Result<Event<V>, FeedError>>,
+{
+    Merged { first: first.into_iter().peekable(), second: second.into_iter().peekable() }
+}
+
+impl<V, A, B> Iterator for Merged<A, B>
+where
+    V: DecimalType,
+    A: Iterator<Item = Result<Event<V>, FeedError>>,
+    B: Iterator<Item = Result<Event<V>, FeedError>>,
+{
+    type Item = Result<Event<V>, FeedError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let take_first = match (self.first.peek(), self.second.peek()) {
+            (Some(Ok(a)), Some(Ok(b))) => a.timestamp <= b.timestamp,
+            (Some(Err(_)), _) | (Some(_), None) => true,
+            (_, Some(_)) => false,
+            (None, None) => return None,
+        };
+        if take_first {
+            self.first.next()
+        } else {
+            self.second.next()
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::str::FromStr as _;
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook as _},
+        decimals::fixed_decimal::FixedDecimal,
+        event_kind::EventKind,
+        feeds::tardis::{merge, TardisReader},
+        fixed,
+        side::{Aggressor, Side},
+    };
+
+    const BOOK: &str = "exchange,symbol,timestamp,local_timestamp,is_snapshot,side,price,amount
+deribit,BTC-PERPETUAL,1585699200000000,1585699200100000,true,ask,6443.5,38640
+deribit,BTC-PERPETUAL,1585699200000000,1585699200100000,true,bid,6443,1000
+deribit,BTC-PERPETUAL,1585699200000000,1585699200100000,true,bid,6442.5,2000
+deribit,BTC-PERPETUAL,1585699209920000,1585699209934201,false,bid,6443,0
+";
+    const TRADES: &str = "exchange,symbol,timestamp,local_timestamp,id,side,price,amount
+deribit,BTC-PERPETUAL,1585699205000000,1585699205100000,1,sell,6442.5,500
+deribit,BTC-PERPETUAL,1585699206000000,1585699206100000,2,unknown,6443.5,10
+";
+
+    #[test]
+    fn test_merged_csv() {
+        let book = TardisReader::<_, FixedDecimal>::new(BOOK.as_bytes());
+        let trades = TardisReader::<_, FixedDecimal>::new(TRADES.as_bytes());
+        let events = merge(book, trades).collect::<Result<Vec<_>, _>>().unwrap();
+
+        let kinds = events.iter().map(|e| e.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [EventKind::Clear, EventKind::Snapshot, EventKind::Snapshot, EventKind::Snapshot, EventKind::Trade, EventKind::L2]
+        );
+        assert_eq!(events[4].side, Side::Buy);
+        assert_eq!(events[0].timestamp.as_nanos(), 1_585_699_200_000_000_000);
+
+        let mut lob = BTreeOrderBook::<FixedDecimal>::new();
+        events.into_iter().for_each(|e| lob.process(e));
+        let bid = lob.best_bid().unwrap();
+        assert_eq!((bid.price, bid.size), (fixed!(6442.5), fixed!(1500)));
+        assert_eq!(lob.best_ask().unwrap().price, fixed!(6443.5));
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let messages = r#"{"type":"book_change","symbol":"XBTUSD","exchange":"bitmex","isSnapshot":true,"bids":[{"price":7985,"amount":283318}],"asks":[{"price":7985.5,"amount":1000}],"timestamp":"2019-10-23T11:29:53.469Z","localTimestamp":"2019-10-23T11:29:53.469Z"}
+{"type":"disconnect","exchange":"bitmex","localTimestamp":"2019-10-23T11:29:54.000Z"}
+{"type":"trade","symbol":"XBTUSD","exchange":"bitmex","id":"a","price":7985.5,"amount":100,"side":"buy","timestamp":"2019-10-23T11:29:55.000Z","localTimestamp":"2019-10-23T11:29:55.001Z"}
+"#;
+        let events = TardisReader::<_, FixedDecimal>::new(messages.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!((events[0].kind, events[2].price), (EventKind::Clear, fixed!(7985.5)));
+        assert_eq!((events[3].kind, events[3].side, events[3].aggressor), (EventKind::Trade, Side::Sell, Aggressor::Buy));
+        assert_eq!(events[3].timestamp.as_nanos(), 1_571_830_195_000_000_000);
+
+        // Read as a float and printed back, the amount would become 5e-8
+        let tiny = r#"{"type":"book_change","symbol":"ETHBTC","exchange":"binance","isSnapshot":false,"bids":[{"price":0.05,"amount":0.00000005}],"asks":[],"timestamp":"2019-10-23T11:29:53.469Z","localTimestamp":"2019-10-23T11:29:53.469Z"}"#;
+        let events = TardisReader::<_, FixedDecimal>::new(tiny.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(events[0].size, FixedDecimal::from_str("0.00000005").unwrap());
+    }
+
+    #[test]
+    fn test_bad_row() {
+        let rows = "exchange,symbol,timestamp,local_timestamp,is_snapshot,side,price,amount\nx,y,1,1,false,middle,1,1\n";
+        let err = TardisReader::<_, FixedDecimal>::new(rows.as_bytes()).next().unwrap().unwrap_err();
+        assert_eq!(err.to_string(), "malformed message: line 2: unknown side middle");
+    }
+}
@@ -0,0 +1,178 @@
+//! C interface to a [`FixedDecimal`] book.
+//!
+//! Books are opaque pointers created by [`freya_book_new`] and released by [`freya_book_free`].
+//! Prices and sizes cross the boundary as raw fixed-point `i64` values (see
+//! [`FixedDecimal::raw_value`]), kinds as `0 = trade, 1 = bbo, 2 = l2, 3 = snapshot, 8 = clear,
+//! 9 = clear side` and sides as `0 = buy, 1 = sell`.
+//!
+//! Build a library for linking with `cargo rustc --release --features ffi --crate-type staticlib`
+//! (or `cdylib`) and generate the header with `cbindgen --config cbindgen.toml --output freya_ob.h`.
+
+use std::ptr;
+
+use crate::{
+    books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+    decimals::fixed_decimal::FixedDecimal,
+    event::Event,
+    event_kind::EventKind,
+    side::Side,
+    timestamp::Timestamp,
+};
+
+/// The call succeeded
+pub const FREYA_OK: i32 = 0;
+/// A required pointer argument was null
+pub const FREYA_NULL_POINTER: i32 = -1;
+/// The kind or side code is not defined
+pub const FREYA_INVALID_ARGUMENT: i32 = -2;
+
+/// Opaque handle to a book.
+pub struct FreyaBook {
+    book: BTreeOrderBook<FixedDecimal>,
+}
+
+#[inline]
+const fn side_from_code(side: u8) -> Option<Side> {
+    match side {
+        0 => Some(Side::Buy),
+        1 => Some(Side::Sell),
+        _ => None,
+    }
+}
+
+#[no_mangle]
+/// Create an empty book, to be released with [`freya_book_free`]
+pub extern "C" fn freya_book_new() -> *mut FreyaBook {
+    Box::into_raw(Box::new(FreyaBook { book: BTreeOrderBook::new() }))
+}
+
+#[no_mangle]
+/// Release a book created by [`freya_book_new`], null is ignored
+///
+/// # Safety
+/// `book` must be null or a pointer returned by [`freya_book_new`] that was not freed yet.
+pub unsafe extern "C" fn freya_book_free(book: *mut FreyaBook) {
+    if !book.is_null() {
+        drop(Box::from_raw(book));
+    }
+}
+
+#[no_mangle]
+/// Apply one event to the book, returning [`FREYA_OK`] or an error code
+///
+/// Snapshot levels are applied like L2 updates, so send a clear before them to drop the levels the
+/// snapshot leaves out.
+///
+/// # Safety
+/// `book` must be null or a live pointer returned by [`freya_book_new`].
+pub unsafe extern "C" fn freya_book_process(
+    book: *mut FreyaBook,
+    kind: u8,
+    side: u8,
+    price: i64,
+    size: i64,
+    timestamp: i64,
+    sequence_id: u64,
+) -> i32 {
+    let Some(book) = book.as_mut() else {
+        return FREYA_NULL_POINTER;
+    };
+    let kind = match kind {
+        0 => EventKind::Trade,
+        1 => EventKind::BBO,
+        2 => EventKind::L2,
+        3 => EventKind::Snapshot,
+        8 => EventKind::Clear,
+        9 => EventKind::ClearSide,
+        _ => return FREYA_INVALID_ARGUMENT,
+    };
+    let Some(side) = side_from_code(side) else {
+        return FREYA_INVALID_ARGUMENT;
+    };
+    let event = Event::new(kind, side, FixedDecimal::new(price), FixedDecimal::new(size), Timestamp::from_nanos(timestamp));
+    book.book.process(event.with_sequence_id(sequence_id));
+    FREYA_OK
+}
+
+#[no_mangle]
+/// Copy up to `capacity` levels of one side, best first, into `prices` and `sizes`.
+///
+/// Returns the number of levels written, or a negative error code.
+///
+/// # Safety
+/// `book` must be null or a live pointer returned by [`freya_book_new`], and `prices` and `sizes`
+/// must each be null or valid for writing `capacity` values.
+pub unsafe extern "C" fn freya_book_levels(
+    book: *const FreyaBook,
+    side: u8,
+    prices: *mut i64,
+    sizes: *mut i64,
+    capacity: usize,
+) -> isize {
+    let Some(book) = book.as_ref() else {
+        return FREYA_NULL_POINTER as isize;
+    };
+    if prices.is_null() || sizes.is_null() {
+        return FREYA_NULL_POINTER as isize;
+    }
+    let Some(side) = side_from_code(side) else {
+        return FREYA_INVALID_ARGUMENT as isize;
+    };
+    let levels = book.book.levels(side, capacity);
+    for (i, level) in levels.iter().enumerate() {
+        ptr::write(prices.add(i), level.price.raw_value());
+        ptr::write(sizes.add(i), level.size.raw_value());
+    }
+    levels.len() as isize
+}
+
+#[no_mangle]
+/// Write the best level of one side into `price` and `size`.
+///
+/// Returns 1 if the side has a level, 0 if it is empty, or a negative error code.
+///
+/// # Safety
+/// `book` must be null or a live pointer returned by [`freya_book_new`], and `price` and `size`
+/// must be null or valid for writing.
+pub unsafe extern "C" fn freya_book_best(book: *const FreyaBook, side: u8, price: *mut i64, size: *mut i64) -> i32 {
+    freya_book_levels(book, side, price, size, 1) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        decimals::fixed_decimal::FixedDecimal,
+        ffi::{
+            freya_book_best, freya_book_free, freya_book_levels, freya_book_new, freya_book_process, FREYA_INVALID_ARGUMENT,
+            FREYA_OK,
+        },
+        fixed,
+    };
+
+    #[test]
+    fn test_book_round_trip() {
+        let raw = |value: FixedDecimal| value.raw_value();
+        unsafe {
+            let book = freya_book_new();
+            for (side, price, size) in [(0, fixed!(99.5), fixed!(2)), (0, fixed!(99), fixed!(4)), (1, fixed!(100), fixed!(1))] {
+                assert_eq!(freya_book_process(book, 2, side, raw(price), raw(size), 1, 0), FREYA_OK);
+            }
+            assert_eq!(freya_book_process(book, 10, 0, 0, 0, 1, 0), FREYA_INVALID_ARGUMENT);
+
+            let (mut prices, mut sizes) = ([0; 4], [0; 4]);
+            assert_eq!(freya_book_levels(book, 0, prices.as_mut_ptr(), sizes.as_mut_ptr(), 4), 2);
+            assert_eq!(prices[..2], [raw(fixed!(99.5)), raw(fixed!(99))]);
+            assert_eq!(sizes[..2], [raw(fixed!(2)), raw(fixed!(4))]);
+
+            let (mut price, mut size) = (0, 0);
+            assert_eq!(freya_book_best(book, 1, &mut price, &mut size), 1);
+            assert_eq!((price, size), (raw(fixed!(100)), raw(fixed!(1))));
+            assert_eq!(freya_book_best(std::ptr::null(), 1, &mut price, &mut size), -1);
+
+            assert_eq!(freya_book_process(book, 9, 1, 0, 0, 2, 0), FREYA_OK);
+            assert_eq!(freya_book_best(book, 1, &mut price, &mut size), 0);
+            assert_eq!(freya_book_levels(book, 0, prices.as_mut_ptr(), sizes.as_mut_ptr(), 4), 2);
+            freya_book_free(book);
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! Fixed-width binary layout for [`Event<FixedDecimal>`].
+//!
+//! Every event takes [`RECORD_LEN`] bytes, all integers little-endian:
+//!
+//! | bytes    | content                                              |
+//! |----------|------------------------------------------------------|
+//! | `0..8`   | raw price (`i64` at the [`FixedDecimal`] scale)      |
+//! | `8..16`  | raw size                                             |
+//! | `16..24` | timestamp (`i64`)                                    |
+//! | `24..31` | sequence ID (`u56`)                                  |
+//! | `31`     | kind in the low nibble, side in the high nibble      |
+//!
+//! Kinds are numbered `trade = 0`, `bbo = 1`, `l2 = 2`, `snapshot = 3`, `add = 4`, `cancel = 5`,
+//! `modify = 6`, `execute = 7`, `clear = 8`, `clear_side = 9` and sides `buy = 0`, `sell = 1`. There
+//! is no room for order IDs or trade aggressors, so Level 3 events and trades that need them should be
+//! journaled instead. A recording is the concatenation of records, so [`BinaryReplayer`] iterates and
+//! binary searches it in place without parsing. With the `mmap` feature [`MmapFile`] maps a
+//! recording into memory to replay it straight from the page cache.
+
+use std::io::Write;
+
+use crate::{
+    decimals::fixed_decimal::FixedDecimal, event::Event, event_kind::EventKind, formats::FormatError, side::Side,
+    timestamp::Timestamp,
+};
+
+pub const RECORD_LEN: usize = 32;
+
+/// Largest sequence ID the layout can hold
+pub const MAX_SEQUENCE_ID: u64 = (1 << 56) - 1;
+
+#[inline(always)]
+const fn kind_code(kind: EventKind) -> u8 {
+    match kind {
+        EventKind::Trade => 0,
+        EventKind::BBO => 1,
+        EventKind::L2 => 2,
+        EventKind::Snapshot => 3,
+        EventKind::Add => 4,
+        EventKind::Cancel => 5,
+        EventKind::Modify => 6,
+        EventKind::Execute => 7,
+        EventKind::Clear => 8,
+        EventKind::ClearSide => 9,
+    }
+}
+
+#[inline(always)]
+fn i64_at(buf: &[u8], at: usize) -> i64 {
+    i64::from_le_bytes(buf[at..at + 8].try_into().expect("slice of eight bytes"))
+}
+
+#[inline]
+/// Write `event` into the first [`RECORD_LEN`] bytes of `buf`
+pub fn encode_into(event: &Event<FixedDecimal>, buf: &mut [u8]) -> Result<(), FormatError> {
+    if buf.len() < RECORD_LEN {
+        return Err(FormatError::Malformed { line: 0, reason: format!("buffer of {} bytes is too small", buf.len()) });
+    }
+    if event.sequence_id > MAX_SEQUENCE_ID {
+        return Err(FormatError::Malformed { line: 0, reason: format!("sequence ID {} exceeds 56 bits", event.sequence_id) });
+    }
+    buf[0..8].copy_from_slice(&event.price.raw_value().to_le_bytes());
+    buf[8..16].copy_from_slice(&event.size.raw_value().to_le_bytes());
+    buf[16..24].copy_from_slice(&event.timestamp.as_nanos().to_le_bytes());
+    buf[24..32].copy_from_slice(&event.sequence_id.to_le_bytes());
+    buf[31] = kind_code(event.kind) | (event.side as u8) << 4;
+    Ok(())
+}
+
+#[inline]
+/// Read the event stored in the first [`RECORD_LEN`] bytes of `buf`
+pub fn decode_from(buf: &[u8]) -> Result<Event<FixedDecimal>, FormatError> {
+    if buf.len() < RECORD_LEN {
+        return Err(FormatError::Malformed { line: 0, reason: format!("record of {} bytes is truncated", buf.len()) });
+    }
+    let kind = match buf[31] & 0x0f {
+        0 => EventKind::Trade,
+        1 => EventKind::BBO,
+        2 => EventKind::L2,
+        3 => EventKind::Snapshot,
+        4 => EventKind::Add,
+        5 => EventKind::Cancel,
+        6 => EventKind::Modify,
+        7 => EventKind::Execute,
+        8 => EventKind::Clear,
+        9 => EventKind::ClearSide,
+        other => return Err(FormatError::Malformed { line: 0, reason: format!("unknown kind {other}") }),
+    };
+    let side = match buf[31] >> 4 {
+        0 => Side::Buy,
+        1 => Side::Sell,
+        other => return Err(FormatError::Malformed { line: 0, reason: format!("unknown side {other}") }),
+    };
+    let sequence_id = i64_at(buf, 24) as u64 & MAX_SEQUENCE_ID;
+    let event = Event::new(
+        kind,
+        side,
+        FixedDecimal::new(i64_at(buf, 0)),
+        FixedDecimal::new(i64_at(buf, 8)),
+        Timestamp::from_nanos(i64_at(buf, 16)),
+    );
+    Ok(event.with_sequence_id(sequence_id))
+}
+
+#[derive(Debug)]
+pub struct BinaryWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> BinaryWriter<W> {
+    #[inline]
+    #[must_use]
+    pub const fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    #[inline]
+    pub fn write(&mut self, event: &Event<FixedDecimal>) -> Result<(), FormatError> {
+        let mut record = [0; RECORD_LEN];
+        encode_into(event, &mut record)?;
+        self.writer.write_all(&record)?;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn flush(&mut self) -> Result<(), FormatError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Iterates the records of an in-memory recording, errors report the record index as the line.
+pub struct BinaryReplayer<'a> {
+    records: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BinaryReplayer<'a> {
+    #[inline]
+    /// Replay `records`, failing if it does not hold a whole number of records
+    pub fn new(records: &'a [u8]) -> Result<Self, FormatError> {
+        if !records.len().is_multiple_of(RECORD_LEN) {
+            let reason = format!("{} bytes do not make up whole records", records.len());
+            return Err(FormatError::Malformed { line: records.len() / RECORD_LEN, reason });
+        }
+        Ok(Self { records, position: 0 })
+    }
+
+    #[inline]
+    #[must_use]
+    /// Total number of records, regardless of the iteration position
+    pub const fn len(&self) -> usize {
+        self.records.len() / RECORD_LEN
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<Result<Event<FixedDecimal>, FormatError>> {
+        let record = self.records.get(index * RECORD_LEN..(index + 1) * RECORD_LEN)?;
+        Some(decode_from(record).map_err(|err| match err {
+            FormatError::Malformed { reason, .. } => FormatError::Malformed { line: index, reason },
+            other => other,
+        }))
+    }
+
+    #[inline]
+    /// Position the replayer on the first event at or after `ts`, assuming timestamps never decrease
+    pub fn seek(&mut self, ts: Timestamp) {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            // The timestamp field is read directly, it does not depend on the rest of the record
+            if i64_at(&self.records[mid * RECORD_LEN..], 16) < ts.as_nanos() {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        self.position = low;
+    }
+}
+
+impl Iterator for BinaryReplayer<'_> {
+    type Item = Result<Event<FixedDecimal>, FormatError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.get(self.position)?;
+        self.position += 1;
+        Some(event)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len() - self.position;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for BinaryReplayer<'_> {}
+
+#[cfg(all(feature = "mmap", unix))]
+#[derive(Debug)]
+/// A read-only memory map of a whole file.
+pub struct MmapFile {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+// SAFETY: the mapping is private and read-only, so it can be shared and sent like a `&[u8]`
+#[cfg(all(feature = "mmap", unix))]
+unsafe impl Send for MmapFile {}
+#[cfg(all(feature = "mmap", unix))]
+unsafe impl Sync for MmapFile {}
+
+#[cfg(all(feature = "mmap", unix))]
+impl MmapFile {
+    pub fn open(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        use std::os::fd::AsRawFd;
+
+        let file = std::fs::File::open(path)?;
+        let len = usize::try_from(file.metadata()?.len()).map_err(std::io::Error::other)?;
+        if len == 0 {
+            // Zero length mappings are rejected by mmap
+            return Ok(Self { ptr: std::ptr::null_mut(), len });
+        }
+        // SAFETY: the descriptor is valid for the duration of the call and the mapping outlives it
+        let ptr = unsafe { libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self { ptr, len })
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        // SAFETY: the mapping is `len` bytes long and lives as long as `self`
+        unsafe { std::slice::from_raw_parts(self.ptr.cast::<u8>(), self.len) }
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl Drop for MmapFile {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            // SAFETY: `ptr` and `len` describe a mapping created by `open`
+            unsafe { libc::munmap(self.ptr, self.len) };
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::binary::{decode_from, encode_into, BinaryReplayer, BinaryWriter, MAX_SEQUENCE_ID, RECORD_LEN},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn recording(count: i64) -> Vec<u8> {
+        let mut writer = BinaryWriter::new(Vec::new());
+        for i in 0..count {
+            let event = Event::new(
+                EventKind::L2,
+                Side::Sell,
+                fixed!(100) + FixedDecimal::from_int(i),
+                fixed!(0.5),
+                Timestamp::from_nanos(i * 10),
+            );
+            writer.write(&event.with_sequence_id(i as u64)).unwrap();
+        }
+        writer.into_inner()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let event = Event::new(EventKind::Snapshot, Side::Sell, fixed!(-12.5), fixed!(3), Timestamp::from_nanos(-7))
+            .with_sequence_id(MAX_SEQUENCE_ID);
+        let mut buf = [0; RECORD_LEN];
+        encode_into(&event, &mut buf).unwrap();
+        assert_eq!(buf[31], 0x13);
+        let decoded = decode_from(&buf).unwrap();
+        assert_eq!((decoded.kind, decoded.side), (EventKind::Snapshot, Side::Sell));
+        assert_eq!(
+            (decoded.price, decoded.size, decoded.timestamp.as_nanos(), decoded.sequence_id),
+            (fixed!(-12.5), fixed!(3), -7, MAX_SEQUENCE_ID)
+        );
+
+        assert!(encode_into(&event.with_sequence_id(MAX_SEQUENCE_ID + 1), &mut buf).is_err());
+        assert!(decode_from(&buf[..31]).is_err());
+    }
+
+    #[test]
+    fn test_replay_and_seek() {
+        let bytes = recording(100);
+        let mut replayer = BinaryReplayer::new(&bytes).unwrap();
+        assert_eq!(replayer.len(), 100);
+        assert_eq!(replayer.get(42).unwrap().unwrap().price, fixed!(142));
+
+        replayer.seek(Timestamp::from_nanos(555));
+        assert_eq!(replayer.size_hint(), (44, Some(44)));
+        assert_eq!(replayer.next().unwrap().unwrap().timestamp.as_nanos(), 560);
+        assert_eq!(replayer.map(|event| event.unwrap().sequence_id).sum::<u64>(), (57..100).sum::<u64>());
+
+        assert!(BinaryReplayer::new(&bytes[..40]).is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "mmap", unix))]
+    fn test_mmap_replay() {
+        let path = std::env::temp_dir().join(format!("freya_ob_mmap_{}.bin", std::process::id()));
+        std::fs::write(&path, recording(10)).unwrap();
+        let map = crate::formats::binary::MmapFile::open(&path).unwrap();
+        let events = BinaryReplayer::new(map.as_bytes()).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(events.len(), 10);
+        assert_eq!(events[9].timestamp.as_nanos(), 90);
+    }
+}
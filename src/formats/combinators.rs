@@ -0,0 +1,238 @@
+//! Combinators over [`EventSource`]s, for the filtering, normalising and merging every replay tool
+//! otherwise writes for itself.
+//!
+//! Errors always pass through a combinator untouched, so a failing source still fails downstream.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+    time::Duration,
+};
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    formats::source::{EventSource, SourceError},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+pub trait EventSourceExt<V: DecimalType>: EventSource<V> + Sized {
+    /// Keep the events `predicate` accepts
+    #[inline]
+    fn filter_events<F: FnMut(&Event<V>) -> bool>(self, mut predicate: F) -> impl EventSource<V> {
+        self.filter(move |event| event.as_ref().map_or(true, &mut predicate))
+    }
+
+    /// Keep the events of the given kinds
+    #[inline]
+    fn filter_kinds(self, kinds: &[EventKind]) -> impl EventSource<V> {
+        let kinds = kinds.to_vec();
+        self.filter_events(move |event| kinds.contains(&event.kind))
+    }
+
+    /// Keep the events of one side
+    #[inline]
+    fn filter_side(self, side: Side) -> impl EventSource<V> {
+        self.filter_events(move |event| event.side == side)
+    }
+
+    /// Keep the events priced within `low..=high`
+    #[inline]
+    fn price_band(self, low: V, high: V) -> impl EventSource<V>
+    where
+        V: PartialOrd + Copy,
+    {
+        self.filter_events(move |event| low <= event.price && event.price <= high)
+    }
+
+    /// Replace every price with `round(price)`, such as rounding to a tick size
+    #[inline]
+    fn map_prices<F: FnMut(V) -> V>(self, mut round: F) -> impl EventSource<V>
+    where
+        V: Copy,
+    {
+        self.map(move |event| event.map(|event| Event { price: round(event.price), ..event }))
+    }
+
+    /// Keep an event only when `interval` of event time has passed since the last one kept
+    #[inline]
+    fn throttle(self, interval: Duration) -> impl EventSource<V> {
+        let mut next = None::<Timestamp>;
+        self.filter_events(move |event| {
+            if next.is_some_and(|next| event.timestamp < next) {
+                return false;
+            }
+            next = Some(event.timestamp + interval);
+            true
+        })
+    }
+
+    /// Keep every `n`th event, starting with the first
+    #[inline]
+    fn sample(self, n: usize) -> impl EventSource<V> {
+        let (n, mut seen) = (n.max(1), 0);
+        self.filter_events(move |_| {
+            seen += 1;
+            (seen - 1) % n == 0
+        })
+    }
+}
+
+impl<V: DecimalType, S: EventSource<V>> EventSourceExt<V> for S {}
+
+#[derive(Debug)]
+/// Merges sources that are each in timestamp order into one stream in timestamp order, see
+/// [`merge_by_timestamp`]
+pub struct Merge<V: DecimalType, S> {
+    sources: Vec<S>,
+    /// The next event of every source that has one, ties go to the source listed first
+    heads: BinaryHeap<Reverse<(Timestamp, usize)>>,
+    pending: Vec<Option<Event<V>>>,
+    /// Errors read while filling the heads with the source that raised them, yielded in order before any
+    /// further event, the source is polled again once its error is yielded
+    errors: VecDeque<(SourceError, usize)>,
+}
+
+/// Merge `sources` by timestamp, as for replaying the recordings of several venues together
+pub fn merge_by_timestamp<V: DecimalType, S: EventSource<V>>(sources: Vec<S>) -> Merge<V, S> {
+    let pending = sources.iter().map(|_| None).collect();
+    let mut merge = Merge { sources, heads: BinaryHeap::new(), pending, errors: VecDeque::new() };
+    for index in (0..merge.sources.len()).rev() {
+        merge.refill(index);
+    }
+    merge
+}
+
+impl<V: DecimalType, S: EventSource<V>> Merge<V, S> {
+    fn refill(&mut self, index: usize) {
+        match self.sources[index].next() {
+            Some(Ok(event)) => {
+                self.heads.push(Reverse((event.timestamp, index)));
+                self.pending[index] = Some(event);
+            }
+            Some(Err(err)) => self.errors.push_back((err, index)),
+            None => {}
+        }
+    }
+}
+
+impl<V: DecimalType, S: EventSource<V>> Iterator for Merge<V, S> {
+    type Item = Result<Event<V>, SourceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((err, index)) = self.errors.pop_front() {
+            self.refill(index);
+            return Some(Err(err));
+        }
+        let Reverse((_, index)) = self.heads.pop()?;
+        let event = self.pending[index].take().expect("every head has a pending event");
+        self.refill(index);
+        Some(Ok(event))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::{
+            combinators::{merge_by_timestamp, EventSourceExt},
+            source::{EventSource, MemorySource, SourceError},
+            FormatError,
+        },
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn event(kind: EventKind, side: Side, price: FixedDecimal, ts: i64) -> Event<FixedDecimal> {
+        Event::new(kind, side, price, fixed!(1), Timestamp::from_millis(ts))
+    }
+
+    fn stream() -> MemorySource<FixedDecimal> {
+        MemorySource::new(vec![
+            event(EventKind::L2, Side::Buy, fixed!(99.98), 0),
+            event(EventKind::Trade, Side::Sell, fixed!(100.03), 1),
+            event(EventKind::L2, Side::Sell, fixed!(100.04), 2),
+            event(EventKind::L2, Side::Buy, fixed!(90), 15),
+            event(EventKind::BBO, Side::Sell, fixed!(100.01), 30),
+        ])
+    }
+
+    fn prices(source: impl EventSource<FixedDecimal>) -> Vec<FixedDecimal> {
+        source.map(|event| event.unwrap().price).collect()
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(prices(stream().filter_kinds(&[EventKind::L2, EventKind::BBO])).len(), 4);
+        assert_eq!(prices(stream().filter_side(Side::Buy)), [fixed!(99.98), fixed!(90)]);
+        assert_eq!(prices(stream().price_band(fixed!(99), fixed!(100.03))), [fixed!(99.98), fixed!(100.03), fixed!(100.01)]);
+        // Round down to a 0.05 tick
+        let tick = |price: FixedDecimal| FixedDecimal::from_int((price * fixed!(20)).to_f64() as i64) / fixed!(20);
+        assert_eq!(prices(stream().filter_side(Side::Sell).map_prices(tick)), [fixed!(100), fixed!(100), fixed!(100)]);
+    }
+
+    #[test]
+    fn test_throttle_and_sample() {
+        let times = |source: &mut dyn EventSource<FixedDecimal>| {
+            source.map(|event| event.unwrap().timestamp.as_millis()).collect::<Vec<_>>()
+        };
+        assert_eq!(times(&mut stream().throttle(Duration::from_millis(10))), [0, 15, 30]);
+        assert_eq!(times(&mut stream().sample(2)), [0, 2, 30]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let venue = |times: &[i64], price| {
+            MemorySource::new(times.iter().map(|&ts| event(EventKind::L2, Side::Buy, price, ts)).collect())
+        };
+        let merged =
+            merge_by_timestamp(vec![venue(&[0, 5, 5, 20], fixed!(1)), venue(&[5, 10], fixed!(2)), venue(&[], fixed!(3))]);
+        let merged =
+            merged.map(|event| event.map(|event| (event.timestamp.as_millis(), event.price))).collect::<Result<Vec<_>, _>>();
+        let expected = [(0, fixed!(1)), (5, fixed!(1)), (5, fixed!(1)), (5, fixed!(2)), (10, fixed!(2)), (20, fixed!(1))];
+        assert_eq!(merged.unwrap(), expected);
+
+        let broken = std::iter::once(Err::<Event<FixedDecimal>, SourceError>(FormatError::Io("gone".to_owned())));
+        let mut merged =
+            merge_by_timestamp::<FixedDecimal, Box<dyn EventSource<FixedDecimal>>>(vec![Box::new(stream()), Box::new(broken)]);
+        assert!(merged.next().unwrap().is_err());
+        assert_eq!(merged.count(), 5);
+    }
+
+    #[test]
+    fn test_merge_keeps_reading_after_error() {
+        let io = |reason: &str| Err::<Event<FixedDecimal>, SourceError>(FormatError::Io(reason.to_owned()));
+        let flaky = vec![
+            Ok(event(EventKind::L2, Side::Buy, fixed!(2), 1)),
+            io("first"),
+            io("second"),
+            Ok(event(EventKind::L2, Side::Buy, fixed!(2), 3)),
+        ];
+        let steady =
+            MemorySource::new(vec![event(EventKind::L2, Side::Buy, fixed!(1), 0), event(EventKind::L2, Side::Buy, fixed!(1), 2)]);
+        let merged = merge_by_timestamp::<FixedDecimal, Box<dyn EventSource<FixedDecimal>>>(vec![
+            Box::new(steady),
+            Box::new(flaky.into_iter()),
+        ]);
+        let merged =
+            merged.map(|event| event.map(|event| event.timestamp.as_millis()).map_err(|err| err.to_string())).collect::<Vec<_>>();
+        let expected = [Ok(0), Ok(1), Err("first"), Err("second"), Ok(2), Ok(3)];
+        assert_eq!(merged.len(), expected.len());
+        for (got, want) in merged.iter().zip(expected) {
+            match (got, want) {
+                (Ok(got), Ok(want)) => assert_eq!(*got, want),
+                (Err(got), Err(want)) => assert!(got.contains(want), "{got}"),
+                _ => panic!("{got:?} != {want:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! CSV layout for events.
+//!
+//! Files start with the header `kind,side,price,size,ts,seq` and hold one event per row:
+//!
+//! | column  | content                                          |
+//! |---------|--------------------------------------------------|
+//! | `kind`  | `trade`, `bbo`, `l2`, `snapshot`, `clear`, `clear_side` or an order kind |
+//! | `side`  | `buy` or `sell`                                  |
+//! | `price` | decimal in the backend's `Display` form          |
+//! | `size`  | decimal in the backend's `Display` form          |
+//! | `ts`    | timestamp as an integer, nanoseconds by convention |
+//! | `seq`   | sequence ID, `0` when the source has none        |
+//!
+//! No field ever needs quoting, so rows can be produced and consumed with plain string splitting.
+//! Order IDs are not recorded, the [journal](crate::formats::journal) keeps them.
+
+use std::{
+    fmt::Display,
+    io::{BufRead, Lines, Write},
+    marker::PhantomData,
+    str::FromStr,
+};
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    formats::{kind_name, parse_kind, parse_side, side_name, FormatError},
+    timestamp::Timestamp,
+};
+
+pub const HEADER: &str = "kind,side,price,size,ts,seq";
+
+#[derive(Debug)]
+/// Streams events as CSV rows, writing the header on construction.
+pub struct CsvWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(mut writer: W) -> Result<Self, FormatError> {
+        writeln!(writer, "{HEADER}")?;
+        Ok(Self { writer })
+    }
+
+    #[inline]
+    pub fn write<V: DecimalType + Display>(&mut self, event: &Event<V>) -> Result<(), FormatError> {
+        writeln!(
+            self.writer,
+            "{},{},{},{},{},{}",
+            kind_name(event.kind),
+            side_name(event.side),
+            event.price,
+            event.size,
+            event.timestamp,
+            event.sequence_id
+        )?;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn flush(&mut self) -> Result<(), FormatError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[derive(Debug)]
+/// Iterates the events of a CSV stream, checking the header first.
+pub struct CsvReader<R, V> {
+    lines: Lines<R>,
+    line: usize,
+    _decimal: PhantomData<V>,
+}
+
+impl<R: BufRead, V: DecimalType + FromStr> CsvReader<R, V> {
+    #[inline]
+    #[must_use]
+    pub fn new(reader: R) -> Self {
+        Self { lines: reader.lines(), line: 0, _decimal: PhantomData }
+    }
+
+    fn parse(&self, row: &str) -> Result<Event<V>, FormatError> {
+        let malformed = |reason: String| FormatError::Malformed { line: self.line, reason };
+        let fields = row.split(',').collect::<Vec<_>>();
+        let [kind, side, price, size, ts, seq] = fields[..] else {
+            return Err(malformed(format!("expected 6 fields, found {}", fields.len())));
+        };
+        let kind = parse_kind(kind).ok_or_else(|| malformed(format!("unknown kind {kind}")))?;
+        let side = parse_side(side).ok_or_else(|| malformed(format!("unknown side {side}")))?;
+        let price = V::from_str(price).map_err(|_| malformed(format!("invalid price {price}")))?;
+        let size = V::from_str(size).map_err(|_| malformed(format!("invalid size {size}")))?;
+        let ts = ts.parse::<i64>().map_err(|_| malformed(format!("invalid ts {ts}")))?;
+        let seq = seq.parse().map_err(|_| malformed(format!("invalid seq {seq}")))?;
+        Ok(Event::new(kind, side, price, size, Timestamp::from_nanos(ts)).with_sequence_id(seq))
+    }
+}
+
+impl<R: BufRead, V: DecimalType + FromStr> Iterator for CsvReader<R, V> {
+    type Item = Result<Event<V>, FormatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            self.line += 1;
+            let row = line.trim_end();
+            if self.line == 1 {
+                if row != HEADER {
+                    return Some(Err(FormatError::Malformed { line: 1, reason: format!("expected header {HEADER}") }));
+                }
+                continue;
+            }
+            if !row.is_empty() {
+                return Some(self.parse(row));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::{
+            csv::{CsvReader, CsvWriter},
+            FormatError,
+        },
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let events = [
+            Event::new(EventKind::Snapshot, Side::Buy, fixed!(100.25), fixed!(3), Timestamp::from_nanos(1_000))
+                .with_sequence_id(7),
+            Event::new(EventKind::Trade, Side::Sell, fixed!(100.5), fixed!(0.125), Timestamp::from_nanos(2_000)),
+        ];
+        let mut writer = CsvWriter::new(Vec::new()).unwrap();
+        events.iter().for_each(|event| writer.write(event).unwrap());
+        let csv = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(csv, "kind,side,price,size,ts,seq\nsnapshot,buy,100.25,3,1000,7\ntrade,sell,100.5,0.125,2000,0\n");
+
+        let read = CsvReader::<_, FixedDecimal>::new(csv.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!((read[0].kind, read[0].price, read[0].sequence_id), (EventKind::Snapshot, fixed!(100.25), 7));
+        assert_eq!((read[1].side, read[1].size, read[1].timestamp.as_nanos()), (Side::Sell, fixed!(0.125), 2_000));
+    }
+
+    #[test]
+    fn test_malformed_rows() {
+        let missing_header = CsvReader::<_, FixedDecimal>::new("l2,buy,1,1,1,0\n".as_bytes()).next().unwrap();
+        assert!(matches!(missing_header, Err(FormatError::Malformed { line: 1, .. })));
+
+        let bad_side = "kind,side,price,size,ts,seq\nl2,up,1,1,1,0\n";
+        assert_eq!(
+            CsvReader::<_, FixedDecimal>::new(bad_side.as_bytes()).next().unwrap().unwrap_err(),
+            FormatError::Malformed { line: 2, reason: "unknown side up".to_owned() }
+        );
+    }
+}
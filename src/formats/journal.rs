@@ -0,0 +1,338 @@
+//! Newline-delimited JSON event journal.
+//!
+//! Every event is one JSON object per line, with decimals written as strings so no precision is
+//! lost:
+//!
+//! ```text
+//! {"sync":0,"ts":1000}
+//! {"kind":"l2","side":"buy","price":"100.25","size":"3","ts":1000,"seq":7}
+//! {"kind":"add","side":"sell","price":"100.5","size":"2","ts":1001,"seq":8,"oid":42,"prio":3}
+//! ```
+//!
+//! Order level events carry their order ID as `oid` and any queue priority as `prio`, both left out
+//! of other events. Trades with a known aggressor carry its side as `aggr`.
+//!
+//! Every `sync_interval` events a sync marker records the number of events written so far and the
+//! timestamp of the next one, which lets [`JournalReader::seek`] binary search a seekable file
+//! instead of parsing it from the start. Compression is left to the caller: wrap the writer in a
+//! compressing one (e.g. `zstd::Encoder`) and the reader in the matching decoder, seeking is then
+//! replaced by [`JournalReader::skip_to`].
+
+use std::{
+    fmt::Display,
+    io::{BufRead, Seek, SeekFrom, Write},
+    str::FromStr,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    formats::{kind_name, parse_kind, parse_side, side_name, FormatError},
+    side::Aggressor,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug)]
+pub struct EventJournal<W: Write> {
+    writer: W,
+    sync_interval: u64,
+    written: u64,
+}
+
+impl<W: Write> EventJournal<W> {
+    #[inline]
+    #[must_use]
+    pub const fn new(writer: W) -> Self {
+        Self { writer, sync_interval: 1024, written: 0 }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Events between sync markers, smaller intervals make seeks cheaper and files larger
+    pub fn with_sync_interval(self, sync_interval: u64) -> Self {
+        Self { sync_interval: sync_interval.max(1), ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn written(&self) -> u64 {
+        self.written
+    }
+
+    pub fn append<V: DecimalType + Display>(&mut self, event: &Event<V>) -> Result<(), FormatError> {
+        if self.written.is_multiple_of(self.sync_interval) {
+            writeln!(self.writer, r#"{{"sync":{},"ts":{}}}"#, self.written, event.timestamp)?;
+        }
+        write!(
+            self.writer,
+            r#"{{"kind":"{}","side":"{}","price":"{}","size":"{}","ts":{},"seq":{}"#,
+            kind_name(event.kind),
+            side_name(event.side),
+            event.price,
+            event.size,
+            event.timestamp,
+            event.sequence_id
+        )?;
+        if event.kind.is_order_level() {
+            write!(self.writer, r#","oid":{}"#, event.order_id)?;
+            if let Some(priority) = event.priority {
+                write!(self.writer, r#","prio":{priority}"#)?;
+            }
+        }
+        if let Some(aggressor) = event.aggressor.side() {
+            write!(self.writer, r#","aggr":"{}""#, side_name(aggressor))?;
+        }
+        writeln!(self.writer, "}}")?;
+        self.written += 1;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn flush(&mut self) -> Result<(), FormatError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[derive(Deserialize)]
+struct Record<'a> {
+    sync: Option<u64>,
+    kind: Option<&'a str>,
+    side: Option<&'a str>,
+    price: Option<&'a str>,
+    size: Option<&'a str>,
+    ts: i64,
+    #[serde(default)]
+    seq: u64,
+    #[serde(default)]
+    oid: u64,
+    prio: Option<u64>,
+    aggr: Option<&'a str>,
+}
+
+#[derive(Debug)]
+pub struct JournalReader<R, V: DecimalType> {
+    reader: R,
+    line: String,
+    /// Lines read since the start or the last seek, for error reporting
+    line_number: usize,
+    /// Event read past by a skip or seek that is yielded next
+    pending: Option<Event<V>>,
+}
+
+impl<R: BufRead, V: DecimalType + FromStr> JournalReader<R, V> {
+    #[inline]
+    #[must_use]
+    pub fn new(reader: R) -> Self {
+        Self { reader, line: String::new(), line_number: 0, pending: None }
+    }
+
+    /// Read the next line into the buffer, returning its length (0 at the end)
+    fn read_line(&mut self) -> Result<usize, FormatError> {
+        self.line.clear();
+        self.line_number += 1;
+        Ok(self.reader.read_line(&mut self.line)?)
+    }
+
+    fn record(&self) -> Result<Record<'_>, FormatError> {
+        serde_json::from_str(self.line.trim_end())
+            .map_err(|err| FormatError::Malformed { line: self.line_number, reason: err.to_string() })
+    }
+
+    fn event(&self, record: &Record<'_>) -> Result<Event<V>, FormatError> {
+        let malformed = |reason: &str| FormatError::Malformed { line: self.line_number, reason: reason.to_owned() };
+        let kind = record.kind.and_then(parse_kind).ok_or_else(|| malformed("missing or unknown kind"))?;
+        let side = record.side.and_then(parse_side).ok_or_else(|| malformed("missing or unknown side"))?;
+        let price = record.price.and_then(|p| V::from_str(p).ok()).ok_or_else(|| malformed("missing or invalid price"))?;
+        let size = record.size.and_then(|s| V::from_str(s).ok()).ok_or_else(|| malformed("missing or invalid size"))?;
+        let aggressor = match record.aggr {
+            Some(aggressor) => parse_side(aggressor).ok_or_else(|| malformed("unknown aggressor"))?.into(),
+            None => Aggressor::Unknown,
+        };
+        let event = Event::new(kind, side, price, size, Timestamp::from_nanos(record.ts))
+            .with_sequence_id(record.seq)
+            .with_order_id(record.oid)
+            .with_aggressor(aggressor);
+        Ok(match record.prio {
+            Some(priority) => event.with_priority(priority),
+            None => event,
+        })
+    }
+
+    /// Next event, skipping sync markers
+    fn read_event(&mut self) -> Result<Option<Event<V>>, FormatError> {
+        loop {
+            if self.read_line()? == 0 {
+                return Ok(None);
+            }
+            if self.line.trim_end().is_empty() {
+                continue;
+            }
+            let record = self.record()?;
+            if record.sync.is_none() {
+                return self.event(&record).map(Some);
+            }
+        }
+    }
+
+    /// Advance to the first event at or after `ts` by reading forward, for streams that cannot seek
+    pub fn skip_to(&mut self, ts: Timestamp) -> Result<(), FormatError> {
+        if self.pending.as_ref().is_some_and(|event| event.timestamp >= ts) {
+            return Ok(());
+        }
+        self.pending = None;
+        while let Some(event) = self.read_event()? {
+            if event.timestamp >= ts {
+                self.pending = Some(event);
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead + Seek, V: DecimalType + FromStr> JournalReader<R, V> {
+    /// First sync marker starting at or after byte `position`, as `(offset, ts)`
+    fn marker_after(&mut self, position: u64) -> Result<Option<(u64, Timestamp)>, FormatError> {
+        let mut offset = self.reader.seek(SeekFrom::Start(position))?;
+        if position > 0 {
+            // Discard the rest of the line the position falls into
+            offset += self.read_line()? as u64;
+        }
+        loop {
+            let len = self.read_line()? as u64;
+            if len == 0 {
+                return Ok(None);
+            }
+            if let Record { sync: Some(_), ts, .. } = self.record()? {
+                return Ok(Some((offset, Timestamp::from_nanos(ts))));
+            }
+            offset += len;
+        }
+    }
+
+    /// Position the reader on the first event at or after `ts`, binary searching the sync markers
+    pub fn seek(&mut self, ts: Timestamp) -> Result<(), FormatError> {
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        // Offset of the last known marker before `ts`, from which a linear scan finishes the seek
+        let (mut best, mut low, mut high) = (0, 0, end);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.marker_after(mid)? {
+                Some((offset, marker_ts)) if offset < high && marker_ts < ts => {
+                    best = offset;
+                    low = offset + 1;
+                }
+                _ => high = mid,
+            }
+        }
+        self.reader.seek(SeekFrom::Start(best))?;
+        self.line_number = 0;
+        self.pending = None;
+        self.skip_to(ts)
+    }
+}
+
+impl<R: BufRead, V: DecimalType + FromStr> Iterator for JournalReader<R, V> {
+    type Item = Result<Event<V>, FormatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(event) = self.pending.take() {
+            return Some(Ok(event));
+        }
+        self.read_event().transpose()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::journal::{EventJournal, JournalReader},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn journal(count: i64, sync_interval: u64) -> Vec<u8> {
+        let mut journal = EventJournal::new(Vec::new()).with_sync_interval(sync_interval);
+        for i in 0..count {
+            // Pairs of events share a timestamp
+            let event = Event::new(
+                EventKind::L2,
+                Side::Buy,
+                fixed!(100) + FixedDecimal::from_int(i),
+                fixed!(1.5),
+                Timestamp::from_nanos(i / 2 * 10),
+            );
+            journal.append(&event.with_sequence_id(i as u64 + 1)).unwrap();
+        }
+        journal.into_inner()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bytes = journal(3, 2);
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        assert_eq!(text.lines().next(), Some(r#"{"sync":0,"ts":0}"#));
+        assert_eq!(text.lines().nth(1), Some(r#"{"kind":"l2","side":"buy","price":"100","size":"1.5","ts":0,"seq":1}"#));
+        assert_eq!(text.lines().filter(|line| line.starts_with(r#"{"sync""#)).count(), 2);
+
+        let events = JournalReader::<_, FixedDecimal>::new(bytes.as_slice()).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!((events[2].price, events[2].timestamp.as_nanos(), events[2].sequence_id), (fixed!(102), 10, 3));
+    }
+
+    #[test]
+    fn test_order_events() {
+        let mut journal = EventJournal::new(Vec::new());
+        let add = Event::new(EventKind::Add, Side::Sell, fixed!(100.5), fixed!(2), Timestamp::from_nanos(1))
+            .with_order_id(42)
+            .with_priority(3);
+        let cancel =
+            Event::new(EventKind::Cancel, Side::Sell, fixed!(100.5), fixed!(2), Timestamp::from_nanos(2)).with_order_id(42);
+        let trade = Event::trade(Side::Buy, fixed!(100.5), fixed!(1), Timestamp::from_nanos(3));
+        journal.append(&add).unwrap();
+        journal.append(&cancel).unwrap();
+        journal.append(&trade).unwrap();
+        let bytes = journal.into_inner();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        assert!(text.lines().nth(1).unwrap().ends_with(r#""seq":0,"oid":42,"prio":3}"#));
+        assert!(text.lines().nth(3).unwrap().ends_with(r#""seq":0,"aggr":"buy"}"#));
+
+        let events = JournalReader::<_, FixedDecimal>::new(bytes.as_slice()).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(events, [add, cancel, trade]);
+    }
+
+    #[test]
+    fn test_seek_and_skip() {
+        let bytes = journal(1_000, 16);
+        for ts in [0, 10, 1_230, 4_990] {
+            let mut reader = JournalReader::<_, FixedDecimal>::new(Cursor::new(bytes.as_slice()));
+            reader.seek(Timestamp::from_nanos(ts)).unwrap();
+            let event = reader.next().unwrap().unwrap();
+            assert_eq!((event.timestamp.as_nanos(), event.sequence_id), (ts, ts as u64 / 5 + 1));
+        }
+
+        let mut reader = JournalReader::<_, FixedDecimal>::new(Cursor::new(bytes.as_slice()));
+        reader.seek(Timestamp::from_nanos(5_000)).unwrap();
+        assert!(reader.next().is_none());
+
+        let mut reader = JournalReader::<_, FixedDecimal>::new(bytes.as_slice());
+        reader.skip_to(Timestamp::from_nanos(1_235)).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap().timestamp.as_nanos(), 1_240);
+    }
+}
@@ -0,0 +1,94 @@
+//! Interchange formats for recording and replaying events.
+
+#[cfg(feature = "fixed_decimal")]
+pub mod binary;
+pub mod combinators;
+pub mod csv;
+#[cfg(feature = "journal")]
+pub mod journal;
+pub mod replay;
+#[cfg(feature = "fixed_decimal")]
+pub mod snapshots;
+pub mod source;
+#[cfg(feature = "zmq")]
+pub mod zmq;
+
+use std::fmt;
+
+use crate::{event_kind::EventKind, side::Side};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    /// The underlying reader or writer failed
+    Io(String),
+    /// A record is not valid for the format, `line` is 1-based where the format is line oriented
+    Malformed { line: usize, reason: String },
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(reason) => write!(f, "io error: {reason}"),
+            Self::Malformed { line, reason } => write!(f, "malformed record on line {line}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<std::io::Error> for FormatError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err.to_string())
+    }
+}
+
+#[inline]
+/// Lowercase name of an event kind as written by the text formats
+pub(crate) const fn kind_name(kind: EventKind) -> &'static str {
+    match kind {
+        EventKind::Trade => "trade",
+        EventKind::BBO => "bbo",
+        EventKind::L2 => "l2",
+        EventKind::Snapshot => "snapshot",
+        EventKind::Add => "add",
+        EventKind::Cancel => "cancel",
+        EventKind::Modify => "modify",
+        EventKind::Execute => "execute",
+        EventKind::Clear => "clear",
+        EventKind::ClearSide => "clear_side",
+    }
+}
+
+#[inline]
+pub(crate) fn parse_kind(name: &str) -> Option<EventKind> {
+    match name {
+        "trade" => Some(EventKind::Trade),
+        "bbo" => Some(EventKind::BBO),
+        "l2" => Some(EventKind::L2),
+        "snapshot" => Some(EventKind::Snapshot),
+        "add" => Some(EventKind::Add),
+        "cancel" => Some(EventKind::Cancel),
+        "modify" => Some(EventKind::Modify),
+        "execute" => Some(EventKind::Execute),
+        "clear" => Some(EventKind::Clear),
+        "clear_side" => Some(EventKind::ClearSide),
+        _ => None,
+    }
+}
+
+#[inline]
+pub(crate) const fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "buy",
+        Side::Sell => "sell",
+    }
+}
+
+#[inline]
+pub(crate) fn parse_side(name: &str) -> Option<Side> {
+    match name {
+        "buy" => Some(Side::Buy),
+        "sell" => Some(Side::Sell),
+        _ => None,
+    }
+}
@@ -0,0 +1,248 @@
+//! Replay of an [`EventSource`] in time with the original recording.
+//!
+//! [`PacedReplayer`] holds each event back until as long has passed since the first one as passed in
+//! the recording, optionally scaled by a speed multiplier. Time comes from a [`Clock`]:
+//! [`SystemClock`] sleeps for demos and live-like testing, while [`SimulatedClock`] jumps straight to
+//! each deadline so backtests see the same timeline without waiting for it.
+//!
+//! [`EventSource`]: crate::formats::source::EventSource
+
+use std::time::Duration;
+
+use crate::{event::Event, formats::source::SourceError, timestamp::Timestamp};
+
+pub trait Clock {
+    /// The current time
+    fn now(&self) -> Timestamp;
+    /// Block until `deadline`, returning straight away if it has passed
+    fn sleep_until(&mut self, deadline: Timestamp);
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Wall clock time, sleeping the thread to wait
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+
+    #[inline]
+    fn sleep_until(&mut self, deadline: Timestamp) {
+        if let Some(wait) = deadline.duration_since(self.now()) {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// A clock that only moves when told to, or when a wait jumps it forward to its deadline
+pub struct SimulatedClock {
+    now: Timestamp,
+}
+
+impl SimulatedClock {
+    #[inline]
+    #[must_use]
+    pub const fn new(now: Timestamp) -> Self {
+        Self { now }
+    }
+
+    #[inline]
+    pub fn advance(&mut self, duration: Duration) {
+        self.now += duration;
+    }
+}
+
+impl Clock for SimulatedClock {
+    #[inline]
+    fn now(&self) -> Timestamp {
+        self.now
+    }
+
+    #[inline]
+    fn sleep_until(&mut self, deadline: Timestamp) {
+        self.now = self.now.max(deadline);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Pace {
+    /// Yield events as soon as they are read
+    #[default]
+    AsFastAsPossible,
+    /// Keep the spacing of the recording
+    Original,
+    /// Keep the spacing scaled down by the multiplier, `2.0` replays twice as fast. Multipliers that
+    /// are not positive replay as fast as possible.
+    Speed(f64),
+}
+
+impl Pace {
+    #[inline]
+    /// The multiplier applied to the recorded spacing, `None` when events are not held back
+    fn speed(self) -> Option<f64> {
+        match self {
+            Self::AsFastAsPossible => None,
+            Self::Original => Some(1.0),
+            Self::Speed(speed) => (speed > 0.0).then_some(speed),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Yields the events of a source no sooner than their place in the recording allows, as measured by
+/// a [`Clock`] from the moment the first event is read.
+///
+/// Events whose timestamp goes backwards, and events the consumer has fallen behind on, are yielded
+/// straight away rather than pushing the schedule back.
+pub struct PacedReplayer<S, C> {
+    source: S,
+    clock: C,
+    pace: Pace,
+    /// Timestamp of the first event and the clock time it was yielded at
+    anchor: Option<(Timestamp, Timestamp)>,
+}
+
+impl<S, C: Clock> PacedReplayer<S, C> {
+    #[inline]
+    #[must_use]
+    pub const fn new(source: S, clock: C, pace: Pace) -> Self {
+        Self { source, clock, pace, anchor: None }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    #[inline]
+    /// The clock, for a caller that also keeps time with it
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn pace(&self) -> Pace {
+        self.pace
+    }
+
+    #[inline]
+    /// Change the pace from the next event on, keeping the schedule continuous
+    pub fn set_pace(&mut self, pace: Pace) {
+        self.pace = pace;
+        self.anchor = None;
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    fn wait_for(&mut self, timestamp: Timestamp) {
+        let Some(speed) = self.pace.speed() else {
+            return;
+        };
+        let Some((first, started)) = self.anchor else {
+            self.anchor = Some((timestamp, self.clock.now()));
+            return;
+        };
+        let Some(elapsed) = timestamp.duration_since(first) else {
+            return;
+        };
+        self.clock.sleep_until(started + elapsed.div_f64(speed));
+    }
+}
+
+impl<V, S, C> Iterator for PacedReplayer<S, C>
+where
+    V: crate::decimals::decimal_type::DecimalType,
+    S: Iterator<Item = Result<Event<V>, SourceError>>,
+    C: Clock,
+{
+    type Item = Result<Event<V>, SourceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.source.next()?;
+        if let Ok(event) = &event {
+            self.wait_for(event.timestamp);
+        }
+        Some(event)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.source.size_hint()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::{
+            replay::{Clock, Pace, PacedReplayer, SimulatedClock, SystemClock},
+            source::MemorySource,
+        },
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn source(millis: &[i64]) -> MemorySource<FixedDecimal> {
+        let event = |ms| Event::new(EventKind::L2, Side::Buy, fixed!(100), fixed!(1), Timestamp::from_millis(ms));
+        MemorySource::new(millis.iter().map(|&ms| event(ms)).collect())
+    }
+
+    /// Clock time at which each event was yielded, relative to the start
+    fn schedule(pace: Pace, millis: &[i64]) -> Vec<Duration> {
+        let start = Timestamp::from_secs(1_000);
+        let mut replayer = PacedReplayer::new(source(millis), SimulatedClock::new(start), pace);
+        let mut times = Vec::new();
+        while let Some(event) = replayer.next() {
+            event.unwrap();
+            times.push(replayer.clock().now().saturating_duration_since(start));
+        }
+        times
+    }
+
+    #[test]
+    fn test_paces() {
+        let ms = Duration::from_millis;
+        let millis = [5_000, 5_010, 5_030, 5_020, 5_040];
+        assert_eq!(schedule(Pace::Original, &millis), [ms(0), ms(10), ms(30), ms(30), ms(40)]);
+        assert_eq!(schedule(Pace::Speed(2.0), &millis), [ms(0), ms(5), ms(15), ms(15), ms(20)]);
+        assert_eq!(schedule(Pace::Speed(0.5), &millis[..2]), [ms(0), ms(20)]);
+        assert_eq!(schedule(Pace::AsFastAsPossible, &millis), [ms(0); 5]);
+        assert_eq!(schedule(Pace::Speed(0.0), &millis), [ms(0); 5]);
+    }
+
+    #[test]
+    fn test_consumer_falling_behind() {
+        let mut replayer = PacedReplayer::new(source(&[0, 10, 20]), SimulatedClock::default(), Pace::Original);
+        replayer.next();
+        // The consumer takes 15ms over the first event, so the second is already due
+        replayer.clock_mut().advance(Duration::from_millis(15));
+        replayer.next();
+        assert_eq!(replayer.clock().now(), Timestamp::from_millis(15));
+        replayer.next();
+        assert_eq!(replayer.clock().now(), Timestamp::from_millis(20));
+    }
+
+    #[test]
+    fn test_system_clock_sleeps() {
+        let started = Instant::now();
+        let events = PacedReplayer::new(source(&[0, 2, 4]), SystemClock, Pace::Original).count();
+        assert_eq!(events, 3);
+        assert!(started.elapsed() >= Duration::from_millis(4));
+    }
+}
@@ -0,0 +1,376 @@
+//! Delta-encoded recording of [`BookSnapshot`]s.
+//!
+//! A recording is a sequence of frames, each a tag byte, the varint length of its body and the body.
+//! A keyframe (tag `0`) holds a whole snapshot and a delta frame (tag `1`) only the levels that changed
+//! since the snapshot before it, so a book that barely moves costs a few bytes per snapshot rather than
+//! its full depth. Every integer in a body is a zigzag varint:
+//!
+//! | field            | keyframe                              | delta frame                                  |
+//! |------------------|---------------------------------------|----------------------------------------------|
+//! | timestamp        | nanoseconds                           | change since the last frame                  |
+//! | sequence ID      | as recorded                           | change since the last frame                  |
+//! | bids, then asks  | level count, then each level best first | changed level count, then each change in price order |
+//! | level price      | raw change from the level before, the first from zero | raw change from the change before, the first from the side's best price |
+//! | level size       | raw size                              | raw change in size, a level left at zero is removed |
+//!
+//! Prices and sizes are the raw [`FixedDecimal`] integers. [`SnapshotWriter`] starts a keyframe every
+//! [`keyframe interval`](SnapshotWriter::with_keyframe_interval) snapshots, and [`SnapshotReplayer`]
+//! seeks by hopping frame lengths to the last keyframe before the target and decoding forward from it.
+
+use std::{collections::BTreeMap, io::Write};
+
+use crate::{
+    decimals::fixed_decimal::FixedDecimal, formats::FormatError, level::Level, sync::BookSnapshot, timestamp::Timestamp,
+};
+
+const KEYFRAME: u8 = 0;
+const DELTA: u8 = 1;
+
+#[inline]
+fn put(buf: &mut Vec<u8>, value: i64) {
+    // Zigzag keeps small negative numbers short
+    let mut value = ((value << 1) ^ (value >> 63)) as u64;
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+#[inline]
+fn take(buf: &[u8], position: &mut usize) -> Option<i64> {
+    let mut value = 0_u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *buf.get(*position)?;
+        *position += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some((value >> 1) as i64 ^ -((value & 1) as i64));
+        }
+    }
+    None
+}
+
+/// Raw size by raw price
+type Levels = BTreeMap<i64, i64>;
+
+fn side_of(levels: &[Level<FixedDecimal>]) -> Levels {
+    levels.iter().map(|level| (level.price.raw_value(), level.size.raw_value())).collect()
+}
+
+#[derive(Debug)]
+/// Appends snapshots as keyframes and delta frames, see the [module documentation](self)
+pub struct SnapshotWriter<W: Write> {
+    writer: W,
+    keyframe_interval: u64,
+    frames: u64,
+    /// The last snapshot written as `(timestamp, sequence ID, bids, asks)`
+    previous: Option<(i64, u64, Levels, Levels)>,
+    body: Vec<u8>,
+    frame: Vec<u8>,
+}
+
+impl<W: Write> SnapshotWriter<W> {
+    pub const DEFAULT_KEYFRAME_INTERVAL: u64 = 100;
+
+    #[inline]
+    #[must_use]
+    pub const fn new(writer: W) -> Self {
+        Self {
+            writer,
+            keyframe_interval: Self::DEFAULT_KEYFRAME_INTERVAL,
+            frames: 0,
+            previous: None,
+            body: Vec::new(),
+            frame: Vec::new(),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Write a keyframe every `snapshots` snapshots, at least one, trading size for shorter seeks
+    pub fn with_keyframe_interval(mut self, snapshots: u64) -> Self {
+        self.keyframe_interval = snapshots.max(1);
+        self
+    }
+
+    /// Append `snapshot`, as a keyframe or as its changes from the snapshot written before
+    pub fn write(&mut self, snapshot: &BookSnapshot<FixedDecimal>) -> Result<(), FormatError> {
+        let (timestamp, sequence_id) = (snapshot.timestamp.as_nanos(), snapshot.sequence_id);
+        let (bids, asks) = (side_of(&snapshot.bids), side_of(&snapshot.asks));
+        self.body.clear();
+        let tag = match self.previous.take().filter(|_| !self.frames.is_multiple_of(self.keyframe_interval)) {
+            None => {
+                put(&mut self.body, timestamp);
+                put(&mut self.body, sequence_id as i64);
+                for (side, best_first) in [(&bids, true), (&asks, false)] {
+                    put(&mut self.body, side.len() as i64);
+                    let mut last = 0;
+                    let mut level = |(&price, &size): (&i64, &i64)| {
+                        put(&mut self.body, price.wrapping_sub(last));
+                        put(&mut self.body, size);
+                        last = price;
+                    };
+                    if best_first {
+                        side.iter().rev().for_each(&mut level);
+                    } else {
+                        side.iter().for_each(&mut level);
+                    }
+                }
+                KEYFRAME
+            }
+            Some((last_timestamp, last_sequence_id, last_bids, last_asks)) => {
+                put(&mut self.body, timestamp.wrapping_sub(last_timestamp));
+                put(&mut self.body, sequence_id.wrapping_sub(last_sequence_id) as i64);
+                for (now, before, best) in
+                    [(&bids, &last_bids, last_bids.last_key_value()), (&asks, &last_asks, last_asks.first_key_value())]
+                {
+                    let mut changes = before
+                        .iter()
+                        .filter(|&(price, _)| !now.contains_key(price))
+                        .map(|(&price, &size)| (price, size.wrapping_neg()))
+                        .chain(now.iter().filter_map(|(&price, &size)| {
+                            let change = size.wrapping_sub(before.get(&price).copied().unwrap_or(0));
+                            (change != 0).then_some((price, change))
+                        }))
+                        .collect::<Vec<_>>();
+                    changes.sort_unstable_by_key(|&(price, _)| price);
+                    put(&mut self.body, changes.len() as i64);
+                    let mut last = best.map_or(0, |(&price, _)| price);
+                    for (price, change) in changes {
+                        put(&mut self.body, price.wrapping_sub(last));
+                        put(&mut self.body, change);
+                        last = price;
+                    }
+                }
+                DELTA
+            }
+        };
+        self.frame.clear();
+        self.frame.push(tag);
+        put(&mut self.frame, self.body.len() as i64);
+        self.frame.extend_from_slice(&self.body);
+        self.writer.write_all(&self.frame)?;
+        self.previous = Some((timestamp, sequence_id, bids, asks));
+        self.frames += 1;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn flush(&mut self) -> Result<(), FormatError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Decodes the snapshots of an in-memory recording, errors report the frame index as the line.
+pub struct SnapshotReplayer<'a> {
+    frames: &'a [u8],
+    position: usize,
+    /// Index of the next frame, reported as the snapshot's version
+    index: u64,
+    timestamp: i64,
+    sequence_id: u64,
+    bids: Levels,
+    asks: Levels,
+    /// Snapshot decoded past by a seek that is yielded next
+    pending: Option<BookSnapshot<FixedDecimal>>,
+}
+
+impl<'a> SnapshotReplayer<'a> {
+    #[inline]
+    #[must_use]
+    pub const fn new(frames: &'a [u8]) -> Self {
+        Self {
+            frames,
+            position: 0,
+            index: 0,
+            timestamp: 0,
+            sequence_id: 0,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            pending: None,
+        }
+    }
+
+    fn malformed(&self, reason: &str) -> FormatError {
+        FormatError::Malformed { line: self.index as usize, reason: reason.to_owned() }
+    }
+
+    /// Tag, body start and body end of the frame at `position`
+    fn frame_at(&self, position: usize) -> Result<(u8, usize, usize), FormatError> {
+        let tag = self.frames[position];
+        let mut start = position + 1;
+        let len = take(self.frames, &mut start).and_then(|len| usize::try_from(len).ok());
+        match len.map(|len| start + len) {
+            Some(end) if end <= self.frames.len() && tag <= DELTA => Ok((tag, start, end)),
+            _ => Err(self.malformed("truncated or unknown frame")),
+        }
+    }
+
+    fn decode(&mut self) -> Result<BookSnapshot<FixedDecimal>, FormatError> {
+        let (tag, mut position, end) = self.frame_at(self.position)?;
+        let body = &self.frames[..end];
+        let mut next = || take(body, &mut position);
+        let (timestamp, sequence_id) = next().zip(next()).ok_or_else(|| self.malformed("truncated frame"))?;
+        let mut sides = [std::mem::take(&mut self.bids), std::mem::take(&mut self.asks)];
+        if tag == KEYFRAME {
+            (self.timestamp, self.sequence_id) = (timestamp, sequence_id as u64);
+            for side in &mut sides {
+                side.clear();
+                let (mut count, mut price) = (next().ok_or_else(|| self.malformed("truncated frame"))?, 0_i64);
+                while count > 0 {
+                    let (change, size) = next().zip(next()).ok_or_else(|| self.malformed("truncated level"))?;
+                    price = price.wrapping_add(change);
+                    side.insert(price, size);
+                    count -= 1;
+                }
+            }
+        } else {
+            self.timestamp = self.timestamp.wrapping_add(timestamp);
+            self.sequence_id = self.sequence_id.wrapping_add(sequence_id as u64);
+            for (index, side) in sides.iter_mut().enumerate() {
+                let best = if index == 0 { side.last_key_value() } else { side.first_key_value() };
+                let count = next().ok_or_else(|| self.malformed("truncated frame"))?;
+                let (mut count, mut price) = (count, best.map_or(0, |(&price, _)| price));
+                while count > 0 {
+                    let (change, size) = next().zip(next()).ok_or_else(|| self.malformed("truncated change"))?;
+                    price = price.wrapping_add(change);
+                    let size = side.get(&price).copied().unwrap_or(0).wrapping_add(size);
+                    if size == 0 {
+                        side.remove(&price);
+                    } else {
+                        side.insert(price, size);
+                    }
+                    count -= 1;
+                }
+            }
+        }
+        [self.bids, self.asks] = sides;
+        let level = |(&price, &size): (&i64, &i64)| Level::new(FixedDecimal::new(price), FixedDecimal::new(size));
+        let snapshot = BookSnapshot {
+            bids: self.bids.iter().rev().map(level).collect(),
+            asks: self.asks.iter().map(level).collect(),
+            timestamp: Timestamp::from_nanos(self.timestamp),
+            sequence_id: self.sequence_id,
+            version: self.index,
+        };
+        self.position = end;
+        self.index += 1;
+        Ok(snapshot)
+    }
+
+    /// Position the replayer on the first snapshot at or after `ts`, assuming timestamps never decrease
+    pub fn seek(&mut self, ts: Timestamp) -> Result<(), FormatError> {
+        // The last keyframe before `ts`, found from the frame lengths without decoding the deltas
+        let (mut keyframe, mut position, mut index) = (None, 0, 0);
+        while position < self.frames.len() {
+            let (tag, mut start, end) = self.frame_at(position)?;
+            if tag == KEYFRAME {
+                match take(self.frames, &mut start) {
+                    Some(timestamp) if timestamp < ts.as_nanos() => keyframe = Some((position, index)),
+                    Some(_) => break,
+                    None => return Err(self.malformed("truncated frame")),
+                }
+            }
+            (position, index) = (end, index + 1);
+        }
+        (self.position, self.index) = keyframe.unwrap_or((0, 0));
+        self.pending = None;
+        while let Some(snapshot) = self.next() {
+            let snapshot = snapshot?;
+            if snapshot.timestamp >= ts {
+                self.pending = Some(snapshot);
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for SnapshotReplayer<'_> {
+    type Item = Result<BookSnapshot<FixedDecimal>, FormatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(snapshot) = self.pending.take() {
+            return Some(Ok(snapshot));
+        }
+        if self.position >= self.frames.len() {
+            return None;
+        }
+        let snapshot = self.decode();
+        if snapshot.is_err() {
+            // A corrupt frame ends the recording
+            self.position = self.frames.len();
+        }
+        Some(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        decimals::fixed_decimal::FixedDecimal,
+        fixed,
+        formats::snapshots::{SnapshotReplayer, SnapshotWriter},
+        level::Level,
+        sync::BookSnapshot,
+        timestamp::Timestamp,
+    };
+
+    fn snapshot(step: i64) -> BookSnapshot<FixedDecimal> {
+        // Twenty levels a side of which only the touch sizes change, and the asks shift every tenth step
+        let shift = FixedDecimal::from_int(step / 10);
+        let level = |price: FixedDecimal, size| Level::new(price, FixedDecimal::from_int(size));
+        BookSnapshot {
+            bids: (0..20)
+                .map(|i| level(fixed!(100) - FixedDecimal::from_int(i), if i == 0 { 1 + step % 3 } else { 5 }))
+                .collect(),
+            asks: (0..20)
+                .map(|i| level(fixed!(101) + shift + FixedDecimal::from_int(i), if i == 0 { 2 + step % 4 } else { 7 }))
+                .collect(),
+            timestamp: Timestamp::from_nanos(1_000 + step * 250),
+            sequence_id: 10 + step as u64 * 3,
+            version: step as u64,
+        }
+    }
+
+    fn same(a: &BookSnapshot<FixedDecimal>, b: &BookSnapshot<FixedDecimal>) -> bool {
+        let levels = |levels: &[Level<FixedDecimal>]| levels.iter().map(|level| (level.price, level.size)).collect::<Vec<_>>();
+        (a.timestamp, a.sequence_id, a.version, levels(&a.bids), levels(&a.asks))
+            == (b.timestamp, b.sequence_id, b.version, levels(&b.bids), levels(&b.asks))
+    }
+
+    #[test]
+    fn test_delta_round_trip() {
+        let mut writer = SnapshotWriter::new(Vec::new()).with_keyframe_interval(25);
+        let mut keyframes = SnapshotWriter::new(Vec::new()).with_keyframe_interval(1);
+        for step in 0..100 {
+            writer.write(&snapshot(step)).unwrap();
+            keyframes.write(&snapshot(step)).unwrap();
+        }
+        let (frames, full) = (writer.into_inner(), keyframes.into_inner());
+        assert!(frames.len() * 10 < full.len(), "{} bytes against {} for keyframes alone", frames.len(), full.len());
+
+        let decoded = SnapshotReplayer::new(&frames).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(decoded.len(), 100);
+        assert!(decoded.iter().zip(0..).all(|(decoded, step)| same(decoded, &snapshot(step))));
+
+        // Seeking lands between keyframes and decodes on from the one before
+        let mut replayer = SnapshotReplayer::new(&frames);
+        replayer.seek(Timestamp::from_nanos(1_000 + 60 * 250 - 1)).unwrap();
+        assert!(same(&replayer.next().unwrap().unwrap(), &snapshot(60)));
+        assert!(same(&replayer.next().unwrap().unwrap(), &snapshot(61)));
+        replayer.seek(Timestamp::from_nanos(i64::MAX)).unwrap();
+        assert!(replayer.next().is_none());
+
+        assert!(SnapshotReplayer::new(&frames[..frames.len() - 1]).last().unwrap().is_err());
+    }
+}
@@ -0,0 +1,203 @@
+//! A common interface over everything that yields recorded events.
+//!
+//! [`EventSource`] is any iterator of `Result<Event<V>, SourceError>`, so the readers of every format
+//! already are one and replay tools, benches and [`BookManager::replay`] take them interchangeably.
+//! The `open_*` functions read the formats from files, [`MemorySource`] replays events held in
+//! memory, and [`replay_until`] rebuilds a book as it stood at a point in a recording.
+//!
+//! [`BookManager::replay`]: crate::books::manager::BookManager::replay
+
+use std::{fs::File, io::BufReader, path::Path, str::FromStr, vec};
+
+use crate::{
+    books::interface::OrderBook,
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    formats::{csv::CsvReader, FormatError},
+    timestamp::Timestamp,
+};
+#[cfg(feature = "fixed_decimal")]
+use crate::{decimals::fixed_decimal::FixedDecimal, formats::binary};
+
+/// Sources fail with the errors of the format they read
+pub type SourceError = FormatError;
+
+/// A stream of events, implemented by every iterator of `Result<Event<V>, SourceError>`
+pub trait EventSource<V: DecimalType>: Iterator<Item = Result<Event<V>, SourceError>> {}
+
+impl<V: DecimalType, I: Iterator<Item = Result<Event<V>, SourceError>>> EventSource<V> for I {}
+
+#[derive(Debug, Clone)]
+/// Replays events held in memory, never failing
+pub struct MemorySource<V: DecimalType> {
+    events: vec::IntoIter<Event<V>>,
+}
+
+impl<V: DecimalType> MemorySource<V> {
+    #[inline]
+    #[must_use]
+    pub fn new(events: Vec<Event<V>>) -> Self {
+        Self { events: events.into_iter() }
+    }
+}
+
+impl<V: DecimalType> From<Vec<Event<V>>> for MemorySource<V> {
+    #[inline]
+    fn from(events: Vec<Event<V>>) -> Self {
+        Self::new(events)
+    }
+}
+
+impl<V: DecimalType> Iterator for MemorySource<V> {
+    type Item = Result<Event<V>, SourceError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.next().map(Ok)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.events.size_hint()
+    }
+}
+
+impl<V: DecimalType> ExactSizeIterator for MemorySource<V> {}
+
+/// Apply the events of `source` stamped at or before `ts` to `book`, stopping at the first later one,
+/// and return how many were applied. With a journal or a recording this rebuilds the book as it stood
+/// at `ts`.
+pub fn replay_until<V: DecimalType, B: OrderBook<V>>(
+    book: &mut B,
+    source: impl EventSource<V>,
+    ts: Timestamp,
+) -> Result<usize, SourceError> {
+    let mut applied = 0;
+    for event in source {
+        let event = event?;
+        if event.timestamp > ts {
+            break;
+        }
+        book.process(event);
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+/// Events of a CSV file
+pub fn open_csv<V: DecimalType + FromStr>(path: impl AsRef<Path>) -> Result<CsvReader<BufReader<File>, V>, SourceError> {
+    Ok(CsvReader::new(BufReader::new(File::open(path)?)))
+}
+
+#[cfg(feature = "journal")]
+/// Events of a JSONL journal file
+pub fn open_journal<V: DecimalType + FromStr>(
+    path: impl AsRef<Path>,
+) -> Result<crate::formats::journal::JournalReader<BufReader<File>, V>, SourceError> {
+    Ok(crate::formats::journal::JournalReader::new(BufReader::new(File::open(path)?)))
+}
+
+#[cfg(feature = "fixed_decimal")]
+#[derive(Debug, Clone)]
+/// Events of a binary recording read into memory, errors report the record index as the line. For a
+/// recording too large to read, map it with `MmapFile` and replay it with a `BinaryReplayer`.
+pub struct BinaryFileSource {
+    records: Vec<u8>,
+    position: usize,
+}
+
+#[cfg(feature = "fixed_decimal")]
+/// Events of a binary recording, failing if the file does not hold a whole number of records
+pub fn open_binary(path: impl AsRef<Path>) -> Result<BinaryFileSource, SourceError> {
+    let records = std::fs::read(path)?;
+    binary::BinaryReplayer::new(&records)?;
+    Ok(BinaryFileSource { records, position: 0 })
+}
+
+#[cfg(feature = "fixed_decimal")]
+impl Iterator for BinaryFileSource {
+    type Item = Result<Event<FixedDecimal>, SourceError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = binary::BinaryReplayer::new(&self.records).ok()?.get(self.position)?;
+        self.position += 1;
+        Some(event)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.records.len() / binary::RECORD_LEN - self.position;
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(feature = "fixed_decimal")]
+impl ExactSizeIterator for BinaryFileSource {}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::fs::File;
+
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, interface::OrderBook, manager::BookManager},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::{
+            binary::BinaryWriter,
+            csv::CsvWriter,
+            source::{open_binary, open_csv, EventSource, MemorySource, SourceError},
+        },
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn events() -> Vec<Event<FixedDecimal>> {
+        (0..6)
+            .map(|i| {
+                let side = if i % 2 == 0 { Side::Buy } else { Side::Sell };
+                let price = if side.is_buy() { fixed!(100) } else { fixed!(101) };
+                Event::new(EventKind::L2, side, price, FixedDecimal::from_int(i + 1), Timestamp::from_nanos(i * 10))
+                    .with_sequence_id(i as u64 + 1)
+            })
+            .collect()
+    }
+
+    fn drain(source: impl EventSource<FixedDecimal>) -> Result<Vec<Event<FixedDecimal>>, SourceError> {
+        source.collect()
+    }
+
+    #[test]
+    fn test_sources_agree() {
+        let dir = std::env::temp_dir().join(format!("freya_ob_source_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (csv_path, binary_path) = (dir.join("events.csv"), dir.join("events.bin"));
+        let mut csv = CsvWriter::new(File::create(&csv_path).unwrap()).unwrap();
+        let mut binary = BinaryWriter::new(File::create(&binary_path).unwrap());
+        for event in events() {
+            csv.write(&event).unwrap();
+            binary.write(&event).unwrap();
+        }
+        csv.flush().unwrap();
+        binary.flush().unwrap();
+
+        let sources: Vec<Box<dyn EventSource<FixedDecimal>>> = vec![
+            Box::new(MemorySource::new(events())),
+            Box::new(open_csv(&csv_path).unwrap()),
+            Box::new(open_binary(&binary_path).unwrap()),
+        ];
+        for source in sources {
+            assert_eq!(drain(source), Ok(events()));
+        }
+        assert!(open_csv::<FixedDecimal>(dir.join("missing.csv")).is_err());
+
+        let mut manager = BookManager::<FixedDecimal, ArrayOrderbook<8, FixedDecimal>>::new();
+        manager.intern("BTC-USD");
+        assert_eq!(manager.replay(open_binary(&binary_path).unwrap()), Ok(6));
+        assert_eq!(manager.book_mut("BTC-USD").and_then(|book| book.best_ask()).map(|level| level.size), Some(fixed!(6)));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
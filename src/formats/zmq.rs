@@ -0,0 +1,151 @@
+//! ZeroMQ PUB/SUB message layout for events and snapshots.
+//!
+//! Every message has two frames: a topic and a payload. Topics start with the symbol so SUB sockets
+//! can filter by prefix (`zmq_setsockopt(ZMQ_SUBSCRIBE, "BTC-USD.")`):
+//!
+//! | topic                | payload                                                          |
+//! |----------------------|------------------------------------------------------------------|
+//! | `{symbol}.events`    | concatenated [`binary`](crate::formats::binary) event records    |
+//! | `{symbol}.snapshot`  | ts (`i64`), sequence ID (`u64`), bid and ask counts (`u32` each), then raw price and size (`i64` each) per level, bids first, best first |
+//!
+//! All integers are little-endian. The frames are plain byte vectors so they can be handed to any
+//! ZeroMQ binding's multipart send, and received frames decoded with [`decode`].
+
+use crate::{
+    decimals::fixed_decimal::FixedDecimal,
+    event::Event,
+    formats::{
+        binary::{encode_into, BinaryReplayer, RECORD_LEN},
+        FormatError,
+    },
+    level::Level,
+    timestamp::Timestamp,
+};
+
+const EVENTS_SUFFIX: &str = ".events";
+const SNAPSHOT_SUFFIX: &str = ".snapshot";
+const SNAPSHOT_HEADER_LEN: usize = 24;
+
+#[derive(Debug)]
+pub enum Message<'a> {
+    Events {
+        symbol: &'a str,
+        events: BinaryReplayer<'a>,
+    },
+    Snapshot {
+        symbol: &'a str,
+        timestamp: Timestamp,
+        sequence_id: u64,
+        bids: Vec<Level<FixedDecimal>>,
+        asks: Vec<Level<FixedDecimal>>,
+    },
+}
+
+#[inline]
+#[must_use]
+/// Prefix that subscribes to every message for `symbol`, and no other symbol's
+pub fn subscription(symbol: &str) -> String {
+    format!("{symbol}.")
+}
+
+/// Topic and payload frames carrying `events`
+pub fn encode_events(symbol: &str, events: &[Event<FixedDecimal>]) -> Result<[Vec<u8>; 2], FormatError> {
+    let mut payload = vec![0; events.len() * RECORD_LEN];
+    for (event, record) in events.iter().zip(payload.chunks_exact_mut(RECORD_LEN)) {
+        encode_into(event, record)?;
+    }
+    Ok([format!("{symbol}{EVENTS_SUFFIX}").into_bytes(), payload])
+}
+
+#[must_use]
+/// Topic and payload frames carrying a snapshot of the given levels, best first
+pub fn encode_snapshot(
+    symbol: &str,
+    timestamp: Timestamp,
+    sequence_id: u64,
+    bids: &[Level<FixedDecimal>],
+    asks: &[Level<FixedDecimal>],
+) -> [Vec<u8>; 2] {
+    let mut payload = Vec::with_capacity(SNAPSHOT_HEADER_LEN + (bids.len() + asks.len()) * 16);
+    payload.extend_from_slice(&timestamp.as_nanos().to_le_bytes());
+    payload.extend_from_slice(&sequence_id.to_le_bytes());
+    payload.extend_from_slice(&(bids.len() as u32).to_le_bytes());
+    payload.extend_from_slice(&(asks.len() as u32).to_le_bytes());
+    for level in bids.iter().chain(asks) {
+        payload.extend_from_slice(&level.price.raw_value().to_le_bytes());
+        payload.extend_from_slice(&level.size.raw_value().to_le_bytes());
+    }
+    [format!("{symbol}{SNAPSHOT_SUFFIX}").into_bytes(), payload]
+}
+
+/// Decode a received topic and payload
+pub fn decode<'a>(topic: &'a [u8], payload: &'a [u8]) -> Result<Message<'a>, FormatError> {
+    let malformed = |reason: String| FormatError::Malformed { line: 0, reason };
+    let topic = std::str::from_utf8(topic).map_err(|_| malformed("topic is not UTF-8".to_owned()))?;
+    if let Some(symbol) = topic.strip_suffix(EVENTS_SUFFIX) {
+        return Ok(Message::Events { symbol, events: BinaryReplayer::new(payload)? });
+    }
+    let Some(symbol) = topic.strip_suffix(SNAPSHOT_SUFFIX) else {
+        return Err(malformed(format!("unknown topic {topic}")));
+    };
+    let header = payload.get(..SNAPSHOT_HEADER_LEN).ok_or_else(|| malformed("snapshot header is truncated".to_owned()))?;
+    let u64_at = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().expect("slice of eight bytes"));
+    let u32_at = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().expect("slice of four bytes")) as usize;
+    let (bid_count, ask_count) = (u32_at(16), u32_at(20));
+    let body = &payload[SNAPSHOT_HEADER_LEN..];
+    if body.len() != (bid_count + ask_count) * 16 {
+        return Err(malformed(format!("expected {} levels, found {} bytes", bid_count + ask_count, body.len())));
+    }
+    let mut levels = body.chunks_exact(16).map(|level| {
+        let raw = |at: usize| i64::from_le_bytes(level[at..at + 8].try_into().expect("slice of eight bytes"));
+        Level::new(FixedDecimal::new(raw(0)), FixedDecimal::new(raw(8)))
+    });
+    let bids = levels.by_ref().take(bid_count).collect();
+    let asks = levels.collect();
+    Ok(Message::Snapshot { symbol, timestamp: Timestamp::from_nanos(u64_at(0) as i64), sequence_id: u64_at(8), bids, asks })
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::zmq::{decode, encode_events, encode_snapshot, subscription, Message},
+        level::Level,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_events_round_trip() {
+        let events = [Event::new(EventKind::L2, Side::Buy, fixed!(100), fixed!(2), Timestamp::from_nanos(5)).with_sequence_id(9)];
+        let [topic, payload] = encode_events("BTC-USD", &events).unwrap();
+        assert!(topic.starts_with(subscription("BTC-USD").as_bytes()));
+        assert!(!topic.starts_with(subscription("BTC").as_bytes()));
+        let Message::Events { symbol, mut events } = decode(&topic, &payload).unwrap() else { panic!("expected events") };
+        assert_eq!(symbol, "BTC-USD");
+        let event = events.next().unwrap().unwrap();
+        assert_eq!((event.price, event.sequence_id), (fixed!(100), 9));
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let bids = [Level::new(fixed!(99), fixed!(1)), Level::new(fixed!(98), fixed!(3))];
+        let asks = [Level::new(fixed!(101), fixed!(0.5))];
+        let [topic, payload] = encode_snapshot("ETH", Timestamp::from_nanos(7), 3, &bids, &asks);
+        assert_eq!(topic, b"ETH.snapshot");
+        let Message::Snapshot { symbol, timestamp, sequence_id, bids: read_bids, asks: read_asks } =
+            decode(&topic, &payload).unwrap()
+        else {
+            panic!("expected a snapshot")
+        };
+        assert_eq!((symbol, timestamp, sequence_id), ("ETH", Timestamp::from_nanos(7), 3));
+        assert_eq!(read_bids.iter().map(|level| level.size).collect::<Vec<_>>(), [fixed!(1), fixed!(3)]);
+        assert_eq!(read_asks[0].price, fixed!(101));
+
+        assert!(decode(&topic, &payload[..30]).is_err());
+        assert!(decode(b"ETH.trades", &payload).is_err());
+    }
+}
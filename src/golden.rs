@@ -0,0 +1,331 @@
+//! Golden replay regression checks.
+//!
+//! A [`GoldenReplay`] replays a recorded [`EventSource`] through a book and takes a [`Checkpoint`] of
+//! it every few events and once more at the end: the [state hash](OrderBook::state_hash), the last
+//! update and the touch. Written to a golden file alongside the recording, the checkpoints pin down how
+//! the book treats that recording, and replaying it after an upgrade reports the first checkpoint that
+//! moved.
+//!
+//! Golden files are text, a `#` comment then one checkpoint per line:
+//!
+//! ```text
+//! # events timestamp sequence hash bid ask
+//! 1000 1700000000123 48211 9f0c2b61d3a7e845 99.5@3 100@2
+//! ```
+//!
+//! [`check`](GoldenReplay::check) compares against the golden file, or rewrites it when the
+//! `FREYA_OB_UPDATE_GOLDEN` environment variable is set, so goldens are regenerated by rerunning the
+//! tests with it after a deliberate change in behaviour.
+//!
+//! ```no_run
+//! # #[cfg(feature = "fixed_decimal")] {
+//! use freya_ob::{
+//!     books::btree_orderbook::BTreeOrderBook, decimals::fixed_decimal::FixedDecimal, formats::source::open_csv,
+//!     golden::GoldenReplay,
+//! };
+//!
+//! let events = open_csv::<FixedDecimal>("tests/data/btc.csv").unwrap();
+//! GoldenReplay::new(1_000).check(&mut BTreeOrderBook::new(), events, "tests/data/btc.golden").unwrap();
+//! # }
+//! ```
+
+use std::{
+    fmt::{self, Display},
+    fs,
+    ops::ControlFlow,
+    path::Path,
+};
+
+use crate::{
+    books::interface::OrderBook,
+    decimals::decimal_type::DecimalType,
+    formats::{
+        source::{EventSource, SourceError},
+        FormatError,
+    },
+    side::Side,
+    timestamp::Timestamp,
+};
+
+const HEADER: &str = "# events timestamp sequence hash bid ask";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// State of the book after some number of events
+pub struct Checkpoint {
+    /// Events read from the recording, ignored ones included
+    pub events: u64,
+    pub timestamp: Timestamp,
+    pub sequence_id: u64,
+    pub hash: u64,
+    /// Best bid and best ask as `price@size`, `-` for an empty side, to make a divergence readable
+    pub touch: String,
+}
+
+impl Checkpoint {
+    fn take<V: DecimalType + Display, B: OrderBook<V>>(book: &B, events: u64) -> Self {
+        let top =
+            |side| book.levels(side, 1).first().map_or_else(|| "-".to_owned(), |level| format!("{}@{}", level.price, level.size));
+        Self {
+            events,
+            timestamp: book.timestamp(),
+            sequence_id: book.sequence_id(),
+            hash: book.state_hash(),
+            touch: format!("{} {}", top(Side::Buy), top(Side::Sell)),
+        }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(5, ' ');
+        Some(Self {
+            events: fields.next()?.parse().ok()?,
+            timestamp: Timestamp::from_nanos(fields.next()?.parse().ok()?),
+            sequence_id: fields.next()?.parse().ok()?,
+            hash: u64::from_str_radix(fields.next()?, 16).ok()?,
+            touch: fields.next()?.to_owned(),
+        })
+    }
+}
+
+impl Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {:016x} {}", self.events, self.timestamp, self.sequence_id, self.hash, self.touch)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GoldenError {
+    /// Reading the recording or the golden file failed, or writing the golden file did
+    Format(FormatError),
+    /// The first checkpoint where the replay and the golden file differ, `None` on the side that ran out
+    /// of checkpoints first
+    Diverged { expected: Option<Checkpoint>, actual: Option<Checkpoint> },
+}
+
+impl Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show =
+            |checkpoint: &Option<Checkpoint>| checkpoint.as_ref().map_or_else(|| "nothing".to_owned(), Checkpoint::to_string);
+        match self {
+            Self::Format(err) => write!(f, "{err}"),
+            Self::Diverged { expected, actual } => {
+                write!(f, "replay diverged, expected {} but got {}", show(expected), show(actual))
+            }
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+impl From<FormatError> for GoldenError {
+    fn from(err: FormatError) -> Self {
+        Self::Format(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Replays recordings against golden files, see the [module documentation](self)
+pub struct GoldenReplay {
+    interval: u64,
+}
+
+impl GoldenReplay {
+    /// Environment variable that makes [`check`](Self::check) rewrite golden files
+    pub const UPDATE_VAR: &'static str = "FREYA_OB_UPDATE_GOLDEN";
+
+    #[inline]
+    #[must_use]
+    /// Checkpoint every `interval` events, at least one
+    pub fn new(interval: u64) -> Self {
+        Self { interval: interval.max(1) }
+    }
+
+    /// Replay `source` through `book`, collecting its checkpoints
+    ///
+    /// # Errors
+    /// The first error reading `source`.
+    pub fn record<V, B>(&self, book: &mut B, source: impl EventSource<V>) -> Result<Vec<Checkpoint>, SourceError>
+    where
+        V: DecimalType + Display,
+        B: OrderBook<V>,
+    {
+        let mut checkpoints = Vec::new();
+        self.replay(book, source, |checkpoint| {
+            checkpoints.push(checkpoint);
+            ControlFlow::Continue(())
+        })?;
+        Ok(checkpoints)
+    }
+
+    /// Replay `source` through `book`, stopping at the first checkpoint that differs from `golden`
+    ///
+    /// # Errors
+    /// [`GoldenError::Diverged`] at the first difference, or the first error reading `source`.
+    pub fn verify<V, B>(&self, book: &mut B, source: impl EventSource<V>, golden: &[Checkpoint]) -> Result<(), GoldenError>
+    where
+        V: DecimalType + Display,
+        B: OrderBook<V>,
+    {
+        let mut expected = golden.iter();
+        let mut diverged = None;
+        self.replay(book, source, |actual| match expected.next() {
+            Some(expected) if *expected == actual => ControlFlow::Continue(()),
+            expected => {
+                diverged = Some(GoldenError::Diverged { expected: expected.cloned(), actual: Some(actual) });
+                ControlFlow::Break(())
+            }
+        })?;
+        match (diverged, expected.next()) {
+            (Some(err), _) => Err(err),
+            (None, Some(expected)) => Err(GoldenError::Diverged { expected: Some(expected.clone()), actual: None }),
+            (None, None) => Ok(()),
+        }
+    }
+
+    /// Replay `source` through `book` and write its checkpoints to the golden file at `path`, returning
+    /// how many there were
+    ///
+    /// # Errors
+    /// The first error reading `source` or writing the file.
+    pub fn regenerate<V, B>(
+        &self,
+        book: &mut B,
+        source: impl EventSource<V>,
+        path: impl AsRef<Path>,
+    ) -> Result<usize, GoldenError>
+    where
+        V: DecimalType + Display,
+        B: OrderBook<V>,
+    {
+        let checkpoints = self.record(book, source)?;
+        write(path, &checkpoints)?;
+        Ok(checkpoints.len())
+    }
+
+    /// [`verify`](Self::verify) against the golden file at `path`, or [`regenerate`](Self::regenerate)
+    /// it when [`UPDATE_VAR`](Self::UPDATE_VAR) is set
+    ///
+    /// # Errors
+    /// As [`verify`](Self::verify), or a [`GoldenError::Format`] when the golden file cannot be read.
+    pub fn check<V, B>(&self, book: &mut B, source: impl EventSource<V>, path: impl AsRef<Path>) -> Result<(), GoldenError>
+    where
+        V: DecimalType + Display,
+        B: OrderBook<V>,
+    {
+        if std::env::var_os(Self::UPDATE_VAR).is_some() {
+            return self.regenerate(book, source, path).map(|_| ());
+        }
+        self.verify(book, source, &read(path)?)
+    }
+
+    fn replay<V, B>(
+        &self,
+        book: &mut B,
+        source: impl EventSource<V>,
+        mut on_checkpoint: impl FnMut(Checkpoint) -> ControlFlow<()>,
+    ) -> Result<(), SourceError>
+    where
+        V: DecimalType + Display,
+        B: OrderBook<V>,
+    {
+        let mut events = 0;
+        for event in source {
+            book.process(event?);
+            events += 1;
+            if events % self.interval == 0 && on_checkpoint(Checkpoint::take(book, events)).is_break() {
+                return Ok(());
+            }
+        }
+        if events % self.interval != 0 || events == 0 {
+            let _ = on_checkpoint(Checkpoint::take(book, events));
+        }
+        Ok(())
+    }
+}
+
+/// Checkpoints of the golden file at `path`
+///
+/// # Errors
+/// If the file cannot be read or a line is not a checkpoint.
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<Checkpoint>, FormatError> {
+    fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            Checkpoint::parse(line)
+                .ok_or_else(|| FormatError::Malformed { line: index + 1, reason: format!("not a checkpoint: {line}") })
+        })
+        .collect()
+}
+
+/// Write `checkpoints` to a golden file at `path`, replacing it
+///
+/// # Errors
+/// If the file cannot be written.
+pub fn write(path: impl AsRef<Path>, checkpoints: &[Checkpoint]) -> Result<(), FormatError> {
+    let mut text = format!("{HEADER}\n");
+    for checkpoint in checkpoints {
+        text += &format!("{checkpoint}\n");
+    }
+    fs::write(path, text)?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, btree_orderbook::BTreeOrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::source::MemorySource,
+        golden::{read, GoldenError, GoldenReplay},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn events() -> Vec<Event<FixedDecimal>> {
+        (0..7)
+            .map(|i| {
+                let (side, price) = if i % 2 == 0 { (Side::Buy, fixed!(99)) } else { (Side::Sell, fixed!(101)) };
+                Event::new(EventKind::L2, side, price, FixedDecimal::from_int(i + 1), Timestamp::from_nanos(i * 10))
+                    .with_sequence_id(i as u64 + 1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_golden_replay() {
+        let path = std::env::temp_dir().join(format!("freya_ob_golden_{}.golden", std::process::id()));
+        let golden = GoldenReplay::new(3);
+        let count = golden.regenerate(&mut BTreeOrderBook::new(), MemorySource::new(events()), &path).unwrap();
+        let checkpoints = read(&path).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(checkpoints.iter().map(|checkpoint| checkpoint.events).collect::<Vec<_>>(), [3, 6, 7]);
+        assert_eq!((checkpoints[2].sequence_id, checkpoints[2].touch.as_str()), (7, "99@7 101@6"));
+
+        // Either book replays the recording the same way
+        assert_eq!(golden.check(&mut ArrayOrderbook::<8, FixedDecimal>::new(), MemorySource::new(events()), &path), Ok(()));
+
+        let mut changed = events();
+        changed[4].size = fixed!(2);
+        let Err(GoldenError::Diverged { expected, actual }) =
+            golden.check(&mut BTreeOrderBook::new(), MemorySource::new(changed), &path)
+        else {
+            panic!("a changed recording should diverge");
+        };
+        assert_eq!(
+            (expected.map(|checkpoint| checkpoint.events), actual.map(|checkpoint| checkpoint.events)),
+            (Some(6), Some(6))
+        );
+
+        let truncated = MemorySource::new(events()[..6].to_vec());
+        assert!(matches!(
+            golden.verify(&mut BTreeOrderBook::new(), truncated, &checkpoints),
+            Err(GoldenError::Diverged { expected: Some(_), actual: None })
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
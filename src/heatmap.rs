@@ -0,0 +1,233 @@
+//! Liquidity heatmaps sampled from a live book.
+//!
+//! [`Heatmap`] wraps any [`OrderBook`] and samples its depth on a fixed time grid as events pass through
+//! `process`. Grid points are multiples of the interval from the epoch, and each is sampled just before
+//! the first event after it, so a sample is the book as of its time whatever the event rate. Levels are
+//! conflated into price [buckets](crate::aggregate::bucket), giving one [`Cell`] per time, side and
+//! bucket with resting size. Quiet intervals repeat the last state, leaving the grid without gaps.
+//!
+//! Cells are written as CSV with the header `ts,side,price,size`, `price` being the bucket's lower
+//! edge, ready to pivot into a time × price matrix.
+
+use std::{
+    fmt::Display,
+    io::Write,
+    ops::{Add, Rem, Sub},
+    time::Duration,
+};
+
+use crate::{
+    aggregate::bucket,
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    formats::{side_name, FormatError},
+    level::Level,
+    metrics::{MetricsRequest, OrderbookMetrics},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+pub const HEADER: &str = "ts,side,price,size";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell<V: DecimalType> {
+    /// Grid point the book was sampled at
+    pub time: Timestamp,
+    pub side: Side,
+    /// Lower edge of the price bucket
+    pub price: V,
+    /// Size resting in the bucket
+    pub size: V,
+}
+
+#[derive(Debug)]
+/// Samples a book onto a time × price grid, see the [module documentation](self)
+pub struct Heatmap<V: DecimalType, B: OrderBook<V>> {
+    book: B,
+    interval: i64,
+    width: V,
+    depth: usize,
+    /// Next grid point to sample, `None` before the first event
+    next: Option<Timestamp>,
+    cells: Vec<Cell<V>>,
+}
+
+impl<V, B> Heatmap<V, B>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Rem<Output = V>,
+    B: OrderBook<V>,
+{
+    pub const DEFAULT_DEPTH: usize = 50;
+
+    #[inline]
+    #[must_use]
+    /// Sample `book` every `interval` into buckets `width` wide
+    pub fn new(book: B, interval: Duration, width: V) -> Self {
+        let interval = i64::try_from(interval.as_nanos()).unwrap_or(i64::MAX).max(1);
+        Self { book, interval, width, depth: Self::DEFAULT_DEPTH, next: None, cells: Vec::new() }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Levels per side to sample
+    pub const fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn inner(&self) -> &B {
+        &self.book
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> B {
+        self.book
+    }
+
+    #[inline]
+    #[must_use]
+    /// Cells sampled so far, in time order
+    pub fn cells(&self) -> &[Cell<V>] {
+        &self.cells
+    }
+
+    #[inline]
+    /// Remove and return the cells sampled so far, to export a long session in pieces
+    pub fn take_cells(&mut self) -> Vec<Cell<V>> {
+        std::mem::take(&mut self.cells)
+    }
+
+    /// Sample the grid points before `now`, for quiet markets where no later event arrives
+    pub fn on_time(&mut self, now: Timestamp) {
+        let Some(mut next) = self.next else {
+            return;
+        };
+        while next < now {
+            self.sample(next);
+            next = Timestamp::from_nanos(next.as_nanos().saturating_add(self.interval));
+        }
+        self.next = Some(next);
+    }
+
+    /// Write the cells sampled so far as CSV, header first
+    ///
+    /// # Errors
+    /// If the writer fails.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), FormatError>
+    where
+        V: Display,
+    {
+        writeln!(writer, "{HEADER}")?;
+        for cell in &self.cells {
+            writeln!(writer, "{},{},{},{}", cell.time, side_name(cell.side), cell.price, cell.size)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn sample(&mut self, time: Timestamp) {
+        for side in [Side::Buy, Side::Sell] {
+            let mut open: Option<Cell<V>> = None;
+            for level in self.book.levels(side, self.depth) {
+                let price = bucket(level.price, self.width);
+                match &mut open {
+                    Some(cell) if cell.price == price => cell.size = cell.size + level.size,
+                    _ => self.cells.extend(open.replace(Cell { time, side, price, size: level.size })),
+                }
+            }
+            self.cells.extend(open);
+        }
+    }
+}
+
+impl<V, B> OrderBook<V> for Heatmap<V, B>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Rem<Output = V>,
+    B: OrderBook<V>,
+{
+    fn process_delta(&mut self, event: Event<V>) -> Option<BookDelta<V>> {
+        let ts = event.timestamp.as_nanos();
+        if self.next.is_none() {
+            // The first grid point at or after the first event
+            let aligned = ts.div_euclid(self.interval) * self.interval;
+            self.next = Some(Timestamp::from_nanos(if aligned < ts { aligned.saturating_add(self.interval) } else { aligned }));
+        }
+        self.on_time(event.timestamp);
+        self.book.process_delta(event)
+    }
+
+    #[inline]
+    fn best_bid(&mut self) -> Option<Level<V>> {
+        self.book.best_bid()
+    }
+
+    #[inline]
+    fn best_ask(&mut self) -> Option<Level<V>> {
+        self.book.best_ask()
+    }
+
+    #[inline]
+    fn levels(&self, side: Side, depth: usize) -> Vec<Level<V>> {
+        self.book.levels(side, depth)
+    }
+
+    #[inline]
+    fn timestamp(&self) -> Timestamp {
+        self.book.timestamp()
+    }
+
+    #[inline]
+    fn sequence_id(&self) -> u64 {
+        self.book.sequence_id()
+    }
+
+    #[inline]
+    fn calculate_metrics_with(&self, depth: usize, request: MetricsRequest) -> OrderbookMetrics<V> {
+        self.book.calculate_metrics_with(depth, request)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        heatmap::Heatmap,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_samples_on_grid() {
+        let mut book = Heatmap::new(BTreeOrderBook::new(), Duration::from_secs(10), fixed!(1));
+        let l2 = |side, price, size, secs| Event::new(EventKind::L2, side, price, size, Timestamp::from_secs(secs));
+        book.process(l2(Side::Buy, fixed!(99.5), fixed!(2), 5));
+        book.process(l2(Side::Buy, fixed!(99.25), fixed!(1), 6));
+        book.process(l2(Side::Buy, fixed!(98.5), fixed!(4), 10));
+        book.process(l2(Side::Sell, fixed!(100.5), fixed!(3), 12));
+        // Nothing until 31, so 20 and 30 repeat the state of 12
+        book.process(l2(Side::Buy, fixed!(99.5), fixed!(0), 31));
+        book.on_time(Timestamp::from_secs(40));
+
+        let mut csv = Vec::new();
+        book.write_csv(&mut csv).unwrap();
+        let expected = "ts,side,price,size\n\
+            10000000000,buy,99,3\n10000000000,buy,98,4\n\
+            20000000000,buy,99,3\n20000000000,buy,98,4\n20000000000,sell,100,3\n\
+            30000000000,buy,99,3\n30000000000,buy,98,4\n30000000000,sell,100,3\n";
+        assert_eq!(String::from_utf8(csv).unwrap(), expected);
+
+        book.on_time(Timestamp::from_secs(41));
+        assert_eq!(book.take_cells().len(), 11);
+        assert!(book.cells().is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Dense identifier of an instrument, assigned by a [`SymbolTable`] in the order symbols are first
+/// seen
+pub struct InstrId(pub u32);
+
+impl InstrId {
+    #[inline(always)]
+    #[must_use]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Interns symbols into [`InstrId`]s, so events can be routed by an index rather than by hashing a
+/// string.
+pub struct SymbolTable {
+    ids: HashMap<String, InstrId>,
+    names: Vec<String>,
+}
+
+impl SymbolTable {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The ID of `symbol`, assigning the next one if it is new
+    pub fn intern(&mut self, symbol: &str) -> InstrId {
+        if let Some(&id) = self.ids.get(symbol) {
+            return id;
+        }
+        let id = InstrId(u32::try_from(self.names.len()).expect("fewer than 2^32 symbols"));
+        self.ids.insert(symbol.to_owned(), id);
+        self.names.push(symbol.to_owned());
+        id
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn get(&self, symbol: &str) -> Option<InstrId> {
+        self.ids.get(symbol).copied()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn name(&self, id: InstrId) -> Option<&str> {
+        self.names.get(id.index()).map(String::as_str)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    #[inline]
+    /// Every interned symbol with its ID, in ID order
+    pub fn iter(&self) -> impl Iterator<Item = (InstrId, &str)> {
+        self.names.iter().enumerate().map(|(index, name)| (InstrId(index as u32), name.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::instrument::{InstrId, SymbolTable};
+
+    #[test]
+    fn test_interns_in_order() {
+        let mut table = SymbolTable::new();
+        assert_eq!(
+            (table.intern("BTC-USD"), table.intern("ETH-USD"), table.intern("BTC-USD")),
+            (InstrId(0), InstrId(1), InstrId(0))
+        );
+        assert_eq!((table.get("ETH-USD"), table.get("SOL-USD")), (Some(InstrId(1)), None));
+        assert_eq!((table.name(InstrId(1)), table.name(InstrId(2))), (Some("ETH-USD"), None));
+        assert_eq!(table.iter().collect::<Vec<_>>(), [(InstrId(0), "BTC-USD"), (InstrId(1), "ETH-USD")]);
+    }
+}
@@ -1,8 +1,48 @@
+pub mod aggregate;
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod backtest;
 pub mod books;
 pub mod buffers;
+pub mod consistency;
 pub mod decimals;
+pub mod dedup;
 pub mod event;
 pub mod event_kind;
+#[cfg(feature = "feeds")]
+pub mod feeds;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod formats;
+pub mod golden;
+#[cfg(feature = "heatmap")]
+pub mod heatmap;
+pub mod instrument;
 pub mod level;
 pub mod metrics;
+#[cfg(feature = "observability")]
+pub mod observability;
+pub mod ofi;
+pub mod paper;
+pub mod pipeline;
+#[cfg(any(feature = "dbn", feature = "itch", feature = "mdp3"))]
+pub mod protocols;
+#[cfg(any(feature = "redis", all(feature = "shm", unix)))]
+pub mod publish;
+pub mod render;
+pub mod risk;
+pub mod service;
+#[cfg(feature = "fixed_decimal")]
+pub mod ring;
 pub mod side;
+pub mod signals;
+pub mod sim;
+pub mod slicing;
+pub mod stats;
+pub mod sync;
+pub mod timestamp;
+pub mod validation;
+#[cfg(feature = "fixed_decimal")]
+pub mod wal;
+#[cfg(feature = "async")]
+pub mod watch;
@@ -0,0 +1,271 @@
+//! Z-score anomaly detection on the touch and near depth.
+//!
+//! Each sample of the spread, the size imbalance `(bid - ask) / (bid + ask)` over the top levels and
+//! their total size is scored against the mean and standard deviation of the previous `window`
+//! samples, `z = (x - mean) / std`. A spread blowout is a spread `z` above the threshold, an imbalance
+//! spike an imbalance `|z|` above it and book thinning a depth `z` below its negative. A window of
+//! equal samples, such as a spread pinned at one tick, has no deviation, so any move away from it
+//! scores as infinitely far. Scores are computed in `f64`, leaving the fixed-point range to the
+//! samples themselves. Scoring starts once the window has filled, and the callback fires when an
+//! anomaly starts rather than on every sample it persists for.
+
+use std::{
+    collections::VecDeque,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    side::Side,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anomaly {
+    SpreadBlowout,
+    ImbalanceSpike,
+    BookThinning,
+}
+
+impl Anomaly {
+    #[inline(always)]
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Anomalies flagged by the latest sample
+pub struct Anomalies(u8);
+
+impl Anomalies {
+    #[inline(always)]
+    #[must_use]
+    pub const fn contains(self, anomaly: Anomaly) -> bool {
+        self.0 & anomaly.bit() != 0
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[inline(always)]
+    const fn with(self, anomaly: Anomaly, flagged: bool) -> Self {
+        if flagged {
+            Self(self.0 | anomaly.bit())
+        } else {
+            self
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Mean and squared deviations of a sliding window, kept in `f64` by Welford's method so that large
+/// samples cannot overflow a fixed-point sum of squares
+struct Stats {
+    samples: VecDeque<f64>,
+    mean: f64,
+    m2: f64,
+}
+
+impl Stats {
+    /// Relative difference below which two samples count as equal, absorbing rounding in the running mean
+    const TOLERANCE: f64 = 64.0 * f64::EPSILON;
+
+    const fn new() -> Self {
+        Self { samples: VecDeque::new(), mean: 0.0, m2: 0.0 }
+    }
+
+    // Score against the window, then slide it on
+    fn score_and_push(&mut self, sample: f64, window: usize) -> Option<f64> {
+        let z = (self.samples.len() == window).then(|| self.z_score(sample));
+        self.samples.push_back(sample);
+        if self.samples.len() > window {
+            let oldest = self.samples.pop_front().expect("window is not empty");
+            let mean = self.mean + (sample - oldest) / window as f64;
+            self.m2 = (self.m2 + (sample - oldest) * (sample - mean + oldest - self.mean)).max(0.0);
+            self.mean = mean;
+        } else {
+            let delta = sample - self.mean;
+            self.mean += delta / self.samples.len() as f64;
+            self.m2 += delta * (sample - self.mean);
+        }
+        z
+    }
+
+    /// A window of equal samples has no deviation, so any sample away from it scores infinite
+    fn z_score(&self, sample: f64) -> f64 {
+        let tolerance = Self::TOLERANCE * sample.abs().max(self.mean.abs());
+        let (distance, std_dev) = (sample - self.mean, (self.m2 / self.samples.len() as f64).sqrt());
+        if distance.abs() <= tolerance {
+            0.0
+        } else if std_dev <= tolerance {
+            f64::INFINITY.copysign(distance)
+        } else {
+            distance / std_dev
+        }
+    }
+}
+
+type Callback = Box<dyn FnMut(Anomaly) + Send>;
+
+pub struct AnomalyDetector<V> {
+    window: usize,
+    threshold: V,
+    depth: usize,
+    spread: Stats,
+    imbalance: Stats,
+    total_size: Stats,
+    active: Anomalies,
+    callback: Option<Callback>,
+}
+
+impl<V> AnomalyDetector<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V>,
+{
+    const DEFAULT_DEPTH: usize = 5;
+
+    #[inline]
+    #[must_use]
+    /// Flag samples more than `threshold` standard deviations from the mean of the last `window`
+    pub fn new(window: usize, threshold: V) -> Self {
+        Self {
+            window: window.max(2),
+            threshold,
+            depth: Self::DEFAULT_DEPTH,
+            spread: Stats::new(),
+            imbalance: Stats::new(),
+            total_size: Stats::new(),
+            active: Anomalies::default(),
+            callback: None,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Number of levels per side summed for the imbalance and depth samples
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// Call `callback` whenever an anomaly starts
+    pub fn with_callback(mut self, callback: impl FnMut(Anomaly) + Send + 'static) -> Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// Anomalies flagged by the latest sample
+    pub const fn active(&self) -> Anomalies {
+        self.active
+    }
+
+    /// Sample `book` after it applied `delta`, ignored while either side is empty
+    pub fn on_delta<B: OrderBook<V>>(&mut self, book: &B, _delta: &BookDelta<V>) -> Anomalies {
+        let (bids, asks) = (book.levels(Side::Buy, self.depth), book.levels(Side::Sell, self.depth));
+        let (Some(bid), Some(ask)) = (bids.first(), asks.first()) else {
+            return self.active;
+        };
+        let bid_size = bids.iter().fold(V::ZERO, |total, level| total + level.size);
+        let ask_size = asks.iter().fold(V::ZERO, |total, level| total + level.size);
+        let total_size = bid_size + ask_size;
+        let imbalance = if total_size > V::ZERO { (bid_size - ask_size) / total_size } else { V::ZERO };
+        self.update(ask.price - bid.price, imbalance, total_size)
+    }
+
+    /// Score one sample of the spread, imbalance and depth
+    pub fn update(&mut self, spread: V, imbalance: V, total_size: V) -> Anomalies {
+        let (window, threshold) = (self.window, self.threshold.to_f64());
+        let spread = self.spread.score_and_push(spread.to_f64(), window);
+        let imbalance = self.imbalance.score_and_push(imbalance.to_f64(), window);
+        let total_size = self.total_size.score_and_push(total_size.to_f64(), window);
+
+        let anomalies = Anomalies::default()
+            .with(Anomaly::SpreadBlowout, spread.is_some_and(|z| z > threshold))
+            .with(Anomaly::ImbalanceSpike, imbalance.is_some_and(|z| z.abs() > threshold))
+            .with(Anomaly::BookThinning, total_size.is_some_and(|z| z < -threshold));
+        if let Some(callback) = self.callback.as_mut() {
+            for anomaly in [Anomaly::SpreadBlowout, Anomaly::ImbalanceSpike, Anomaly::BookThinning] {
+                if anomalies.contains(anomaly) && !self.active.contains(anomaly) {
+                    callback(anomaly);
+                }
+            }
+        }
+        self.active = anomalies;
+        anomalies
+    }
+}
+
+impl<V: std::fmt::Debug> std::fmt::Debug for AnomalyDetector<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnomalyDetector")
+            .field("window", &self.window)
+            .field("threshold", &self.threshold)
+            .field("depth", &self.depth)
+            .field("active", &self.active)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        metrics::anomaly::{Anomaly, AnomalyDetector},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_spread_blowout_fires_once() {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let sink = fired.clone();
+        let mut detector = AnomalyDetector::new(4, fixed!(3)).with_callback(move |anomaly| sink.lock().unwrap().push(anomaly));
+        for spread in [fixed!(1), fixed!(2), fixed!(1), fixed!(2)] {
+            assert!(detector.update(spread, fixed!(0), fixed!(10)).is_empty());
+        }
+        // Mean 1.5 and deviation 0.5, so 4 scores 5
+        assert!(detector.update(fixed!(4), fixed!(0), fixed!(10)).contains(Anomaly::SpreadBlowout));
+        assert!(detector.update(fixed!(9), fixed!(0), fixed!(10)).contains(Anomaly::SpreadBlowout));
+        assert_eq!(*fired.lock().unwrap(), vec![Anomaly::SpreadBlowout]);
+    }
+
+    #[test]
+    fn test_flat_window_and_large_depth() {
+        let mut detector = AnomalyDetector::new(4, fixed!(3));
+        // A spread pinned at one tick, on a depth whose squares are far beyond the fixed-point range
+        for _ in 0..4 {
+            assert!(detector.update(fixed!(0.01), fixed!(0), fixed!(500000)).is_empty());
+        }
+        assert!(detector.update(fixed!(0.01), fixed!(0), fixed!(500000)).is_empty());
+        let active = detector.update(fixed!(0.02), fixed!(0), fixed!(400000));
+        assert!(active.contains(Anomaly::SpreadBlowout) && active.contains(Anomaly::BookThinning));
+    }
+
+    #[test]
+    fn test_book_thinning() {
+        let mut book = BTreeOrderBook::new();
+        let mut detector = AnomalyDetector::new(4, fixed!(2)).with_depth(2);
+        book.process(Event::new(EventKind::L2, Side::Sell, fixed!(101), fixed!(10), Timestamp::from_nanos(1)));
+        for (ts, size) in [(2, fixed!(10)), (3, fixed!(12)), (4, fixed!(10)), (5, fixed!(12)), (6, fixed!(1))] {
+            let delta =
+                book.process_delta(Event::new(EventKind::L2, Side::Buy, fixed!(100), size, Timestamp::from_nanos(ts))).unwrap();
+            detector.on_delta(&book, &delta);
+        }
+        let active = detector.active();
+        assert!(active.contains(Anomaly::BookThinning) && !active.contains(Anomaly::SpreadBlowout));
+    }
+}
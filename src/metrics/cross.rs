@@ -0,0 +1,191 @@
+//! Metrics across two books of the same instrument on different venues.
+//!
+//! The books are crossed when one venue bids above the other's ask. Taker fees, in basis points of
+//! notional, are charged on both legs, so a crossed spread is only executable while the bid net of
+//! the selling venue's fee stays above the ask plus the buying venue's fee.
+
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::{books::interface::OrderBook, decimals::decimal_type::DecimalType, level::Level, side::Side};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Venue {
+    A,
+    B,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Buy on one venue and sell the same size on the other
+pub struct Arbitrage<V> {
+    pub buy: Venue,
+    pub sell: Venue,
+    /// Size executable at a profit after fees over the walked depth
+    pub size: V,
+    /// Profit after fees of executing `size`
+    pub profit: V,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrossBookSnapshot<V> {
+    /// Largest bid on one venue minus the ask on the other, positive when the books are crossed
+    pub crossed_spread: V,
+    /// Mid price of venue A minus that of venue B
+    pub mid_divergence: V,
+    /// The profitable direction, `None` when fees eat the crossed spread or the books do not cross
+    pub arbitrage: Option<Arbitrage<V>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// ```
+/// # #[cfg(feature = "fixed_decimal")] {
+/// use freya_ob::{books::btree_orderbook::BTreeOrderBook, fixed, metrics::cross::CrossBookMetrics};
+///
+/// let cross = CrossBookMetrics::new(fixed!(5), fixed!(2)).with_depth(10);
+/// let (a, b) = (BTreeOrderBook::new(), BTreeOrderBook::new());
+/// assert!(cross.compute(&a, &b).is_none());
+/// # }
+/// ```
+pub struct CrossBookMetrics<V> {
+    fee_a: V,
+    fee_b: V,
+    depth: usize,
+}
+
+impl<V> CrossBookMetrics<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V>,
+{
+    const DEFAULT_DEPTH: usize = 20;
+
+    #[inline]
+    #[must_use]
+    /// Taker fees of each venue in basis points of notional
+    pub fn new(fee_a: V, fee_b: V) -> Self {
+        Self { fee_a, fee_b, depth: Self::DEFAULT_DEPTH }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Number of levels per side walked when sizing the arbitrage
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Compare the two books, `None` unless both have both sides populated
+    pub fn compute<A, B>(&self, a: &A, b: &B) -> Option<CrossBookSnapshot<V>>
+    where
+        A: OrderBook<V>,
+        B: OrderBook<V>,
+    {
+        let (bids_a, asks_a) = (a.levels(Side::Buy, self.depth), a.levels(Side::Sell, self.depth));
+        let (bids_b, asks_b) = (b.levels(Side::Buy, self.depth), b.levels(Side::Sell, self.depth));
+        let (bid_a, ask_a) = (bids_a.first()?.price, asks_a.first()?.price);
+        let (bid_b, ask_b) = (bids_b.first()?.price, asks_b.first()?.price);
+
+        let (b_over_a, a_over_b) = (bid_b - ask_a, bid_a - ask_b);
+        let crossed_spread = if b_over_a > a_over_b { b_over_a } else { a_over_b };
+        let mid_divergence = (bid_a + ask_a) / V::TWO - (bid_b + ask_b) / V::TWO;
+
+        let arbitrage = self
+            .execute(Venue::A, &asks_a, self.fee_a, &bids_b, self.fee_b)
+            .or_else(|| self.execute(Venue::B, &asks_b, self.fee_b, &bids_a, self.fee_a));
+        Some(CrossBookSnapshot { crossed_spread, mid_divergence, arbitrage })
+    }
+
+    // Match the buying venue's asks against the selling venue's bids while the pair is profitable after fees
+    fn execute(&self, buy: Venue, asks: &[Level<V>], buy_fee: V, bids: &[Level<V>], sell_fee: V) -> Option<Arbitrage<V>> {
+        let bps = V::ONE_HUNDRED * V::ONE_HUNDRED;
+        let (buy_cost, sell_proceeds) = (V::ONE + buy_fee / bps, V::ONE - sell_fee / bps);
+        let (mut asks, mut bids) = (asks.iter().copied().peekable(), bids.iter().copied().peekable());
+        let (mut ask_left, mut bid_left) = (V::ZERO, V::ZERO);
+        let (mut size, mut profit) = (V::ZERO, V::ZERO);
+
+        while let (Some(ask), Some(bid)) = (asks.peek().copied(), bids.peek().copied()) {
+            let edge = bid.price * sell_proceeds - ask.price * buy_cost;
+            if edge <= V::ZERO {
+                break;
+            }
+            if ask_left == V::ZERO {
+                ask_left = ask.size;
+            }
+            if bid_left == V::ZERO {
+                bid_left = bid.size;
+            }
+            let matched = if ask_left < bid_left { ask_left } else { bid_left };
+            size = size + matched;
+            profit = profit + matched * edge;
+            ask_left = ask_left - matched;
+            bid_left = bid_left - matched;
+            if ask_left == V::ZERO {
+                asks.next();
+            }
+            if bid_left == V::ZERO {
+                bids.next();
+            }
+        }
+
+        let sell = match buy {
+            Venue::A => Venue::B,
+            Venue::B => Venue::A,
+        };
+        (size > V::ZERO).then_some(Arbitrage { buy, sell, size, profit })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        metrics::cross::{Arbitrage, CrossBookMetrics, Venue},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn book(levels: &[(Side, FixedDecimal, FixedDecimal)]) -> BTreeOrderBook<FixedDecimal> {
+        let mut book = BTreeOrderBook::new();
+        for &(side, price, size) in levels {
+            book.process(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(1)));
+        }
+        book
+    }
+
+    #[test]
+    fn test_crossed_books() {
+        let a = book(&[
+            (Side::Buy, fixed!(99), fixed!(1)),
+            (Side::Sell, fixed!(100), fixed!(2)),
+            (Side::Sell, fixed!(101), fixed!(5)),
+        ]);
+        let b = book(&[
+            (Side::Buy, fixed!(102), fixed!(3)),
+            (Side::Buy, fixed!(100.5), fixed!(4)),
+            (Side::Sell, fixed!(103), fixed!(1)),
+        ]);
+
+        let snapshot = CrossBookMetrics::new(fixed!(0), fixed!(0)).compute(&a, &b).unwrap();
+        assert_eq!((snapshot.crossed_spread, snapshot.mid_divergence), (fixed!(2), fixed!(-3)));
+        // 2 at 100 against 102, 1 at 101 against 102
+        let expected = Arbitrage { buy: Venue::A, sell: Venue::B, size: fixed!(3), profit: fixed!(5) };
+        assert_eq!(snapshot.arbitrage, Some(expected));
+
+        // 100 bps on the sell leg leaves 102 * 0.99 = 100.98, only the 100 ask still pays
+        let snapshot = CrossBookMetrics::new(fixed!(0), fixed!(100)).compute(&a, &b).unwrap();
+        let expected = Arbitrage { buy: Venue::A, sell: Venue::B, size: fixed!(2), profit: fixed!(1.96) };
+        assert_eq!(snapshot.arbitrage, Some(expected));
+    }
+
+    #[test]
+    fn test_uncrossed_books() {
+        let a = book(&[(Side::Buy, fixed!(99), fixed!(1)), (Side::Sell, fixed!(101), fixed!(1))]);
+        let b = book(&[(Side::Buy, fixed!(100), fixed!(1)), (Side::Sell, fixed!(102), fixed!(1))]);
+        let snapshot = CrossBookMetrics::new(fixed!(1), fixed!(1)).compute(&a, &b).unwrap();
+        assert_eq!((snapshot.crossed_spread, snapshot.mid_divergence, snapshot.arbitrage), (fixed!(-1), fixed!(-1), None));
+        assert!(CrossBookMetrics::new(fixed!(1), fixed!(1)).compute(&a, &BTreeOrderBook::new()).is_none());
+    }
+}
@@ -0,0 +1,103 @@
+//! Distribution of resting size across the levels of one side.
+//!
+//! A single wall and evenly spread liquidity can add up to the same total volume, [`SizeDistribution`]
+//! tells them apart. The Gini coefficient runs from zero when every level holds the same size towards
+//! one as the size concentrates in a single level.
+
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::{decimals::decimal_type::DecimalType, metrics::sqrt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeDistribution<V: DecimalType> {
+    /// Number of populated levels summarised
+    pub levels: usize,
+    pub total: V,
+    pub mean: V,
+    pub median: V,
+    /// Population standard deviation, rounded to the precision of the decimal backend
+    pub std_dev: V,
+    pub max: V,
+    /// Gini coefficient of the level sizes, from zero (even) towards one (concentrated)
+    pub gini: V,
+}
+
+impl<V> SizeDistribution<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V>,
+{
+    /// Summarise the given level sizes, `None` when there are none
+    pub fn from_sizes(mut sizes: Vec<V>) -> Option<Self> {
+        if sizes.is_empty() {
+            return None;
+        }
+        sizes.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        let levels = sizes.len();
+        let count = V::from_scaled(levels as i64, 0);
+        let total = sizes.iter().fold(V::ZERO, |acc, &size| acc + size);
+        let mean = total / count;
+        let median = match levels % 2 {
+            0 => (sizes[levels / 2 - 1] + sizes[levels / 2]) / V::TWO,
+            _ => sizes[levels / 2],
+        };
+        let variance = sizes.iter().fold(V::ZERO, |acc, &size| acc + (size - mean) * (size - mean)) / count;
+
+        // G = 2 * sum(i * x_i / sum(x)) / n - (n + 1) / n, with x sorted ascending and i from one. Taking
+        // each size as a share of the total first keeps the ranked sum within n, where the raw one
+        // overflows the decimal on deep or large books.
+        let gini = if total > V::ZERO {
+            let ranked =
+                sizes.iter().enumerate().fold(V::ZERO, |acc, (i, &size)| acc + V::from_scaled(i as i64 + 1, 0) * (size / total));
+            V::TWO * ranked / count - (count + V::ONE) / count
+        } else {
+            V::ZERO
+        };
+
+        Some(Self { levels, total, mean, median, std_dev: sqrt(variance), max: sizes[levels - 1], gini })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        metrics::{distribution::SizeDistribution, MetricsCalculator as _},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_size_distribution() {
+        let sizes = [2, 4, 4, 4, 5, 5, 7, 9].map(FixedDecimal::from_int).to_vec();
+        let distribution = SizeDistribution::from_sizes(sizes).unwrap();
+        assert_eq!((distribution.levels, distribution.total, distribution.mean), (8, fixed!(40), fixed!(5)));
+        assert_eq!((distribution.median, distribution.std_dev, distribution.max), (fixed!(4.5), fixed!(2), fixed!(9)));
+        assert_eq!(distribution.gini, fixed!(0.2125));
+        assert_eq!(SizeDistribution::<FixedDecimal>::from_sizes(Vec::new()), None);
+
+        // The raw ranked sum, 2,100,000, is beyond what the decimal holds
+        let even = SizeDistribution::from_sizes(vec![fixed!(10000); 20]).unwrap();
+        assert_eq!((even.total, even.gini), (fixed!(200000), fixed!(0)));
+    }
+
+    #[test]
+    fn test_single_wall() {
+        let mut book = BTreeOrderBook::new();
+        for price in [fixed!(100), fixed!(99), fixed!(98), fixed!(97)] {
+            book.process(Event::new(EventKind::L2, Side::Buy, price, fixed!(1), Timestamp::from_nanos(1)));
+        }
+        let even = book.size_distribution(Side::Buy, 4).unwrap();
+        assert_eq!((even.std_dev, even.gini), (fixed!(0), fixed!(0)));
+
+        book.process(Event::new(EventKind::L2, Side::Buy, fixed!(98), fixed!(97), Timestamp::from_nanos(1)));
+        let wall = book.size_distribution(Side::Buy, 4).unwrap();
+        assert_eq!((wall.median, wall.max, wall.gini), (fixed!(1), fixed!(97), fixed!(0.72)));
+        assert_eq!(book.size_distribution(Side::Sell, 4), None);
+    }
+}
@@ -0,0 +1,170 @@
+//! Execution quality measured against the mid price.
+//!
+//! For a trade at price `p` with mid `m` when it printed, the effective spread is `2 * |p - m|`. The
+//! realized spread compares the same price with the mid `horizon` later, `2 * d * (p - m')`, where
+//! `d` is `+1` for buyer-initiated trades and `-1` for seller-initiated ones. Trades are reported on
+//! the resting side, so a trade against the bids was initiated by a seller.
+
+use std::{
+    collections::VecDeque,
+    ops::{Add, Div, Mul, Sub},
+    time::Duration,
+};
+
+use crate::{
+    books::interface::OrderBook, decimals::decimal_type::DecimalType, event::Event, event_kind::EventKind, side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpreadSummary<V: DecimalType> {
+    pub trades: u64,
+    /// Mean effective spread, zero before the first trade
+    pub mean_effective: V,
+    /// Trades whose horizon has passed
+    pub realized_trades: u64,
+    /// Mean realized spread, zero before the first trade's horizon passes
+    pub mean_realized: V,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PendingTrade<V> {
+    due: Timestamp,
+    price: V,
+    buyer_initiated: bool,
+}
+
+#[derive(Debug)]
+pub struct ExecutionQuality<V: DecimalType> {
+    horizon: Duration,
+    mid: Option<(Timestamp, V)>,
+    pending: VecDeque<PendingTrade<V>>,
+    trades: u64,
+    effective_sum: V,
+    realized_trades: u64,
+    realized_sum: V,
+}
+
+impl<V> ExecutionQuality<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V>,
+{
+    #[inline]
+    #[must_use]
+    /// Measure realized spreads `horizon` after each trade
+    pub fn new(horizon: Duration) -> Self {
+        Self {
+            horizon,
+            mid: None,
+            pending: VecDeque::new(),
+            trades: 0,
+            effective_sum: V::ZERO,
+            realized_trades: 0,
+            realized_sum: V::ZERO,
+        }
+    }
+
+    /// Record the mid price in force from `ts`, settling trades whose horizon passed before it
+    pub fn on_mid(&mut self, ts: Timestamp, mid: V) {
+        self.settle(ts);
+        self.mid = Some((ts, mid));
+    }
+
+    #[inline]
+    /// Record the mid of `book` as of `ts`, ignored while either side is empty
+    pub fn on_book<B: OrderBook<V>>(&mut self, book: &mut B, ts: Timestamp) {
+        if let (Some(bid), Some(ask)) = (book.best_bid(), book.best_ask()) {
+            self.on_mid(ts, (bid.price + ask.price) / V::TWO);
+        }
+    }
+
+    /// Record a trade against the current mid, returning its effective spread.
+    ///
+    /// Call before the book applies the trade so the mid is the one the trade printed against. Other
+    /// event kinds and trades before the first mid are ignored.
+    pub fn on_trade(&mut self, trade: &Event<V>) -> Option<V> {
+        if trade.kind != EventKind::Trade {
+            return None;
+        }
+        self.settle(trade.timestamp);
+        let (_, mid) = self.mid?;
+        let effective = V::TWO * abs(trade.price - mid);
+        self.trades += 1;
+        self.effective_sum = self.effective_sum + effective;
+        self.pending.push_back(PendingTrade {
+            due: trade.timestamp + self.horizon,
+            price: trade.price,
+            buyer_initiated: trade.side == Side::Sell,
+        });
+        Some(effective)
+    }
+
+    #[must_use]
+    pub fn summary(&self) -> SpreadSummary<V> {
+        let mean = |sum: V, count: u64| if count == 0 { V::ZERO } else { sum / V::from_scaled(count as i64, 0) };
+        SpreadSummary {
+            trades: self.trades,
+            mean_effective: mean(self.effective_sum, self.trades),
+            realized_trades: self.realized_trades,
+            mean_realized: mean(self.realized_sum, self.realized_trades),
+        }
+    }
+
+    /// Settle trades due before `ts` against the mid that was in force at their horizon
+    fn settle(&mut self, ts: Timestamp) {
+        let Some((_, mid)) = self.mid else {
+            return;
+        };
+        while let Some(trade) = self.pending.front().filter(|trade| trade.due < ts).copied() {
+            let realized = V::TWO * (trade.price - mid);
+            let realized = if trade.buyer_initiated { realized } else { V::ZERO - realized };
+            self.realized_trades += 1;
+            self.realized_sum = self.realized_sum + realized;
+            self.pending.pop_front();
+        }
+    }
+}
+
+#[inline(always)]
+fn abs<V: DecimalType + PartialOrd + Sub<Output = V>>(value: V) -> V {
+    if value < V::ZERO {
+        V::ZERO - value
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        event::Event, event_kind::EventKind, fixed, metrics::execution::ExecutionQuality, side::Side, timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_effective_and_realized() {
+        let mut quality = ExecutionQuality::new(Duration::from_nanos(10));
+        assert!(quality.on_trade(&Event::new(EventKind::Trade, Side::Sell, fixed!(100.1), fixed!(1), Timestamp::ZERO)).is_none());
+
+        quality.on_mid(Timestamp::ZERO, fixed!(100));
+        // Buyer lifts the ask at 100.1 and the mid moves up to 100.05 before the horizon
+        assert_eq!(
+            quality.on_trade(&Event::new(EventKind::Trade, Side::Sell, fixed!(100.1), fixed!(1), Timestamp::from_nanos(1))),
+            Some(fixed!(0.2))
+        );
+        quality.on_mid(Timestamp::from_nanos(5), fixed!(100.05));
+        // Seller hits the bid at 99.9 against the 100.05 mid
+        assert_eq!(
+            quality.on_trade(&Event::new(EventKind::Trade, Side::Buy, fixed!(99.9), fixed!(1), Timestamp::from_nanos(6))),
+            Some(fixed!(0.3))
+        );
+        quality.on_mid(Timestamp::from_nanos(20), fixed!(100));
+
+        let summary = quality.summary();
+        assert_eq!((summary.trades, summary.mean_effective), (2, fixed!(0.25)));
+        // 2 * (100.1 - 100.05) = 0.1 and -2 * (99.9 - 100.05) = 0.3
+        assert_eq!((summary.realized_trades, summary.mean_realized), (2, fixed!(0.2)));
+    }
+}
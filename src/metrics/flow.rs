@@ -0,0 +1,179 @@
+//! Signed trade flow from the tape.
+//!
+//! Trades are reported on the resting side, so a trade against the asks was initiated by a buyer.
+//! [`TradeFlow`] keeps the trades of a trailing [`Window`] and sums the volume each aggressor side
+//! traded, their imbalance `(buy - sell) / (buy + sell)` and the rate trades arrived at.
+
+use std::{
+    collections::VecDeque,
+    ops::{Add, Div, Sub},
+    time::Duration,
+};
+
+use crate::{
+    decimals::decimal_type::DecimalType, event::Event, event_kind::EventKind, ofi::Window, side::Side, timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeFlowSnapshot<V> {
+    /// Volume of buyer-initiated trades
+    pub buy_volume: V,
+    /// Volume of seller-initiated trades
+    pub sell_volume: V,
+    pub buy_trades: usize,
+    pub sell_trades: usize,
+    /// Volume imbalance (-1 to 1), `None` before the first trade
+    pub imbalance: Option<V>,
+    /// Buyer-initiated trades per second
+    pub buy_rate: Option<f64>,
+    /// Seller-initiated trades per second
+    pub sell_rate: Option<f64>,
+}
+
+#[derive(Debug)]
+pub struct TradeFlow<V> {
+    window: Window,
+    trades: VecDeque<(Timestamp, Side, V)>,
+    buy_volume: V,
+    sell_volume: V,
+    buy_trades: usize,
+}
+
+impl<V> TradeFlow<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Div<Output = V>,
+{
+    #[inline]
+    #[must_use]
+    pub fn new(window: Window) -> Self {
+        Self { window, trades: VecDeque::new(), buy_volume: V::ZERO, sell_volume: V::ZERO, buy_trades: 0 }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Number of trades currently in the window
+    pub fn len(&self) -> usize {
+        self.trades.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Volume traded by both aggressor sides over the window
+    pub fn volume(&self) -> V {
+        self.buy_volume + self.sell_volume
+    }
+
+    #[must_use]
+    /// Time the window covers, its span for a time window, or for an event window the time between its
+    /// first and last trade
+    pub fn span(&self) -> Duration {
+        match (self.window, self.trades.front(), self.trades.back()) {
+            (Window::Time(span), ..) => span,
+            (Window::Events(_), Some(&(first, ..)), Some(&(last, ..))) => last.saturating_duration_since(first),
+            (Window::Events(_), ..) => Duration::ZERO,
+        }
+    }
+
+    /// Record a trade, other event kinds are ignored
+    pub fn on_trade(&mut self, trade: &Event<V>) {
+        if trade.kind != EventKind::Trade {
+            return;
+        }
+        let aggressor = trade.taker_side();
+        match aggressor {
+            Side::Buy => {
+                self.buy_volume = self.buy_volume + trade.size;
+                self.buy_trades += 1;
+            }
+            Side::Sell => self.sell_volume = self.sell_volume + trade.size,
+        }
+        self.trades.push_back((trade.timestamp, aggressor, trade.size));
+        self.advance(trade.timestamp);
+    }
+
+    /// Roll a time window forward to `ts` without a trade
+    pub fn advance(&mut self, ts: Timestamp) {
+        while let Some(&(oldest, aggressor, size)) = self.trades.front() {
+            let expired = match self.window {
+                Window::Time(span) => oldest <= ts - span,
+                Window::Events(count) => self.trades.len() > count,
+            };
+            if !expired {
+                break;
+            }
+            match aggressor {
+                Side::Buy => {
+                    self.buy_volume = self.buy_volume - size;
+                    self.buy_trades -= 1;
+                }
+                Side::Sell => self.sell_volume = self.sell_volume - size,
+            }
+            self.trades.pop_front();
+        }
+    }
+
+    /// Flow over the window. Rates are over the [`span`](Self::span), `None` while that is empty.
+    #[must_use]
+    pub fn snapshot(&self) -> TradeFlowSnapshot<V> {
+        let total = self.volume();
+        let imbalance = (total > V::ZERO).then(|| (self.buy_volume - self.sell_volume) / total);
+        let span = self.span().as_secs_f64();
+        let sell_trades = self.trades.len() - self.buy_trades;
+        let rate = |trades: usize| (span > 0.0).then(|| trades as f64 / span);
+        TradeFlowSnapshot {
+            buy_volume: self.buy_volume,
+            sell_volume: self.sell_volume,
+            buy_trades: self.buy_trades,
+            sell_trades,
+            imbalance,
+            buy_rate: rate(self.buy_trades),
+            sell_rate: rate(sell_trades),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        event::Event, event_kind::EventKind, fixed, metrics::flow::TradeFlow, ofi::Window, side::Side, timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_time_window() {
+        let mut flow = TradeFlow::new(Window::Time(Duration::from_secs(10)));
+        // Against the asks is buyer-initiated
+        flow.on_trade(&Event::new(EventKind::Trade, Side::Sell, fixed!(101), fixed!(3), Timestamp::from_secs(1)));
+        flow.on_trade(&Event::new(EventKind::Trade, Side::Sell, fixed!(101), fixed!(2), Timestamp::from_secs(4)));
+        flow.on_trade(&Event::new(EventKind::Trade, Side::Buy, fixed!(100), fixed!(1), Timestamp::from_secs(8)));
+        flow.on_trade(&Event::new(EventKind::L2, Side::Buy, fixed!(100), fixed!(7), Timestamp::from_secs(9)));
+
+        let snapshot = flow.snapshot();
+        assert_eq!((snapshot.buy_volume, snapshot.sell_volume, snapshot.buy_trades), (fixed!(5), fixed!(1), 2));
+        assert_eq!(snapshot.imbalance, Some(fixed!(4) / fixed!(6)));
+        assert_eq!((snapshot.buy_rate, snapshot.sell_rate), (Some(0.2), Some(0.1)));
+
+        flow.advance(Timestamp::from_secs(12));
+        let snapshot = flow.snapshot();
+        assert_eq!((snapshot.buy_volume, snapshot.buy_trades, snapshot.imbalance), (fixed!(2), 1, Some(fixed!(1) / fixed!(3))));
+    }
+
+    #[test]
+    fn test_event_window() {
+        let mut flow = TradeFlow::new(Window::Events(2));
+        assert_eq!((flow.snapshot().imbalance, flow.snapshot().buy_rate), (None, None));
+        for ts in [0, 10, 15] {
+            flow.on_trade(&Event::new(EventKind::Trade, Side::Buy, fixed!(100), fixed!(1), Timestamp::from_secs(ts)));
+        }
+        let snapshot = flow.snapshot();
+        assert_eq!((flow.len(), snapshot.imbalance, snapshot.sell_rate), (2, Some(fixed!(-1)), Some(0.4)));
+    }
+}
@@ -0,0 +1,184 @@
+//! Fixed-capacity history of [`OrderbookMetrics`] samples.
+//!
+//! [`MetricsHistory`] keeps the last `N` samples in a ring buffer and summarises one field over a
+//! trailing span of time, ending at the newest sample. Samples where the field is `None`
+//! are skipped.
+
+use std::{
+    ops::{Add, Div},
+    time::Duration,
+};
+
+use crate::{decimals::decimal_type::DecimalType, metrics::OrderbookMetrics, timestamp::Timestamp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricField {
+    QuoteImbalance,
+    MidPrice,
+    Spread,
+    SpreadPercentage,
+    VwapBid,
+    VwapAsk,
+}
+
+impl MetricField {
+    #[inline(always)]
+    #[must_use]
+    pub fn get<V: DecimalType + Copy>(self, metrics: &OrderbookMetrics<V>) -> Option<V> {
+        match self {
+            Self::QuoteImbalance => metrics.quote_imbalance,
+            Self::MidPrice => metrics.mid_price,
+            Self::Spread => metrics.spread,
+            Self::SpreadPercentage => metrics.spread_percentage,
+            Self::VwapBid => metrics.vwap_bid,
+            Self::VwapAsk => metrics.vwap_ask,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSummary<V> {
+    pub samples: usize,
+    pub min: V,
+    pub max: V,
+    pub mean: V,
+}
+
+#[derive(Debug)]
+pub struct MetricsHistory<V: DecimalType, const N: usize> {
+    samples: [Option<(Timestamp, OrderbookMetrics<V>)>; N],
+    // Slot the next sample is written to
+    head: usize,
+    len: usize,
+}
+
+impl<V, const N: usize> MetricsHistory<V, N>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Div<Output = V>,
+{
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        const { assert!(N > 0, "a history needs room for at least one sample") };
+        Self { samples: [None; N], head: 0, len: 0 }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    #[must_use]
+    /// The newest sample and its timestamp
+    pub fn latest(&self) -> Option<&(Timestamp, OrderbookMetrics<V>)> {
+        self.samples[(self.head + N - 1) % N].as_ref()
+    }
+
+    /// Record a sample taken at `ts`, overwriting the oldest once full
+    pub fn push(&mut self, ts: Timestamp, metrics: OrderbookMetrics<V>) {
+        self.samples[self.head] = Some((ts, metrics));
+        self.head = (self.head + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// Min, max and mean of `field` over the samples within `span` of the newest one
+    #[must_use]
+    pub fn summary(&self, field: MetricField, span: Duration) -> Option<FieldSummary<V>> {
+        let mut values = self.values(field, span);
+        let first = values.next()?;
+        let mut summary = FieldSummary { samples: 1, min: first, max: first, mean: first };
+        for value in values {
+            summary.samples += 1;
+            summary.mean = summary.mean + value;
+            if value < summary.min {
+                summary.min = value;
+            }
+            if value > summary.max {
+                summary.max = value;
+            }
+        }
+        summary.mean = summary.mean / V::from_scaled(summary.samples as i64, 0);
+        Some(summary)
+    }
+
+    /// Nearest-rank `percentile` (0 to 100) of `field` over the samples within `span` of the newest one
+    #[must_use]
+    pub fn percentile(&self, field: MetricField, span: Duration, percentile: u8) -> Option<V> {
+        let mut values: Vec<V> = self.values(field, span).collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let rank = (usize::from(percentile.min(100)) * values.len()).div_ceil(100);
+        Some(values[rank.saturating_sub(1)])
+    }
+
+    fn values(&self, field: MetricField, span: Duration) -> impl Iterator<Item = V> + '_ {
+        let cutoff = self.latest().map_or(Timestamp::ZERO, |&(ts, _)| ts - span);
+        self.samples.iter().flatten().filter(move |(ts, _)| *ts > cutoff).filter_map(move |(_, metrics)| field.get(metrics))
+    }
+}
+
+impl<V, const N: usize> Default for MetricsHistory<V, N>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Div<Output = V>,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        decimals::fixed_decimal::FixedDecimal,
+        fixed,
+        metrics::{
+            history::{FieldSummary, MetricField, MetricsHistory},
+            OrderbookMetrics,
+        },
+        timestamp::Timestamp,
+    };
+
+    fn sample(spread: FixedDecimal) -> OrderbookMetrics<FixedDecimal> {
+        OrderbookMetrics {
+            quote_imbalance: None,
+            mid_price: Some(fixed!(100)),
+            spread: Some(spread),
+            spread_percentage: None,
+            vwap_bid: None,
+            vwap_ask: None,
+        }
+    }
+
+    #[test]
+    fn test_trailing_summary() {
+        let mut history = MetricsHistory::<FixedDecimal, 4>::new();
+        for (ts, spread) in [(1, fixed!(9)), (2, fixed!(1)), (3, fixed!(2)), (4, fixed!(4)), (5, fixed!(3))] {
+            history.push(Timestamp::from_nanos(ts), sample(spread));
+        }
+        // The first sample was overwritten
+        assert_eq!(history.len(), 4);
+        let expected = FieldSummary { samples: 4, min: fixed!(1), max: fixed!(4), mean: fixed!(2.5) };
+        assert_eq!(history.summary(MetricField::Spread, Duration::from_nanos(10)), Some(expected));
+        let expected = FieldSummary { samples: 2, min: fixed!(3), max: fixed!(4), mean: fixed!(3.5) };
+        assert_eq!(history.summary(MetricField::Spread, Duration::from_nanos(2)), Some(expected));
+
+        assert_eq!(history.percentile(MetricField::Spread, Duration::from_nanos(10), 50), Some(fixed!(2)));
+        assert_eq!(history.percentile(MetricField::Spread, Duration::from_nanos(10), 100), Some(fixed!(4)));
+        assert_eq!(history.percentile(MetricField::Spread, Duration::from_nanos(10), 0), Some(fixed!(1)));
+        assert_eq!(history.summary(MetricField::VwapBid, Duration::from_nanos(10)), None);
+    }
+}
@@ -0,0 +1,217 @@
+//! Iceberg order detection.
+//!
+//! An iceberg shows a fraction of its size and tops the level back up each time the shown part trades.
+//! [`IcebergDetector`] follows each event alongside the delta the book returned for it, remembers the
+//! size a level displayed when a trade hit it, and counts a refill when the next update of the level,
+//! no later than the latency threshold after the trade, shows at least that size again. A level that
+//! refills the threshold number of times in a row is flagged with an [`IcebergSuspect`] on that refill
+//! and every one after. An update that comes too late or falls short starts the count again.
+//!
+//! Resets and cleared sides forget their levels. Levels a BBO event removes implicitly are not reported
+//! as deltas, so they are kept until the price is reported again.
+
+use std::{collections::BTreeMap, ops::Add, time::Duration};
+
+use crate::{
+    books::delta::BookDelta, decimals::decimal_type::DecimalType, event::Event, event_kind::EventKind, side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A level that keeps refilling straight after it trades
+pub struct IcebergSuspect<V: DecimalType> {
+    pub side: Side,
+    pub price: V,
+    /// Refills in a row so far
+    pub refills: u32,
+    /// Size traded at the level across those refills
+    pub traded: V,
+    /// Size the level displays after the latest refill
+    pub displayed: V,
+    pub timestamp: Timestamp,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Hit<V> {
+    at: Timestamp,
+    /// Size the level displayed before the trade
+    displayed: V,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LevelState<V> {
+    displayed: V,
+    /// The trade waiting for the level to refill
+    hit: Option<Hit<V>>,
+    refills: u32,
+    traded: V,
+}
+
+#[derive(Debug)]
+pub struct IcebergDetector<V> {
+    min_refills: u32,
+    max_latency: Duration,
+    bids: BTreeMap<V, LevelState<V>>,
+    asks: BTreeMap<V, LevelState<V>>,
+    suspects: u64,
+}
+
+impl<V> IcebergDetector<V>
+where
+    V: DecimalType + Copy + Ord + Add<Output = V>,
+{
+    #[inline]
+    #[must_use]
+    /// Flag a level after `min_refills` refills in a row, each arriving within `max_latency` of the trade
+    /// before it
+    pub fn new(min_refills: u32, max_latency: Duration) -> Self {
+        Self { min_refills: min_refills.max(1), max_latency, bids: BTreeMap::new(), asks: BTreeMap::new(), suspects: 0 }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Suspects flagged so far, counting each refill of a flagged level
+    pub const fn suspects(&self) -> u64 {
+        self.suspects
+    }
+
+    #[inline]
+    #[must_use]
+    /// Refills in a row the level at `price` has shown, zero when it is not being followed
+    pub fn refills(&self, side: Side, price: V) -> u32 {
+        self.side(side).get(&price).map_or(0, |level| level.refills)
+    }
+
+    /// Follow `event` and the delta the book returned for it, returning a suspect when the event
+    /// refilled a level at least the threshold number of times in a row
+    pub fn on_event(&mut self, event: &Event<V>, delta: Option<&BookDelta<V>>) -> Option<IcebergSuspect<V>> {
+        let delta = delta?;
+        if delta.reset {
+            self.bids.clear();
+            self.asks.clear();
+        } else if delta.cleared_side {
+            self.side_mut(delta.side).clear();
+        }
+
+        if event.kind == EventKind::Trade {
+            // The trade may have emptied the level, which is kept to see whether it refills
+            let level = self.side_mut(delta.side).get_mut(&delta.price)?;
+            level.hit = Some(Hit { at: delta.timestamp, displayed: level.displayed });
+            level.traded = level.traded + event.size;
+            level.displayed = delta.size;
+            return None;
+        }
+
+        let (min_refills, max_latency) = (self.min_refills, self.max_latency);
+        let levels = self.side_mut(delta.side);
+        let Some(level) = levels.get_mut(&delta.price) else {
+            if !delta.is_removal() {
+                levels.insert(delta.price, LevelState { displayed: delta.size, hit: None, refills: 0, traded: V::ZERO });
+            }
+            return None;
+        };
+        let hit = level.hit.take();
+        level.displayed = delta.size;
+        let refilled = hit.is_some_and(|hit| {
+            delta.timestamp.duration_since(hit.at).is_some_and(|latency| latency <= max_latency) && delta.size >= hit.displayed
+        });
+        if !refilled {
+            if delta.is_removal() {
+                levels.remove(&delta.price);
+            } else if hit.is_some() {
+                (level.refills, level.traded) = (0, V::ZERO);
+            }
+            return None;
+        }
+        level.refills += 1;
+        if level.refills < min_refills {
+            return None;
+        }
+        let suspect = IcebergSuspect {
+            side: delta.side,
+            price: delta.price,
+            refills: level.refills,
+            traded: level.traded,
+            displayed: level.displayed,
+            timestamp: delta.timestamp,
+        };
+        self.suspects += 1;
+        Some(suspect)
+    }
+
+    #[inline(always)]
+    fn side(&self, side: Side) -> &BTreeMap<V, LevelState<V>> {
+        if side.is_buy() {
+            &self.bids
+        } else {
+            &self.asks
+        }
+    }
+
+    #[inline(always)]
+    fn side_mut(&mut self, side: Side) -> &mut BTreeMap<V, LevelState<V>> {
+        if side.is_buy() {
+            &mut self.bids
+        } else {
+            &mut self.asks
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        metrics::iceberg::{IcebergDetector, IcebergSuspect},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    struct Level {
+        book: BTreeOrderBook<FixedDecimal>,
+        detector: IcebergDetector<FixedDecimal>,
+    }
+
+    impl Level {
+        fn apply(&mut self, kind: EventKind, size: FixedDecimal, ms: i64) -> Option<IcebergSuspect<FixedDecimal>> {
+            let event = Event::new(kind, Side::Sell, fixed!(101), size, Timestamp::from_millis(ms));
+            let delta = self.book.process_delta(event);
+            self.detector.on_event(&event, delta.as_ref())
+        }
+    }
+
+    #[test]
+    fn test_flags_repeated_refills() {
+        let mut level = Level { book: BTreeOrderBook::new(), detector: IcebergDetector::new(2, Duration::from_millis(5)) };
+        assert_eq!(level.apply(EventKind::L2, fixed!(10), 0), None);
+
+        // The shown 10 trades and is back within 2ms, twice
+        assert_eq!(level.apply(EventKind::Trade, fixed!(10), 10), None);
+        assert_eq!(level.apply(EventKind::L2, fixed!(10), 12), None);
+        assert_eq!(level.apply(EventKind::Trade, fixed!(6), 20), None);
+        let suspect = level.apply(EventKind::L2, fixed!(10), 21).unwrap();
+        assert_eq!((suspect.refills, suspect.traded, suspect.displayed), (2, fixed!(16), fixed!(10)));
+        assert_eq!(suspect.timestamp, Timestamp::from_millis(21));
+
+        // A refill after the latency threshold starts the count again
+        assert_eq!(level.apply(EventKind::Trade, fixed!(10), 30), None);
+        assert_eq!(level.apply(EventKind::L2, fixed!(10), 40), None);
+        assert_eq!(level.detector.refills(Side::Sell, fixed!(101)), 0);
+
+        // A level that only shrinks after a trade is not refilling
+        assert_eq!(level.apply(EventKind::Trade, fixed!(10), 50), None);
+        assert_eq!(level.apply(EventKind::L2, fixed!(3), 51), None);
+        assert_eq!(level.apply(EventKind::Trade, fixed!(3), 52), None);
+        assert_eq!(level.apply(EventKind::L2, fixed!(10), 53), None);
+        assert_eq!(level.detector.refills(Side::Sell, fixed!(101)), 1);
+        assert_eq!(level.apply(EventKind::L2, fixed!(0), 60), None);
+        assert_eq!((level.detector.refills(Side::Sell, fixed!(101)), level.detector.suspects()), (0, 1));
+    }
+}
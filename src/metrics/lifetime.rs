@@ -0,0 +1,173 @@
+//! Quote lifetimes and flicker.
+//!
+//! [`QuoteLifetimes`] keeps the timestamp each price level appeared at, alongside the book rather than
+//! in [`Level`](crate::level::Level), and times the level when a [`BookDelta`] removes it. Only levels
+//! that were within the top `k` of their side when removed are counted, and those that lived for less
+//! than the flicker threshold are counted as flickers. Resets forget the live levels without timing
+//! them. Levels a BBO event removes implicitly are not reported as deltas, so they stay live until the
+//! price is reported again.
+
+use std::{
+    collections::BTreeMap,
+    ops::Bound::{Excluded, Unbounded},
+    time::Duration,
+};
+
+use crate::{books::delta::BookDelta, decimals::decimal_type::DecimalType, side::Side, timestamp::Timestamp};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LifetimeSummary {
+    /// Top `k` levels removed so far
+    pub removed: u64,
+    /// Mean lifetime of the removed levels
+    pub mean_lifetime: Option<Duration>,
+    /// Removed levels that lived for less than the flicker threshold
+    pub flickers: u64,
+    /// Share of the removed levels that flickered
+    pub flicker_rate: Option<f64>,
+}
+
+#[derive(Debug)]
+pub struct QuoteLifetimes<V> {
+    top: usize,
+    flicker_threshold: Duration,
+    bids: BTreeMap<V, Timestamp>,
+    asks: BTreeMap<V, Timestamp>,
+    removed: u64,
+    total_lifetime: Duration,
+    flickers: u64,
+}
+
+impl<V> QuoteLifetimes<V>
+where
+    V: DecimalType + Copy + Ord,
+{
+    #[inline]
+    #[must_use]
+    /// Time the top `top` levels of each side, flagging those removed within `flicker_threshold` of
+    /// appearing
+    pub fn new(top: usize, flicker_threshold: Duration) -> Self {
+        Self {
+            top,
+            flicker_threshold,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            removed: 0,
+            total_lifetime: Duration::ZERO,
+            flickers: 0,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Timestamp the level at `price` appeared at, `None` when it is not live
+    pub fn inserted_at(&self, side: Side, price: V) -> Option<Timestamp> {
+        self.side(side).get(&price).copied()
+    }
+
+    pub fn on_delta(&mut self, delta: &BookDelta<V>) {
+        if delta.reset {
+            self.bids.clear();
+            self.asks.clear();
+        } else if delta.cleared_side {
+            self.side_mut(delta.side).clear();
+        }
+
+        if !delta.is_removal() {
+            self.side_mut(delta.side).entry(delta.price).or_insert(delta.timestamp);
+            return;
+        }
+
+        let Some(inserted) = self.side_mut(delta.side).remove(&delta.price) else {
+            return;
+        };
+        // The level has gone, so its rank is the number of live levels priced better
+        let live = self.side(delta.side);
+        let better = match delta.side {
+            Side::Buy => live.range((Excluded(delta.price), Unbounded)).take(self.top).count(),
+            Side::Sell => live.range(..delta.price).take(self.top).count(),
+        };
+        if better < self.top {
+            let lifetime = delta.timestamp.saturating_duration_since(inserted);
+            self.removed += 1;
+            self.total_lifetime += lifetime;
+            if lifetime < self.flicker_threshold {
+                self.flickers += 1;
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn summary(&self) -> LifetimeSummary {
+        let (mean_lifetime, flicker_rate) = match self.removed {
+            0 => (None, None),
+            removed => (
+                Some(self.total_lifetime / u32::try_from(removed).unwrap_or(u32::MAX)),
+                Some(self.flickers as f64 / removed as f64),
+            ),
+        };
+        LifetimeSummary { removed: self.removed, mean_lifetime, flickers: self.flickers, flicker_rate }
+    }
+
+    #[inline(always)]
+    fn side(&self, side: Side) -> &BTreeMap<V, Timestamp> {
+        if side.is_buy() {
+            &self.bids
+        } else {
+            &self.asks
+        }
+    }
+
+    #[inline(always)]
+    fn side_mut(&mut self, side: Side) -> &mut BTreeMap<V, Timestamp> {
+        if side.is_buy() {
+            &mut self.bids
+        } else {
+            &mut self.asks
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        metrics::lifetime::QuoteLifetimes,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_lifetimes_and_flicker() {
+        let mut book = BTreeOrderBook::new();
+        let mut lifetimes = QuoteLifetimes::new(1, Duration::from_nanos(5));
+        let events = [
+            (Side::Buy, fixed!(99), fixed!(1), 10),
+            (Side::Buy, fixed!(98), fixed!(1), 11),
+            (Side::Buy, fixed!(99), fixed!(2), 12),
+            // Removed from the top after 20
+            (Side::Buy, fixed!(99), fixed!(0), 30),
+            (Side::Buy, fixed!(100), fixed!(1), 31),
+            // Below the top, not counted
+            (Side::Buy, fixed!(98), fixed!(0), 32),
+            // Flickers after 2
+            (Side::Buy, fixed!(100), fixed!(0), 33),
+        ];
+        for (side, price, size, timestamp) in events {
+            let delta =
+                book.process_delta(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(timestamp))).unwrap();
+            lifetimes.on_delta(&delta);
+        }
+
+        let summary = lifetimes.summary();
+        assert_eq!((summary.removed, summary.mean_lifetime, summary.flickers), (2, Some(Duration::from_nanos(11)), 1));
+        assert_eq!(summary.flicker_rate, Some(0.5));
+        assert_eq!(lifetimes.inserted_at(Side::Buy, fixed!(99)), None);
+    }
+}
@@ -0,0 +1,305 @@
+pub mod anomaly;
+pub mod cross;
+pub mod distribution;
+pub mod execution;
+pub mod flow;
+pub mod history;
+pub mod iceberg;
+pub mod lifetime;
+pub mod resiliency;
+pub mod rolling;
+pub mod surveillance;
+pub mod volatility;
+
+use std::{
+    iter::Sum,
+    ops::{Add, ControlFlow, Div, Mul, Sub},
+};
+
+use crate::{decimals::decimal_type::DecimalType, level::Level, metrics::distribution::SizeDistribution, side::Side};
+
+#[derive(Debug, Clone, Copy)]
+/// Metrics of one book, each `None` when it was not requested or the book cannot produce it, as when a
+/// side is empty
+pub struct OrderbookMetrics<V: DecimalType> {
+    /// Quote imbalance ratio (-1 to 1), positive values indicate more bids
+    pub quote_imbalance: Option<V>,
+    /// Mid price between best bid and ask
+    pub mid_price: Option<V>,
+    /// Absolute spread (ask - bid)
+    pub spread: Option<V>,
+    /// Spread as percentage of mid price
+    ///
+    /// The spread_percentage represents the bid-ask spread expressed
+    /// as a percentage of the mid price.
+    pub spread_percentage: Option<V>,
+    /// Volume-weighted average bid price over the requested depth
+    pub vwap_bid: Option<V>,
+    /// Volume-weighted average ask price over the requested depth
+    pub vwap_ask: Option<V>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Resting size and notional (price times size) within a distance of the mid price
+pub struct Liquidity<V: DecimalType> {
+    pub bid_size: V,
+    pub ask_size: V,
+    pub bid_notional: V,
+    pub ask_notional: V,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Expected cost of filling one target notional with a market order
+pub struct ImpactPoint<V: DecimalType> {
+    /// Notional requested
+    pub notional: V,
+    /// Notional the visible book could fill, short of `notional` when the side runs out
+    pub filled: V,
+    /// Size bought or sold to fill `filled`
+    pub size: V,
+    /// Volume-weighted fill price, zero when nothing could be filled
+    pub average_price: V,
+    /// Distance of the average price from the mid as a percentage of the mid, positive as a cost
+    pub slippage: V,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Selects the [`OrderbookMetrics`] fields to compute, the others are left as `None`.
+///
+/// ```
+/// use freya_ob::metrics::MetricsRequest;
+///
+/// let request = MetricsRequest::new().spread().imbalance();
+/// assert!(request.needs_depth());
+/// ```
+pub struct MetricsRequest(u8);
+
+impl MetricsRequest {
+    const MID_PRICE: u8 = 1;
+    const SPREAD: u8 = 1 << 1;
+    const SPREAD_PERCENTAGE: u8 = 1 << 2;
+    const IMBALANCE: u8 = 1 << 3;
+    const VWAP: u8 = 1 << 4;
+
+    #[inline]
+    #[must_use]
+    /// Request nothing, add fields with the builder methods
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn all() -> Self {
+        Self(Self::MID_PRICE | Self::SPREAD | Self::SPREAD_PERCENTAGE | Self::IMBALANCE | Self::VWAP)
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn mid_price(self) -> Self {
+        Self(self.0 | Self::MID_PRICE)
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn spread(self) -> Self {
+        Self(self.0 | Self::SPREAD)
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn spread_percentage(self) -> Self {
+        Self(self.0 | Self::SPREAD_PERCENTAGE)
+    }
+
+    #[inline]
+    #[must_use]
+    /// The notional-weighted quote imbalance over the depth
+    pub const fn imbalance(self) -> Self {
+        Self(self.0 | Self::IMBALANCE)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Both VWAP fields
+    pub const fn vwap(self) -> Self {
+        Self(self.0 | Self::VWAP)
+    }
+
+    #[inline(always)]
+    const fn has(self, flag: u8) -> bool {
+        self.0 & flag != 0
+    }
+
+    #[inline]
+    #[must_use]
+    /// Whether any requested field needs the levels beyond the touch
+    pub const fn needs_depth(self) -> bool {
+        self.has(Self::IMBALANCE | Self::VWAP)
+    }
+}
+
+impl Default for MetricsRequest {
+    #[inline]
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+// Shared implementation for metric calculation
+pub trait MetricsCalculator<V>
+where
+    V: DecimalType + Sub<Output = V> + Add<Output = V> + Mul<Output = V> + Div<Output = V> + PartialOrd + Sum + Copy,
+{
+    fn calculate_metrics_internal(
+        &self,
+        request: MetricsRequest,
+        bid_sizes: Vec<V>,
+        ask_sizes: Vec<V>,
+        bid_prices: Vec<V>,
+        ask_prices: Vec<V>,
+    ) -> OrderbookMetrics<V> {
+        let mut metrics = OrderbookMetrics {
+            quote_imbalance: None,
+            mid_price: None,
+            spread: None,
+            spread_percentage: None,
+            vwap_bid: None,
+            vwap_ask: None,
+        };
+
+        if let (Some(bid), Some(ask)) = (self.best_bid(), self.best_ask()) {
+            // Calculate mid price and spread
+            let (mid_price, spread) = ((bid.price + ask.price) / V::TWO, ask.price - bid.price);
+            if request.has(MetricsRequest::MID_PRICE) {
+                metrics.mid_price = Some(mid_price);
+            }
+            if request.has(MetricsRequest::SPREAD) {
+                metrics.spread = Some(spread);
+            }
+            // Calculate spread percentage
+            if request.has(MetricsRequest::SPREAD_PERCENTAGE) && mid_price > V::ZERO {
+                metrics.spread_percentage = Some(spread / mid_price * V::ONE_HUNDRED);
+            }
+        }
+
+        if request.needs_depth() {
+            let bid_value: V = bid_sizes.iter().zip(bid_prices.iter()).map(|(&size, &price)| size * price).sum();
+            let ask_value: V = ask_sizes.iter().zip(ask_prices.iter()).map(|(&size, &price)| size * price).sum();
+
+            // Calculate quote imbalance
+            let total_value = bid_value + ask_value;
+            if request.has(MetricsRequest::IMBALANCE) && total_value > V::ZERO {
+                metrics.quote_imbalance = Some((bid_value - ask_value) / total_value);
+            }
+
+            // Calculate volume-weighted average prices
+            if request.has(MetricsRequest::VWAP) {
+                let bid_volume: V = bid_sizes.iter().copied().sum();
+                let ask_volume: V = ask_sizes.iter().copied().sum();
+                metrics.vwap_bid = (bid_volume > V::ZERO).then(|| bid_value / bid_volume);
+                metrics.vwap_ask = (ask_volume > V::ZERO).then(|| ask_value / ask_volume);
+            }
+        }
+
+        metrics
+    }
+
+    /// Size and notional resting within `bps` basis points of the mid price, `None` unless both sides
+    /// are populated
+    fn liquidity_within_bps(&self, bps: V) -> Option<Liquidity<V>> {
+        let mid_price = (self.best_bid()?.price + self.best_ask()?.price) / V::TWO;
+        let distance = mid_price * bps / (V::ONE_HUNDRED * V::ONE_HUNDRED);
+        let (low, high) = (mid_price - distance, mid_price + distance);
+        let mut liquidity = Liquidity { bid_size: V::ZERO, ask_size: V::ZERO, bid_notional: V::ZERO, ask_notional: V::ZERO };
+        self.walk_levels(Side::Buy, &mut |level| {
+            if level.price < low {
+                return ControlFlow::Break(());
+            }
+            liquidity.bid_size = liquidity.bid_size + level.size;
+            liquidity.bid_notional = liquidity.bid_notional + level.price * level.size;
+            ControlFlow::Continue(())
+        });
+        self.walk_levels(Side::Sell, &mut |level| {
+            if level.price > high {
+                return ControlFlow::Break(());
+            }
+            liquidity.ask_size = liquidity.ask_size + level.size;
+            liquidity.ask_notional = liquidity.ask_notional + level.price * level.size;
+            ControlFlow::Continue(())
+        });
+        Some(liquidity)
+    }
+
+    /// Walk the book for a market order on `side` (a buy consumes asks) once per target notional,
+    /// `None` unless both sides are populated
+    fn impact_curve(&self, side: Side, notionals: &[V]) -> Option<Vec<ImpactPoint<V>>> {
+        let mid_price = (self.best_bid()?.price + self.best_ask()?.price) / V::TWO;
+        let curve = notionals
+            .iter()
+            .map(|&notional| {
+                let (mut filled, mut size) = (V::ZERO, V::ZERO);
+                self.walk_levels(side.opposite(), &mut |level| {
+                    let level_notional = level.price * level.size;
+                    if filled + level_notional >= notional {
+                        size = size + (notional - filled) / level.price;
+                        filled = notional;
+                        return ControlFlow::Break(());
+                    }
+                    filled = filled + level_notional;
+                    size = size + level.size;
+                    ControlFlow::Continue(())
+                });
+                let average_price = if size > V::ZERO { filled / size } else { V::ZERO };
+                let slippage = match side {
+                    _ if size == V::ZERO => V::ZERO,
+                    Side::Buy => (average_price - mid_price) / mid_price * V::ONE_HUNDRED,
+                    Side::Sell => (mid_price - average_price) / mid_price * V::ONE_HUNDRED,
+                };
+                ImpactPoint { notional, filled, size, average_price, slippage }
+            })
+            .collect();
+        Some(curve)
+    }
+
+    /// Distribution of the level sizes over up to `depth` populated levels of one side, `None` when it is
+    /// empty
+    fn size_distribution(&self, side: Side, depth: usize) -> Option<SizeDistribution<V>> {
+        let mut sizes = Vec::with_capacity(depth);
+        self.walk_levels(side, &mut |level| {
+            if sizes.len() == depth {
+                return ControlFlow::Break(());
+            }
+            sizes.push(level.size);
+            ControlFlow::Continue(())
+        });
+        SizeDistribution::from_sizes(sizes)
+    }
+
+    fn best_bid(&self) -> Option<Level<V>>;
+    fn best_ask(&self) -> Option<Level<V>>;
+    /// Visit the populated levels of one side, best price first, until `visit` breaks
+    fn walk_levels(&self, side: Side, visit: &mut dyn FnMut(Level<V>) -> ControlFlow<()>);
+}
+
+// Newton iterations are capped, the square root converges well before this for any realistic variance
+const MAX_SQRT_ITERATIONS: usize = 64;
+
+/// Square root by Newton's method, rounded to the precision of the decimal backend
+pub(crate) fn sqrt<V>(value: V) -> V
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Div<Output = V>,
+{
+    if value <= V::ZERO {
+        return V::ZERO;
+    }
+    let mut root = if value > V::ONE { value } else { V::ONE };
+    for _ in 0..MAX_SQRT_ITERATIONS {
+        let next = (root + value / root) / V::TWO;
+        if next >= root {
+            break;
+        }
+        root = next;
+    }
+    root
+}
@@ -0,0 +1,186 @@
+//! Resiliency of the touch.
+//!
+//! After a trade consumes depth at the touch, [`Resiliency`] waits for the best level on that side to
+//! hold a target fraction of the size it held before the trade and records how long that took. The
+//! best level is followed wherever its price moves, so depth refilled one tick back also counts.
+//! Trades while a side is still recovering keep the original target, and the reported figure is the
+//! mean recovery time of the last recoveries.
+
+use std::{collections::VecDeque, ops::Mul, time::Duration};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Pending<V> {
+    since: Timestamp,
+    target: V,
+}
+
+#[derive(Debug)]
+pub struct Resiliency<V> {
+    fraction: V,
+    window: usize,
+    bid: Option<Pending<V>>,
+    ask: Option<Pending<V>>,
+    recoveries: VecDeque<Duration>,
+    total: Duration,
+}
+
+impl<V> Resiliency<V>
+where
+    V: DecimalType + Copy + PartialOrd + Mul<Output = V>,
+{
+    #[inline]
+    #[must_use]
+    /// Wait for `fraction` (0 to 1) of the pre-trade touch size, averaging over the last `window`
+    /// recoveries
+    pub fn new(fraction: V, window: usize) -> Self {
+        Self { fraction, window: window.max(1), bid: None, ask: None, recoveries: VecDeque::new(), total: Duration::ZERO }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Whether `side` is waiting to recover from a trade
+    pub fn is_recovering(&self, side: Side) -> bool {
+        self.pending(side).is_some()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Mean time to recover over the window, `None` before the first recovery
+    pub fn mean_recovery(&self) -> Option<Duration> {
+        (!self.recoveries.is_empty()).then(|| self.total / u32::try_from(self.recoveries.len()).unwrap_or(u32::MAX))
+    }
+
+    /// Record a trade against the touch of `book`.
+    ///
+    /// Call before the book applies the trade so its touch is the pre-trade one; the trade side is the
+    /// resting side that was consumed. Other event kinds are ignored.
+    pub fn on_trade<B: OrderBook<V>>(&mut self, book: &mut B, trade: &Event<V>) {
+        if trade.kind != EventKind::Trade || self.is_recovering(trade.side) {
+            return;
+        }
+        let best = if trade.side.is_buy() { book.best_bid() } else { book.best_ask() };
+        if let Some(best) = best {
+            let target = best.size * self.fraction;
+            *self.pending_mut(trade.side) = Some(Pending { since: trade.timestamp, target });
+        }
+    }
+
+    /// Check the side `delta` touched of `book`, after the book applied it
+    pub fn on_delta<B: OrderBook<V>>(&mut self, book: &mut B, delta: &BookDelta<V>) {
+        if delta.reset {
+            (self.bid, self.ask) = (None, None);
+            return;
+        }
+        if delta.cleared_side {
+            *self.pending_mut(delta.side) = None;
+            return;
+        }
+        let Some(pending) = self.pending(delta.side) else {
+            return;
+        };
+        let best = if delta.side.is_buy() { book.best_bid() } else { book.best_ask() };
+        if best.is_some_and(|best| best.size >= pending.target) {
+            *self.pending_mut(delta.side) = None;
+            let recovery = delta.timestamp.saturating_duration_since(pending.since);
+            self.recoveries.push_back(recovery);
+            self.total += recovery;
+            if self.recoveries.len() > self.window {
+                self.total -= self.recoveries.pop_front().expect("window is not empty");
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn pending(&self, side: Side) -> Option<Pending<V>> {
+        if side.is_buy() {
+            self.bid
+        } else {
+            self.ask
+        }
+    }
+
+    #[inline(always)]
+    fn pending_mut(&mut self, side: Side) -> &mut Option<Pending<V>> {
+        if side.is_buy() {
+            &mut self.bid
+        } else {
+            &mut self.ask
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        metrics::resiliency::Resiliency,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn apply(book: &mut BTreeOrderBook<FixedDecimal>, resiliency: &mut Resiliency<FixedDecimal>, event: Event<FixedDecimal>) {
+        resiliency.on_trade(book, &event);
+        let delta = book.process_delta(event).unwrap();
+        resiliency.on_delta(book, &delta);
+    }
+
+    #[test]
+    fn test_recovery_time() {
+        let mut book = BTreeOrderBook::new();
+        let mut resiliency = Resiliency::new(fixed!(0.8), 10);
+        apply(
+            &mut book,
+            &mut resiliency,
+            Event::new(EventKind::L2, Side::Sell, fixed!(101), fixed!(10), Timestamp::from_nanos(1)),
+        );
+        apply(
+            &mut book,
+            &mut resiliency,
+            Event::new(EventKind::Trade, Side::Sell, fixed!(101), fixed!(6), Timestamp::from_nanos(2)),
+        );
+        assert!(resiliency.is_recovering(Side::Sell));
+
+        // 7 is short of the 8 target, 9 is enough
+        apply(
+            &mut book,
+            &mut resiliency,
+            Event::new(EventKind::L2, Side::Sell, fixed!(101), fixed!(7), Timestamp::from_nanos(5)),
+        );
+        apply(
+            &mut book,
+            &mut resiliency,
+            Event::new(EventKind::L2, Side::Sell, fixed!(101), fixed!(9), Timestamp::from_nanos(12)),
+        );
+        assert!(!resiliency.is_recovering(Side::Sell));
+        assert_eq!(resiliency.mean_recovery(), Some(Duration::from_nanos(10)));
+
+        // Clearing the level moves the touch, which is already deep enough
+        apply(
+            &mut book,
+            &mut resiliency,
+            Event::new(EventKind::L2, Side::Sell, fixed!(102), fixed!(20), Timestamp::from_nanos(13)),
+        );
+        apply(
+            &mut book,
+            &mut resiliency,
+            Event::new(EventKind::Trade, Side::Sell, fixed!(101), fixed!(9), Timestamp::from_nanos(14)),
+        );
+        assert_eq!(resiliency.mean_recovery(), Some(Duration::from_nanos(5)));
+    }
+}
@@ -0,0 +1,188 @@
+//! Incrementally smoothed top-of-book metrics.
+//!
+//! [`RollingMetrics`] samples the touch after every [`BookDelta`] and keeps running averages of the
+//! spread, the top-level size imbalance `(bid_size - ask_size) / (bid_size + ask_size)`, the simple
+//! mid return and its square. The mean squared return is the variance estimator; its square root, the
+//! volatility, is left to the caller since the decimal backends have no exact square root.
+
+use std::{
+    collections::VecDeque,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    level::Level,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Smoothing<V> {
+    /// Exponentially weighted, each sample moves the average by `alpha` of its distance
+    Ewma { alpha: V },
+    /// Arithmetic mean of the last `n` samples
+    Window(usize),
+}
+
+#[derive(Debug, Clone)]
+/// A running average under a [`Smoothing`]
+pub(crate) enum Average<V> {
+    Ewma { alpha: V, value: Option<V> },
+    Window { size: usize, samples: VecDeque<V>, sum: V },
+}
+
+impl<V> Average<V>
+where
+    V: DecimalType + Copy + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V>,
+{
+    pub(crate) fn new(smoothing: Smoothing<V>) -> Self {
+        match smoothing {
+            Smoothing::Ewma { alpha } => Self::Ewma { alpha, value: None },
+            Smoothing::Window(size) => Self::Window { size: size.max(1), samples: VecDeque::new(), sum: V::ZERO },
+        }
+    }
+
+    pub(crate) fn push(&mut self, sample: V) {
+        match self {
+            Self::Ewma { alpha, value } => {
+                *value = Some(value.map_or(sample, |value| value + *alpha * (sample - value)));
+            }
+            Self::Window { size, samples, sum } => {
+                samples.push_back(sample);
+                *sum = *sum + sample;
+                if samples.len() > *size {
+                    *sum = *sum - samples.pop_front().expect("window is not empty");
+                }
+            }
+        }
+    }
+
+    pub(crate) fn value(&self) -> Option<V> {
+        match self {
+            Self::Ewma { value, .. } => *value,
+            Self::Window { samples, .. } if samples.is_empty() => None,
+            Self::Window { samples, sum, .. } => Some(*sum / V::from_scaled(samples.len() as i64, 0)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Current averages, each `None` until it has a sample
+pub struct RollingSnapshot<V> {
+    pub spread: Option<V>,
+    pub imbalance: Option<V>,
+    pub mid_return: Option<V>,
+    pub return_variance: Option<V>,
+    /// Touches sampled so far
+    pub samples: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RollingMetrics<V: DecimalType> {
+    spread: Average<V>,
+    imbalance: Average<V>,
+    mid_return: Average<V>,
+    return_variance: Average<V>,
+    last_mid: Option<V>,
+    samples: u64,
+}
+
+impl<V> RollingMetrics<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V>,
+{
+    #[inline]
+    #[must_use]
+    pub fn new(smoothing: Smoothing<V>) -> Self {
+        Self {
+            spread: Average::new(smoothing),
+            imbalance: Average::new(smoothing),
+            mid_return: Average::new(smoothing),
+            return_variance: Average::new(smoothing),
+            last_mid: None,
+            samples: 0,
+        }
+    }
+
+    #[inline]
+    /// Sample `book` after it applied `delta`
+    pub fn on_delta<B: OrderBook<V>>(&mut self, book: &mut B, _delta: &BookDelta<V>) {
+        let (best_bid, best_ask) = (book.best_bid(), book.best_ask());
+        self.update(best_bid, best_ask);
+    }
+
+    /// Sample the touch, skipped while either side is empty
+    pub fn update(&mut self, best_bid: Option<Level<V>>, best_ask: Option<Level<V>>) {
+        let (Some(bid), Some(ask)) = (best_bid, best_ask) else {
+            return;
+        };
+        self.samples += 1;
+        self.spread.push(ask.price - bid.price);
+        let depth = bid.size + ask.size;
+        if depth > V::ZERO {
+            self.imbalance.push((bid.size - ask.size) / depth);
+        }
+        let mid = (bid.price + ask.price) / V::TWO;
+        if let Some(last_mid) = self.last_mid.filter(|&last_mid| last_mid > V::ZERO) {
+            let mid_return = (mid - last_mid) / last_mid;
+            self.mid_return.push(mid_return);
+            self.return_variance.push(mid_return * mid_return);
+        }
+        self.last_mid = Some(mid);
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> RollingSnapshot<V> {
+        RollingSnapshot {
+            spread: self.spread.value(),
+            imbalance: self.imbalance.value(),
+            mid_return: self.mid_return.value(),
+            return_variance: self.return_variance.value(),
+            samples: self.samples,
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        level::Level,
+        metrics::rolling::{RollingMetrics, Smoothing},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_window_averages() {
+        let mut rolling = RollingMetrics::new(Smoothing::Window(2));
+        let level = |price, size| Some(Level::new(price, size));
+        rolling.update(level(fixed!(99), fixed!(3)), level(fixed!(101), fixed!(1)));
+        rolling.update(level(fixed!(109), fixed!(1)), level(fixed!(111), fixed!(1)));
+        rolling.update(level(fixed!(98.5), fixed!(1)), level(fixed!(99.5), fixed!(3)));
+
+        let snapshot = rolling.snapshot();
+        assert_eq!(snapshot.samples, 3);
+        // Only the last two spreads (2 and 1) and imbalances (0 and -0.5) remain
+        assert_eq!((snapshot.spread, snapshot.imbalance), (Some(fixed!(1.5)), Some(fixed!(-0.25))));
+        // Returns of +10% and -10%
+        assert_eq!((snapshot.mid_return, snapshot.return_variance), (Some(fixed!(0)), Some(fixed!(0.01))));
+    }
+
+    #[test]
+    fn test_ewma_from_deltas() {
+        let mut rolling = RollingMetrics::new(Smoothing::Ewma { alpha: fixed!(0.5) });
+        let mut book = BTreeOrderBook::new();
+        for (side, price) in [(Side::Buy, fixed!(99)), (Side::Sell, fixed!(101)), (Side::Sell, fixed!(100))] {
+            let delta = book.process_delta(Event::new(EventKind::L2, side, price, fixed!(1), Timestamp::from_nanos(1))).unwrap();
+            rolling.on_delta(&mut book, &delta);
+        }
+        // The first delta leaves the book one-sided, then spreads of 2 and 1
+        let snapshot = rolling.snapshot();
+        assert_eq!((snapshot.samples, snapshot.spread), (2, Some(fixed!(1.5))));
+    }
+}
@@ -0,0 +1,281 @@
+//! Layering and spoofing surveillance.
+//!
+//! A spoofer rests large size away from the touch on one side to lean on the price, trades on the other
+//! side, then pulls the size before it can be hit. [`SpoofingDetector`] follows each event alongside
+//! the delta the book returned for it and tracks every level that grows to at least the size threshold
+//! while at least the distance threshold behind the best price of its side. When such a level is
+//! cancelled back below the size threshold shortly after an execution against the opposite side, and
+//! within the lifetime threshold of appearing, it raises a [`SpoofingAlert`]. A tracked level that
+//! trades is taken as genuine and no longer followed.
+//!
+//! Trades are reported on the resting side, so an execution against the bids is a trade on the buy
+//! side. Resets and cleared sides forget their levels.
+
+use std::{
+    collections::BTreeMap,
+    ops::{Add, Sub},
+    time::Duration,
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What makes a cancelled level suspicious, see the [module documentation](self)
+pub struct SpoofingThresholds<V: DecimalType> {
+    /// Size a level must reach to be followed
+    pub min_size: V,
+    /// How far behind the best price of its side, in price units, a level must rest to be followed
+    pub min_distance: V,
+    /// Longest a level may have rested before it was cancelled
+    pub max_lifetime: Duration,
+    /// Longest after an opposite side execution the cancel may come
+    pub max_after_execution: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A large level away from the touch pulled shortly after the other side traded
+pub struct SpoofingAlert<V: DecimalType> {
+    pub side: Side,
+    pub price: V,
+    /// Largest size the level showed
+    pub size: V,
+    pub placed_at: Timestamp,
+    pub cancelled_at: Timestamp,
+    /// Time of the latest execution against the opposite side before the cancel
+    pub execution_at: Timestamp,
+    /// Size traded by that execution
+    pub execution_size: V,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// What a [`SpoofingDetector`] has seen so far
+pub struct SpoofingReport<V: DecimalType> {
+    /// Levels that grew large enough far enough from the touch to be followed
+    pub large_levels: u64,
+    /// Followed levels cancelled, suspicious or not
+    pub cancelled: u64,
+    pub alerts: Vec<SpoofingAlert<V>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Large<V> {
+    size: V,
+    placed_at: Timestamp,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Execution<V> {
+    at: Timestamp,
+    size: V,
+}
+
+#[derive(Debug)]
+pub struct SpoofingDetector<V: DecimalType> {
+    thresholds: SpoofingThresholds<V>,
+    bids: BTreeMap<V, Large<V>>,
+    asks: BTreeMap<V, Large<V>>,
+    /// Latest execution against the bids and against the asks
+    bid_execution: Option<Execution<V>>,
+    ask_execution: Option<Execution<V>>,
+    large_levels: u64,
+    cancelled: u64,
+    alerts: Vec<SpoofingAlert<V>>,
+}
+
+impl<V> SpoofingDetector<V>
+where
+    V: DecimalType + Copy + Ord + Add<Output = V> + Sub<Output = V>,
+{
+    #[inline]
+    #[must_use]
+    pub fn new(thresholds: SpoofingThresholds<V>) -> Self {
+        Self {
+            thresholds,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            bid_execution: None,
+            ask_execution: None,
+            large_levels: 0,
+            cancelled: 0,
+            alerts: Vec::new(),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn thresholds(&self) -> &SpoofingThresholds<V> {
+        &self.thresholds
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn alerts(&self) -> &[SpoofingAlert<V>] {
+        &self.alerts
+    }
+
+    #[must_use]
+    pub fn report(&self) -> SpoofingReport<V> {
+        SpoofingReport { large_levels: self.large_levels, cancelled: self.cancelled, alerts: self.alerts.clone() }
+    }
+
+    /// Follow `event` and the delta `book` returned for it, after the book applied it, returning an
+    /// alert when the event cancelled a suspicious level
+    pub fn on_event<B: OrderBook<V>>(
+        &mut self,
+        book: &mut B,
+        event: &Event<V>,
+        delta: Option<&BookDelta<V>>,
+    ) -> Option<SpoofingAlert<V>> {
+        let delta = delta?;
+        if delta.reset {
+            self.bids.clear();
+            self.asks.clear();
+        } else if delta.cleared_side {
+            self.levels_mut(delta.side).clear();
+        }
+
+        if event.kind == EventKind::Trade {
+            let execution = Some(Execution { at: delta.timestamp, size: event.size });
+            if delta.side.is_buy() {
+                self.bid_execution = execution;
+            } else {
+                self.ask_execution = execution;
+            }
+            // A large level that trades is genuine liquidity
+            self.levels_mut(delta.side).remove(&delta.price);
+            return None;
+        }
+
+        let min_size = self.thresholds.min_size;
+        if delta.size >= min_size {
+            if let Some(large) = self.levels_mut(delta.side).get_mut(&delta.price) {
+                large.size = large.size.max(delta.size);
+            } else if self.is_far(book, delta) {
+                self.levels_mut(delta.side).insert(delta.price, Large { size: delta.size, placed_at: delta.timestamp });
+                self.large_levels += 1;
+            }
+            return None;
+        }
+
+        let large = self.levels_mut(delta.side).remove(&delta.price)?;
+        self.cancelled += 1;
+        let execution = if delta.side.is_buy() { self.ask_execution } else { self.bid_execution }?;
+        let within = |from: Timestamp, limit: Duration| delta.timestamp.duration_since(from).is_some_and(|gap| gap <= limit);
+        let suspicious = execution.at >= large.placed_at
+            && within(execution.at, self.thresholds.max_after_execution)
+            && within(large.placed_at, self.thresholds.max_lifetime);
+        if !suspicious {
+            return None;
+        }
+        let alert = SpoofingAlert {
+            side: delta.side,
+            price: delta.price,
+            size: large.size,
+            placed_at: large.placed_at,
+            cancelled_at: delta.timestamp,
+            execution_at: execution.at,
+            execution_size: execution.size,
+        };
+        self.alerts.push(alert);
+        Some(alert)
+    }
+
+    /// Whether the level of `delta` rests at least the distance threshold behind the best price of its
+    /// side, a level at the touch never does
+    fn is_far<B: OrderBook<V>>(&self, book: &mut B, delta: &BookDelta<V>) -> bool {
+        let best = if delta.side.is_buy() { book.best_bid() } else { book.best_ask() };
+        let Some(best) = best else {
+            return false;
+        };
+        let distance = if delta.side.is_buy() { best.price - delta.price } else { delta.price - best.price };
+        distance > V::ZERO && distance >= self.thresholds.min_distance
+    }
+
+    #[inline(always)]
+    fn levels_mut(&mut self, side: Side) -> &mut BTreeMap<V, Large<V>> {
+        if side.is_buy() {
+            &mut self.bids
+        } else {
+            &mut self.asks
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        metrics::surveillance::{SpoofingAlert, SpoofingDetector, SpoofingThresholds},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    struct Surveilled {
+        book: BTreeOrderBook<FixedDecimal>,
+        detector: SpoofingDetector<FixedDecimal>,
+    }
+
+    impl Surveilled {
+        fn apply(
+            &mut self,
+            kind: EventKind,
+            side: Side,
+            price: FixedDecimal,
+            size: FixedDecimal,
+            ms: i64,
+        ) -> Option<SpoofingAlert<FixedDecimal>> {
+            let event = Event::new(kind, side, price, size, Timestamp::from_millis(ms));
+            let delta = self.book.process_delta(event);
+            self.detector.on_event(&mut self.book, &event, delta.as_ref())
+        }
+    }
+
+    #[test]
+    fn test_flags_layering() {
+        let thresholds = SpoofingThresholds {
+            min_size: fixed!(50),
+            min_distance: fixed!(2),
+            max_lifetime: Duration::from_millis(100),
+            max_after_execution: Duration::from_millis(20),
+        };
+        let mut book = Surveilled { book: BTreeOrderBook::new(), detector: SpoofingDetector::new(thresholds) };
+        book.apply(EventKind::L2, Side::Buy, fixed!(99), fixed!(5), 0);
+        book.apply(EventKind::L2, Side::Sell, fixed!(100), fixed!(5), 0);
+        // Large at the touch is not followed, large three ticks back is
+        book.apply(EventKind::L2, Side::Sell, fixed!(100), fixed!(60), 1);
+        book.apply(EventKind::L2, Side::Sell, fixed!(103), fixed!(80), 2);
+        book.apply(EventKind::L2, Side::Sell, fixed!(103), fixed!(90), 3);
+
+        // Sellers hit the bids, then the asks are pulled
+        book.apply(EventKind::Trade, Side::Buy, fixed!(99), fixed!(3), 30);
+        let alert = book.apply(EventKind::L2, Side::Sell, fixed!(103), fixed!(0), 40).unwrap();
+        assert_eq!((alert.side, alert.price, alert.size), (Side::Sell, fixed!(103), fixed!(90)));
+        assert_eq!((alert.placed_at, alert.execution_at), (Timestamp::from_millis(2), Timestamp::from_millis(30)));
+        assert_eq!(alert.execution_size, fixed!(3));
+
+        // Pulled too long after the execution, then a large level that trades
+        book.apply(EventKind::L2, Side::Sell, fixed!(104), fixed!(70), 41);
+        assert_eq!(book.apply(EventKind::L2, Side::Sell, fixed!(104), fixed!(10), 60), None);
+        book.apply(EventKind::L2, Side::Buy, fixed!(96), fixed!(70), 61);
+        book.apply(EventKind::Trade, Side::Buy, fixed!(96), fixed!(1), 62);
+        book.apply(EventKind::Trade, Side::Sell, fixed!(100), fixed!(1), 63);
+        assert_eq!(book.apply(EventKind::L2, Side::Buy, fixed!(96), fixed!(0), 64), None);
+
+        let report = book.detector.report();
+        assert_eq!((report.large_levels, report.cancelled, report.alerts.len()), (3, 2, 1));
+    }
+}
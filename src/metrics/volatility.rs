@@ -0,0 +1,176 @@
+//! Realized volatility of the mid price.
+//!
+//! Every change of the mid contributes its squared simple return `((m - m') / m')^2`. The realized
+//! variance is the sum of the contributions in a trailing [`Window`], kept incrementally, and the
+//! realized volatility is its square root. Simple returns stand in for log returns, the two agree to
+//! first order at tick scale and the decimal backends have no logarithm.
+//!
+//! Returns are squared and summed in `f64`: a one-tick move on a large price is a return of around
+//! `1e-7`, whose square is below the last place of the fixed-point backend.
+
+use std::{
+    collections::VecDeque,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    level::Level,
+    ofi::Window,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug)]
+pub struct RealizedVolatility<V> {
+    window: Window,
+    mid_price: Option<V>,
+    squared_returns: VecDeque<(Timestamp, f64)>,
+    variance: f64,
+}
+
+impl<V> RealizedVolatility<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V>,
+{
+    #[inline]
+    #[must_use]
+    pub fn new(window: Window) -> Self {
+        Self { window, mid_price: None, squared_returns: VecDeque::new(), variance: 0.0 }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Sum of the squared mid returns in the window as of the last update
+    pub const fn variance(&self) -> f64 {
+        self.variance
+    }
+
+    #[inline]
+    #[must_use]
+    /// Square root of [`Self::variance`]
+    pub fn volatility(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Number of mid changes currently in the window
+    pub fn len(&self) -> usize {
+        self.squared_returns.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.squared_returns.is_empty()
+    }
+
+    #[inline]
+    /// Update from `book` after it applied `delta`
+    pub fn on_delta<B: OrderBook<V>>(&mut self, book: &mut B, delta: &BookDelta<V>) -> f64 {
+        let (best_bid, best_ask) = (book.best_bid(), book.best_ask());
+        self.update(best_bid, best_ask, delta.timestamp)
+    }
+
+    /// Record the touch as of `ts`, returning the realized variance over the window. A one-sided touch
+    /// has no mid and leaves the last one in place.
+    pub fn update(&mut self, best_bid: Option<Level<V>>, best_ask: Option<Level<V>>, ts: Timestamp) -> f64 {
+        if let (Some(bid), Some(ask)) = (best_bid, best_ask) {
+            let mid_price = (bid.price + ask.price) / V::TWO;
+            match self.mid_price {
+                Some(previous) if previous != mid_price && previous > V::ZERO => {
+                    let simple_return = (mid_price - previous).to_f64() / previous.to_f64();
+                    let squared = simple_return * simple_return;
+                    self.squared_returns.push_back((ts, squared));
+                    self.variance += squared;
+                }
+                _ => {}
+            }
+            self.mid_price = Some(mid_price);
+        }
+        self.expire(ts);
+        self.variance
+    }
+
+    fn expire(&mut self, ts: Timestamp) {
+        while let Some(&(oldest, squared)) = self.squared_returns.front() {
+            let expired = match self.window {
+                Window::Time(span) => oldest <= ts - span,
+                Window::Events(count) => self.squared_returns.len() > count,
+            };
+            if !expired {
+                break;
+            }
+            self.squared_returns.pop_front();
+            // Rounding left over from the running sum would otherwise linger once the window empties
+            self.variance = if self.squared_returns.is_empty() { 0.0 } else { (self.variance - squared).max(0.0) };
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        level::Level,
+        metrics::volatility::RealizedVolatility,
+        ofi::Window,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_time_window() {
+        let mut volatility = RealizedVolatility::new(Window::Time(Duration::from_nanos(10)));
+        let touch = |bid, ask| (Some(Level::new(bid, fixed!(1))), Some(Level::new(ask, fixed!(1))));
+        let (bid, ask) = touch(fixed!(99), fixed!(101));
+        volatility.update(bid, ask, Timestamp::ZERO);
+        // +10%, then -10%
+        let (bid, ask) = touch(fixed!(109), fixed!(111));
+        assert!((volatility.update(bid, ask, Timestamp::from_nanos(5)) - 0.01).abs() < 1e-12);
+        let (bid, ask) = touch(fixed!(98.5), fixed!(99.5));
+        assert!((volatility.update(bid, ask, Timestamp::from_nanos(12)) - 0.02).abs() < 1e-12);
+        assert!((volatility.volatility() - 0.02_f64.sqrt()).abs() < 1e-12);
+
+        // The first return leaves the window
+        assert!((volatility.update(bid, None, Timestamp::from_nanos(15)) - 0.01).abs() < 1e-12);
+        assert_eq!(volatility.len(), 1);
+        assert!((volatility.volatility() - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_from_deltas() {
+        let mut volatility = RealizedVolatility::new(Window::Events(5));
+        let mut book = BTreeOrderBook::new();
+        for (side, price, ts) in [(Side::Buy, fixed!(99), 1), (Side::Sell, fixed!(101), 2), (Side::Sell, fixed!(103), 3)] {
+            let delta = book.process_delta(Event::new(EventKind::L2, side, price, fixed!(1), Timestamp::from_nanos(ts))).unwrap();
+            volatility.on_delta(&mut book, &delta);
+        }
+        // The new ask sits behind 101, so the mid did not move
+        assert!(volatility.is_empty());
+
+        let delta =
+            book.process_delta(Event::new(EventKind::L2, Side::Sell, fixed!(101), fixed!(0), Timestamp::from_nanos(4))).unwrap();
+        // Mid 100 to 101
+        assert!((volatility.on_delta(&mut book, &delta) - 0.0001).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_one_tick_on_large_price() {
+        let mut volatility = RealizedVolatility::new(Window::Events(10));
+        let touch = |bid, ask| (Some(Level::new(bid, fixed!(1))), Some(Level::new(ask, fixed!(1))));
+        let (bid, ask) = touch(fixed!(59999.99), fixed!(60000.01));
+        volatility.update(bid, ask, Timestamp::ZERO);
+        // A cent on a 60,000 mid, whose squared return is below the last place of the decimal
+        let (bid, ask) = touch(fixed!(60000), fixed!(60000.02));
+        let expected = (0.01_f64 / 60_000.0).powi(2);
+        assert!((volatility.update(bid, ask, Timestamp::from_nanos(1)) - expected).abs() < expected * 1e-6);
+    }
+}
@@ -0,0 +1,211 @@
+//! Operational telemetry for live books, exported in the Prometheus text format.
+//!
+//! [`Instrumented`] wraps any [`OrderBook`] and counts events by kind, sequence gaps, dropped stale
+//! events and levels evicted at capacity as they pass through `process`, and tracks the spread in
+//! ticks at the touch. Depth is read from the book when rendering, so scrapes stay off the hot path.
+//!
+//! The exporter is written by hand rather than going through the `prometheus` crate or the `metrics`
+//! facade, so the `observability` feature pulls in no dependencies. The counters are plain fields of
+//! the wrapper rather than a global registry, and [`Instrumented::render`] returns the text for the
+//! caller to serve from its own scrape endpoint. Applications already running a `metrics` recorder
+//! can copy [`Instrumented::telemetry`] into it instead.
+
+use std::{
+    fmt::{Display, Write as _},
+    ops::{Div, Sub},
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    formats::{kind_name, side_name},
+    level::Level,
+    metrics::{MetricsRequest, OrderbookMetrics},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone)]
+pub struct BookTelemetry<V> {
+    /// Accepted events, indexed by `EventKind as usize`
+    pub events: [u64; EventKind::ALL.len()],
+    /// Events whose sequence ID skipped ahead of the last one seen
+    pub gaps: u64,
+    /// Events the book ignored as stale, out of sequence or order level
+    pub dropped: u64,
+    /// Levels a side of the book dropped because it was at capacity
+    pub evicted: u64,
+    /// Spread at the touch in ticks, `None` while either side is empty
+    pub spread_ticks: Option<V>,
+}
+
+#[derive(Debug)]
+pub struct Instrumented<V: DecimalType, B: OrderBook<V>> {
+    book: B,
+    tick_size: V,
+    telemetry: BookTelemetry<V>,
+    last_sequence_id: u64,
+}
+
+impl<V, B> Instrumented<V, B>
+where
+    V: DecimalType + Copy + Sub<Output = V> + Div<Output = V> + Display,
+    B: OrderBook<V>,
+{
+    #[inline]
+    #[must_use]
+    pub fn new(book: B, tick_size: V) -> Self {
+        Self {
+            book,
+            tick_size,
+            telemetry: BookTelemetry { events: [0; EventKind::ALL.len()], gaps: 0, dropped: 0, evicted: 0, spread_ticks: None },
+            last_sequence_id: 0,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn inner(&self) -> &B {
+        &self.book
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn telemetry(&self) -> &BookTelemetry<V> {
+        &self.telemetry
+    }
+
+    /// Render the telemetry in the Prometheus text exposition format, labelled with `symbol`
+    #[must_use]
+    pub fn render(&self, symbol: &str) -> String {
+        let mut out = String::new();
+        let telemetry = &self.telemetry;
+        let _ = writeln!(out, "# TYPE freya_ob_events_total counter");
+        for kind in EventKind::ALL {
+            let count = telemetry.events[kind as usize];
+            let _ = writeln!(out, r#"freya_ob_events_total{{symbol="{symbol}",kind="{}"}} {count}"#, kind_name(kind));
+        }
+        let _ = writeln!(out, "# TYPE freya_ob_sequence_gaps_total counter");
+        let _ = writeln!(out, r#"freya_ob_sequence_gaps_total{{symbol="{symbol}"}} {}"#, telemetry.gaps);
+        let _ = writeln!(out, "# TYPE freya_ob_dropped_events_total counter");
+        let _ = writeln!(out, r#"freya_ob_dropped_events_total{{symbol="{symbol}"}} {}"#, telemetry.dropped);
+        let _ = writeln!(out, "# TYPE freya_ob_evicted_levels_total counter");
+        let _ = writeln!(out, r#"freya_ob_evicted_levels_total{{symbol="{symbol}"}} {}"#, telemetry.evicted);
+        if let Some(spread) = telemetry.spread_ticks {
+            let _ = writeln!(out, "# TYPE freya_ob_spread_ticks gauge");
+            let _ = writeln!(out, r#"freya_ob_spread_ticks{{symbol="{symbol}"}} {spread}"#);
+        }
+        let _ = writeln!(out, "# TYPE freya_ob_depth_levels gauge");
+        for side in [Side::Buy, Side::Sell] {
+            let depth = self.book.levels(side, usize::MAX).len();
+            let _ = writeln!(out, r#"freya_ob_depth_levels{{symbol="{symbol}",side="{}"}} {depth}"#, side_name(side));
+        }
+        out
+    }
+}
+
+impl<V, B> OrderBook<V> for Instrumented<V, B>
+where
+    V: DecimalType + Copy + Sub<Output = V> + Div<Output = V> + Display,
+    B: OrderBook<V>,
+{
+    fn process_delta(&mut self, event: Event<V>) -> Option<BookDelta<V>> {
+        let (kind, sequence_id) = (event.kind, event.sequence_id);
+        if sequence_id != 0 && self.last_sequence_id != 0 && sequence_id > self.last_sequence_id + 1 {
+            self.telemetry.gaps += 1;
+        }
+        let Some(delta) = self.book.process_delta(event) else {
+            self.telemetry.dropped += 1;
+            return None;
+        };
+        if sequence_id != 0 {
+            self.last_sequence_id = sequence_id;
+        }
+        self.telemetry.events[kind as usize] += 1;
+        self.telemetry.evicted += u64::from(delta.evicted.is_some());
+        self.telemetry.spread_ticks = match (self.book.best_bid(), self.book.best_ask()) {
+            (Some(bid), Some(ask)) => Some((ask.price - bid.price) / self.tick_size),
+            _ => None,
+        };
+        Some(delta)
+    }
+
+    #[inline]
+    fn best_bid(&mut self) -> Option<Level<V>> {
+        self.book.best_bid()
+    }
+
+    #[inline]
+    fn best_ask(&mut self) -> Option<Level<V>> {
+        self.book.best_ask()
+    }
+
+    #[inline]
+    fn levels(&self, side: Side, depth: usize) -> Vec<Level<V>> {
+        self.book.levels(side, depth)
+    }
+
+    #[inline]
+    fn timestamp(&self) -> Timestamp {
+        self.book.timestamp()
+    }
+
+    #[inline]
+    fn sequence_id(&self) -> u64 {
+        self.book.sequence_id()
+    }
+
+    #[inline]
+    fn calculate_metrics_with(&self, depth: usize, request: MetricsRequest) -> OrderbookMetrics<V> {
+        self.book.calculate_metrics_with(depth, request)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        observability::Instrumented,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_counts_and_render() {
+        let mut book = Instrumented::new(BTreeOrderBook::new(), fixed!(0.5));
+        book.process(
+            Event::new(EventKind::Snapshot, Side::Buy, fixed!(100), fixed!(1), Timestamp::from_nanos(1)).with_sequence_id(1),
+        );
+        book.process(Event::new(EventKind::L2, Side::Sell, fixed!(101), fixed!(1), Timestamp::from_nanos(2)).with_sequence_id(4));
+        book.process(Event::new(EventKind::L2, Side::Sell, fixed!(102), fixed!(1), Timestamp::from_nanos(1)).with_sequence_id(5));
+        book.process(
+            Event::new(EventKind::Trade, Side::Sell, fixed!(101), fixed!(0.5), Timestamp::from_nanos(3)).with_sequence_id(5),
+        );
+
+        let telemetry = book.telemetry();
+        assert_eq!(telemetry.events, [1, 0, 1, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!((telemetry.gaps, telemetry.dropped, telemetry.evicted, telemetry.spread_ticks), (1, 1, 0, Some(fixed!(2))));
+
+        let text = book.render("BTC");
+        assert!(text.contains("freya_ob_events_total{symbol=\"BTC\",kind=\"l2\"} 1\n"));
+        assert!(text.contains("freya_ob_sequence_gaps_total{symbol=\"BTC\"} 1\n"));
+        assert!(text.contains("freya_ob_spread_ticks{symbol=\"BTC\"} 2\n"));
+        assert!(text.contains("freya_ob_depth_levels{symbol=\"BTC\",side=\"sell\"} 1\n"));
+    }
+
+    #[test]
+    fn test_counts_evictions() {
+        let mut book = Instrumented::new(ArrayOrderbook::<1, _>::new(), fixed!(1));
+        book.process(Event::new(EventKind::L2, Side::Sell, fixed!(101), fixed!(1), Timestamp::from_nanos(1)));
+        book.process(Event::new(EventKind::L2, Side::Sell, fixed!(100), fixed!(1), Timestamp::from_nanos(2)));
+        book.process(Event::new(EventKind::L2, Side::Sell, fixed!(102), fixed!(1), Timestamp::from_nanos(3)));
+        assert_eq!(book.telemetry().evicted, 2);
+        assert!(book.render("BTC").contains("freya_ob_evicted_levels_total{symbol=\"BTC\"} 2\n"));
+    }
+}
@@ -0,0 +1,188 @@
+//! Order flow imbalance (Cont, Kukanov and Stoikov, 2014).
+//!
+//! Every change at the touch contributes
+//!
+//! ```text
+//! e = 1{Pb >= Pb'} qb - 1{Pb <= Pb'} qb' - 1{Pa <= Pa'} qa + 1{Pa >= Pa'} qa'
+//! ```
+//!
+//! where primed values are the best bid/ask before the event. Adds at the bid and cancels or trades at
+//! the ask push OFI up, the opposite flows push it down. [`OrderFlowImbalance`] sums the contributions
+//! over a trailing window of time or events.
+
+use std::{
+    collections::VecDeque,
+    ops::{Add, Sub},
+    time::Duration,
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    level::Level,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    /// Contributions from the trailing span of time
+    Time(Duration),
+    /// Contributions from the trailing number of touch changes
+    Events(usize),
+}
+
+#[derive(Debug)]
+pub struct OrderFlowImbalance<V: DecimalType> {
+    window: Window,
+    best_bid: Option<Level<V>>,
+    best_ask: Option<Level<V>>,
+    contributions: VecDeque<(Timestamp, V)>,
+    total: V,
+}
+
+impl<V> OrderFlowImbalance<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V>,
+{
+    #[inline]
+    #[must_use]
+    pub fn new(window: Window) -> Self {
+        Self { window, best_bid: None, best_ask: None, contributions: VecDeque::new(), total: V::ZERO }
+    }
+
+    #[inline]
+    #[must_use]
+    /// OFI over the window as of the last update
+    pub const fn value(&self) -> V {
+        self.total
+    }
+
+    #[inline]
+    #[must_use]
+    /// Number of contributions currently in the window
+    pub fn len(&self) -> usize {
+        self.contributions.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.contributions.is_empty()
+    }
+
+    #[inline]
+    /// Update from `book` after it applied `delta`
+    pub fn on_delta<B: OrderBook<V>>(&mut self, book: &mut B, delta: &BookDelta<V>) -> V {
+        let (best_bid, best_ask) = (book.best_bid(), book.best_ask());
+        self.update(best_bid, best_ask, delta.timestamp)
+    }
+
+    /// Record the touch as of `ts`, returning OFI over the window
+    pub fn update(&mut self, best_bid: Option<Level<V>>, best_ask: Option<Level<V>>, ts: Timestamp) -> V {
+        let contribution = side_flow(self.best_bid, best_bid, true) - side_flow(self.best_ask, best_ask, false);
+        let moved = self.best_bid.map(|l| (l.price, l.size)) != best_bid.map(|l| (l.price, l.size))
+            || self.best_ask.map(|l| (l.price, l.size)) != best_ask.map(|l| (l.price, l.size));
+        (self.best_bid, self.best_ask) = (best_bid, best_ask);
+        if moved {
+            self.contributions.push_back((ts, contribution));
+            self.total = self.total + contribution;
+        }
+        self.expire(ts);
+        self.total
+    }
+
+    fn expire(&mut self, ts: Timestamp) {
+        while let Some(&(oldest, contribution)) = self.contributions.front() {
+            let expired = match self.window {
+                Window::Time(span) => oldest <= ts - span,
+                Window::Events(count) => self.contributions.len() > count,
+            };
+            if !expired {
+                break;
+            }
+            self.total = self.total - contribution;
+            self.contributions.pop_front();
+        }
+    }
+}
+
+/// Flow at one side of the touch, positive when liquidity was added on the bid or an ask was improved
+fn side_flow<V>(previous: Option<Level<V>>, current: Option<Level<V>>, is_bid: bool) -> V
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V>,
+{
+    let (price, size) = current.map_or((None, V::ZERO), |level| (Some(level.price), level.size));
+    let (previous_price, previous_size) = previous.map_or((None, V::ZERO), |level| (Some(level.price), level.size));
+    // A side appearing counts as an improvement and one disappearing as a retreat
+    let (improved, retreated) = match (price, previous_price) {
+        (Some(price), Some(previous_price)) if is_bid => (price >= previous_price, price <= previous_price),
+        (Some(price), Some(previous_price)) => (price <= previous_price, price >= previous_price),
+        (Some(_), None) => (true, false),
+        (None, Some(_)) => (false, true),
+        (None, None) => (false, false),
+    };
+    let added = if improved { size } else { V::ZERO };
+    let removed = if retreated { previous_size } else { V::ZERO };
+    added - removed
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        level::Level,
+        ofi::{OrderFlowImbalance, Window},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_contributions() {
+        let mut ofi = OrderFlowImbalance::new(Window::Events(10));
+        let level = |price, size| Some(Level::new(price, size));
+        ofi.update(level(fixed!(100), fixed!(5)), level(fixed!(101), fixed!(5)), Timestamp::from_nanos(1));
+        assert_eq!(ofi.value(), fixed!(0));
+        // Bid size grows at the same price
+        assert_eq!(ofi.update(level(fixed!(100), fixed!(8)), level(fixed!(101), fixed!(5)), Timestamp::from_nanos(2)), fixed!(3));
+        // Ask traded down by 2
+        assert_eq!(ofi.update(level(fixed!(100), fixed!(8)), level(fixed!(101), fixed!(3)), Timestamp::from_nanos(3)), fixed!(5));
+        // Bid retreats a tick, removing the 8 at 100 and adding 4 at 99.5 is not counted
+        assert_eq!(
+            ofi.update(level(fixed!(99.5), fixed!(4)), level(fixed!(101), fixed!(3)), Timestamp::from_nanos(4)),
+            fixed!(-3)
+        );
+        // Ask improves with 2, counted as selling pressure
+        assert_eq!(
+            ofi.update(level(fixed!(99.5), fixed!(4)), level(fixed!(100.5), fixed!(2)), Timestamp::from_nanos(5)),
+            fixed!(-5)
+        );
+        // Unchanged touch does not add a contribution
+        ofi.update(level(fixed!(99.5), fixed!(4)), level(fixed!(100.5), fixed!(2)), Timestamp::from_nanos(6));
+        assert_eq!(ofi.len(), 5);
+    }
+
+    #[test]
+    fn test_windows_from_book() {
+        let mut book = BTreeOrderBook::new();
+        let mut by_time = OrderFlowImbalance::new(Window::Time(Duration::from_nanos(10)));
+        let mut by_events = OrderFlowImbalance::new(Window::Events(1));
+        for (side, price, size, ts) in [
+            (Side::Buy, fixed!(100), fixed!(1), 0),
+            (Side::Buy, fixed!(100), fixed!(3), 5),
+            (Side::Buy, fixed!(100), fixed!(4), 12),
+        ] {
+            let delta = book.process_delta(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(ts))).unwrap();
+            by_time.on_delta(&mut book, &delta);
+            by_events.on_delta(&mut book, &delta);
+        }
+        // The first contribution (1 at ts 0) has left the time window
+        assert_eq!(by_time.value(), fixed!(3));
+        assert_eq!(by_events.value(), fixed!(1));
+    }
+}
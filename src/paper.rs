@@ -0,0 +1,268 @@
+//! Paper trading against a book.
+//!
+//! An [`Account`] takes the [`Fill`]s of simulated orders and keeps the position, its average entry
+//! price, the PnL realized by reducing it and the fees paid. Open positions are marked to the book,
+//! either at the mid or at the micro price, `(bid * ask size + ask * bid size) / (bid size + ask size)`,
+//! which leans towards the side more likely to trade through next. Fills carry their fee, which
+//! [`Fill::charged`] works out from a venue's [`FeeModel`]:
+//!
+//! ```
+//! # #[cfg(feature = "fixed_decimal")] {
+//! use freya_ob::{
+//!     decimals::fixed_decimal::FixedDecimal, fixed, paper::{Account, Fill, Mark}, side::Side,
+//!     sim::fees::{BasisPoints, Liquidity}, timestamp::Timestamp,
+//! };
+//!
+//! let mut account = Account::<FixedDecimal>::new(Mark::Mid);
+//! let fees = BasisPoints::new(fixed!(0), fixed!(10));
+//! account.on_fill(Fill::new(Side::Buy, fixed!(100), fixed!(2), Timestamp::from_nanos(1)).charged(&fees, Liquidity::Taker));
+//! account.on_fill(Fill::new(Side::Sell, fixed!(101), fixed!(1), Timestamp::from_nanos(2)).charged(&fees, Liquidity::Maker));
+//! account.mark_at(fixed!(102));
+//! assert_eq!((account.realized(), account.unrealized()), (fixed!(1), fixed!(2)));
+//! assert_eq!(account.pnl(), fixed!(2.8));
+//! # }
+//! ```
+
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::{
+    books::interface::OrderBook,
+    decimals::decimal_type::DecimalType,
+    side::Side,
+    sim::fees::{FeeModel, Liquidity},
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Part or all of an order of the account executing
+pub struct Fill<V: DecimalType> {
+    /// Side of the account's order, `Buy` when the account bought
+    pub side: Side,
+    pub price: V,
+    pub size: V,
+    /// Fee charged on the fill, negative for a rebate
+    pub fee: V,
+    pub timestamp: Timestamp,
+}
+
+impl<V: DecimalType> Fill<V> {
+    #[inline]
+    #[must_use]
+    /// A fill without a fee
+    pub fn new(side: Side, price: V, size: V, timestamp: Timestamp) -> Self {
+        Self { side, price, size, fee: V::ZERO, timestamp }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_fee(self, fee: V) -> Self {
+        Self { fee, ..self }
+    }
+}
+
+impl<V: DecimalType + Copy> Fill<V> {
+    #[inline]
+    #[must_use]
+    /// Charge the fill by a venue's fees, having added or taken `liquidity`
+    pub fn charged<F: FeeModel<V> + ?Sized>(self, fees: &F, liquidity: Liquidity) -> Self {
+        Self { fee: fees.fee(liquidity, self.price, self.size), ..self }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// The price an open position is valued at
+pub enum Mark {
+    #[default]
+    Mid,
+    /// The mid weighted by the size resting at the best level of each side
+    MicroPrice,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Position and PnL of a paper trading account in one instrument
+pub struct Account<V: DecimalType> {
+    mark: Mark,
+    /// Size held, negative when short
+    position: V,
+    /// Average entry price of the open position, zero when flat
+    average_price: V,
+    realized: V,
+    fees: V,
+    /// Latest mark price, `None` until the first mark
+    mark_price: Option<V>,
+    fills: u64,
+}
+
+impl<V> Account<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V>,
+{
+    #[inline]
+    #[must_use]
+    /// A flat account valuing positions at `mark`
+    pub fn new(mark: Mark) -> Self {
+        Self { mark, position: V::ZERO, average_price: V::ZERO, realized: V::ZERO, fees: V::ZERO, mark_price: None, fills: 0 }
+    }
+
+    /// Apply a fill, realizing the PnL of whatever part of it reduces the position. A fill that flips
+    /// the position opens the remainder at its price.
+    pub fn on_fill(&mut self, fill: Fill<V>) {
+        let (signed, opposite) = match fill.side {
+            Side::Buy => (fill.size, self.position < V::ZERO),
+            Side::Sell => (V::ZERO - fill.size, self.position > V::ZERO),
+        };
+        self.fees = self.fees + fill.fee;
+        self.fills += 1;
+        if !opposite {
+            let held = abs(self.position);
+            if held + fill.size > V::ZERO {
+                self.average_price = (self.average_price * held + fill.price * fill.size) / (held + fill.size);
+            }
+            self.position = self.position + signed;
+            return;
+        }
+        let closed = min(abs(self.position), fill.size);
+        let per_unit = if self.position > V::ZERO { fill.price - self.average_price } else { self.average_price - fill.price };
+        self.realized = self.realized + per_unit * closed;
+        self.position = self.position + signed;
+        if self.position == V::ZERO {
+            self.average_price = V::ZERO;
+        } else if closed < fill.size {
+            self.average_price = fill.price;
+        }
+    }
+
+    /// Mark the position to `book`, returning the mark price or `None`, keeping the last mark, while
+    /// either side is empty
+    pub fn mark_to<B: OrderBook<V>>(&mut self, book: &mut B) -> Option<V> {
+        let (bid, ask) = (book.best_bid()?, book.best_ask()?);
+        let price = match self.mark {
+            Mark::MicroPrice if bid.size + ask.size > V::ZERO => {
+                (bid.price * ask.size + ask.price * bid.size) / (bid.size + ask.size)
+            }
+            _ => (bid.price + ask.price) / V::TWO,
+        };
+        self.mark_at(price);
+        Some(price)
+    }
+
+    #[inline]
+    /// Mark the position at `price`
+    pub fn mark_at(&mut self, price: V) {
+        self.mark_price = Some(price);
+    }
+
+    #[inline]
+    #[must_use]
+    /// Size held, negative when short
+    pub const fn position(&self) -> V {
+        self.position
+    }
+
+    #[inline]
+    #[must_use]
+    /// Average entry price of the open position, zero when flat
+    pub const fn average_price(&self) -> V {
+        self.average_price
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn mark_price(&self) -> Option<V> {
+        self.mark_price
+    }
+
+    #[inline]
+    #[must_use]
+    /// PnL of the position closed so far, before fees
+    pub const fn realized(&self) -> V {
+        self.realized
+    }
+
+    #[inline]
+    #[must_use]
+    /// PnL of the open position at the mark price, zero before the first mark
+    pub fn unrealized(&self) -> V {
+        self.mark_price.map_or(V::ZERO, |price| (price - self.average_price) * self.position)
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn fees(&self) -> V {
+        self.fees
+    }
+
+    #[inline]
+    #[must_use]
+    /// Realized and unrealized PnL net of fees
+    pub fn pnl(&self) -> V {
+        self.realized + self.unrealized() - self.fees
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn fills(&self) -> u64 {
+        self.fills
+    }
+}
+
+#[inline(always)]
+fn abs<V: DecimalType + PartialOrd + Sub<Output = V>>(value: V) -> V {
+    if value < V::ZERO {
+        V::ZERO - value
+    } else {
+        value
+    }
+}
+
+#[inline(always)]
+fn min<V: PartialOrd>(a: V, b: V) -> V {
+    if b < a {
+        b
+    } else {
+        a
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        paper::{Account, Fill, Mark},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_position_and_pnl() {
+        let mut account = Account::new(Mark::Mid);
+        account.on_fill(Fill::new(Side::Buy, fixed!(100), fixed!(2), Timestamp::from_nanos(1)).with_fee(fixed!(0.1)));
+        account.on_fill(Fill::new(Side::Buy, fixed!(103), fixed!(1), Timestamp::from_nanos(2)).with_fee(fixed!(0.1)));
+        assert_eq!((account.position(), account.average_price()), (fixed!(3), fixed!(101)));
+
+        // Selling 4 closes the 3 held at 101 and opens a short of 1 at 104
+        account.on_fill(Fill::new(Side::Sell, fixed!(104), fixed!(4), Timestamp::from_nanos(3)).with_fee(fixed!(-0.05)));
+        assert_eq!((account.position(), account.average_price()), (fixed!(-1), fixed!(104)));
+        assert_eq!((account.realized(), account.fees(), account.unrealized()), (fixed!(9), fixed!(0.15), fixed!(0)));
+
+        let mut book = BTreeOrderBook::new();
+        book.process(Event::new(EventKind::L2, Side::Buy, fixed!(102), fixed!(3), Timestamp::from_nanos(4)));
+        assert_eq!(account.mark_to(&mut book), None);
+        book.process(Event::new(EventKind::L2, Side::Sell, fixed!(104), fixed!(1), Timestamp::from_nanos(4)));
+        assert_eq!(account.mark_to(&mut book), Some(fixed!(103)));
+        assert_eq!((account.unrealized(), account.pnl()), (fixed!(1), fixed!(9.85)));
+
+        let mut micro = Account { mark: Mark::MicroPrice, ..account };
+        // (102 * 1 + 104 * 3) / 4
+        assert_eq!(micro.mark_to(&mut book), Some(fixed!(103.5)));
+        assert_eq!(micro.unrealized(), fixed!(0.5));
+
+        account.on_fill(Fill::new(Side::Buy, fixed!(103), fixed!(1), Timestamp::from_nanos(5)));
+        assert_eq!((account.position(), account.average_price(), account.realized()), (fixed!(0), fixed!(0), fixed!(10)));
+        assert_eq!((account.unrealized(), account.fills()), (fixed!(0), 4));
+    }
+}
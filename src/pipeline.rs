@@ -0,0 +1,945 @@
+//! Threads wiring feeds to a book.
+//!
+//! A [`Pipeline`] runs each feed on its own thread and the book on another, joined by a bounded
+//! channel, so a slow book backs up into the queue rather than stalling the socket reads. The
+//! [`Overflow`] policy decides what a feed does when the queue is full: wait, drop the event, or hold
+//! it back and conflate later level updates into it. The book thread records how long each event takes
+//! to apply in a [`LatencyHistogram`] and, with the `affinity` feature on Linux, can be pinned to a
+//! core so the scheduler never migrates it. Downstream, each [`DeltaSubscriber`] has a queue of its own
+//! whose [`ConsumerPolicy`] decides what happens when that consumer falls behind. Describe the
+//! topology with [`Pipeline::builder`]:
+//!
+//! ```
+//! # #[cfg(feature = "fixed_decimal")] {
+//! use freya_ob::{
+//!     books::btree_orderbook::BTreeOrderBook, decimals::fixed_decimal::FixedDecimal, event::Event,
+//!     event_kind::EventKind, fixed, formats::source::MemorySource, pipeline::{Overflow, Pipeline}, side::Side,
+//!     timestamp::Timestamp,
+//! };
+//!
+//! let events = vec![Event::new(EventKind::L2, Side::Buy, fixed!(100), fixed!(1), Timestamp::from_nanos(1))];
+//! let pipeline = Pipeline::builder(BTreeOrderBook::<FixedDecimal>::new())
+//!     .capacity(1_024)
+//!     .overflow(Overflow::Conflate)
+//!     .feed(MemorySource::new(events))
+//!     .spawn();
+//! let report = pipeline.join();
+//! assert_eq!(report.stats.processed, 1);
+//! # }
+//! ```
+
+#[cfg(all(feature = "affinity", target_os = "linux"))]
+use std::io;
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, SyncSender, TrySendError},
+        Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    formats::source::SourceError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// What a feed does with an event when the queue to the book is full
+pub enum Overflow {
+    /// Wait for room, slowing the feed to the pace of the book
+    #[default]
+    Block,
+    /// Drop the event, leaving the book to recover from the next snapshot
+    DropNewest,
+    /// Hold the event back in the feed, replacing the held back BBO or Level 2 update of the same
+    /// level so only the latest state is sent once there is room. Updates are never moved across a
+    /// clear, snapshot, trade or order event held between them, and other kinds are held in order.
+    /// Held events are retried on a timer while the feed is quiet, and a feed holding more than the
+    /// [cap](PipelineBuilder::max_held) waits for room.
+    Conflate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The book thread has stopped, so nothing more can be sent
+pub struct Disconnected;
+
+impl fmt::Display for Disconnected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the book thread has stopped")
+    }
+}
+
+impl std::error::Error for Disconnected {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PipelineStats {
+    /// Events the book thread received
+    pub processed: u64,
+    /// Events dropped by [`Overflow::DropNewest`]
+    pub dropped: u64,
+    /// Events replaced by a later update under [`Overflow::Conflate`]
+    pub conflated: u64,
+}
+
+/// Buckets of a [`LatencyHistogram`]
+pub const LATENCY_BUCKETS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// Counts of the time a worker took to apply each event, the bucket `i` holding times from `2^i` up to
+/// `2^(i + 1)` nanoseconds and the last bucket everything slower
+pub struct LatencyHistogram {
+    pub buckets: [u64; LATENCY_BUCKETS],
+}
+
+impl LatencyHistogram {
+    #[inline]
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    #[must_use]
+    /// Upper bound of the bucket holding the `quantile` of the times, such as `0.999` for the p99.9,
+    /// `None` before any event
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        let rank = ((self.count() as f64 * quantile.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+        let bucket = self.buckets.iter().position(|&count| {
+            seen += count;
+            seen >= rank
+        })?;
+        Some(Duration::from_nanos(2 << bucket))
+    }
+}
+
+#[derive(Debug, Default)]
+/// A [`LatencyHistogram`] written by one worker thread and read from any
+pub(crate) struct LatencyRecorder {
+    buckets: [AtomicU64; LATENCY_BUCKETS],
+}
+
+impl LatencyRecorder {
+    #[inline]
+    pub(crate) fn record(&self, elapsed: Duration) {
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX).max(1);
+        let bucket = (nanos.ilog2() as usize).min(LATENCY_BUCKETS - 1);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn histogram(&self) -> LatencyHistogram {
+        LatencyHistogram { buckets: std::array::from_fn(|bucket| self.buckets[bucket].load(Ordering::Relaxed)) }
+    }
+}
+
+#[cfg(all(feature = "affinity", target_os = "linux"))]
+/// Pin the thread of `handle` to the CPU `core`, so the scheduler never migrates it
+///
+/// # Errors
+/// When `core` is outside the CPUs the process may run on.
+pub fn pin_thread<T>(handle: &JoinHandle<T>, core: usize) -> io::Result<()> {
+    use std::os::unix::thread::JoinHandleExt;
+
+    if core >= libc::CPU_SETSIZE as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("core {core} is beyond the CPU set")));
+    }
+    // The set is plain data, and the thread cannot be joined while `handle` is borrowed, so its ID
+    // stays valid for the call
+    let code = unsafe {
+        let mut set = std::mem::zeroed::<libc::cpu_set_t>();
+        libc::CPU_SET(core, &mut set);
+        libc::pthread_setaffinity_np(handle.as_pthread_t(), std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if code == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::from_raw_os_error(code))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    processed: AtomicU64,
+    dropped: AtomicU64,
+    conflated: AtomicU64,
+    latency: LatencyRecorder,
+}
+
+impl Counters {
+    fn stats(&self) -> PipelineStats {
+        PipelineStats {
+            processed: self.processed.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            conflated: self.conflated.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Held<V: DecimalType> {
+    /// Events held back by [`Overflow::Conflate`], oldest first
+    events: VecDeque<Event<V>>,
+    /// A thread is sending the held events as the queue makes room
+    flushing: bool,
+}
+
+#[derive(Debug)]
+struct Shared<V: DecimalType> {
+    sender: SyncSender<Event<V>>,
+    held: Mutex<Held<V>>,
+}
+
+impl<V: DecimalType> Shared<V> {
+    fn lock(&self) -> MutexGuard<'_, Held<V>> {
+        self.held.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Debug)]
+/// Sends events to the book thread, applying the pipeline's [`Overflow`] policy
+pub struct FeedSender<V: DecimalType> {
+    shared: Arc<Shared<V>>,
+    overflow: Overflow,
+    max_held: usize,
+    counters: Arc<Counters>,
+}
+
+impl<V: DecimalType + Copy + PartialEq + Send + 'static> FeedSender<V> {
+    /// Interval at which events held back are retried while the feed is quiet
+    pub const FLUSH_INTERVAL: Duration = Duration::from_millis(1);
+
+    fn new(sender: SyncSender<Event<V>>, overflow: Overflow, max_held: usize, counters: Arc<Counters>) -> Self {
+        let held = Mutex::new(Held { events: VecDeque::new(), flushing: false });
+        Self { shared: Arc::new(Shared { sender, held }), overflow, max_held: max_held.max(1), counters }
+    }
+
+    pub fn send(&mut self, event: Event<V>) -> Result<(), Disconnected> {
+        let sender = &self.shared.sender;
+        match self.overflow {
+            Overflow::Block => sender.send(event).map_err(|_| Disconnected),
+            Overflow::DropNewest => match sender.try_send(event) {
+                Err(TrySendError::Full(_)) => {
+                    self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                    Ok(())
+                }
+                Err(TrySendError::Disconnected(_)) => Err(Disconnected),
+                Ok(()) => Ok(()),
+            },
+            Overflow::Conflate => {
+                let mut held = self.shared.lock();
+                self.hold(&mut held.events, event);
+                send_held(sender, &mut held.events)?;
+                // Past the cap the feed waits for room, as under `Overflow::Block`
+                while held.events.len() > self.max_held {
+                    let oldest = held.events.pop_front().expect("more events held than the cap");
+                    sender.send(oldest).map_err(|_| Disconnected)?;
+                }
+                if !held.events.is_empty() && !held.flushing {
+                    held.flushing = true;
+                    spawn_flusher(Arc::downgrade(&self.shared));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Wait until every held back event is sent
+    pub fn flush(&mut self) -> Result<(), Disconnected> {
+        let mut held = self.shared.lock();
+        while let Some(event) = held.events.pop_front() {
+            self.shared.sender.send(event).map_err(|_| Disconnected)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    #[must_use]
+    /// Events held back waiting for room in the queue
+    pub fn held(&self) -> usize {
+        self.shared.lock().events.len()
+    }
+
+    /// Replace the latest held update of the same level, unless an event that could change the level
+    /// in between was held after it
+    fn hold(&self, held: &mut VecDeque<Event<V>>, event: Event<V>) {
+        if matches!(event.kind, EventKind::BBO | EventKind::L2) {
+            for queued in held.iter_mut().rev() {
+                if queued.kind == event.kind
+                    && queued.side == event.side
+                    && queued.price == event.price
+                    && queued.instrument == event.instrument
+                {
+                    *queued = event;
+                    self.counters.conflated.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                // Clears, snapshots, trades and order events apply in order, as does a BBO update
+                // removing levels ahead of it on the side a Level 2 update touches, and the reverse
+                let barrier = !matches!(queued.kind, EventKind::BBO | EventKind::L2)
+                    || (queued.kind != event.kind && queued.side == event.side);
+                if barrier {
+                    break;
+                }
+            }
+        }
+        held.push_back(event);
+    }
+}
+
+/// Send held back events in order for as long as there is room
+fn send_held<V: DecimalType>(sender: &SyncSender<Event<V>>, held: &mut VecDeque<Event<V>>) -> Result<(), Disconnected> {
+    while let Some(event) = held.pop_front() {
+        match sender.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(event)) => {
+                held.push_front(event);
+                break;
+            }
+            Err(TrySendError::Disconnected(_)) => return Err(Disconnected),
+        }
+    }
+    Ok(())
+}
+
+/// Retry the held events every [`FeedSender::FLUSH_INTERVAL`] until they are all sent, so a feed going
+/// quiet does not strand them. Stops early when the sender is dropped, which flushes nothing further.
+fn spawn_flusher<V: DecimalType + Copy + PartialEq + Send + 'static>(shared: Weak<Shared<V>>) {
+    thread::spawn(move || loop {
+        thread::sleep(FeedSender::<V>::FLUSH_INTERVAL);
+        let Some(shared) = shared.upgrade() else { return };
+        let mut held = shared.lock();
+        if send_held(&shared.sender, &mut held.events).is_err() || held.events.is_empty() {
+            held.flushing = false;
+            return;
+        }
+    });
+}
+
+impl<V: DecimalType> Clone for FeedSender<V> {
+    /// A sender for another producer, starting with nothing held back
+    fn clone(&self) -> Self {
+        let held = Mutex::new(Held { events: VecDeque::new(), flushing: false });
+        Self {
+            shared: Arc::new(Shared { sender: self.shared.sender.clone(), held }),
+            overflow: self.overflow,
+            max_held: self.max_held,
+            counters: Arc::clone(&self.counters),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// What the book thread does with a delta when a subscriber's queue is full
+pub enum ConsumerPolicy {
+    /// Wait for room, slowing the book to the pace of the subscriber
+    #[default]
+    Block,
+    /// Drop the oldest queued delta to make room, leaving the subscriber to recover from a snapshot
+    DropOldest,
+    /// Replace the queued delta of the same level, so only the latest size of each level waits, and
+    /// discard queued deltas a reset or side clear supersedes. A new level waits for room.
+    Conflate,
+}
+
+#[derive(Debug)]
+struct Queue<V: DecimalType> {
+    deltas: VecDeque<BookDelta<V>>,
+    /// Either end has been dropped
+    closed: bool,
+    dropped: u64,
+    conflated: u64,
+}
+
+#[derive(Debug)]
+struct DeltaQueue<V: DecimalType> {
+    queue: Mutex<Queue<V>>,
+    /// Signalled when a delta is queued or the publisher dropped
+    ready: Condvar,
+    /// Signalled when a delta is taken or the subscriber dropped
+    room: Condvar,
+    capacity: usize,
+    policy: ConsumerPolicy,
+}
+
+impl<V: DecimalType> DeltaQueue<V> {
+    fn lock(&self) -> MutexGuard<'_, Queue<V>> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn close(&self) {
+        self.lock().closed = true;
+        self.ready.notify_all();
+        self.room.notify_all();
+    }
+}
+
+/// A queue of deltas from the book thread to one consumer, holding up to `capacity` deltas
+pub fn delta_channel<V: DecimalType>(capacity: usize, policy: ConsumerPolicy) -> (DeltaPublisher<V>, DeltaSubscriber<V>) {
+    let queue = Arc::new(DeltaQueue {
+        queue: Mutex::new(Queue { deltas: VecDeque::new(), closed: false, dropped: 0, conflated: 0 }),
+        ready: Condvar::new(),
+        room: Condvar::new(),
+        capacity: capacity.max(1),
+        policy,
+    });
+    (DeltaPublisher { queue: Arc::clone(&queue) }, DeltaSubscriber { queue })
+}
+
+#[derive(Debug)]
+/// The book side of a [`delta_channel`], see [`PipelineBuilder::publish_to`]
+pub struct DeltaPublisher<V: DecimalType> {
+    queue: Arc<DeltaQueue<V>>,
+}
+
+impl<V: DecimalType + Copy + PartialEq> DeltaPublisher<V> {
+    /// Queue `delta` by the channel's policy, returning false once the subscriber has been dropped
+    pub fn publish(&self, delta: BookDelta<V>) -> bool {
+        let mut queue = self.queue.lock();
+        if self.queue.policy == ConsumerPolicy::Conflate && Self::conflate(&mut queue, &delta) {
+            return !queue.closed;
+        }
+        while queue.deltas.len() >= self.queue.capacity && !queue.closed {
+            if self.queue.policy == ConsumerPolicy::DropOldest {
+                queue.deltas.pop_front();
+                queue.dropped += 1;
+            } else {
+                queue = self.queue.room.wait(queue).unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+        if queue.closed {
+            return false;
+        }
+        queue.deltas.push_back(delta);
+        self.queue.ready.notify_one();
+        true
+    }
+
+    /// Fold `delta` into the queue, returning whether it took the place of a queued delta
+    fn conflate(queue: &mut Queue<V>, delta: &BookDelta<V>) -> bool {
+        let before = queue.deltas.len();
+        if delta.reset {
+            queue.deltas.clear();
+        } else if delta.cleared_side {
+            // A queued reset also empties the other side, so it stays
+            queue.deltas.retain(|queued| queued.reset || queued.side != delta.side);
+        } else {
+            for queued in queue.deltas.iter_mut().rev() {
+                if queued.side == delta.side && queued.price == delta.price {
+                    // A queued reset or side clear still has to reach the subscriber
+                    *queued = BookDelta {
+                        reset: queued.reset,
+                        cleared_side: queued.cleared_side,
+                        evicted: delta.evicted.or(queued.evicted),
+                        ..*delta
+                    };
+                    queue.conflated += 1;
+                    return true;
+                }
+                // Levels queued ahead of a clear of this side cannot take a later update
+                if queued.reset || (queued.cleared_side && queued.side == delta.side) {
+                    break;
+                }
+            }
+        }
+        queue.conflated += (before - queue.deltas.len()) as u64;
+        false
+    }
+}
+
+impl<V: DecimalType> Drop for DeltaPublisher<V> {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+#[derive(Debug)]
+/// The consumer side of a [`delta_channel`], iterating until the publisher is dropped and the queue
+/// drained
+pub struct DeltaSubscriber<V: DecimalType> {
+    queue: Arc<DeltaQueue<V>>,
+}
+
+impl<V: DecimalType> DeltaSubscriber<V> {
+    /// The oldest queued delta, waiting for one while the publisher lives
+    pub fn recv(&self) -> Option<BookDelta<V>> {
+        let mut queue = self.queue.lock();
+        loop {
+            if let Some(delta) = queue.deltas.pop_front() {
+                self.queue.room.notify_one();
+                return Some(delta);
+            }
+            if queue.closed {
+                return None;
+            }
+            queue = self.queue.ready.wait(queue).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    #[must_use]
+    pub fn try_recv(&self) -> Option<BookDelta<V>> {
+        let delta = self.queue.lock().deltas.pop_front();
+        self.queue.room.notify_one();
+        delta
+    }
+
+    #[must_use]
+    /// Deltas dropped by [`ConsumerPolicy::DropOldest`]
+    pub fn dropped(&self) -> u64 {
+        self.queue.lock().dropped
+    }
+
+    #[must_use]
+    /// Deltas replaced or discarded by [`ConsumerPolicy::Conflate`]
+    pub fn conflated(&self) -> u64 {
+        self.queue.lock().conflated
+    }
+}
+
+impl<V: DecimalType> Iterator for DeltaSubscriber<V> {
+    type Item = BookDelta<V>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+impl<V: DecimalType> Drop for DeltaSubscriber<V> {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+type Feed<V> = Box<dyn FnOnce(FeedSender<V>, Arc<AtomicBool>) -> Option<SourceError> + Send>;
+type DeltaHandler<V> = Box<dyn FnMut(&BookDelta<V>) + Send>;
+
+#[must_use]
+/// Describes a [`Pipeline`], see [`Pipeline::builder`]
+pub struct PipelineBuilder<V: DecimalType, B> {
+    book: B,
+    capacity: usize,
+    overflow: Overflow,
+    max_held: usize,
+    feeds: Vec<Feed<V>>,
+    on_delta: Option<DeltaHandler<V>>,
+    publishers: Vec<DeltaPublisher<V>>,
+    #[cfg(all(feature = "affinity", target_os = "linux"))]
+    book_core: Option<usize>,
+}
+
+impl<V, B> PipelineBuilder<V, B>
+where
+    V: DecimalType + Copy + PartialEq + Send + 'static,
+    B: OrderBook<V> + Send + 'static,
+{
+    /// Events the queue to the book holds before the overflow policy applies, `1024` by default
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub const fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Events each feed holds back under [`Overflow::Conflate`] before it waits for room, `65536` by
+    /// default
+    pub const fn max_held(mut self, max_held: usize) -> Self {
+        self.max_held = max_held;
+        self
+    }
+
+    /// Read `source` on its own thread, stopping at its end, its first error or shutdown
+    pub fn feed<S>(mut self, source: S) -> Self
+    where
+        S: Iterator<Item = Result<Event<V>, SourceError>> + Send + 'static,
+    {
+        self.feeds.push(Box::new(move |mut sender, stop| {
+            for event in source {
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                match event {
+                    Ok(event) if sender.send(event).is_ok() => {}
+                    Ok(_) => return None,
+                    Err(err) => return Some(err),
+                }
+            }
+            let _ = sender.flush();
+            None
+        }));
+        self
+    }
+
+    /// Call `on_delta` on the book thread with every delta the book accepts
+    pub fn on_delta(mut self, on_delta: impl FnMut(&BookDelta<V>) + Send + 'static) -> Self {
+        self.on_delta = Some(Box::new(on_delta));
+        self
+    }
+
+    /// Queue every delta the book accepts for the subscriber of `publisher`, after `on_delta`
+    pub fn publish_to(mut self, publisher: DeltaPublisher<V>) -> Self {
+        self.publishers.push(publisher);
+        self
+    }
+
+    #[cfg(all(feature = "affinity", target_os = "linux"))]
+    /// Pin the book thread to the CPU `core`, see [`pin_thread`]. A book thread that cannot be pinned
+    /// runs unpinned, see [`Pipeline::pin_error`].
+    pub const fn pin_book(mut self, core: usize) -> Self {
+        self.book_core = Some(core);
+        self
+    }
+
+    /// Start the book thread and a thread per feed
+    pub fn spawn(self) -> Pipeline<V, B> {
+        let (sender, receiver) = mpsc::sync_channel(self.capacity);
+        let (stop, counters) = (Arc::new(AtomicBool::new(false)), Arc::new(Counters::default()));
+        let sender = FeedSender::new(sender, self.overflow, self.max_held, Arc::clone(&counters));
+
+        let (mut book, mut on_delta, processed) = (self.book, self.on_delta, Arc::clone(&counters));
+        let publishers = self.publishers;
+        let book = thread::spawn(move || {
+            for event in receiver {
+                processed.processed.fetch_add(1, Ordering::Relaxed);
+                let start = Instant::now();
+                let delta = book.process_delta(event);
+                processed.latency.record(start.elapsed());
+                let Some(delta) = delta else { continue };
+                if let Some(on_delta) = on_delta.as_mut() {
+                    on_delta(&delta);
+                }
+                for publisher in &publishers {
+                    publisher.publish(delta);
+                }
+            }
+            book
+        });
+        #[cfg(all(feature = "affinity", target_os = "linux"))]
+        let pin_error = self.book_core.and_then(|core| pin_thread(&book, core).err());
+        let feeds = self
+            .feeds
+            .into_iter()
+            .map(|feed| {
+                let (sender, stop) = (sender.clone(), Arc::clone(&stop));
+                thread::spawn(move || feed(sender, stop))
+            })
+            .collect();
+        Pipeline {
+            sender,
+            stop,
+            counters,
+            feeds,
+            book,
+            #[cfg(all(feature = "affinity", target_os = "linux"))]
+            pin_error,
+        }
+    }
+}
+
+#[derive(Debug)]
+/// What a pipeline leaves behind once it stops
+pub struct PipelineReport<B> {
+    pub book: B,
+    pub stats: PipelineStats,
+    pub latency: LatencyHistogram,
+    /// The error each failed feed stopped on
+    pub errors: Vec<SourceError>,
+}
+
+#[derive(Debug)]
+/// Running feed and book threads, see [`Pipeline::builder`]
+pub struct Pipeline<V: DecimalType, B> {
+    sender: FeedSender<V>,
+    stop: Arc<AtomicBool>,
+    counters: Arc<Counters>,
+    feeds: Vec<JoinHandle<Option<SourceError>>>,
+    book: JoinHandle<B>,
+    /// Why the book thread could not be pinned to the core given to `pin_book`
+    #[cfg(all(feature = "affinity", target_os = "linux"))]
+    pin_error: Option<io::Error>,
+}
+
+impl<V: DecimalType, B> Pipeline<V, B> {
+    /// Start describing a pipeline feeding `book`
+    pub fn builder(book: B) -> PipelineBuilder<V, B> {
+        PipelineBuilder {
+            book,
+            capacity: 1_024,
+            overflow: Overflow::Block,
+            max_held: 65_536,
+            feeds: Vec::new(),
+            on_delta: None,
+            publishers: Vec::new(),
+            #[cfg(all(feature = "affinity", target_os = "linux"))]
+            book_core: None,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// A sender for a producer of the caller's own, which must be dropped before the pipeline stops
+    pub fn sender(&self) -> FeedSender<V> {
+        self.sender.clone()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn stats(&self) -> PipelineStats {
+        self.counters.stats()
+    }
+
+    #[cfg(all(feature = "affinity", target_os = "linux"))]
+    #[inline]
+    #[must_use]
+    /// Why the book thread runs unpinned despite `pin_book`, `None` when it was pinned or never asked to be
+    pub const fn pin_error(&self) -> Option<&io::Error> {
+        self.pin_error.as_ref()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Time the book thread has taken to apply each event so far
+    pub fn latency(&self) -> LatencyHistogram {
+        self.counters.latency.histogram()
+    }
+
+    /// Wait for every feed to end, then for the book to apply what they sent
+    #[must_use]
+    pub fn join(self) -> PipelineReport<B> {
+        self.finish()
+    }
+
+    /// Stop the feeds after the event each is sending, then wait for the book to apply what they sent
+    #[must_use]
+    pub fn shutdown(self) -> PipelineReport<B> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.finish()
+    }
+
+    fn finish(self) -> PipelineReport<B> {
+        let Self { sender, counters, feeds, book, .. } = self;
+        drop(sender);
+        let errors =
+            feeds.into_iter().filter_map(|feed| feed.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))).collect();
+        let book = book.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+        PipelineReport { book, stats: counters.stats(), latency: counters.latency.histogram(), errors }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            mpsc, Arc,
+        },
+        time::Duration,
+    };
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, delta::BookDelta, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::{source::MemorySource, FormatError},
+        pipeline::{delta_channel, ConsumerPolicy, Counters, FeedSender, LatencyRecorder, Overflow, Pipeline, LATENCY_BUCKETS},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn l2(side: Side, price: FixedDecimal, size: FixedDecimal) -> Event<FixedDecimal> {
+        Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(1))
+    }
+
+    #[test]
+    fn test_feeds_reach_book() {
+        let bids = (1..=100).map(|i| l2(Side::Buy, FixedDecimal::from_int(i), fixed!(1))).collect();
+        let asks = (101..=200).map(|i| l2(Side::Sell, FixedDecimal::from_int(i), fixed!(1))).collect();
+        let deltas = Arc::new(AtomicU64::new(0));
+        let counted = Arc::clone(&deltas);
+        let broken = std::iter::once(Err(FormatError::Io("gone".to_owned())));
+        let pipeline = Pipeline::builder(BTreeOrderBook::new())
+            .capacity(4)
+            .feed(MemorySource::new(bids))
+            .feed(MemorySource::new(asks))
+            .feed(broken)
+            .on_delta(move |_| {
+                counted.fetch_add(1, Ordering::Relaxed);
+            })
+            .spawn();
+        let mut sender = pipeline.sender();
+        sender.send(l2(Side::Buy, fixed!(50), fixed!(7))).unwrap();
+        drop(sender);
+
+        let mut report = pipeline.join();
+        assert_eq!((report.stats.processed, deltas.load(Ordering::Relaxed)), (201, 201));
+        assert_eq!(report.errors, [FormatError::Io("gone".to_owned())]);
+        assert_eq!(report.latency.count(), 201);
+        assert_eq!(report.book.best_bid().map(|level| level.price), Some(fixed!(100)));
+        assert_eq!(report.book.levels(Side::Sell, usize::MAX).len(), 100);
+    }
+
+    #[test]
+    fn test_overflow_policies() {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let counters = Arc::new(Counters::default());
+        let mut dropping = FeedSender::new(sender.clone(), Overflow::DropNewest, 16, Arc::clone(&counters));
+        dropping.send(l2(Side::Buy, fixed!(100), fixed!(1))).unwrap();
+        dropping.send(l2(Side::Buy, fixed!(100), fixed!(2))).unwrap();
+        assert_eq!(counters.stats().dropped, 1);
+        assert_eq!(receiver.recv().unwrap().size, fixed!(1));
+        drop(dropping);
+
+        let mut conflating = FeedSender::new(sender, Overflow::Conflate, 16, Arc::clone(&counters));
+        conflating.send(l2(Side::Buy, fixed!(100), fixed!(1))).unwrap();
+        for size in [fixed!(2), fixed!(3)] {
+            conflating.send(l2(Side::Buy, fixed!(100), size)).unwrap();
+        }
+        conflating.send(l2(Side::Sell, fixed!(101), fixed!(1))).unwrap();
+        conflating.send(Event::new(EventKind::Trade, Side::Sell, fixed!(101), fixed!(1), Timestamp::from_nanos(2))).unwrap();
+        conflating.send(Event::new(EventKind::Trade, Side::Sell, fixed!(101), fixed!(1), Timestamp::from_nanos(3))).unwrap();
+        assert_eq!((conflating.held(), counters.stats().conflated), (4, 1));
+
+        let receiving = std::thread::spawn(move || receiver.iter().map(|event| (event.kind, event.size)).collect::<Vec<_>>());
+        conflating.flush().unwrap();
+        drop(conflating);
+        let sizes = receiving.join().unwrap();
+        let expected = [
+            (EventKind::L2, fixed!(1)),
+            (EventKind::L2, fixed!(3)),
+            (EventKind::L2, fixed!(1)),
+            (EventKind::Trade, fixed!(1)),
+            (EventKind::Trade, fixed!(1)),
+        ];
+        assert_eq!(sizes, expected);
+    }
+
+    #[test]
+    fn test_conflation_bounds() {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let counters = Arc::new(Counters::default());
+        let mut conflating = FeedSender::new(sender, Overflow::Conflate, 3, Arc::clone(&counters));
+        conflating.send(l2(Side::Sell, fixed!(105), fixed!(1))).unwrap();
+        // A clear held between two updates of a level keeps both
+        conflating.send(l2(Side::Buy, fixed!(100), fixed!(1))).unwrap();
+        conflating.send(Event::new(EventKind::Clear, Side::Buy, fixed!(0), fixed!(0), Timestamp::from_nanos(1))).unwrap();
+        conflating.send(l2(Side::Buy, fixed!(100), fixed!(2))).unwrap();
+        assert_eq!((conflating.held(), counters.stats().conflated), (3, 0));
+
+        // Past the cap the oldest held event waits for room
+        let receiving = std::thread::spawn(move || receiver.iter().map(|event| (event.kind, event.size)).collect::<Vec<_>>());
+        conflating.send(l2(Side::Buy, fixed!(99), fixed!(1))).unwrap();
+        assert!(conflating.held() <= 3);
+        // Nothing more is sent, the held events still reach the book
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while conflating.held() > 0 && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(conflating.held(), 0);
+        drop(conflating);
+        let expected = [
+            (EventKind::L2, fixed!(1)),
+            (EventKind::L2, fixed!(1)),
+            (EventKind::Clear, fixed!(0)),
+            (EventKind::L2, fixed!(2)),
+            (EventKind::L2, fixed!(1)),
+        ];
+        assert_eq!(receiving.join().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_consumer_policies() {
+        let delta = |side, price, size| BookDelta {
+            side,
+            price,
+            size,
+            timestamp: Timestamp::from_nanos(1),
+            sequence_id: 0,
+            reset: false,
+            cleared_side: false,
+            evicted: None,
+        };
+        let (publisher, subscriber) = delta_channel(2, ConsumerPolicy::DropOldest);
+        for size in [fixed!(1), fixed!(2), fixed!(3)] {
+            assert!(publisher.publish(delta(Side::Buy, fixed!(100), size)));
+        }
+        assert_eq!((subscriber.dropped(), subscriber.try_recv().map(|delta| delta.size)), (1, Some(fixed!(2))));
+
+        let (publisher, subscriber) = delta_channel(2, ConsumerPolicy::Conflate);
+        publisher.publish(BookDelta { reset: true, ..delta(Side::Buy, fixed!(100), fixed!(1)) });
+        publisher.publish(delta(Side::Sell, fixed!(101), fixed!(1)));
+        publisher.publish(delta(Side::Buy, fixed!(100), fixed!(4)));
+        let first = subscriber.try_recv().unwrap();
+        assert_eq!((first.size, first.reset, subscriber.conflated()), (fixed!(4), true, 1));
+        publisher.publish(BookDelta { cleared_side: true, ..delta(Side::Sell, fixed!(0), fixed!(0)) });
+        assert_eq!(subscriber.conflated(), 2);
+        drop(publisher);
+        assert_eq!(subscriber.map(|delta| delta.cleared_side).collect::<Vec<_>>(), [true]);
+
+        let (publisher, subscriber) = delta_channel(1, ConsumerPolicy::Block);
+        let pipeline = Pipeline::builder(BTreeOrderBook::new())
+            .feed(MemorySource::new((1..=50).map(|i| l2(Side::Buy, FixedDecimal::from_int(i), fixed!(1))).collect()))
+            .publish_to(publisher)
+            .spawn();
+        let slow = std::thread::spawn(move || subscriber.count());
+        assert_eq!(pipeline.join().stats.processed, 50);
+        assert_eq!(slow.join().unwrap(), 50);
+    }
+
+    #[test]
+    fn test_latency_quantiles() {
+        let recorder = LatencyRecorder::default();
+        assert_eq!(recorder.histogram().quantile(0.5), None);
+        for nanos in [0, 100, 150, 200, 5_000] {
+            recorder.record(Duration::from_nanos(nanos));
+        }
+        recorder.record(Duration::from_secs(3_600));
+        let histogram = recorder.histogram();
+        assert_eq!((histogram.buckets[0], histogram.buckets[6], histogram.buckets[7]), (1, 1, 2));
+        assert_eq!(histogram.quantile(0.5), Some(Duration::from_nanos(256)));
+        assert_eq!(histogram.quantile(0.8), Some(Duration::from_nanos(8_192)));
+        assert_eq!(histogram.quantile(1.0), Some(Duration::from_nanos(1 << LATENCY_BUCKETS)));
+    }
+
+    #[cfg(all(feature = "affinity", target_os = "linux"))]
+    #[test]
+    fn test_pin_book() {
+        let pipeline = Pipeline::builder(BTreeOrderBook::new())
+            .pin_book(unsafe { libc::sched_getcpu() } as usize)
+            .feed(MemorySource::new(vec![l2(Side::Buy, fixed!(100), fixed!(1))]))
+            .spawn();
+        assert!(pipeline.pin_error().is_none());
+        assert_eq!(pipeline.join().stats.processed, 1);
+        let idle = std::thread::spawn(|| ());
+        assert!(super::pin_thread(&idle, 1 << 20).is_err());
+
+        // A core beyond the CPU set leaves the book running unpinned
+        let pipeline = Pipeline::builder(BTreeOrderBook::new())
+            .pin_book(1 << 20)
+            .feed(MemorySource::new(vec![l2(Side::Buy, fixed!(100), fixed!(1))]))
+            .spawn();
+        assert_eq!(pipeline.pin_error().map(std::io::Error::kind), Some(std::io::ErrorKind::InvalidInput));
+        assert_eq!(pipeline.join().stats.processed, 1);
+    }
+
+    #[test]
+    fn test_shutdown_stops_endless_feed() {
+        let endless = std::iter::repeat(Ok(l2(Side::Buy, fixed!(100), fixed!(1))));
+        let pipeline = Pipeline::builder(BTreeOrderBook::new()).capacity(8).overflow(Overflow::DropNewest).feed(endless).spawn();
+        while pipeline.stats().processed == 0 {
+            std::thread::yield_now();
+        }
+        let report = pipeline.shutdown();
+        assert!(report.stats.processed > 0 && report.errors.is_empty());
+    }
+}
@@ -0,0 +1,491 @@
+//! Reader for Databento Binary Encoding (DBN) MBO and MBP-10 records.
+//!
+//! A DBN stream starts with a metadata block (`DBN`, a version byte and a little-endian `u32`
+//! length) followed by records, each led by a 16-byte header whose first byte is the record length
+//! in 4-byte units. All integers are little-endian, prices are `i64` with nine implied decimal
+//! places ([`PRICE_SCALE`]) and [`UNDEF_PRICE`] marks a missing price, timestamps are nanoseconds
+//! since the UNIX epoch.
+//!
+//! [`MboBook`] aggregates the order-by-order MBO schema into per-price Level 2 events and
+//! [`Mbp10Book`] turns the ten-level images of MBP-10 into the Level 2 events that changed between
+//! them. Events are stamped with `ts_recv`, which Databento guarantees never decreases.
+
+use std::collections::HashMap;
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    protocols::{ensure_len, DecodeError},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+/// Decimal places implied by DBN prices
+pub const PRICE_SCALE: u32 = 9;
+/// Sentinel for a price that is not set
+pub const UNDEF_PRICE: i64 = i64::MAX;
+/// Record type of the MBO schema
+pub const RTYPE_MBO: u8 = 0xa0;
+/// Record type of the MBP-10 schema
+pub const RTYPE_MBP10: u8 = 0x0a;
+/// Flag set on the last record of an event, after which the book is consistent
+pub const F_LAST: u8 = 1 << 7;
+/// Flag set on records replaying the book rather than reporting live changes
+pub const F_SNAPSHOT: u8 = 1 << 5;
+
+const HEADER_LEN: usize = 16;
+const MBO_LEN: usize = 56;
+const MBP10_LEN: usize = 368;
+const DEPTH: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Fields shared by every record
+pub struct RecordHeader {
+    pub rtype: u8,
+    pub publisher_id: u16,
+    pub instrument_id: u32,
+    pub ts_event: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MboMsg {
+    pub header: RecordHeader,
+    pub order_id: u64,
+    pub price: i64,
+    pub size: u32,
+    pub flags: u8,
+    pub channel_id: u8,
+    /// `A`dd, `C`ancel, `M`odify, clea`R`, `T`rade, `F`ill or `N`one
+    pub action: u8,
+    /// `None` when the record has no side, as for clears and some trades
+    pub side: Option<Side>,
+    pub ts_recv: u64,
+    pub sequence: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BidAskPair {
+    pub bid_px: i64,
+    pub ask_px: i64,
+    pub bid_sz: u32,
+    pub ask_sz: u32,
+    pub bid_ct: u32,
+    pub ask_ct: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mbp10Msg {
+    pub header: RecordHeader,
+    /// Price and size of the change that produced this image
+    pub price: i64,
+    pub size: u32,
+    pub action: u8,
+    pub side: Option<Side>,
+    pub flags: u8,
+    /// Level the change applied to
+    pub depth: u8,
+    pub ts_recv: u64,
+    pub sequence: u32,
+    /// The top ten levels after the change, best first
+    pub levels: [BidAskPair; DEPTH],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Records are decoded by value so iterating a stream never allocates
+#[allow(clippy::large_enum_variant)]
+pub enum Record<'a> {
+    Mbo(MboMsg),
+    Mbp10(Mbp10Msg),
+    /// Any other record type, such as instrument definitions or statistics
+    Other {
+        header: RecordHeader,
+        payload: &'a [u8],
+    },
+}
+
+#[inline(always)]
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+#[inline(always)]
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(buf[at..at + 4].try_into().expect("slice of four bytes"))
+}
+
+#[inline(always)]
+fn u64_at(buf: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(buf[at..at + 8].try_into().expect("slice of eight bytes"))
+}
+
+#[inline(always)]
+fn side(value: u8) -> Result<Option<Side>, DecodeError> {
+    match value {
+        b'B' => Ok(Some(Side::Buy)),
+        b'A' => Ok(Some(Side::Sell)),
+        b'N' => Ok(None),
+        other => Err(DecodeError::InvalidField { field: "side", value: u64::from(other) }),
+    }
+}
+
+/// Skip the metadata block at the start of a stream, returning the DBN version and the records
+pub fn skip_metadata(buf: &[u8]) -> Result<(u8, &[u8]), DecodeError> {
+    ensure_len(buf, 8)?;
+    if &buf[..3] != b"DBN" {
+        return Err(DecodeError::InvalidField { field: "magic", value: u64::from(u32_at(buf, 0) & 0x00ff_ffff) });
+    }
+    let len = 8 + u32_at(buf, 4) as usize;
+    ensure_len(buf, len)?;
+    Ok((buf[3], &buf[len..]))
+}
+
+/// Decode one record, `buf` starting at its header and holding exactly the record
+pub fn parse(buf: &[u8]) -> Result<Record<'_>, DecodeError> {
+    ensure_len(buf, HEADER_LEN)?;
+    let header =
+        RecordHeader { rtype: buf[1], publisher_id: u16_at(buf, 2), instrument_id: u32_at(buf, 4), ts_event: u64_at(buf, 8) };
+    let record = match header.rtype {
+        RTYPE_MBO => {
+            ensure_len(buf, MBO_LEN)?;
+            Record::Mbo(MboMsg {
+                header,
+                order_id: u64_at(buf, 16),
+                price: u64_at(buf, 24) as i64,
+                size: u32_at(buf, 32),
+                flags: buf[36],
+                channel_id: buf[37],
+                action: buf[38],
+                side: side(buf[39])?,
+                ts_recv: u64_at(buf, 40),
+                sequence: u32_at(buf, 52),
+            })
+        }
+        RTYPE_MBP10 => {
+            ensure_len(buf, MBP10_LEN)?;
+            let mut levels = [BidAskPair::default(); DEPTH];
+            for (i, level) in levels.iter_mut().enumerate() {
+                let at = 48 + i * 32;
+                *level = BidAskPair {
+                    bid_px: u64_at(buf, at) as i64,
+                    ask_px: u64_at(buf, at + 8) as i64,
+                    bid_sz: u32_at(buf, at + 16),
+                    ask_sz: u32_at(buf, at + 20),
+                    bid_ct: u32_at(buf, at + 24),
+                    ask_ct: u32_at(buf, at + 28),
+                };
+            }
+            Record::Mbp10(Mbp10Msg {
+                header,
+                price: u64_at(buf, 16) as i64,
+                size: u32_at(buf, 24),
+                action: buf[28],
+                side: side(buf[29])?,
+                flags: buf[30],
+                depth: buf[31],
+                ts_recv: u64_at(buf, 32),
+                sequence: u32_at(buf, 44),
+                levels,
+            })
+        }
+        _ => Record::Other { header, payload: &buf[HEADER_LEN..] },
+    };
+    Ok(record)
+}
+
+#[derive(Debug, Clone)]
+/// Iterator over the records following the metadata block
+pub struct Records<'a> {
+    buf: &'a [u8],
+}
+
+#[inline]
+#[must_use]
+pub const fn records(buf: &[u8]) -> Records<'_> {
+    Records { buf }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<Record<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = usize::from(*self.buf.first()?) * 4;
+        let Some(record) = self.buf.get(..len).filter(|_| len >= HEADER_LEN) else {
+            let err = DecodeError::Truncated { needed: len.max(HEADER_LEN), available: self.buf.len() };
+            self.buf = &[];
+            return Some(Err(err));
+        };
+        self.buf = &self.buf[len..];
+        Some(parse(record))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Order {
+    side: Side,
+    price: i64,
+    size: u32,
+}
+
+#[derive(Debug, Default)]
+/// Tracks resting MBO orders of one instrument and emits Level 2 events carrying the aggregate size
+/// left at the affected price.
+pub struct MboBook {
+    instrument_id: Option<u32>,
+    orders: HashMap<u64, Order>,
+    levels: HashMap<(Side, i64), u64>,
+}
+
+impl MboBook {
+    #[inline]
+    #[must_use]
+    /// Track every instrument in the stream as a single book
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn for_instrument(instrument_id: u32) -> Self {
+        Self { instrument_id: Some(instrument_id), ..Self::default() }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn order_count(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// Apply a record, appending the resulting events to `events`.
+    ///
+    /// Trades and fills are skipped because Databento follows each fill with a cancel of the filled size,
+    /// which is what reduces the resting order, and a clear removes every level with a zero-size event.
+    pub fn apply<V: DecimalType>(&mut self, msg: &MboMsg, events: &mut Vec<Event<V>>) -> Result<(), DecodeError> {
+        if self.instrument_id.is_some_and(|id| id != msg.header.instrument_id) {
+            return Ok(());
+        }
+        let ts = Timestamp::from_nanos(msg.ts_recv as i64);
+        match msg.action {
+            b'A' => {
+                let side = msg.side.ok_or(DecodeError::InvalidField { field: "side", value: u64::from(b'N') })?;
+                self.orders.insert(msg.order_id, Order { side, price: msg.price, size: msg.size });
+                events.push(self.change_level(side, msg.price, i64::from(msg.size), ts));
+            }
+            b'C' => {
+                let order = self.orders.get_mut(&msg.order_id).ok_or(DecodeError::UnknownOrder(msg.order_id))?;
+                let size = msg.size.min(order.size);
+                order.size -= size;
+                let Order { side, price, size: left } = *order;
+                if left == 0 {
+                    self.orders.remove(&msg.order_id);
+                }
+                events.push(self.change_level(side, price, -i64::from(size), ts));
+            }
+            b'M' => {
+                let order = self.orders.get_mut(&msg.order_id).ok_or(DecodeError::UnknownOrder(msg.order_id))?;
+                let previous = *order;
+                (order.price, order.size) = (msg.price, msg.size);
+                events.push(self.change_level(previous.side, previous.price, -i64::from(previous.size), ts));
+                events.push(self.change_level(previous.side, msg.price, i64::from(msg.size), ts));
+            }
+            b'R' => {
+                self.orders.clear();
+                for ((side, price), _) in self.levels.drain() {
+                    events.push(Event::new(EventKind::L2, side, V::from_scaled(price, PRICE_SCALE), V::ZERO, ts));
+                }
+            }
+            b'T' | b'F' | b'N' => {}
+            other => return Err(DecodeError::InvalidField { field: "action", value: u64::from(other) }),
+        }
+        Ok(())
+    }
+
+    fn change_level<V: DecimalType>(&mut self, side: Side, price: i64, delta: i64, ts: Timestamp) -> Event<V> {
+        let size = self.levels.entry((side, price)).or_default();
+        *size = size.saturating_add_signed(delta);
+        let size = *size;
+        if size == 0 {
+            self.levels.remove(&(side, price));
+        }
+        Event::new(EventKind::L2, side, V::from_scaled(price, PRICE_SCALE), V::from_scaled(size as i64, 0), ts)
+    }
+}
+
+#[derive(Debug, Default)]
+/// Diffs successive MBP-10 images per instrument into Level 2 events.
+///
+/// Levels pushed out of the top ten are removed, so the book mirrors the ten-level view.
+pub struct Mbp10Book {
+    images: HashMap<u32, [BidAskPair; DEPTH]>,
+}
+
+impl Mbp10Book {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a record, appending an event for every level that changed since the instrument's last image
+    pub fn apply<V: DecimalType>(&mut self, msg: &Mbp10Msg, events: &mut Vec<Event<V>>) {
+        let previous = self.images.insert(msg.header.instrument_id, msg.levels).unwrap_or_default();
+        let ts = Timestamp::from_nanos(msg.ts_recv as i64);
+        for side in [Side::Buy, Side::Sell] {
+            for (price, _) in side_levels(&previous, side) {
+                if !side_levels(&msg.levels, side).any(|(current, _)| current == price) {
+                    events.push(Event::new(EventKind::L2, side, V::from_scaled(price, PRICE_SCALE), V::ZERO, ts));
+                }
+            }
+            for (price, size) in side_levels(&msg.levels, side) {
+                if !side_levels(&previous, side).any(|level| level == (price, size)) {
+                    let (price, size) = (V::from_scaled(price, PRICE_SCALE), V::from_scaled(i64::from(size), 0));
+                    events.push(Event::new(EventKind::L2, side, price, size, ts));
+                }
+            }
+        }
+    }
+}
+
+/// Populated `(price, size)` levels of one side of an image
+fn side_levels(image: &[BidAskPair; DEPTH], side: Side) -> impl Iterator<Item = (i64, u32)> + '_ {
+    image
+        .iter()
+        .map(move |pair| if side.is_buy() { (pair.bid_px, pair.bid_sz) } else { (pair.ask_px, pair.ask_sz) })
+        .filter(|&(price, size)| price != UNDEF_PRICE && size > 0)
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook as _},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        fixed,
+        protocols::{
+            dbn::{parse, records, skip_metadata, BidAskPair, MboBook, Mbp10Book, Record, RTYPE_MBO, RTYPE_MBP10, UNDEF_PRICE},
+            DecodeError,
+        },
+        side::Side,
+    };
+
+    fn header(len: usize, rtype: u8, instrument_id: u32) -> Vec<u8> {
+        let mut buf = vec![(len / 4) as u8, rtype];
+        buf.extend(1_u16.to_le_bytes());
+        buf.extend(instrument_id.to_le_bytes());
+        buf.extend(0_u64.to_le_bytes());
+        buf
+    }
+
+    fn mbo(order_id: u64, action: u8, side: u8, price: i64, size: u32, ts_recv: u64) -> Vec<u8> {
+        let mut buf = header(56, RTYPE_MBO, 7);
+        buf.extend(order_id.to_le_bytes());
+        buf.extend(price.to_le_bytes());
+        buf.extend(size.to_le_bytes());
+        buf.extend([0, 0, action, side]);
+        buf.extend(ts_recv.to_le_bytes());
+        buf.extend(0_i32.to_le_bytes());
+        buf.extend(1_u32.to_le_bytes());
+        buf
+    }
+
+    fn mbp10(levels: &[(i64, u32, i64, u32)], ts_recv: u64) -> Vec<u8> {
+        let mut buf = header(368, RTYPE_MBP10, 7);
+        buf.extend(0_i64.to_le_bytes());
+        buf.extend(0_u32.to_le_bytes());
+        buf.extend([b'A', b'B', 0, 0]);
+        buf.extend(ts_recv.to_le_bytes());
+        buf.extend(0_i32.to_le_bytes());
+        buf.extend(1_u32.to_le_bytes());
+        for i in 0..10 {
+            let (bid_px, bid_sz, ask_px, ask_sz) = levels.get(i).copied().unwrap_or((UNDEF_PRICE, 0, UNDEF_PRICE, 0));
+            for field in [bid_px.to_le_bytes(), ask_px.to_le_bytes()] {
+                buf.extend(field);
+            }
+            for field in [bid_sz, ask_sz, 1, 1] {
+                buf.extend(field.to_le_bytes());
+            }
+        }
+        buf
+    }
+
+    #[test]
+    fn test_metadata_and_records() {
+        let mut stream = b"DBN\x02".to_vec();
+        stream.extend(4_u32.to_le_bytes());
+        stream.extend(b"meta");
+        stream.extend(mbo(1, b'A', b'B', 100_250_000_000, 5, 10));
+        stream.extend(mbp10(&[(100_000_000_000, 1, 101_000_000_000, 2)], 11));
+
+        let (version, body) = skip_metadata(&stream).unwrap();
+        assert_eq!(version, 2);
+        let parsed = records(body).collect::<Result<Vec<_>, _>>().unwrap();
+        let Record::Mbo(msg) = parsed[0] else { panic!("expected an MBO record") };
+        assert_eq!((msg.order_id, msg.price, msg.size, msg.side, msg.ts_recv), (1, 100_250_000_000, 5, Some(Side::Buy), 10));
+        let Record::Mbp10(msg) = parsed[1] else { panic!("expected an MBP-10 record") };
+        assert_eq!(
+            msg.levels[0],
+            BidAskPair { bid_px: 100_000_000_000, ask_px: 101_000_000_000, bid_sz: 1, ask_sz: 2, bid_ct: 1, ask_ct: 1 }
+        );
+
+        assert_eq!(parse(&body[..20]), Err(DecodeError::Truncated { needed: 56, available: 20 }));
+        assert!(skip_metadata(b"XYZ\x02\x00\x00\x00\x00").is_err());
+    }
+
+    #[test]
+    fn test_mbo_feeds_book() {
+        let stream = [
+            mbo(1, b'A', b'B', 100_000_000_000, 10, 1),
+            mbo(2, b'A', b'B', 100_000_000_000, 5, 2),
+            mbo(3, b'A', b'A', 100_500_000_000, 8, 3),
+            // A fill is reported again as a cancel of the filled size, which alone reduces the order
+            mbo(1, b'F', b'B', 0, 4, 4),
+            mbo(1, b'C', b'B', 0, 4, 4),
+            mbo(3, b'M', b'A', 100_250_000_000, 6, 5),
+            mbo(2, b'C', b'B', 0, 5, 6),
+        ]
+        .concat();
+        let mut tracker = MboBook::for_instrument(7);
+        let mut events = Vec::<Event<FixedDecimal>>::new();
+        for record in records(&stream) {
+            let Record::Mbo(msg) = record.unwrap() else { panic!("expected an MBO record") };
+            tracker.apply(&msg, &mut events).unwrap();
+        }
+        let mut book = BTreeOrderBook::new();
+        events.into_iter().for_each(|event| book.process(event));
+        assert_eq!(tracker.order_count(), 2);
+        let bid = book.best_bid().unwrap();
+        assert_eq!((bid.price, bid.size), (fixed!(100), fixed!(6)));
+        let ask = book.best_ask().unwrap();
+        assert_eq!((ask.price, ask.size), (fixed!(100.25), fixed!(6)));
+
+        let mut events = Vec::<Event<FixedDecimal>>::new();
+        let Record::Mbo(msg) = parse(&mbo(9, b'C', b'B', 0, 1, 7)).unwrap() else { panic!("expected an MBO record") };
+        assert_eq!(tracker.apply(&msg, &mut events), Err(DecodeError::UnknownOrder(9)));
+    }
+
+    #[test]
+    fn test_mbp10_diffs_images() {
+        let mut images = Mbp10Book::new();
+        let mut book = BTreeOrderBook::new();
+        for (i, levels) in [
+            vec![(100_000_000_000, 1, 101_000_000_000, 2), (99_000_000_000, 3, 102_000_000_000, 4)],
+            vec![(100_000_000_000, 2, 102_000_000_000, 4)],
+        ]
+        .iter()
+        .enumerate()
+        {
+            let Record::Mbp10(msg) = parse(&mbp10(levels, i as u64 + 1)).unwrap() else { panic!("expected an MBP-10 record") };
+            let mut events = Vec::<Event<FixedDecimal>>::new();
+            images.apply(&msg, &mut events);
+            if i == 1 {
+                // Two removals and the resized bid
+                assert_eq!(events.len(), 3);
+            }
+            events.into_iter().for_each(|event| book.process(event));
+        }
+        assert_eq!(book.levels(Side::Buy, 10).len(), 1);
+        assert_eq!(book.best_bid().unwrap().size, fixed!(2));
+        assert_eq!(book.best_ask().unwrap().price, fixed!(102));
+    }
+}
@@ -0,0 +1,381 @@
+//! Parser for NASDAQ TotalView-ITCH 5.0.
+//!
+//! [`parse`] decodes a single message without copying: stock symbols and unhandled messages borrow
+//! from the input buffer. [`messages`] walks a BinaryFILE capture, where every message is preceded
+//! by its big-endian `u16` length. All integers are big-endian, prices carry four implied decimal
+//! places and timestamps are nanoseconds since midnight.
+//!
+//! The feed is order-by-order, [`OrderTracker`] aggregates it into per-price Level 2 events for the
+//! crate's books.
+
+use std::collections::HashMap;
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    protocols::{ensure_len, DecodeError},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+/// Decimal places implied by ITCH prices
+pub const PRICE_SCALE: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Fields shared by every message
+pub struct Header {
+    /// Locate code identifying the security for the day
+    pub locate: u16,
+    pub tracking: u16,
+    /// Nanoseconds since midnight
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message<'a> {
+    /// `A` and `F`, the latter with the market participant attribution
+    AddOrder { header: Header, order_ref: u64, side: Side, shares: u32, stock: &'a [u8], price: u32, mpid: Option<&'a [u8]> },
+    /// `E`
+    OrderExecuted { header: Header, order_ref: u64, shares: u32, match_number: u64 },
+    /// `C`, executed at a price different from the one the order was added with
+    OrderExecutedWithPrice { header: Header, order_ref: u64, shares: u32, match_number: u64, printable: bool, price: u32 },
+    /// `X`, a partial cancellation
+    OrderCancel { header: Header, order_ref: u64, shares: u32 },
+    /// `D`
+    OrderDelete { header: Header, order_ref: u64 },
+    /// `U`, the original order is removed and a new one added on the same side
+    OrderReplace { header: Header, original_ref: u64, new_ref: u64, shares: u32, price: u32 },
+    /// Any message type without effect on the book
+    Other { kind: u8, payload: &'a [u8] },
+}
+
+impl Message<'_> {
+    #[inline]
+    #[must_use]
+    pub const fn header(&self) -> Option<Header> {
+        match self {
+            Self::AddOrder { header, .. }
+            | Self::OrderExecuted { header, .. }
+            | Self::OrderExecutedWithPrice { header, .. }
+            | Self::OrderCancel { header, .. }
+            | Self::OrderDelete { header, .. }
+            | Self::OrderReplace { header, .. } => Some(*header),
+            Self::Other { .. } => None,
+        }
+    }
+}
+
+#[inline(always)]
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([buf[at], buf[at + 1]])
+}
+
+#[inline(always)]
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(buf[at..at + 4].try_into().expect("slice of four bytes"))
+}
+
+#[inline(always)]
+fn u64_at(buf: &[u8], at: usize) -> u64 {
+    u64::from_be_bytes(buf[at..at + 8].try_into().expect("slice of eight bytes"))
+}
+
+#[inline(always)]
+fn header(buf: &[u8]) -> Header {
+    let mut timestamp = [0; 8];
+    timestamp[2..].copy_from_slice(&buf[5..11]);
+    Header { locate: u16_at(buf, 1), tracking: u16_at(buf, 3), timestamp: u64::from_be_bytes(timestamp) }
+}
+
+/// Decode one message, `buf` starting at the message type byte
+pub fn parse(buf: &[u8]) -> Result<Message<'_>, DecodeError> {
+    ensure_len(buf, 1)?;
+    let (kind, len) = match buf[0] {
+        b'A' => (b'A', 36),
+        b'F' => (b'F', 40),
+        b'E' => (b'E', 31),
+        b'C' => (b'C', 36),
+        b'X' => (b'X', 23),
+        b'D' => (b'D', 19),
+        b'U' => (b'U', 35),
+        other => return Ok(Message::Other { kind: other, payload: &buf[1..] }),
+    };
+    ensure_len(buf, len)?;
+    let header = header(buf);
+
+    let message = match kind {
+        b'A' | b'F' => Message::AddOrder {
+            header,
+            order_ref: u64_at(buf, 11),
+            side: match buf[19] {
+                b'B' => Side::Buy,
+                b'S' => Side::Sell,
+                other => return Err(DecodeError::InvalidField { field: "side", value: u64::from(other) }),
+            },
+            shares: u32_at(buf, 20),
+            stock: &buf[24..32],
+            price: u32_at(buf, 32),
+            mpid: (kind == b'F').then(|| &buf[36..40]),
+        },
+        b'E' => {
+            Message::OrderExecuted { header, order_ref: u64_at(buf, 11), shares: u32_at(buf, 19), match_number: u64_at(buf, 23) }
+        }
+        b'C' => Message::OrderExecutedWithPrice {
+            header,
+            order_ref: u64_at(buf, 11),
+            shares: u32_at(buf, 19),
+            match_number: u64_at(buf, 23),
+            printable: buf[31] == b'Y',
+            price: u32_at(buf, 32),
+        },
+        b'X' => Message::OrderCancel { header, order_ref: u64_at(buf, 11), shares: u32_at(buf, 19) },
+        b'D' => Message::OrderDelete { header, order_ref: u64_at(buf, 11) },
+        _ => Message::OrderReplace {
+            header,
+            original_ref: u64_at(buf, 11),
+            new_ref: u64_at(buf, 19),
+            shares: u32_at(buf, 27),
+            price: u32_at(buf, 31),
+        },
+    };
+    Ok(message)
+}
+
+#[derive(Debug, Clone)]
+/// Iterator over the messages of a length-prefixed BinaryFILE buffer
+pub struct Messages<'a> {
+    buf: &'a [u8],
+}
+
+#[inline]
+#[must_use]
+pub const fn messages(buf: &[u8]) -> Messages<'_> {
+    Messages { buf }
+}
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = Result<Message<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        if let Err(err) = ensure_len(self.buf, 2) {
+            self.buf = &[];
+            return Some(Err(err));
+        }
+        let len = usize::from(u16_at(self.buf, 0));
+        let Some(message) = self.buf.get(2..2 + len) else {
+            let err = DecodeError::Truncated { needed: 2 + len, available: self.buf.len() };
+            self.buf = &[];
+            return Some(Err(err));
+        };
+        self.buf = &self.buf[2 + len..];
+        Some(parse(message))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Order {
+    side: Side,
+    price: u32,
+    shares: u32,
+}
+
+#[derive(Debug, Default)]
+/// Tracks resting orders and turns order-level messages into Level 2 events carrying the aggregate
+/// size left at the affected price.
+pub struct OrderTracker {
+    /// Only messages for this locate code are applied, all when unset
+    locate: Option<u16>,
+    orders: HashMap<u64, Order>,
+    levels: HashMap<(Side, u32), u64>,
+}
+
+impl OrderTracker {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Track a single security, identified by its locate code from the stock directory
+    pub fn for_locate(locate: u16) -> Self {
+        Self { locate: Some(locate), ..Self::default() }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn order_count(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// Apply a message, appending the resulting events to `events`.
+    ///
+    /// Executions and cancellations reduce the resting order, deletes and replaces remove it. Messages
+    /// referring to orders added before tracking started fail with [`DecodeError::UnknownOrder`].
+    pub fn apply<V: DecimalType>(&mut self, message: &Message<'_>, events: &mut Vec<Event<V>>) -> Result<(), DecodeError> {
+        let Some(header) = message.header() else {
+            return Ok(());
+        };
+        if self.locate.is_some_and(|locate| locate != header.locate) {
+            return Ok(());
+        }
+        let ts = Timestamp::from_nanos(header.timestamp as i64);
+
+        match *message {
+            Message::AddOrder { order_ref, side, shares, price, .. } => {
+                self.orders.insert(order_ref, Order { side, price, shares });
+                events.push(self.change_level(side, price, i64::from(shares), ts));
+            }
+            Message::OrderExecuted { order_ref, shares, .. }
+            | Message::OrderExecutedWithPrice { order_ref, shares, .. }
+            | Message::OrderCancel { order_ref, shares, .. } => {
+                let order = self.orders.get_mut(&order_ref).ok_or(DecodeError::UnknownOrder(order_ref))?;
+                let shares = shares.min(order.shares);
+                order.shares -= shares;
+                let Order { side, price, shares: left } = *order;
+                if left == 0 {
+                    self.orders.remove(&order_ref);
+                }
+                events.push(self.change_level(side, price, -i64::from(shares), ts));
+            }
+            Message::OrderDelete { order_ref, .. } => {
+                let order = self.orders.remove(&order_ref).ok_or(DecodeError::UnknownOrder(order_ref))?;
+                events.push(self.change_level(order.side, order.price, -i64::from(order.shares), ts));
+            }
+            Message::OrderReplace { original_ref, new_ref, shares, price, .. } => {
+                let order = self.orders.remove(&original_ref).ok_or(DecodeError::UnknownOrder(original_ref))?;
+                events.push(self.change_level(order.side, order.price, -i64::from(order.shares), ts));
+                self.orders.insert(new_ref, Order { side: order.side, price, shares });
+                events.push(self.change_level(order.side, price, i64::from(shares), ts));
+            }
+            Message::Other { .. } => {}
+        }
+        Ok(())
+    }
+
+    fn change_level<V: DecimalType>(&mut self, side: Side, price: u32, delta: i64, ts: Timestamp) -> Event<V> {
+        let size = self.levels.entry((side, price)).or_default();
+        *size = size.saturating_add_signed(delta);
+        let size = *size;
+        if size == 0 {
+            self.levels.remove(&(side, price));
+        }
+        Event::new(EventKind::L2, side, V::from_scaled(i64::from(price), PRICE_SCALE), V::from_scaled(size as i64, 0), ts)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, interface::OrderBook as _},
+        decimals::fixed_decimal::FixedDecimal,
+        fixed,
+        protocols::{
+            itch::{messages, parse, Message, OrderTracker},
+            DecodeError,
+        },
+        side::Side,
+    };
+
+    fn header(kind: u8, locate: u16, timestamp: u64) -> Vec<u8> {
+        let mut buf = vec![kind];
+        buf.extend(locate.to_be_bytes());
+        buf.extend(0_u16.to_be_bytes());
+        buf.extend(&timestamp.to_be_bytes()[2..]);
+        buf
+    }
+
+    fn add(order_ref: u64, side: u8, shares: u32, price: u32, timestamp: u64) -> Vec<u8> {
+        let mut buf = header(b'A', 1, timestamp);
+        buf.extend(order_ref.to_be_bytes());
+        buf.push(side);
+        buf.extend(shares.to_be_bytes());
+        buf.extend(b"AAPL    ");
+        buf.extend(price.to_be_bytes());
+        buf
+    }
+
+    fn executed(order_ref: u64, shares: u32, timestamp: u64) -> Vec<u8> {
+        let mut buf = header(b'E', 1, timestamp);
+        buf.extend(order_ref.to_be_bytes());
+        buf.extend(shares.to_be_bytes());
+        buf.extend(7_u64.to_be_bytes());
+        buf
+    }
+
+    fn replace(original_ref: u64, new_ref: u64, shares: u32, price: u32, timestamp: u64) -> Vec<u8> {
+        let mut buf = header(b'U', 1, timestamp);
+        buf.extend(original_ref.to_be_bytes());
+        buf.extend(new_ref.to_be_bytes());
+        buf.extend(shares.to_be_bytes());
+        buf.extend(price.to_be_bytes());
+        buf
+    }
+
+    fn delete(order_ref: u64, timestamp: u64) -> Vec<u8> {
+        let mut buf = header(b'D', 1, timestamp);
+        buf.extend(order_ref.to_be_bytes());
+        buf
+    }
+
+    fn binary_file(messages: &[Vec<u8>]) -> Vec<u8> {
+        messages.iter().flat_map(|m| (m.len() as u16).to_be_bytes().into_iter().chain(m.iter().copied())).collect()
+    }
+
+    #[test]
+    fn test_parse_add_order() {
+        let buf = add(42, b'B', 100, 1_502_500, 34_200_000_000_123);
+        let Message::AddOrder { header, order_ref, side, shares, stock, price, mpid } = parse(&buf).unwrap() else {
+            panic!("expected an add order");
+        };
+        assert_eq!((header.locate, header.timestamp), (1, 34_200_000_000_123));
+        assert_eq!((order_ref, side, shares, price, mpid), (42, Side::Buy, 100, 1_502_500, None));
+        assert_eq!(stock, b"AAPL    ");
+
+        assert_eq!(parse(&buf[..20]), Err(DecodeError::Truncated { needed: 36, available: 20 }));
+        assert!(matches!(parse(b"S\x00\x00"), Ok(Message::Other { kind: b'S', .. })));
+    }
+
+    #[test]
+    fn test_tracker_feeds_book() {
+        let capture = binary_file(&[
+            add(1, b'B', 100, 1_500_000, 1),
+            add(2, b'B', 50, 1_500_000, 2),
+            add(3, b'S', 80, 1_501_000, 3),
+            executed(1, 40, 4),
+            replace(3, 4, 60, 1_500_500, 5),
+            delete(2, 6),
+        ]);
+        let mut tracker = OrderTracker::for_locate(1);
+        let mut lob = ArrayOrderbook::<10, FixedDecimal>::new();
+        let mut events = Vec::new();
+        for message in messages(&capture) {
+            tracker.apply(&message.unwrap(), &mut events).unwrap();
+        }
+        assert_eq!(events.len(), 7);
+        assert_eq!(events[3].size, fixed!(110));
+        events.into_iter().for_each(|e| lob.process(e));
+
+        assert_eq!(tracker.order_count(), 2);
+        let bid = lob.best_bid().unwrap();
+        assert_eq!((bid.price, bid.size), (fixed!(150), fixed!(60)));
+        let ask = lob.best_ask().unwrap();
+        assert_eq!((ask.price, ask.size), (fixed!(150.05), fixed!(60)));
+    }
+
+    #[test]
+    fn test_unknown_order() {
+        let mut events = Vec::<crate::event::Event<FixedDecimal>>::new();
+        let buf = delete(9, 1);
+        let message = parse(&buf).unwrap();
+        assert_eq!(OrderTracker::new().apply(&message, &mut events), Err(DecodeError::UnknownOrder(9)));
+        // Other securities are skipped before their orders are looked up
+        assert_eq!(OrderTracker::for_locate(2).apply(&message, &mut events), Ok(()));
+    }
+}
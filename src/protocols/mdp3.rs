@@ -0,0 +1,354 @@
+//! Decoder for CME MDP 3.0 incremental refresh packets (SBE encoding).
+//!
+//! A packet is a 12 byte header (`MsgSeqNum: u32`, `SendingTime: u64`) followed by messages, each
+//! prefixed by its `u16` size and an 8 byte SBE header (block length, template, schema, version).
+//! All integers are little-endian. `MDIncrementalRefreshBook` (template 46) entries address the
+//! book by price level rather than price, so [`Mdp3Decoder`] keeps a ladder per instrument and side
+//! to translate `New`, `Delete`, `DeleteThru`, `DeleteFrom` and `Overlay` into Level 2 events
+//! carrying absolute sizes. Outright and implied books are kept apart, see [`BookUpdate::implied`].
+//!
+//! Packets are arbitrated by sequence number so the A and B feeds can be fed into one decoder:
+//! duplicates are dropped and gaps fail with [`DecodeError::SequenceGap`]. Other templates are
+//! skipped, except `ChannelReset` (template 4) which empties every book.
+
+use std::collections::HashMap;
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    protocols::{ensure_len, DecodeError},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+/// Exponent of `PRICE9` prices
+pub const PRICE_SCALE: u32 = 9;
+
+const PACKET_HEADER_LEN: usize = 12;
+const SBE_HEADER_LEN: usize = 8;
+const CHANNEL_RESET: u16 = 4;
+const INCREMENTAL_REFRESH_BOOK: u16 = 46;
+
+#[derive(Debug)]
+/// A Level 2 event for one of an instrument's books
+pub struct BookUpdate<V: DecimalType> {
+    pub security_id: i32,
+    /// Whether the event is for the implied book rather than the outright one
+    pub implied: bool,
+    pub event: Event<V>,
+}
+
+#[derive(Debug, Default)]
+struct Instrument {
+    rpt_seq: Option<u32>,
+    /// Prices (`PRICE9` mantissas) and sizes by level, best first, indexed by [`ladder_index`]
+    ladders: [Vec<(i64, i32)>; 4],
+}
+
+#[inline(always)]
+const fn ladder_index(side: Side, implied: bool) -> usize {
+    (implied as usize) << 1 | (side as usize)
+}
+
+#[derive(Debug)]
+pub struct Mdp3Decoder {
+    next_seq: Option<u32>,
+    /// Maximum levels of the outright and implied books, levels pushed past them are removed
+    depth: (usize, usize),
+    instruments: HashMap<i32, Instrument>,
+}
+
+impl Default for Mdp3Decoder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[inline(always)]
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+#[inline(always)]
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(buf[at..at + 4].try_into().expect("slice of four bytes"))
+}
+
+#[inline(always)]
+fn u64_at(buf: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(buf[at..at + 8].try_into().expect("slice of eight bytes"))
+}
+
+impl Mdp3Decoder {
+    #[inline]
+    #[must_use]
+    /// Decoder for the default CME depths, 10 outright and 2 implied levels
+    pub fn new() -> Self {
+        Self::with_depth(10, 2)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_depth(outright: usize, implied: usize) -> Self {
+        Self { next_seq: None, depth: (outright, implied), instruments: HashMap::new() }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Sequence number the next packet must carry, `None` before the first packet
+    pub const fn next_seq(&self) -> Option<u32> {
+        self.next_seq
+    }
+
+    /// Decode one packet, appending the resulting events to `updates`.
+    ///
+    /// A packet already seen (the other feed of the A/B pair) is ignored. After a
+    /// [`DecodeError::SequenceGap`] the next packet is accepted so decoding can carry on once the
+    /// books were recovered, e.g. from a snapshot channel.
+    pub fn decode_packet<V: DecimalType>(&mut self, packet: &[u8], updates: &mut Vec<BookUpdate<V>>) -> Result<(), DecodeError> {
+        ensure_len(packet, PACKET_HEADER_LEN)?;
+        let seq = u32_at(packet, 0);
+        match self.next_seq {
+            Some(expected) if seq < expected => return Ok(()),
+            Some(expected) if seq > expected => {
+                self.next_seq = None;
+                return Err(DecodeError::SequenceGap { expected: u64::from(expected), received: u64::from(seq) });
+            }
+            _ => self.next_seq = Some(seq.wrapping_add(1)),
+        }
+
+        let mut buf = &packet[PACKET_HEADER_LEN..];
+        while !buf.is_empty() {
+            ensure_len(buf, 2 + SBE_HEADER_LEN)?;
+            let size = usize::from(u16_at(buf, 0));
+            ensure_len(buf, size.max(2 + SBE_HEADER_LEN))?;
+            let (message, rest) = buf.split_at(size.max(2 + SBE_HEADER_LEN));
+            buf = rest;
+
+            let block_length = usize::from(u16_at(message, 2));
+            let body = &message[2 + SBE_HEADER_LEN..];
+            match u16_at(message, 4) {
+                INCREMENTAL_REFRESH_BOOK => self.decode_book(body, block_length, updates)?,
+                CHANNEL_RESET => {
+                    ensure_len(body, 8)?;
+                    self.reset(Timestamp::from_nanos(u64_at(body, 0) as i64), updates);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_book<V: DecimalType>(
+        &mut self,
+        body: &[u8],
+        block_length: usize,
+        updates: &mut Vec<BookUpdate<V>>,
+    ) -> Result<(), DecodeError> {
+        // Root block: TransactTime, MatchEventIndicator, padding
+        ensure_len(body, block_length.max(8) + 3)?;
+        let ts = Timestamp::from_nanos(u64_at(body, 0) as i64);
+        let group = &body[block_length..];
+        let (entry_length, count) = (usize::from(u16_at(group, 0)), usize::from(group[2]));
+        ensure_len(group, 3 + entry_length.max(27) * count)?;
+
+        for entry in group[3..].chunks_exact(entry_length.max(27)).take(count) {
+            let price = u64_at(entry, 0) as i64;
+            let size = u32_at(entry, 8) as i32;
+            let security_id = u32_at(entry, 12) as i32;
+            let rpt_seq = u32_at(entry, 16);
+            let level = usize::from(entry[24]);
+            let (side, implied) = match entry[26] {
+                b'0' => (Side::Buy, false),
+                b'1' => (Side::Sell, false),
+                b'E' => (Side::Buy, true),
+                b'F' => (Side::Sell, true),
+                other => return Err(DecodeError::InvalidField { field: "MDEntryType", value: u64::from(other) }),
+            };
+
+            let instrument = self.instruments.entry(security_id).or_default();
+            if let Some(last) = instrument.rpt_seq {
+                if rpt_seq <= last {
+                    continue;
+                }
+                if rpt_seq != last.wrapping_add(1) {
+                    instrument.rpt_seq = None;
+                    return Err(DecodeError::SequenceGap { expected: u64::from(last) + 1, received: u64::from(rpt_seq) });
+                }
+            }
+            instrument.rpt_seq = Some(rpt_seq);
+
+            let depth = if implied { self.depth.1 } else { self.depth.0 };
+            let ladder = &mut instrument.ladders[ladder_index(side, implied)];
+            let mut emit = |price: i64, size: i32| {
+                let event =
+                    Event::new(EventKind::L2, side, V::from_scaled(price, PRICE_SCALE), V::from_scaled(i64::from(size), 0), ts);
+                updates.push(BookUpdate { security_id, implied, event: event.with_sequence_id(u64::from(rpt_seq)) });
+            };
+
+            let index = level.saturating_sub(1);
+            match entry[25] {
+                // New
+                0 => {
+                    ladder.insert(index.min(ladder.len()), (price, size));
+                    emit(price, size);
+                    if ladder.len() > depth {
+                        let (price, _) = ladder.pop().expect("ladder is over depth");
+                        emit(price, 0);
+                    }
+                }
+                // Change
+                1 => {
+                    if let Some(slot) = ladder.get_mut(index) {
+                        *slot = (price, size);
+                    }
+                    emit(price, size);
+                }
+                // Delete
+                2 => {
+                    if index < ladder.len() {
+                        ladder.remove(index);
+                    }
+                    emit(price, 0);
+                }
+                // DeleteThru, the whole side
+                3 => ladder.drain(..).for_each(|(price, _)| emit(price, 0)),
+                // DeleteFrom, the top `level` levels
+                4 => ladder.drain(..level.min(ladder.len())).for_each(|(price, _)| emit(price, 0)),
+                // Overlay, the level moves to a new price
+                5 => {
+                    if let Some(slot) = ladder.get_mut(index) {
+                        let (old, _) = std::mem::replace(slot, (price, size));
+                        if old != price {
+                            emit(old, 0);
+                        }
+                    }
+                    emit(price, size);
+                }
+                other => return Err(DecodeError::InvalidField { field: "MDUpdateAction", value: u64::from(other) }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Empty every book after a channel reset, emitting removals for all known levels
+    fn reset<V: DecimalType>(&mut self, ts: Timestamp, updates: &mut Vec<BookUpdate<V>>) {
+        for (&security_id, instrument) in &mut self.instruments {
+            instrument.rpt_seq = None;
+            for (index, ladder) in instrument.ladders.iter_mut().enumerate() {
+                let (side, implied) = (if index & 1 == 0 { Side::Buy } else { Side::Sell }, index >= 2);
+                for (price, _) in ladder.drain(..) {
+                    let event = Event::new(EventKind::L2, side, V::from_scaled(price, PRICE_SCALE), V::ZERO, ts);
+                    updates.push(BookUpdate { security_id, implied, event });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook as _},
+        decimals::fixed_decimal::FixedDecimal,
+        fixed,
+        protocols::{
+            mdp3::{BookUpdate, Mdp3Decoder},
+            DecodeError,
+        },
+    };
+
+    /// `(price, size, rpt_seq, level, action, entry type)`
+    type Entry = (i64, i32, u32, u8, u8, u8);
+
+    fn packet(seq: u32, entries: &[Entry]) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend(1_700_000_000_000_000_000_u64.to_le_bytes());
+        body.extend([0x81, 0, 0]);
+        body.extend(32_u16.to_le_bytes());
+        body.push(entries.len() as u8);
+        for &(price, size, rpt_seq, level, action, kind) in entries {
+            body.extend(price.to_le_bytes());
+            body.extend(size.to_le_bytes());
+            body.extend(7_i32.to_le_bytes());
+            body.extend(rpt_seq.to_le_bytes());
+            body.extend(3_i32.to_le_bytes());
+            body.extend([level, action, kind, 0, 0, 0, 0, 0]);
+        }
+        // Empty order entry group
+        body.extend(24_u16.to_le_bytes());
+        body.extend([0, 0, 0, 0, 0, 0]);
+
+        let mut packet = Vec::new();
+        packet.extend(seq.to_le_bytes());
+        packet.extend(0_u64.to_le_bytes());
+        packet.extend(((2 + 8 + body.len()) as u16).to_le_bytes());
+        for field in [11_u16, 46, 1, 9] {
+            packet.extend(field.to_le_bytes());
+        }
+        packet.extend(body);
+        packet
+    }
+
+    const PX: i64 = 1_000_000_000;
+
+    #[test]
+    fn test_ladder_actions() {
+        let mut decoder = Mdp3Decoder::with_depth(2, 2);
+        let mut updates = Vec::<BookUpdate<FixedDecimal>>::new();
+        decoder
+            .decode_packet(
+                &packet(1, &[(4500 * PX, 10, 1, 1, 0, b'0'), (4499 * PX, 5, 2, 2, 0, b'0'), (4501 * PX, 7, 3, 1, 0, b'1')]),
+                &mut updates,
+            )
+            .unwrap();
+        // A better bid pushes the 4499 level out of the two level book, then the ask is overlaid
+        decoder
+            .decode_packet(
+                &packet(
+                    2,
+                    &[(450_025 * PX / 100, 2, 4, 1, 0, b'0'), (4502 * PX, 9, 5, 1, 5, b'1'), (4500 * PX, 1, 6, 1, 0, b'E')],
+                ),
+                &mut updates,
+            )
+            .unwrap();
+
+        let mut outright = BTreeOrderBook::<FixedDecimal>::new();
+        let mut implied = BTreeOrderBook::<FixedDecimal>::new();
+        for update in updates {
+            assert_eq!(update.security_id, 7);
+            if update.implied {
+                implied.process(update.event)
+            } else {
+                outright.process(update.event)
+            }
+        }
+        assert_eq!(outright.best_bid().unwrap().price, fixed!(4500.25));
+        assert_eq!(outright.best_ask().unwrap().price, fixed!(4502));
+        assert_eq!(outright.levels(crate::side::Side::Buy, 5).len(), 2);
+        assert_eq!(implied.best_bid().unwrap().size, fixed!(1));
+    }
+
+    #[test]
+    fn test_arbitration_and_gaps() {
+        let mut decoder = Mdp3Decoder::new();
+        let mut updates = Vec::<BookUpdate<FixedDecimal>>::new();
+        let first = packet(10, &[(4500 * PX, 10, 1, 1, 0, b'0')]);
+        decoder.decode_packet(&first, &mut updates).unwrap();
+        // The same packet from the B feed is dropped
+        decoder.decode_packet(&first, &mut updates).unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(decoder.next_seq(), Some(11));
+
+        let late = packet(12, &[(4500 * PX, 8, 2, 1, 1, b'0')]);
+        assert_eq!(decoder.decode_packet(&late, &mut updates), Err(DecodeError::SequenceGap { expected: 11, received: 12 }));
+
+        let skipped = packet(13, &[(4500 * PX, 8, 4, 1, 1, b'0')]);
+        assert_eq!(decoder.decode_packet(&skipped, &mut updates), Err(DecodeError::SequenceGap { expected: 2, received: 4 }));
+        let truncated = packet(14, &[(4500 * PX, 8, 5, 1, 1, b'0')]);
+        assert!(matches!(decoder.decode_packet(&truncated[..40], &mut updates), Err(DecodeError::Truncated { .. })));
+    }
+}
@@ -0,0 +1,44 @@
+//! Decoders for binary exchange protocols, each behind its own feature.
+
+#[cfg(feature = "dbn")]
+pub mod dbn;
+#[cfg(feature = "itch")]
+pub mod itch;
+#[cfg(feature = "mdp3")]
+pub mod mdp3;
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The buffer ends before the message or field does
+    Truncated { needed: usize, available: usize },
+    /// A field holds a value the protocol does not define
+    InvalidField { field: &'static str, value: u64 },
+    /// A message refers to an order that is not being tracked
+    UnknownOrder(u64),
+    /// A packet does not follow on from the previous one of its channel
+    SequenceGap { expected: u64, received: u64 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated { needed, available } => write!(f, "truncated message: needed {needed} bytes, {available} available"),
+            Self::InvalidField { field, value } => write!(f, "invalid {field}: {value}"),
+            Self::UnknownOrder(order) => write!(f, "unknown order {order}"),
+            Self::SequenceGap { expected, received } => write!(f, "sequence gap (expected {expected}, received {received})"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[inline]
+/// Fail with [`DecodeError::Truncated`] unless `buf` holds at least `needed` bytes
+pub(crate) const fn ensure_len(buf: &[u8], needed: usize) -> Result<(), DecodeError> {
+    if buf.len() < needed {
+        return Err(DecodeError::Truncated { needed, available: buf.len() });
+    }
+    Ok(())
+}
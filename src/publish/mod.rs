@@ -0,0 +1,6 @@
+//! Publishers pushing book state to external consumers.
+
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(all(feature = "shm", unix))]
+pub mod shm;
@@ -0,0 +1,314 @@
+//! Redis publisher of [`BookDelta`]s and periodic top-N snapshots.
+//!
+//! Messages are JSON with decimals written as strings, published to channels keyed by symbol:
+//!
+//! ```text
+//! PUBLISH {prefix}:{symbol}:deltas    [{"side":"buy","price":"100.5","size":"2","ts":1000,"seq":7,"reset":false}]
+//! PUBLISH {prefix}:{symbol}:snapshots {"ts":1000,"seq":7,"bids":[["100.5","2"]],"asks":[["101","1"]]}
+//! ```
+//!
+//! A delta that emptied its side before applying the level also carries `"cleared_side":true`.
+//!
+//! With [`RedisPublisher::with_streams`] the same payloads are appended to streams of the same names
+//! under a `data` field instead (`XADD key MAXLEN ~ n * data ...`), so late consumers can read history.
+//!
+//! The crate does not depend on a Redis client. Commands are encoded by hand as RESP arrays of bulk
+//! strings and written to any [`Write`], and [`RedisPublisher::connect`] opens a plain TCP connection
+//! and turns server replies off so they never need to be read. That keeps the publisher to a few
+//! dozen lines, at the cost of never seeing a server error, and of no TLS, authentication, pipelining
+//! or cluster support.
+//!
+//! Conflation windows run on event time, so a window only closes when a later delta arrives. Call
+//! [`RedisPublisher::advance`] from a timer to publish the last window of a book that went quiet.
+
+use std::{
+    fmt::{Display, Write as _},
+    io::{self, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    formats::side_name,
+    level::Level,
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug)]
+pub struct RedisPublisher<W: Write, V: DecimalType> {
+    writer: W,
+    prefix: String,
+    symbol: String,
+    /// Maximum stream length when publishing to streams, `None` for pub/sub channels
+    stream_len: Option<usize>,
+    /// Window of event time over which deltas are conflated, `None` to publish every delta
+    conflation: Option<Duration>,
+    pending: Vec<BookDelta<V>>,
+    window_start: Timestamp,
+    snapshot_interval: Option<Duration>,
+    snapshot_depth: usize,
+    last_snapshot: Option<Timestamp>,
+}
+
+impl<V: DecimalType + Copy + PartialEq + Display> RedisPublisher<TcpStream, V> {
+    /// Connect to a Redis server and disable replies for the connection
+    pub fn connect(addr: impl ToSocketAddrs, symbol: &str) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        stream.write_all(&command(&["CLIENT", "REPLY", "OFF"]))?;
+        Ok(Self::new(stream, symbol))
+    }
+}
+
+impl<W: Write, V: DecimalType + Copy + PartialEq + Display> RedisPublisher<W, V> {
+    #[inline]
+    #[must_use]
+    pub fn new(writer: W, symbol: &str) -> Self {
+        Self {
+            writer,
+            prefix: "book".to_owned(),
+            symbol: symbol.to_owned(),
+            stream_len: None,
+            conflation: None,
+            pending: Vec::new(),
+            window_start: Timestamp::ZERO,
+            snapshot_interval: None,
+            snapshot_depth: 0,
+            last_snapshot: None,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Key prefix, `book` by default
+    pub fn with_prefix(self, prefix: &str) -> Self {
+        Self { prefix: prefix.to_owned(), ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Append to streams capped at roughly `max_len` entries instead of publishing to channels
+    pub fn with_streams(self, max_len: usize) -> Self {
+        Self { stream_len: Some(max_len), ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Keep only the latest delta per level over windows of `window` of event time, publishing each
+    /// window as one message
+    pub fn with_conflation(self, window: Duration) -> Self {
+        Self { conflation: Some(window.max(Duration::from_nanos(1))), ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Publish the top `depth` levels of each side every `interval` of event time
+    pub fn with_snapshots(self, interval: Duration, depth: usize) -> Self {
+        Self { snapshot_interval: Some(interval.max(Duration::from_nanos(1))), snapshot_depth: depth, ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    /// Publish `delta`, the change just applied to `book`, and a snapshot of `book` when one is due
+    pub fn publish<B: OrderBook<V>>(&mut self, book: &B, delta: BookDelta<V>) -> io::Result<()> {
+        let ts = delta.timestamp;
+        if self.conflation.is_none() {
+            self.send_deltas(&[delta])?;
+        } else {
+            self.advance(ts)?;
+            if self.pending.is_empty() {
+                self.window_start = ts;
+            }
+            self.conflate(delta);
+        }
+        if let Some(interval) = self.snapshot_interval {
+            if self.last_snapshot.is_none_or(|last| ts.saturating_duration_since(last) >= interval) {
+                self.last_snapshot = Some(ts);
+                self.send_snapshot(book, ts, delta.sequence_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Publish the conflated deltas once `now` is past the end of their window, for a timer to call so
+    /// a book that went quiet does not hold its last changes back
+    pub fn advance(&mut self, now: Timestamp) -> io::Result<()> {
+        match self.conflation {
+            Some(window) if now.saturating_duration_since(self.window_start) >= window => self.flush_deltas(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Publish any conflated deltas still pending and flush the writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.flush_deltas()?;
+        self.writer.flush()
+    }
+
+    fn conflate(&mut self, delta: BookDelta<V>) {
+        if delta.reset {
+            // Nothing published before a reset matters to consumers
+            self.pending.clear();
+            self.pending.push(delta);
+            return;
+        }
+        if delta.cleared_side {
+            self.pending.retain(|pending| pending.reset || pending.side != delta.side);
+            self.pending.push(delta);
+            return;
+        }
+        match self.pending.iter_mut().find(|pending| pending.side == delta.side && pending.price == delta.price) {
+            Some(pending) => *pending = BookDelta { reset: pending.reset, cleared_side: pending.cleared_side, ..delta },
+            None => self.pending.push(delta),
+        }
+    }
+
+    fn flush_deltas(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let pending = std::mem::take(&mut self.pending);
+        self.send_deltas(&pending)
+    }
+
+    fn send_deltas(&mut self, deltas: &[BookDelta<V>]) -> io::Result<()> {
+        let mut payload = String::from("[");
+        for (i, delta) in deltas.iter().enumerate() {
+            let side = side_name(delta.side);
+            let separator = if i == 0 { "" } else { "," };
+            let _ = write!(
+                payload,
+                r#"{separator}{{"side":"{side}","price":"{}","size":"{}","ts":{},"seq":{},"reset":{}"#,
+                delta.price, delta.size, delta.timestamp, delta.sequence_id, delta.reset
+            );
+            if delta.cleared_side {
+                payload.push_str(r#","cleared_side":true"#);
+            }
+            payload.push('}');
+        }
+        payload.push(']');
+        self.send("deltas", &payload)
+    }
+
+    fn send_snapshot<B: OrderBook<V>>(&mut self, book: &B, ts: Timestamp, sequence_id: u64) -> io::Result<()> {
+        let levels = |levels: Vec<Level<V>>| {
+            let levels = levels.iter().map(|level| format!(r#"["{}","{}"]"#, level.price, level.size)).collect::<Vec<_>>();
+            levels.join(",")
+        };
+        let payload = format!(
+            r#"{{"ts":{ts},"seq":{sequence_id},"bids":[{}],"asks":[{}]}}"#,
+            levels(book.levels(Side::Buy, self.snapshot_depth)),
+            levels(book.levels(Side::Sell, self.snapshot_depth))
+        );
+        self.send("snapshots", &payload)
+    }
+
+    fn send(&mut self, topic: &str, payload: &str) -> io::Result<()> {
+        let key = format!("{}:{}:{topic}", self.prefix, self.symbol);
+        let command = match self.stream_len {
+            None => command(&["PUBLISH", &key, payload]),
+            Some(max_len) => command(&["XADD", &key, "MAXLEN", "~", &max_len.to_string(), "*", "data", payload]),
+        };
+        self.writer.write_all(&command)
+    }
+}
+
+/// Encode a command as a RESP array of bulk strings
+fn command(args: &[&str]) -> Vec<u8> {
+    let mut out = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        out.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        out.extend_from_slice(arg.as_bytes());
+        out.extend_from_slice(b"\r\n");
+    }
+    out
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        publish::redis::RedisPublisher,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn run(publisher: &mut RedisPublisher<Vec<u8>, FixedDecimal>, events: Vec<Event<FixedDecimal>>) -> String {
+        let mut book = BTreeOrderBook::new();
+        for event in events {
+            let delta = book.process_delta(event).unwrap();
+            publisher.publish(&book, delta).unwrap();
+        }
+        publisher.flush().unwrap();
+        String::from_utf8(publisher.writer().clone()).unwrap()
+    }
+
+    #[test]
+    fn test_publish_deltas_and_snapshots() {
+        let mut publisher = RedisPublisher::new(Vec::new(), "BTC-USD").with_snapshots(Duration::from_nanos(100), 1);
+        let out = run(
+            &mut publisher,
+            vec![Event::new(EventKind::L2, Side::Buy, fixed!(100.5), fixed!(2), Timestamp::from_nanos(10)).with_sequence_id(7)],
+        );
+        let delta = r#"[{"side":"buy","price":"100.5","size":"2","ts":10,"seq":7,"reset":false}]"#;
+        let snapshot = r#"{"ts":10,"seq":7,"bids":[["100.5","2"]],"asks":[]}"#;
+        assert_eq!(
+            out,
+            format!(
+                "*3\r\n$7\r\nPUBLISH\r\n$19\r\nbook:BTC-USD:deltas\r\n${}\r\n{delta}\r\n*3\r\n$7\r\nPUBLISH\r\n$22\r\nbook:BTC-USD:snapshots\r\n${}\r\n{snapshot}\r\n",
+                delta.len(),
+                snapshot.len()
+            )
+        );
+    }
+
+    #[test]
+    fn test_conflation_keeps_latest_per_level() {
+        let mut publisher = RedisPublisher::new(Vec::new(), "ETH")
+            .with_prefix("md")
+            .with_conflation(Duration::from_nanos(100))
+            .with_streams(1000);
+        let out = run(
+            &mut publisher,
+            vec![
+                Event::new(EventKind::L2, Side::Sell, fixed!(10), fixed!(1), Timestamp::ZERO),
+                Event::new(EventKind::L2, Side::Sell, fixed!(11), fixed!(1), Timestamp::from_nanos(10)),
+                Event::new(EventKind::L2, Side::Sell, fixed!(10), fixed!(3), Timestamp::from_nanos(20)),
+                Event::new(EventKind::L2, Side::Sell, fixed!(10), fixed!(0), Timestamp::from_nanos(150)),
+            ],
+        );
+        let messages = out.matches("XADD").count();
+        assert_eq!(messages, 2);
+        assert!(out.contains("md:ETH:deltas\r\n$6\r\nMAXLEN\r\n$1\r\n~\r\n$4\r\n1000\r\n$1\r\n*\r\n$4\r\ndata\r\n"));
+        assert!(
+            out.contains(r#"[{"side":"sell","price":"10","size":"3","ts":20,"seq":0,"reset":false},{"side":"sell","price":"11""#)
+        );
+        assert!(out.contains(r#"[{"side":"sell","price":"10","size":"0","ts":150,"seq":0,"reset":false}]"#));
+    }
+
+    #[test]
+    fn test_advance_closes_quiet_window() {
+        let mut publisher = RedisPublisher::new(Vec::new(), "ETH").with_conflation(Duration::from_nanos(100));
+        let mut book = BTreeOrderBook::new();
+        let delta = book.process_delta(Event::new(EventKind::L2, Side::Buy, fixed!(10), fixed!(1), Timestamp::from_nanos(5)));
+        publisher.publish(&book, delta.unwrap()).unwrap();
+        publisher.advance(Timestamp::from_nanos(104)).unwrap();
+        assert!(publisher.writer().is_empty());
+        publisher.advance(Timestamp::from_nanos(105)).unwrap();
+        assert_eq!(String::from_utf8_lossy(publisher.writer()).matches("PUBLISH").count(), 1);
+    }
+}
@@ -0,0 +1,281 @@
+//! Top-of-book publishing through POSIX shared memory.
+//!
+//! [`ShmWriter`] owns a named segment holding one [`ShmLayout`], a `repr(C)` block of the top `N`
+//! levels per side guarded by a seqlock: the writer makes the version odd, updates the levels and
+//! makes it even again, and [`ShmReader`] retries any copy during which the version was odd or
+//! changed. Readers in other processes never block the writer, and non-Rust readers can follow the
+//! same protocol from the layout alone:
+//!
+//! | offset            | content                                                   |
+//! |-------------------|-----------------------------------------------------------|
+//! | `0`               | version (`u64`), odd while an update is in progress       |
+//! | `8`               | timestamp (`i64`)                                         |
+//! | `16`              | sequence ID (`u64`)                                       |
+//! | `24`              | populated bid levels (`u64`)                              |
+//! | `32`              | populated ask levels (`u64`)                              |
+//! | `48`              | `N` bid [`Level`]s, best first, 16 bytes each             |
+//! | `48 + 16 * N`     | `N` ask [`Level`]s, best first                            |
+//!
+//! Each level is a raw [`FixedDecimal`] price followed by a raw size.
+
+use std::{
+    ffi::CString,
+    io, mem, ptr,
+    sync::atomic::{fence, AtomicU64, Ordering},
+};
+
+use crate::{books::interface::OrderBook, decimals::fixed_decimal::FixedDecimal, level::Level, side::Side, timestamp::Timestamp};
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct ShmLayout<const N: usize> {
+    version: AtomicU64,
+    timestamp: i64,
+    sequence_id: u64,
+    bid_len: u64,
+    ask_len: u64,
+    bids: [Level<FixedDecimal>; N],
+    asks: [Level<FixedDecimal>; N],
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A consistent copy of the published levels.
+pub struct ShmSnapshot<const N: usize> {
+    pub timestamp: Timestamp,
+    pub sequence_id: u64,
+    /// Number of times the writer has published
+    pub version: u64,
+    bid_len: usize,
+    ask_len: usize,
+    bids: [Level<FixedDecimal>; N],
+    asks: [Level<FixedDecimal>; N],
+}
+
+impl<const N: usize> ShmSnapshot<N> {
+    #[inline]
+    #[must_use]
+    pub fn bids(&self) -> &[Level<FixedDecimal>] {
+        &self.bids[..self.bid_len]
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn asks(&self) -> &[Level<FixedDecimal>] {
+        &self.asks[..self.ask_len]
+    }
+}
+
+#[derive(Debug)]
+struct Segment {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Segment {
+    fn open(name: &CString, len: usize, create: bool) -> io::Result<Self> {
+        let (flags, prot) = if create {
+            (libc::O_CREAT | libc::O_RDWR, libc::PROT_READ | libc::PROT_WRITE)
+        } else {
+            (libc::O_RDONLY, libc::PROT_READ)
+        };
+        // SAFETY: `name` is NUL terminated and the descriptor is closed on every path below
+        unsafe {
+            let fd = libc::shm_open(name.as_ptr(), flags, 0o600);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let result = Self::map(fd, len, create, prot);
+            libc::close(fd);
+            result
+        }
+    }
+
+    unsafe fn map(fd: libc::c_int, len: usize, create: bool, prot: libc::c_int) -> io::Result<Self> {
+        if create {
+            if libc::ftruncate(fd, len as libc::off_t) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        } else {
+            let mut stat = mem::zeroed::<libc::stat>();
+            if libc::fstat(fd, &mut stat) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if stat.st_size as usize != len {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "segment size does not match the layout depth"));
+            }
+        }
+        let ptr = libc::mmap(ptr::null_mut(), len, prot, libc::MAP_SHARED, fd, 0);
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { ptr, len })
+    }
+}
+
+impl Drop for Segment {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` and `len` describe a mapping created by `open`
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
+
+fn segment_name(name: &str) -> io::Result<CString> {
+    CString::new(format!("/{}", name.trim_start_matches('/'))).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+}
+
+#[derive(Debug)]
+/// Creates a segment and publishes levels into it, removing the segment on drop.
+pub struct ShmWriter<const N: usize> {
+    segment: Segment,
+    name: CString,
+}
+
+// SAFETY: the writer is the only one mutating the mapping and it requires `&mut self` to do so
+unsafe impl<const N: usize> Send for ShmWriter<N> {}
+
+impl<const N: usize> ShmWriter<N> {
+    /// Create (or take over) the segment `name`
+    pub fn create(name: &str) -> io::Result<Self> {
+        let name = segment_name(name)?;
+        let segment = Segment::open(&name, mem::size_of::<ShmLayout<N>>(), true)?;
+        // SAFETY: the mapping is writable and `len` bytes long, a segment left by a crashed writer may
+        // hold an odd version that would stall readers forever
+        unsafe { ptr::write_bytes(segment.ptr.cast::<u8>(), 0, segment.len) };
+        Ok(Self { segment, name })
+    }
+
+    #[inline(always)]
+    fn layout(&self) -> *mut ShmLayout<N> {
+        self.segment.ptr.cast()
+    }
+
+    /// Publish the top `N` levels of each side of `book`
+    pub fn publish<B: OrderBook<FixedDecimal>>(&mut self, book: &B, timestamp: Timestamp, sequence_id: u64) {
+        self.publish_levels(&book.levels(Side::Buy, N), &book.levels(Side::Sell, N), timestamp, sequence_id);
+    }
+
+    /// Publish up to `N` levels of each side, best first
+    pub fn publish_levels(
+        &mut self,
+        bids: &[Level<FixedDecimal>],
+        asks: &[Level<FixedDecimal>],
+        timestamp: Timestamp,
+        sequence_id: u64,
+    ) {
+        let (bids, asks) = (&bids[..bids.len().min(N)], &asks[..asks.len().min(N)]);
+        let layout = self.layout();
+        // SAFETY: the mapping holds a `ShmLayout<N>` and this writer is its only mutator
+        unsafe {
+            let version = &(*layout).version;
+            let start = version.load(Ordering::Relaxed);
+            version.store(start.wrapping_add(1), Ordering::Relaxed);
+            fence(Ordering::Release);
+            ptr::addr_of_mut!((*layout).timestamp).write_volatile(timestamp.as_nanos());
+            ptr::addr_of_mut!((*layout).sequence_id).write_volatile(sequence_id);
+            ptr::addr_of_mut!((*layout).bid_len).write_volatile(bids.len() as u64);
+            ptr::addr_of_mut!((*layout).ask_len).write_volatile(asks.len() as u64);
+            ptr::copy_nonoverlapping(bids.as_ptr(), ptr::addr_of_mut!((*layout).bids).cast(), bids.len());
+            ptr::copy_nonoverlapping(asks.as_ptr(), ptr::addr_of_mut!((*layout).asks).cast(), asks.len());
+            version.store(start.wrapping_add(2), Ordering::Release);
+        }
+    }
+}
+
+impl<const N: usize> Drop for ShmWriter<N> {
+    fn drop(&mut self) {
+        // SAFETY: `name` is NUL terminated, existing readers keep their mappings
+        unsafe { libc::shm_unlink(self.name.as_ptr()) };
+    }
+}
+
+#[derive(Debug)]
+/// Maps an existing segment read-only.
+pub struct ShmReader<const N: usize> {
+    segment: Segment,
+}
+
+// SAFETY: readers only ever read the mapping
+unsafe impl<const N: usize> Send for ShmReader<N> {}
+unsafe impl<const N: usize> Sync for ShmReader<N> {}
+
+impl<const N: usize> ShmReader<N> {
+    /// Open the segment `name`, failing if it was created for a different depth
+    pub fn open(name: &str) -> io::Result<Self> {
+        let segment = Segment::open(&segment_name(name)?, mem::size_of::<ShmLayout<N>>(), false)?;
+        Ok(Self { segment })
+    }
+
+    /// Copy the latest published levels, retrying while the writer is mid-update
+    #[must_use]
+    pub fn read(&self) -> ShmSnapshot<N> {
+        let layout = self.segment.ptr.cast::<ShmLayout<N>>().cast_const();
+        let empty = Level::new(FixedDecimal::ZERO, FixedDecimal::ZERO);
+        let mut snapshot = ShmSnapshot {
+            timestamp: Timestamp::ZERO,
+            sequence_id: 0,
+            version: 0,
+            bid_len: 0,
+            ask_len: 0,
+            bids: [empty; N],
+            asks: [empty; N],
+        };
+        loop {
+            // SAFETY: the mapping holds a `ShmLayout<N>`, torn copies are discarded by the version check
+            unsafe {
+                let version = &(*layout).version;
+                let start = version.load(Ordering::Acquire);
+                if start % 2 == 1 {
+                    std::hint::spin_loop();
+                    continue;
+                }
+                snapshot.timestamp = Timestamp::from_nanos(ptr::addr_of!((*layout).timestamp).read_volatile());
+                snapshot.sequence_id = ptr::addr_of!((*layout).sequence_id).read_volatile();
+                snapshot.bid_len = (ptr::addr_of!((*layout).bid_len).read_volatile() as usize).min(N);
+                snapshot.ask_len = (ptr::addr_of!((*layout).ask_len).read_volatile() as usize).min(N);
+                snapshot.bids = ptr::addr_of!((*layout).bids).read_volatile();
+                snapshot.asks = ptr::addr_of!((*layout).asks).read_volatile();
+                fence(Ordering::Acquire);
+                if version.load(Ordering::Relaxed) == start {
+                    snapshot.version = start / 2;
+                    return snapshot;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        publish::shm::{ShmReader, ShmWriter},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_publish_and_read() {
+        let name = format!("freya_ob_shm_{}", std::process::id());
+        let mut book = BTreeOrderBook::new();
+        for (side, price) in
+            [(Side::Buy, fixed!(99)), (Side::Buy, fixed!(98)), (Side::Buy, fixed!(97)), (Side::Sell, fixed!(100))]
+        {
+            book.process(Event::new(EventKind::L2, side, price, fixed!(1), Timestamp::from_nanos(1)));
+        }
+        let mut writer = ShmWriter::<2>::create(&name).unwrap();
+        let reader = ShmReader::<2>::open(&name).unwrap();
+        assert_eq!(reader.read().version, 0);
+
+        writer.publish(&book, Timestamp::from_nanos(1), 42);
+        let snapshot = reader.read();
+        assert_eq!((snapshot.version, snapshot.timestamp, snapshot.sequence_id), (1, Timestamp::from_nanos(1), 42));
+        assert_eq!(snapshot.bids().iter().map(|level| level.price).collect::<Vec<_>>(), [fixed!(99), fixed!(98)]);
+        assert_eq!(snapshot.asks().len(), 1);
+
+        assert!(ShmReader::<3>::open(&name).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! Text rendering of a book as a depth ladder.
+//!
+//! A [`Ladder`] lays out the top levels of each side in aligned columns, asks above bids and best
+//! prices next to the spread, with the size and the cumulative size from the touch, and a bar scaled
+//! to the cumulative size against the deeper of the two sides:
+//!
+//! ```text
+//! side  price  size  total
+//!  ask    102     6     10  ####################
+//!  ask    101     4      4  ########
+//!       spread 2
+//!  bid     99     5      5  ##########
+//! ```
+
+use std::{
+    fmt::{self, Display, Write},
+    ops::{Add, Sub},
+};
+
+use crate::{books::interface::OrderBook, decimals::decimal_type::DecimalType, level::Level, side::Side};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Renders a book as text, see the [module documentation](self)
+pub struct Ladder {
+    depth: usize,
+    bar_width: usize,
+}
+
+impl Ladder {
+    pub const DEFAULT_BAR_WIDTH: usize = 20;
+
+    #[inline]
+    #[must_use]
+    /// Show up to `depth` levels of each side
+    pub const fn new(depth: usize) -> Self {
+        Self { depth, bar_width: Self::DEFAULT_BAR_WIDTH }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Characters the longest bar takes, zero to leave the bars out
+    pub const fn with_bar_width(self, bar_width: usize) -> Self {
+        Self { bar_width, ..self }
+    }
+
+    #[must_use]
+    pub fn render<V, B>(&self, book: &B) -> String
+    where
+        V: DecimalType + Copy + Display + Add<Output = V> + Sub<Output = V>,
+        B: OrderBook<V>,
+    {
+        let mut out = String::new();
+        self.write(book, &mut out).expect("writing to a String cannot fail");
+        out
+    }
+
+    /// Write the ladder to `out`, one line per level and a trailing newline
+    ///
+    /// # Errors
+    /// Whatever `out` returns.
+    pub fn write<V, B, W>(&self, book: &B, out: &mut W) -> fmt::Result
+    where
+        V: DecimalType + Copy + Display + Add<Output = V> + Sub<Output = V>,
+        B: OrderBook<V>,
+        W: Write,
+    {
+        let (asks, bids) = (book.levels(Side::Sell, self.depth), book.levels(Side::Buy, self.depth));
+        let rows = |levels: &[Level<V>]| {
+            let mut total = V::ZERO;
+            levels
+                .iter()
+                .map(|level| {
+                    total = total + level.size;
+                    (level.price, level.size, total)
+                })
+                .collect::<Vec<_>>()
+        };
+        let (asks, bids) = (rows(&asks), rows(&bids));
+        let deepest = asks.iter().chain(&bids).map(|&(.., total)| total.to_f64()).fold(0.0, f64::max);
+
+        let cells = |&(price, size, total): &(V, V, V)| [price.to_string(), size.to_string(), total.to_string()];
+        let (ask_cells, bid_cells) = (asks.iter().map(cells).collect::<Vec<_>>(), bids.iter().map(cells).collect::<Vec<_>>());
+        let mut widths = ["price".len(), "size".len(), "total".len()];
+        for row in ask_cells.iter().chain(&bid_cells) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        let [price, size, total] = widths;
+
+        writeln!(out, "side  {:>price$}  {:>size$}  {:>total$}", "price", "size", "total")?;
+        let line = |out: &mut W, label: &str, row: &[String; 3], cumulative: V| {
+            write!(out, "{label:>4}  {:>price$}  {:>size$}  {:>total$}", row[0], row[1], row[2])?;
+            if self.bar_width > 0 && deepest > 0.0 {
+                let bar = (cumulative.to_f64() / deepest * self.bar_width as f64).round() as usize;
+                write!(out, "  {}", "#".repeat(bar))?;
+            }
+            writeln!(out)
+        };
+        // Deepest ask first so the best prices meet at the spread
+        for (row, &(.., cumulative)) in ask_cells.iter().zip(&asks).rev() {
+            line(out, "ask", row, cumulative)?;
+        }
+        if let (Some(&(ask, ..)), Some(&(bid, ..))) = (asks.first(), bids.first()) {
+            writeln!(out, "      spread {}", ask - bid)?;
+        }
+        for (row, &(.., cumulative)) in bid_cells.iter().zip(&bids) {
+            line(out, "bid", row, cumulative)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        render::Ladder,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_renders_ladder() {
+        let events = [
+            (Side::Buy, fixed!(99), fixed!(5)),
+            (Side::Buy, fixed!(98.5), fixed!(15)),
+            (Side::Sell, fixed!(101), fixed!(4)),
+            (Side::Sell, fixed!(102), fixed!(6)),
+            (Side::Sell, fixed!(103), fixed!(30)),
+        ];
+        let mut btree = BTreeOrderBook::<FixedDecimal>::new();
+        let mut array = ArrayOrderbook::<8, FixedDecimal>::new();
+        for (side, price, size) in events {
+            btree.process(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(1)));
+            array.process(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(1)));
+        }
+        let ladder = Ladder::new(2).render(&btree);
+        let expected = "\
+side  price  size  total
+ ask    102     6     10  ##########
+ ask    101     4      4  ####
+      spread 2
+ bid     99     5      5  #####
+ bid   98.5    15     20  ####################
+";
+        assert_eq!(ladder, expected);
+        assert_eq!(Ladder::new(2).render(&array), expected);
+
+        let bare = Ladder::new(1).with_bar_width(0).render(&BTreeOrderBook::<FixedDecimal>::new());
+        assert_eq!(bare, "side  price  size  total\n");
+    }
+}
@@ -0,0 +1,297 @@
+//! A single-producer, single-consumer ring of events, for handing events from a network thread to the
+//! book thread without allocating or locking.
+//!
+//! [`ring`] preallocates a power of two slots and returns the two ends. The [`RingProducer`] advances
+//! the tail and the [`RingConsumer`] the head, each on its own cache line and each end caching its
+//! last view of the other's index, so the threads only share a line when the ring looks full or
+//! empty. A consumer waiting for events either spins or parks its thread, see [`WaitMode`].
+
+use std::{
+    cell::UnsafeCell,
+    hint,
+    mem::MaybeUninit,
+    sync::{
+        atomic::{fence, AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread::{self, Thread},
+};
+
+use crate::{decimals::fixed_decimal::FixedDecimal, event::Event, formats::source::SourceError, pipeline::Disconnected};
+
+type Slot = UnsafeCell<MaybeUninit<Event<FixedDecimal>>>;
+
+#[derive(Debug, Default)]
+#[repr(align(64))]
+struct CachePadded<T>(T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// How a [`RingConsumer`] waits on an empty ring
+pub enum WaitMode {
+    /// Spin on the tail, for the lowest latency from a core given over to the book
+    #[default]
+    BusyPoll,
+    /// Park the thread until the producer pushes, leaving the core to others between bursts
+    Park,
+}
+
+struct Ring {
+    /// Next slot to read, written only by the consumer
+    head: CachePadded<AtomicUsize>,
+    /// Next slot to write, written only by the producer
+    tail: CachePadded<AtomicUsize>,
+    slots: Box<[Slot]>,
+    mask: usize,
+    /// Either end has been dropped
+    closed: AtomicBool,
+    /// The consumer waits by parking, so pushes have to check whether it needs waking
+    parks: AtomicBool,
+    /// The consumer may be parked, so the producer has to wake it
+    parked: AtomicBool,
+    consumer: Mutex<Option<Thread>>,
+}
+
+// Each slot is written by the producer and read by the consumer, never both at once: the producer
+// only writes slots the head has passed and the consumer only reads slots the tail has passed
+unsafe impl Sync for Ring {}
+
+impl Ring {
+    fn wake(&self) {
+        // A busy polling consumer never parks, which spares its producer the fence and the lock
+        if !self.parks.load(Ordering::Relaxed) {
+            return;
+        }
+        // Pairs with the fence in `RingConsumer::park`, so either the consumer sees the new tail or
+        // the producer sees it parked
+        fence(Ordering::SeqCst);
+        if self.parked.load(Ordering::Relaxed) {
+            if let Some(consumer) = &*self.consumer.lock().unwrap_or_else(PoisonError::into_inner) {
+                consumer.unpark();
+            }
+        }
+    }
+}
+
+#[must_use]
+/// A ring holding at least `capacity` events, rounded up to a power of two
+pub fn ring(capacity: usize) -> (RingProducer, RingConsumer) {
+    let capacity = capacity.max(2).next_power_of_two();
+    let ring = Arc::new(Ring {
+        head: CachePadded::default(),
+        tail: CachePadded::default(),
+        slots: (0..capacity).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect(),
+        mask: capacity - 1,
+        closed: AtomicBool::new(false),
+        parks: AtomicBool::new(false),
+        parked: AtomicBool::new(false),
+        consumer: Mutex::new(None),
+    });
+    let producer = RingProducer { ring: Arc::clone(&ring), tail: 0, cached_head: 0 };
+    (producer, RingConsumer { ring, head: 0, cached_tail: 0, wait: WaitMode::BusyPoll })
+}
+
+/// The pushing end of a [`ring`]
+pub struct RingProducer {
+    ring: Arc<Ring>,
+    tail: usize,
+    cached_head: usize,
+}
+
+impl RingProducer {
+    #[inline]
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.ring.slots.len()
+    }
+
+    /// Push `event` if there is room, handing it back when the ring is full
+    #[inline]
+    pub fn try_push(&mut self, event: Event<FixedDecimal>) -> Result<(), Event<FixedDecimal>> {
+        if self.tail - self.cached_head == self.ring.slots.len() {
+            self.cached_head = self.ring.head.0.load(Ordering::Acquire);
+            if self.tail - self.cached_head == self.ring.slots.len() {
+                return Err(event);
+            }
+        }
+        // The consumer has read this slot, see the `Sync` impl of `Ring`
+        unsafe { (*self.ring.slots[self.tail & self.ring.mask].get()).write(event) };
+        self.tail += 1;
+        self.ring.tail.0.store(self.tail, Ordering::Release);
+        self.ring.wake();
+        Ok(())
+    }
+
+    /// Push `event`, spinning while the ring is full
+    ///
+    /// # Errors
+    /// When the consumer has been dropped.
+    pub fn push(&mut self, mut event: Event<FixedDecimal>) -> Result<(), Disconnected> {
+        loop {
+            if self.ring.closed.load(Ordering::Acquire) {
+                return Err(Disconnected);
+            }
+            match self.try_push(event) {
+                Ok(()) => return Ok(()),
+                Err(full) => event = full,
+            }
+            hint::spin_loop();
+        }
+    }
+}
+
+impl Drop for RingProducer {
+    fn drop(&mut self) {
+        self.ring.closed.store(true, Ordering::Release);
+        self.ring.wake();
+    }
+}
+
+/// The popping end of a [`ring`], also an event source that ends once the producer is dropped and
+/// the ring drained
+pub struct RingConsumer {
+    ring: Arc<Ring>,
+    head: usize,
+    cached_tail: usize,
+    wait: WaitMode,
+}
+
+impl RingConsumer {
+    #[inline]
+    #[must_use]
+    /// Wait on an empty ring by `wait`, chosen before the producer starts pushing since a producer
+    /// that has not seen the switch to [`WaitMode::Park`] may leave the consumer parked
+    pub fn wait_mode(mut self, wait: WaitMode) -> Self {
+        self.ring.parks.store(wait == WaitMode::Park, Ordering::SeqCst);
+        self.wait = wait;
+        self
+    }
+
+    /// Pop the oldest event, `None` when the ring is empty
+    #[inline]
+    pub fn try_pop(&mut self) -> Option<Event<FixedDecimal>> {
+        if self.head == self.cached_tail {
+            self.cached_tail = self.ring.tail.0.load(Ordering::Acquire);
+            if self.head == self.cached_tail {
+                return None;
+            }
+        }
+        // The producer has written this slot, see the `Sync` impl of `Ring`
+        let event = unsafe { (*self.ring.slots[self.head & self.ring.mask].get()).assume_init() };
+        self.head += 1;
+        self.ring.head.0.store(self.head, Ordering::Release);
+        Some(event)
+    }
+
+    /// Pop the oldest event, waiting by the [`WaitMode`] while the ring is empty. Returns `None` once
+    /// the producer has been dropped and every event it pushed popped.
+    pub fn pop(&mut self) -> Option<Event<FixedDecimal>> {
+        loop {
+            if let Some(event) = self.try_pop() {
+                return Some(event);
+            }
+            if self.ring.closed.load(Ordering::Acquire) {
+                // Events pushed before the producer was dropped
+                return self.try_pop();
+            }
+            match self.wait {
+                WaitMode::BusyPoll => hint::spin_loop(),
+                WaitMode::Park => self.park(),
+            }
+        }
+    }
+
+    fn park(&self) {
+        *self.ring.consumer.lock().unwrap_or_else(PoisonError::into_inner) = Some(thread::current());
+        self.ring.parked.store(true, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+        if self.ring.tail.0.load(Ordering::Relaxed) == self.head && !self.ring.closed.load(Ordering::Relaxed) {
+            thread::park();
+        }
+        self.ring.parked.store(false, Ordering::Relaxed);
+    }
+}
+
+impl Iterator for RingConsumer {
+    type Item = Result<Event<FixedDecimal>, SourceError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pop().map(Ok)
+    }
+}
+
+impl Drop for RingConsumer {
+    fn drop(&mut self) {
+        self.ring.closed.store(true, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        pipeline::Disconnected,
+        ring::{ring, WaitMode},
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn bid(ts: i64) -> Event<FixedDecimal> {
+        Event::new(EventKind::L2, Side::Buy, FixedDecimal::from_int(ts), fixed!(1), Timestamp::from_nanos(ts))
+            .with_sequence_id(ts as u64)
+    }
+
+    #[test]
+    fn test_full_and_empty() {
+        let (mut producer, mut consumer) = ring(3);
+        assert_eq!(producer.capacity(), 4);
+        assert!(consumer.try_pop().is_none());
+        for ts in 1..=4 {
+            assert!(producer.try_push(bid(ts)).is_ok());
+        }
+        assert_eq!(producer.try_push(bid(5)).map_err(|event| event.timestamp.as_nanos()), Err(5));
+        assert_eq!(consumer.try_pop().map(|event| event.sequence_id), Some(1));
+        assert!(producer.try_push(bid(5)).is_ok());
+        drop(producer);
+        let rest: Vec<_> = consumer.by_ref().map(|event| event.unwrap().sequence_id).collect();
+        assert_eq!(rest, [2, 3, 4, 5]);
+
+        let (mut producer, consumer) = ring(2);
+        drop(consumer);
+        assert_eq!(producer.push(bid(1)), Err(Disconnected));
+    }
+
+    #[test]
+    fn test_across_threads() {
+        for wait in [WaitMode::BusyPoll, WaitMode::Park] {
+            let (mut producer, consumer) = ring(64);
+            let consumer = consumer.wait_mode(wait);
+            let book = std::thread::spawn(move || {
+                let mut book = BTreeOrderBook::new();
+                let mut last = 0;
+                for event in consumer {
+                    let event = event.unwrap();
+                    assert_eq!(event.sequence_id, last + 1);
+                    last = event.sequence_id;
+                    book.process(event);
+                }
+                book
+            });
+            for ts in 1..=10_000 {
+                producer.push(bid(ts)).unwrap();
+                if ts % 1_000 == 0 {
+                    // Let a parking consumer catch up and park
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
+            }
+            drop(producer);
+            let mut book = book.join().unwrap();
+            assert_eq!(book.best_bid().map(|level| level.price), Some(fixed!(10000)));
+        }
+    }
+}
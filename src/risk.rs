@@ -0,0 +1,265 @@
+//! Pre-trade risk checks against the book.
+//!
+//! [`RiskChecks`] vets an [`Order`] before it is sent, looking at the book as it stands and at the
+//! account's own resting orders:
+//!
+//! - a price collar rejects orders reaching more than a percentage through the touch, a limit order by
+//!   its price and a market order by the furthest level it would sweep to
+//! - a liquidity limit rejects orders whose notional exceeds a share of the notional visible within a
+//!   depth, on the other side for a market order and on its own side for a resting order
+//! - self-cross prevention rejects orders that would trade against the account's own resting orders
+//!
+//! ```
+//! # #[cfg(feature = "fixed_decimal")] {
+//! use freya_ob::{
+//!     backtest::Order, books::{btree_orderbook::BTreeOrderBook, interface::OrderBook}, event::Event,
+//!     event_kind::EventKind, fixed, risk::{RiskChecks, RiskRejection}, side::Side, timestamp::Timestamp,
+//! };
+//!
+//! let mut book = BTreeOrderBook::new();
+//! book.process(Event::new(EventKind::L2, Side::Buy, fixed!(99), fixed!(10), Timestamp::from_nanos(1)));
+//! book.process(Event::new(EventKind::L2, Side::Sell, fixed!(100), fixed!(10), Timestamp::from_nanos(1)));
+//!
+//! let checks = RiskChecks::new().with_price_collar(fixed!(5));
+//! let order = Order::Post { side: Side::Sell, price: fixed!(90), size: fixed!(1) };
+//! assert_eq!(checks.check(&book, &order, &[]), Err(RiskRejection::PriceCollar { price: fixed!(90), limit: fixed!(94.05) }));
+//! # }
+//! ```
+
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, ControlFlow, Div, Mul, Sub},
+};
+
+use crate::{
+    backtest::{Order, RestingOrder},
+    decimals::decimal_type::DecimalType,
+    metrics::MetricsCalculator,
+    side::Side,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Why [`RiskChecks`] turned an order away
+pub enum RiskRejection<V: DecimalType> {
+    /// The order reaches `price`, beyond the collar at `limit`
+    PriceCollar { price: V, limit: V },
+    /// The order's notional is above the share of visible liquidity allowed
+    Liquidity { notional: V, limit: V },
+    /// The order would trade against the account's own order resting at `price`
+    SelfCross { price: V },
+}
+
+impl<V: DecimalType + fmt::Display> fmt::Display for RiskRejection<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PriceCollar { price, limit } => write!(f, "price {price} is through the collar at {limit}"),
+            Self::Liquidity { notional, limit } => write!(f, "notional {notional} is above the liquidity limit of {limit}"),
+            Self::SelfCross { price } => write!(f, "order would cross the account's own order at {price}"),
+        }
+    }
+}
+
+impl<V: DecimalType + fmt::Debug + fmt::Display> std::error::Error for RiskRejection<V> {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Checks run before an order is sent, see the [module documentation](self)
+pub struct RiskChecks<V: DecimalType> {
+    /// Furthest through the touch an order may reach, as a percentage of the touch
+    price_collar: Option<V>,
+    /// Largest share of the visible notional an order may take, and the depth it is counted over
+    liquidity_share: Option<(V, usize)>,
+    self_cross: bool,
+}
+
+impl<V: DecimalType> Default for RiskChecks<V> {
+    #[inline]
+    fn default() -> Self {
+        Self { price_collar: None, liquidity_share: None, self_cross: false }
+    }
+}
+
+impl<V> RiskChecks<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V> + Sum,
+{
+    #[inline]
+    #[must_use]
+    /// No checks, add them with the builder methods
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Reject orders reaching more than `percent` through the touch, such as `5` for 5%
+    pub const fn with_price_collar(self, percent: V) -> Self {
+        Self { price_collar: Some(percent), ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Reject orders whose notional is above `share` of the notional visible over `depth` levels, such
+    /// as `0.2` for a fifth
+    pub const fn with_liquidity_share(self, share: V, depth: usize) -> Self {
+        Self { liquidity_share: Some((share, depth)), ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Reject orders that would trade against the account's own resting orders
+    pub const fn with_self_cross_prevention(self) -> Self {
+        Self { self_cross: true, ..self }
+    }
+
+    /// Vet `order` against `book` and the account's `resting` orders
+    ///
+    /// # Errors
+    /// The first check the order fails, in the order collar, liquidity then self-cross. An order
+    /// checked against an empty side passes the checks that need that side.
+    pub fn check<B: MetricsCalculator<V>>(
+        &self,
+        book: &B,
+        order: &Order<V>,
+        resting: &[RestingOrder<V>],
+    ) -> Result<(), RiskRejection<V>> {
+        // The price the order reaches, its notional and the side whose liquidity it is measured against
+        let (side, reach, notional, against) = match *order {
+            Order::Market { side, size } => {
+                let Some((furthest, notional)) = sweep(book, side, size) else {
+                    return Ok(());
+                };
+                (side, furthest, notional, side.opposite())
+            }
+            Order::Post { side, price, size } => (side, price, price * size, side),
+            Order::CancelAll => return Ok(()),
+        };
+        if let Some(percent) = self.price_collar {
+            let touch = match side {
+                Side::Buy => book.best_ask().or_else(|| book.best_bid()),
+                Side::Sell => book.best_bid().or_else(|| book.best_ask()),
+            };
+            if let Some(touch) = touch {
+                let allowance = touch.price * percent / V::ONE_HUNDRED;
+                let (limit, through) = match side {
+                    Side::Buy => (touch.price + allowance, reach > touch.price + allowance),
+                    Side::Sell => (touch.price - allowance, reach < touch.price - allowance),
+                };
+                if through {
+                    return Err(RiskRejection::PriceCollar { price: reach, limit });
+                }
+            }
+        }
+        self.check_liquidity(book, against, notional)?;
+        if self.self_cross {
+            let crossed = resting.iter().filter(|own| own.side != side).find(|own| match side {
+                Side::Buy => own.price <= reach,
+                Side::Sell => own.price >= reach,
+            });
+            if let Some(own) = crossed {
+                return Err(RiskRejection::SelfCross { price: own.price });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_liquidity<B: MetricsCalculator<V>>(&self, book: &B, side: Side, notional: V) -> Result<(), RiskRejection<V>> {
+        let Some((share, depth)) = self.liquidity_share else {
+            return Ok(());
+        };
+        let mut visible = V::ZERO;
+        let mut levels = 0;
+        book.walk_levels(side, &mut |level| {
+            visible = visible + level.price * level.size;
+            levels += 1;
+            if levels < depth {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        });
+        let limit = visible * share;
+        if levels > 0 && notional > limit {
+            return Err(RiskRejection::Liquidity { notional, limit });
+        }
+        Ok(())
+    }
+}
+
+/// The furthest price a market order to `side` of `size` would reach and the notional it would
+/// trade, pricing any size the book cannot fill at the last level, `None` when the other side is empty
+fn sweep<V, B>(book: &B, side: Side, size: V) -> Option<(V, V)>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V> + Sum,
+    B: MetricsCalculator<V>,
+{
+    let (mut furthest, mut left, mut notional) = (None, size, V::ZERO);
+    book.walk_levels(side.opposite(), &mut |level| {
+        let take = if left < level.size { left } else { level.size };
+        (furthest, left, notional) = (Some(level.price), left - take, notional + level.price * take);
+        if left > V::ZERO {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    });
+    furthest.map(|furthest| (furthest, notional + furthest * left))
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        backtest::{Order, RestingOrder},
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        risk::{RiskChecks, RiskRejection},
+        side::Side,
+        sim::queue::QueuePosition,
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_checks() {
+        let mut book = BTreeOrderBook::<FixedDecimal>::new();
+        for (side, price, size) in
+            [(Side::Buy, fixed!(99), fixed!(10)), (Side::Sell, fixed!(100), fixed!(2)), (Side::Sell, fixed!(110), fixed!(8))]
+        {
+            book.process(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(1)));
+        }
+        let market = |side, size| Order::Market { side, size };
+        let post = |side, price, size| Order::Post { side, price, size };
+
+        // A market buy of 5 sweeps to 110, 10% through the ask
+        let collar = RiskChecks::new().with_price_collar(fixed!(5));
+        assert_eq!(collar.check(&book, &market(Side::Buy, fixed!(2)), &[]), Ok(()));
+        assert_eq!(
+            collar.check(&book, &market(Side::Buy, fixed!(5)), &[]),
+            Err(RiskRejection::PriceCollar { price: fixed!(110), limit: fixed!(105) })
+        );
+        assert_eq!(collar.check(&book, &post(Side::Buy, fixed!(104), fixed!(1)), &[]), Ok(()));
+
+        // Half the 1080 visible on the asks over two levels, or the 990 on the bids for a resting bid
+        let liquidity = RiskChecks::new().with_liquidity_share(fixed!(0.5), 2);
+        assert_eq!(liquidity.check(&book, &market(Side::Buy, fixed!(4)), &[]), Ok(()));
+        assert_eq!(
+            liquidity.check(&book, &market(Side::Buy, fixed!(6)), &[]),
+            Err(RiskRejection::Liquidity { notional: fixed!(640), limit: fixed!(540) })
+        );
+        assert!(liquidity.check(&book, &post(Side::Buy, fixed!(98), fixed!(6)), &[]).is_err());
+        assert_eq!(liquidity.check(&BTreeOrderBook::new(), &market(Side::Sell, fixed!(6)), &[]), Ok(()));
+
+        let own = RestingOrder::new(Side::Sell, fixed!(101), QueuePosition::new(fixed!(0), fixed!(1)));
+        let self_cross = RiskChecks::new().with_self_cross_prevention();
+        assert_eq!(
+            self_cross.check(&book, &post(Side::Buy, fixed!(101), fixed!(1)), &[own]),
+            Err(RiskRejection::SelfCross { price: fixed!(101) })
+        );
+        assert_eq!(self_cross.check(&book, &market(Side::Buy, fixed!(1)), &[own]), Ok(()));
+        assert!(self_cross.check(&book, &market(Side::Buy, fixed!(3)), &[own]).is_err());
+        assert_eq!(self_cross.check(&book, &Order::CancelAll, &[own]), Ok(()));
+    }
+}
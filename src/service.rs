@@ -0,0 +1,269 @@
+//! A thread that owns a book and serves snapshots of it on request.
+//!
+//! A [`SnapshotService`] applies a live stream of events and, between them, answers
+//! [`SnapshotRequest`]s for the top levels of the book. A request names the lowest sequence ID it will
+//! accept, so a late joining consumer that buffers deltas from some point onwards asks for a snapshot
+//! at least that recent, then applies only the buffered deltas beyond the snapshot's `sequence_id`.
+//! Requests the book has not caught up with wait until it does. Events and requests share one channel,
+//! so each snapshot falls exactly between two events:
+//!
+//! ```
+//! # #[cfg(feature = "fixed_decimal")] {
+//! use freya_ob::{
+//!     books::btree_orderbook::BTreeOrderBook, decimals::fixed_decimal::FixedDecimal, event::Event,
+//!     event_kind::EventKind, fixed, service::SnapshotService, side::Side, timestamp::Timestamp,
+//! };
+//!
+//! let (handle, service) = SnapshotService::new(BTreeOrderBook::<FixedDecimal>::new()).spawn(1_024);
+//! let snapshot = handle.snapshot(5, 2).unwrap();
+//! for sequence_id in 1..=2 {
+//!     let event = Event::new(EventKind::L2, Side::Buy, fixed!(100), fixed!(1), Timestamp::from_nanos(1));
+//!     handle.send(event.with_sequence_id(sequence_id)).unwrap();
+//! }
+//! assert_eq!(snapshot.recv().unwrap().sequence_id, 2);
+//! drop(handle);
+//! service.join().unwrap();
+//! # }
+//! ```
+
+use std::{
+    sync::mpsc::{self, Receiver, Sender, SyncSender},
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    pipeline::{DeltaPublisher, Disconnected},
+    side::Side,
+    sync::BookSnapshot,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug)]
+/// Asks a [`SnapshotService`] for the top `depth` levels per side once the book has applied the event
+/// with `min_sequence_id`
+pub struct SnapshotRequest<V: DecimalType> {
+    pub depth: usize,
+    pub min_sequence_id: u64,
+    /// Where the snapshot goes, dropped unanswered when the stream ends first
+    pub reply: Sender<BookSnapshot<V>>,
+}
+
+#[derive(Debug)]
+/// What a [`SnapshotService`] reads from its channel
+pub enum ServiceMessage<V: DecimalType> {
+    Event(Event<V>),
+    Snapshot(SnapshotRequest<V>),
+}
+
+#[derive(Debug)]
+/// Owns a book, applies events to it and answers snapshot requests in between
+pub struct SnapshotService<V: DecimalType, B> {
+    book: B,
+    timestamp: Timestamp,
+    sequence_id: u64,
+    /// Snapshots served so far
+    served: u64,
+    /// Requests waiting for the book to reach their sequence ID
+    pending: Vec<SnapshotRequest<V>>,
+    publishers: Vec<DeltaPublisher<V>>,
+}
+
+impl<V, B> SnapshotService<V, B>
+where
+    V: DecimalType + Copy + PartialEq,
+    B: OrderBook<V>,
+{
+    #[must_use]
+    pub fn new(book: B) -> Self {
+        Self { book, timestamp: Timestamp::ZERO, sequence_id: 0, served: 0, pending: Vec::new(), publishers: Vec::new() }
+    }
+
+    #[must_use]
+    /// Queue every delta the book accepts for the subscriber of `publisher`, so the subscriber sees
+    /// deltas and snapshots in one order
+    pub fn publish_to(mut self, publisher: DeltaPublisher<V>) -> Self {
+        self.publishers.push(publisher);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn inner(&self) -> &B {
+        &self.book
+    }
+
+    #[inline]
+    #[must_use]
+    /// Sequence ID of the last event applied
+    pub const fn sequence_id(&self) -> u64 {
+        self.sequence_id
+    }
+
+    #[inline]
+    #[must_use]
+    /// Requests waiting for the book to catch up
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn handle(&mut self, message: ServiceMessage<V>) {
+        match message {
+            ServiceMessage::Event(event) => {
+                self.apply(event);
+            }
+            ServiceMessage::Snapshot(request) => self.request(request),
+        }
+    }
+
+    /// Apply `event`, then answer the requests it brings within reach
+    pub fn apply(&mut self, event: Event<V>) -> Option<BookDelta<V>> {
+        (self.timestamp, self.sequence_id) = (event.timestamp, event.sequence_id);
+        let delta = self.book.process_delta(event);
+        if let Some(delta) = delta {
+            for publisher in &self.publishers {
+                publisher.publish(delta);
+            }
+        }
+        if !self.pending.is_empty() {
+            let (ready, waiting) = std::mem::take(&mut self.pending)
+                .into_iter()
+                .partition::<Vec<_>, _>(|request| request.min_sequence_id <= self.sequence_id);
+            self.pending = waiting;
+            ready.into_iter().for_each(|request| self.answer(&request));
+        }
+        delta
+    }
+
+    /// Answer `request` now when the book has reached its sequence ID, otherwise once it does
+    pub fn request(&mut self, request: SnapshotRequest<V>) {
+        if request.min_sequence_id <= self.sequence_id {
+            self.answer(&request);
+        } else {
+            self.pending.push(request);
+        }
+    }
+
+    #[must_use]
+    /// The top `depth` levels per side as the book stands
+    pub fn snapshot(&mut self, depth: usize) -> BookSnapshot<V> {
+        let snapshot = BookSnapshot {
+            bids: self.book.levels(Side::Buy, depth),
+            asks: self.book.levels(Side::Sell, depth),
+            timestamp: self.timestamp,
+            sequence_id: self.sequence_id,
+            version: self.served,
+        };
+        self.served += 1;
+        snapshot
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> B {
+        self.book
+    }
+
+    fn answer(&mut self, request: &SnapshotRequest<V>) {
+        // A requester that has gone away no longer needs its snapshot
+        let _ = request.reply.send(self.snapshot(request.depth));
+    }
+}
+
+impl<V, B> SnapshotService<V, B>
+where
+    V: DecimalType + Copy + PartialEq + Send + 'static,
+    B: OrderBook<V> + Send + 'static,
+{
+    /// Run the service on its own thread behind a channel holding up to `capacity` messages, until every
+    /// [`ServiceHandle`] is dropped. The thread returns the book.
+    pub fn spawn(self, capacity: usize) -> (ServiceHandle<V>, JoinHandle<B>) {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        (ServiceHandle { sender }, thread::spawn(move || self.run(receiver)))
+    }
+
+    fn run(mut self, receiver: Receiver<ServiceMessage<V>>) -> B {
+        for message in receiver {
+            self.handle(message);
+        }
+        self.into_inner()
+    }
+}
+
+#[derive(Debug)]
+/// Sends events and snapshot requests to a spawned [`SnapshotService`], cheap to clone
+pub struct ServiceHandle<V: DecimalType> {
+    sender: SyncSender<ServiceMessage<V>>,
+}
+
+impl<V: DecimalType> Clone for ServiceHandle<V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self { sender: self.sender.clone() }
+    }
+}
+
+impl<V: DecimalType> ServiceHandle<V> {
+    /// Send an event for the book, waiting for room in the channel
+    pub fn send(&self, event: Event<V>) -> Result<(), Disconnected> {
+        self.sender.send(ServiceMessage::Event(event)).map_err(|_| Disconnected)
+    }
+
+    /// Ask for the top `depth` levels per side once the book has applied `min_sequence_id`, the
+    /// snapshot arriving on the returned receiver, which disconnects instead when the stream ends first
+    pub fn snapshot(&self, depth: usize, min_sequence_id: u64) -> Result<Receiver<BookSnapshot<V>>, Disconnected> {
+        let (reply, receiver) = mpsc::channel();
+        let request = SnapshotRequest { depth, min_sequence_id, reply };
+        self.sender.send(ServiceMessage::Snapshot(request)).map_err(|_| Disconnected)?;
+        Ok(receiver)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        pipeline::{delta_channel, ConsumerPolicy},
+        service::SnapshotService,
+        side::Side,
+        timestamp::Timestamp,
+    };
+
+    fn bid(price: i64, sequence_id: u64) -> Event<FixedDecimal> {
+        Event::new(EventKind::L2, Side::Buy, FixedDecimal::from_int(price), fixed!(1), Timestamp::from_nanos(1))
+            .with_sequence_id(sequence_id)
+    }
+
+    #[test]
+    fn test_answers_at_min_sequence_id() {
+        let (publisher, subscriber) = delta_channel(64, ConsumerPolicy::Block);
+        let (handle, service) = SnapshotService::new(BTreeOrderBook::new()).publish_to(publisher).spawn(4);
+        handle.send(bid(100, 1)).unwrap();
+        let now = handle.snapshot(1, 0).unwrap();
+        let later = handle.snapshot(2, 3).unwrap();
+        let never = handle.snapshot(2, 10).unwrap();
+        for (price, sequence_id) in [(101, 2), (102, 3), (103, 4)] {
+            handle.send(bid(price, sequence_id)).unwrap();
+        }
+
+        let now = now.recv().unwrap();
+        assert_eq!((now.sequence_id, now.version, now.bids.len()), (1, 0, 1));
+        let later = later.recv().unwrap();
+        assert_eq!((later.sequence_id, later.version), (3, 1));
+        assert_eq!(later.bids.iter().map(|level| level.price).collect::<Vec<_>>(), [fixed!(102), fixed!(101)]);
+
+        drop(handle);
+        let book = service.join().unwrap();
+        assert_eq!(book.levels(Side::Buy, usize::MAX).len(), 4);
+        assert!(never.recv().is_err());
+        // Deltas beyond the snapshot bring a late joiner up to date
+        let missed = subscriber.filter(|delta| delta.sequence_id > later.sequence_id).count();
+        assert_eq!(missed, 1);
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Side {
     Buy,
@@ -24,6 +24,49 @@ impl Side {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Side of the order that took liquidity in a trade, for venues that report it
+pub enum Aggressor {
+    Buy,
+    Sell,
+    /// The venue did not say, or the event is not a trade
+    #[default]
+    Unknown,
+}
+
+impl Aggressor {
+    #[inline(always)]
+    #[must_use]
+    pub const fn side(self) -> Option<Side> {
+        match self {
+            Self::Buy => Some(Side::Buy),
+            Self::Sell => Some(Side::Sell),
+            Self::Unknown => None,
+        }
+    }
+
+    #[inline(always)]
+    #[must_use]
+    /// Side of the book the aggressor traded against
+    pub const fn resting_side(self) -> Option<Side> {
+        match self.side() {
+            Some(side) => Some(side.opposite()),
+            None => None,
+        }
+    }
+}
+
+impl From<Side> for Aggressor {
+    #[inline(always)]
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Buy => Self::Buy,
+            Side::Sell => Self::Sell,
+        }
+    }
+}
+
 impl AsRef<str> for Side {
     fn as_ref(&self) -> &str {
         match self {
@@ -0,0 +1,190 @@
+//! Trading signals derived from the book.
+//!
+//! [`ImbalanceSignal`] sums resting size over the top levels of each side after every [`BookDelta`],
+//! takes the imbalance `(bid - ask) / (bid + ask)` and smooths it as the [rolling
+//! metrics](crate::metrics::rolling) do. The signal goes long once the smoothed imbalance reaches the
+//! entry threshold and short once it reaches its negative, and only returns to flat when it falls back
+//! inside the exit threshold, so an imbalance hovering around the entry does not flip it back and
+//! forth. The callback fires on each change with the delta that caused it.
+
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    metrics::rolling::{Average, Smoothing},
+    side::Side,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Signal {
+    /// Bids outweigh asks
+    Long,
+    #[default]
+    Flat,
+    /// Asks outweigh bids
+    Short,
+}
+
+impl Signal {
+    #[inline(always)]
+    #[must_use]
+    /// `1` long, `0` flat and `-1` short
+    pub const fn sign(self) -> i8 {
+        match self {
+            Self::Long => 1,
+            Self::Flat => 0,
+            Self::Short => -1,
+        }
+    }
+}
+
+type Callback<V> = Box<dyn FnMut(Signal, &BookDelta<V>) + Send>;
+
+/// Smoothed depth imbalance with hysteresis, see the [module documentation](self)
+pub struct ImbalanceSignal<V: DecimalType> {
+    depth: usize,
+    enter: V,
+    exit: V,
+    imbalance: Average<V>,
+    signal: Signal,
+    callback: Option<Callback<V>>,
+}
+
+impl<V> ImbalanceSignal<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V>,
+{
+    const DEFAULT_DEPTH: usize = 5;
+
+    #[inline]
+    #[must_use]
+    /// Enter a position when the smoothed imbalance reaches `enter` in size and leave it when it falls
+    /// below `exit`, which should not exceed `enter`
+    pub fn new(smoothing: Smoothing<V>, enter: V, exit: V) -> Self {
+        Self { depth: Self::DEFAULT_DEPTH, enter, exit, imbalance: Average::new(smoothing), signal: Signal::Flat, callback: None }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Number of levels per side summed for the imbalance
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// Call `callback` with the new signal and the delta that changed it
+    pub fn with_callback(mut self, callback: impl FnMut(Signal, &BookDelta<V>) + Send + 'static) -> Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn signal(&self) -> Signal {
+        self.signal
+    }
+
+    #[inline]
+    #[must_use]
+    /// Smoothed imbalance, `None` before the first sample
+    pub fn imbalance(&self) -> Option<V> {
+        self.imbalance.value()
+    }
+
+    /// Sample `book` after it applied `delta`, ignored while both sides are empty over the depth
+    pub fn on_delta<B: OrderBook<V>>(&mut self, book: &B, delta: &BookDelta<V>) -> Signal {
+        let total = |side| book.levels(side, self.depth).iter().fold(V::ZERO, |total, level| total + level.size);
+        let (bid_size, ask_size) = (total(Side::Buy), total(Side::Sell));
+        let depth = bid_size + ask_size;
+        if depth <= V::ZERO {
+            return self.signal;
+        }
+        if let (Some(signal), Some(callback)) = (self.update((bid_size - ask_size) / depth), self.callback.as_mut()) {
+            callback(signal, delta);
+        }
+        self.signal
+    }
+
+    /// Smooth in one imbalance sample, returning the new signal when it changed
+    pub fn update(&mut self, imbalance: V) -> Option<Signal> {
+        self.imbalance.push(imbalance);
+        let smoothed = self.imbalance.value()?;
+        let (enter, exit) = (self.enter, self.exit);
+        let signal = if smoothed >= enter {
+            Signal::Long
+        } else if smoothed <= V::ZERO - enter {
+            Signal::Short
+        } else {
+            match self.signal {
+                Signal::Long if smoothed >= exit => Signal::Long,
+                Signal::Short if smoothed <= V::ZERO - exit => Signal::Short,
+                _ => Signal::Flat,
+            }
+        };
+        (signal != self.signal).then(|| {
+            self.signal = signal;
+            signal
+        })
+    }
+}
+
+impl<V: DecimalType + std::fmt::Debug> std::fmt::Debug for ImbalanceSignal<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImbalanceSignal")
+            .field("depth", &self.depth)
+            .field("enter", &self.enter)
+            .field("exit", &self.exit)
+            .field("signal", &self.signal)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        metrics::rolling::Smoothing,
+        side::Side,
+        signals::{ImbalanceSignal, Signal},
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_hysteresis() {
+        let mut signal = ImbalanceSignal::new(Smoothing::Window(2), fixed!(0.5), fixed!(0.2));
+        // Averages of 0.4, 0.6, 0.3 and 0.1: long at 0.6, held at 0.3 and dropped at 0.1
+        let changes = [fixed!(0.4), fixed!(0.8), fixed!(-0.2), fixed!(0.4)].map(|imbalance| signal.update(imbalance));
+        assert_eq!(changes, [None, Some(Signal::Long), None, Some(Signal::Flat)]);
+        // From -0.5 straight to short, then back across to long
+        assert_eq!(signal.update(fixed!(-1.4)), Some(Signal::Short));
+        assert_eq!((signal.update(fixed!(1)), signal.update(fixed!(1))), (None, Some(Signal::Long)));
+
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let sink = fired.clone();
+        let mut signal = ImbalanceSignal::new(Smoothing::Ewma { alpha: fixed!(0.5) }, fixed!(0.5), fixed!(0.2))
+            .with_depth(2)
+            .with_callback(move |signal, delta| sink.lock().unwrap().push((signal, delta.timestamp.as_nanos())));
+        let mut book = BTreeOrderBook::new();
+        for (ts, side, price, size) in [
+            (1, Side::Buy, fixed!(100), fixed!(1)),
+            (2, Side::Sell, fixed!(101), fixed!(1)),
+            (3, Side::Sell, fixed!(103), fixed!(8)),
+            (4, Side::Sell, fixed!(102), fixed!(1)),
+        ] {
+            let delta = book.process_delta(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(ts))).unwrap();
+            signal.on_delta(&book, &delta);
+        }
+        // Imbalances of 1, 0 and -0.8 smooth to 1, 0.5 and -0.15, then -1/3 pushes the 103 level out
+        assert_eq!(*fired.lock().unwrap(), [(Signal::Long, 1), (Signal::Flat, 3)]);
+        assert_eq!(signal.signal().sign(), 0);
+    }
+}
@@ -0,0 +1,217 @@
+//! Venue fees and rebates.
+//!
+//! A [`FeeModel`] prices a fill from its price, size and whether it added or took liquidity, positive
+//! as a cost and negative as a rebate. [`BasisPoints`] charges a share of the notional, [`PerContract`]
+//! a fixed amount per unit of size, [`Tiered`] the basis points of the volume tier an account sits in,
+//! and a pair of models charges both. The presets on [`BasisPoints`] are the base tiers the venues
+//! published at the time of writing, venues change them and accounts rarely stay on the base tier.
+
+use std::{
+    fmt::Debug,
+    ops::{Add, Div, Mul},
+};
+
+use crate::decimals::decimal_type::DecimalType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Whether a fill rested on the book or crossed the spread
+pub enum Liquidity {
+    Maker,
+    Taker,
+}
+
+pub trait FeeModel<V: DecimalType>: Debug {
+    /// Fee charged on a fill of `size` at `price`, negative for a rebate
+    fn fee(&self, liquidity: Liquidity, price: V, size: V) -> V;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A share of the notional in basis points, negative for a rebate
+pub struct BasisPoints<V: DecimalType> {
+    pub maker: V,
+    pub taker: V,
+}
+
+impl<V: DecimalType> BasisPoints<V> {
+    #[inline]
+    #[must_use]
+    pub const fn new(maker: V, taker: V) -> Self {
+        Self { maker, taker }
+    }
+
+    #[inline]
+    fn bps(maker_tenths: i64, taker_tenths: i64) -> Self {
+        Self::new(V::from_scaled(maker_tenths, 1), V::from_scaled(taker_tenths, 1))
+    }
+
+    #[must_use]
+    /// Binance spot, 10 bps maker and taker
+    pub fn binance_spot() -> Self {
+        Self::bps(100, 100)
+    }
+
+    #[must_use]
+    /// Binance USDⓈ-M futures, 2 bps maker and 5 bps taker
+    pub fn binance_usdm() -> Self {
+        Self::bps(20, 50)
+    }
+
+    #[must_use]
+    /// Coinbase Advanced, 40 bps maker and 60 bps taker
+    pub fn coinbase_advanced() -> Self {
+        Self::bps(400, 600)
+    }
+
+    #[must_use]
+    /// Kraken Pro spot, 25 bps maker and 40 bps taker
+    pub fn kraken_spot() -> Self {
+        Self::bps(250, 400)
+    }
+
+    #[must_use]
+    /// OKX spot, 8 bps maker and 10 bps taker
+    pub fn okx_spot() -> Self {
+        Self::bps(80, 100)
+    }
+
+    #[must_use]
+    /// Bybit perpetuals, 2 bps maker and 5.5 bps taker
+    pub fn bybit_perpetual() -> Self {
+        Self::bps(20, 55)
+    }
+
+    #[must_use]
+    /// Deribit perpetuals, no maker fee and 5 bps taker
+    pub fn deribit_perpetual() -> Self {
+        Self::bps(0, 50)
+    }
+}
+
+impl<V> FeeModel<V> for BasisPoints<V>
+where
+    V: DecimalType + Copy + Debug + Mul<Output = V> + Div<Output = V>,
+{
+    #[inline]
+    fn fee(&self, liquidity: Liquidity, price: V, size: V) -> V {
+        let bps = match liquidity {
+            Liquidity::Maker => self.maker,
+            Liquidity::Taker => self.taker,
+        };
+        // The notional can overflow where the fee does not, so scale the size down to its share first
+        price * (size * (bps / V::from_scaled(10_000, 0)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A fixed amount per unit of size, as futures and options venues charge per contract
+pub struct PerContract<V: DecimalType> {
+    pub maker: V,
+    pub taker: V,
+}
+
+impl<V: DecimalType> PerContract<V> {
+    #[inline]
+    #[must_use]
+    pub const fn new(maker: V, taker: V) -> Self {
+        Self { maker, taker }
+    }
+}
+
+impl<V: DecimalType + Copy + Debug + Mul<Output = V>> FeeModel<V> for PerContract<V> {
+    #[inline]
+    fn fee(&self, liquidity: Liquidity, _price: V, size: V) -> V {
+        match liquidity {
+            Liquidity::Maker => self.maker * size,
+            Liquidity::Taker => self.taker * size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Basis points that fall as trailing volume grows
+pub struct Tiered<V: DecimalType> {
+    /// The volume each tier starts at and its fees, lowest first
+    tiers: Vec<(V, BasisPoints<V>)>,
+    volume: V,
+}
+
+impl<V: DecimalType + Copy + PartialOrd> Tiered<V> {
+    #[must_use]
+    /// Tiers as the volume each starts at and its fees, in any order, charging the lowest tier until
+    /// [`with_volume`](Self::with_volume)
+    pub fn new(mut tiers: Vec<(V, BasisPoints<V>)>) -> Self {
+        tiers.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        Self { tiers, volume: V::ZERO }
+    }
+
+    #[must_use]
+    /// Charge the tier of an account that traded `volume` over the venue's window
+    pub fn with_volume(self, volume: V) -> Self {
+        Self { volume, ..self }
+    }
+
+    #[must_use]
+    /// Fees of the highest tier the volume reaches, the lowest tier when it reaches none and `None`
+    /// without tiers
+    pub fn tier(&self) -> Option<&BasisPoints<V>> {
+        let reached = self.tiers.iter().rev().find(|(from, _)| *from <= self.volume);
+        reached.or(self.tiers.first()).map(|(_, fees)| fees)
+    }
+}
+
+impl<V> FeeModel<V> for Tiered<V>
+where
+    V: DecimalType + Copy + PartialOrd + Debug + Mul<Output = V> + Div<Output = V>,
+{
+    #[inline]
+    fn fee(&self, liquidity: Liquidity, price: V, size: V) -> V {
+        self.tier().map_or(V::ZERO, |fees| fees.fee(liquidity, price, size))
+    }
+}
+
+impl<V, A, B> FeeModel<V> for (A, B)
+where
+    V: DecimalType + Copy + Add<Output = V>,
+    A: FeeModel<V>,
+    B: FeeModel<V>,
+{
+    #[inline]
+    fn fee(&self, liquidity: Liquidity, price: V, size: V) -> V {
+        self.0.fee(liquidity, price, size) + self.1.fee(liquidity, price, size)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        fixed,
+        sim::fees::{BasisPoints, FeeModel, Liquidity, PerContract, Tiered},
+    };
+
+    #[test]
+    fn test_fee_models() {
+        let bybit = BasisPoints::bybit_perpetual();
+        assert_eq!(bybit.fee(Liquidity::Taker, fixed!(100), fixed!(2)), fixed!(0.11));
+        assert_eq!(bybit.fee(Liquidity::Maker, fixed!(100), fixed!(2)), fixed!(0.04));
+        let rebate = BasisPoints::new(fixed!(-1), fixed!(3));
+        assert_eq!(rebate.fee(Liquidity::Maker, fixed!(50), fixed!(4)), fixed!(-0.02));
+        // A 3,000,000 notional is beyond what the decimal holds, the 3,000 fee on it is not
+        let binance = BasisPoints::binance_spot();
+        assert_eq!(binance.fee(Liquidity::Taker, fixed!(60000), fixed!(50)), fixed!(3000));
+
+        let contracts = PerContract::new(fixed!(0.1), fixed!(0.25));
+        assert_eq!(contracts.fee(Liquidity::Taker, fixed!(3000), fixed!(4)), fixed!(1));
+        let both = (BasisPoints::new(fixed!(0), fixed!(10)), contracts);
+        assert_eq!(both.fee(Liquidity::Taker, fixed!(100), fixed!(2)), fixed!(0.7));
+
+        let tiers = Tiered::new(vec![
+            (fixed!(1000000), BasisPoints::new(fixed!(8), fixed!(9))),
+            (fixed!(0), BasisPoints::new(fixed!(10), fixed!(10))),
+        ]);
+        assert_eq!(tiers.fee(Liquidity::Maker, fixed!(100), fixed!(1)), fixed!(0.1));
+        let tiers = tiers.with_volume(fixed!(2500000));
+        assert_eq!(tiers.fee(Liquidity::Maker, fixed!(100), fixed!(1)), fixed!(0.08));
+        assert_eq!(Tiered::new(Vec::new()).fee(Liquidity::Taker, fixed!(100), fixed!(1)), fixed!(0));
+    }
+}
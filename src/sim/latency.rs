@@ -0,0 +1,150 @@
+//! Latency distributions for simulation.
+//!
+//! A [`LatencyModel`] describes how long a message takes, whether market data on its way from the
+//! venue or an order on its way to it, and a [`LatencySampler`] draws from it. Draws come from a small
+//! seeded generator rather than the thread's randomness, so a backtest with the same seed sees the same
+//! latencies on every run.
+
+use std::time::Duration;
+
+use crate::pipeline::LatencyHistogram;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LatencyModel {
+    /// The same latency every time
+    Fixed(Duration),
+    /// Normally distributed latency, draws below zero taken as zero
+    Normal { mean: Duration, std_dev: Duration },
+    /// Latencies drawn in proportion to how often each was observed, as `(latency, count)`
+    Empirical(Vec<(Duration, u64)>),
+}
+
+impl LatencyModel {
+    #[must_use]
+    /// Latencies as a pipeline measured them, each bucket standing for its upper bound
+    pub fn from_histogram(histogram: &LatencyHistogram) -> Self {
+        let observed = histogram
+            .buckets
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(bucket, &count)| (Duration::from_nanos(2 << bucket), count))
+            .collect();
+        Self::Empirical(observed)
+    }
+}
+
+impl Default for LatencyModel {
+    #[inline]
+    fn default() -> Self {
+        Self::Fixed(Duration::ZERO)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Draws latencies from a [`LatencyModel`], the same ones for the same seed
+pub struct LatencySampler {
+    model: LatencyModel,
+    rng: SplitMix64,
+    /// Total count of an empirical model
+    total: u64,
+}
+
+impl LatencySampler {
+    #[must_use]
+    pub fn new(model: LatencyModel, seed: u64) -> Self {
+        let total = match &model {
+            LatencyModel::Empirical(observed) => observed.iter().map(|&(_, count)| count).sum(),
+            _ => 0,
+        };
+        Self { model, rng: SplitMix64(seed), total }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn model(&self) -> &LatencyModel {
+        &self.model
+    }
+
+    /// Draw the next latency, zero from an empirical model with nothing observed
+    pub fn sample(&mut self) -> Duration {
+        match &self.model {
+            LatencyModel::Fixed(latency) => *latency,
+            LatencyModel::Normal { mean, std_dev } => {
+                let (mean, std_dev) = (mean.as_secs_f64(), std_dev.as_secs_f64());
+                // Box-Muller, keeping the open interval away from the log of zero
+                let (u1, u2) = (1.0 - self.rng.next_f64(), self.rng.next_f64());
+                let z = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+                Duration::from_secs_f64((mean + std_dev * z).max(0.0))
+            }
+            LatencyModel::Empirical(observed) => {
+                if self.total == 0 {
+                    return Duration::ZERO;
+                }
+                let mut rank = self.rng.next_u64() % self.total;
+                for &(latency, count) in observed {
+                    if rank < count {
+                        return latency;
+                    }
+                    rank -= count;
+                }
+                unreachable!("the rank is below the total count")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The SplitMix64 generator, small and fast with no state beyond its counter
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        pipeline::LatencyHistogram,
+        sim::latency::{LatencyModel, LatencySampler},
+    };
+
+    #[test]
+    fn test_samples() {
+        let us = Duration::from_micros;
+        let mut fixed = LatencySampler::new(LatencyModel::Fixed(us(5)), 1);
+        assert_eq!((fixed.sample(), fixed.sample()), (us(5), us(5)));
+
+        let normal = LatencyModel::Normal { mean: us(100), std_dev: us(10) };
+        let draws = |seed| {
+            let mut sampler = LatencySampler::new(normal.clone(), seed);
+            (0..10_000).map(|_| sampler.sample()).collect::<Vec<_>>()
+        };
+        let first = draws(7);
+        assert_eq!(first, draws(7));
+        assert_ne!(first, draws(8));
+        let mean = first.iter().sum::<Duration>() / first.len() as u32;
+        assert!(us(99) < mean && mean < us(101), "{mean:?}");
+        assert!(first.iter().all(|&latency| us(40) < latency && latency < us(160)));
+
+        let mut histogram = LatencyHistogram::default();
+        (histogram.buckets[3], histogram.buckets[9]) = (3, 1);
+        let mut empirical = LatencySampler::new(LatencyModel::from_histogram(&histogram), 3);
+        let fast = (0..4_000).filter(|_| empirical.sample() == Duration::from_nanos(16)).count();
+        assert!((2_800..3_200).contains(&fast), "{fast}");
+        assert_eq!(LatencySampler::new(LatencyModel::Empirical(Vec::new()), 0).sample(), Duration::ZERO);
+    }
+}
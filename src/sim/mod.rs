@@ -0,0 +1,155 @@
+//! Deterministic simulation for backtests.
+//!
+//! [`execute_market`] walks the visible book for a market order and returns the [`Fill`] it would get
+//! at each level, which a paper trading [`Account`](crate::paper::Account) takes as they are. The
+//! order may be limited to a share of each level's size, standing in for the queue of other
+//! takers, and charged by a [`FeeModel`](fees::FeeModel) as a taker.
+
+pub mod fees;
+pub mod latency;
+pub mod queue;
+pub mod scheduler;
+
+use std::{
+    iter::Sum,
+    ops::{Add, ControlFlow, Div, Mul, Sub},
+};
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    metrics::MetricsCalculator,
+    paper::Fill,
+    side::Side,
+    sim::fees::{FeeModel, Liquidity},
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// What a market order would get from the visible book, see [`execute_market`]
+pub struct Execution<V: DecimalType> {
+    /// One fill per level taken from, best price first
+    pub fills: Vec<Fill<V>>,
+    pub filled: V,
+    /// Size the book could not fill, zero unless the side ran out or the participation limit held the
+    /// order back
+    pub unfilled: V,
+    /// Volume-weighted fill price, zero when nothing could be filled
+    pub average_price: V,
+    /// Distance of the average price from the best price, positive as a cost, zero when nothing could be
+    /// filled
+    pub slippage: V,
+}
+
+impl<V: DecimalType + Copy + Add<Output = V> + Mul<Output = V>> Execution<V> {
+    #[must_use]
+    /// Charge every fill by `fees` as a taker
+    pub fn with_fees<F: FeeModel<V> + ?Sized>(mut self, fees: &F) -> Self {
+        for fill in &mut self.fills {
+            *fill = fill.charged(fees, Liquidity::Taker);
+        }
+        self
+    }
+
+    #[must_use]
+    /// Stamp every fill with the time the order was sent
+    pub fn at(mut self, timestamp: Timestamp) -> Self {
+        for fill in &mut self.fills {
+            fill.timestamp = timestamp;
+        }
+        self
+    }
+
+    #[must_use]
+    /// Fees charged across the fills
+    pub fn fees(&self) -> V {
+        self.fills.iter().fold(V::ZERO, |fees, fill| fees + fill.fee)
+    }
+}
+
+/// Walk `book` for a market order to `side` `size` (a buy consumes asks), taking at most
+/// `participation_limit` of each level's size, such as `0.25` for a quarter, or all of it when `None`.
+/// Fills carry no fee and a zero timestamp until [`Execution::with_fees`] and [`Execution::at`].
+pub fn execute_market<V, B>(book: &B, side: Side, size: V, participation_limit: Option<V>) -> Execution<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V> + Sum,
+    B: MetricsCalculator<V>,
+{
+    let (mut fills, mut filled, mut notional) = (Vec::new(), V::ZERO, V::ZERO);
+    book.walk_levels(side.opposite(), &mut |level| {
+        if filled >= size {
+            return ControlFlow::Break(());
+        }
+        let available = participation_limit.map_or(level.size, |limit| level.size * limit);
+        let take = if size - filled < available { size - filled } else { available };
+        if take > V::ZERO {
+            fills.push(Fill::new(side, level.price, take, Timestamp::ZERO));
+            filled = filled + take;
+            notional = notional + level.price * take;
+        }
+        ControlFlow::Continue(())
+    });
+    let average_price = if filled > V::ZERO { notional / filled } else { V::ZERO };
+    let slippage = match (fills.first(), side) {
+        (None, _) => V::ZERO,
+        (Some(best), Side::Buy) => average_price - best.price,
+        (Some(best), Side::Sell) => best.price - average_price,
+    };
+    Execution { fills, filled, unfilled: size - filled, average_price, slippage }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        paper::{Account, Mark},
+        side::Side,
+        sim::{
+            execute_market,
+            fees::{BasisPoints, PerContract},
+        },
+        timestamp::Timestamp,
+    };
+
+    fn book() -> BTreeOrderBook<FixedDecimal> {
+        let mut book = BTreeOrderBook::new();
+        for (side, price, size) in [
+            (Side::Buy, fixed!(99), fixed!(4)),
+            (Side::Sell, fixed!(101), fixed!(2)),
+            (Side::Sell, fixed!(102), fixed!(4)),
+            (Side::Sell, fixed!(104), fixed!(10)),
+        ] {
+            book.process(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(1)));
+        }
+        book
+    }
+
+    #[test]
+    fn test_walks_levels() {
+        let book = book();
+        let fees = (BasisPoints::new(fixed!(0), fixed!(10)), PerContract::new(fixed!(0), fixed!(0.005)));
+        let execution = execute_market(&book, Side::Buy, fixed!(4), None).at(Timestamp::from_nanos(7)).with_fees(&fees);
+        let fills = execution.fills.iter().map(|fill| (fill.price, fill.size, fill.fee)).collect::<Vec<_>>();
+        assert_eq!(fills, [(fixed!(101), fixed!(2), fixed!(0.212)), (fixed!(102), fixed!(2), fixed!(0.214))]);
+        assert_eq!((execution.average_price, execution.slippage, execution.unfilled), (fixed!(101.5), fixed!(0.5), fixed!(0)));
+        assert_eq!((execution.fees(), execution.fills[0].timestamp), (fixed!(0.426), Timestamp::from_nanos(7)));
+
+        // Half of each level at most, running out of asks
+        let limited = execute_market(&book, Side::Buy, fixed!(10), Some(fixed!(0.5)));
+        assert_eq!(limited.fills.iter().map(|fill| fill.size).collect::<Vec<_>>(), [fixed!(1), fixed!(2), fixed!(5)]);
+        assert_eq!((limited.filled, limited.unfilled), (fixed!(8), fixed!(2)));
+
+        let sold = execute_market(&book, Side::Sell, fixed!(5), None);
+        assert_eq!((sold.filled, sold.unfilled, sold.slippage), (fixed!(4), fixed!(1), fixed!(0)));
+        let empty = execute_market(&BTreeOrderBook::new(), Side::Sell, fixed!(1), None);
+        assert_eq!((empty.fills.len(), empty.average_price), (0, fixed!(0)));
+
+        let mut account = Account::new(Mark::Mid);
+        execution.fills.into_iter().for_each(|fill| account.on_fill(fill));
+        assert_eq!((account.position(), account.average_price(), account.fees()), (fixed!(4), fixed!(101.5), fixed!(0.426)));
+    }
+}
@@ -0,0 +1,97 @@
+//! Fill models for passive orders.
+//!
+//! A recorded book shows the size resting at each price but not the orders behind it, so an order
+//! joining a level is taken to queue behind everything the level displayed when it arrived. A
+//! [`FillModel`] decides how that queue drains as the level changes and how much of the order a trade
+//! printing at its level fills. [`QueueReactive`] lets trades consume the queue ahead first and takes
+//! cancellations from the queue ahead in proportion to its share of the level.
+
+use std::{
+    fmt::Debug,
+    ops::{Div, Mul, Sub},
+};
+
+use crate::decimals::decimal_type::DecimalType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Where a resting order stands in the queue at its level
+pub struct QueuePosition<V: DecimalType> {
+    /// Size resting ahead of the order
+    pub ahead: V,
+    /// Size of the order still unfilled
+    pub remaining: V,
+}
+
+impl<V: DecimalType> QueuePosition<V> {
+    #[inline]
+    #[must_use]
+    pub const fn new(ahead: V, remaining: V) -> Self {
+        Self { ahead, remaining }
+    }
+}
+
+pub trait FillModel<V: DecimalType>: Debug {
+    /// A trade of `size` printed at the order's level, return how much of the order it filled, which the
+    /// caller takes off the remaining size
+    fn on_trade(&mut self, queue: &mut QueuePosition<V>, size: V) -> V;
+    /// The level's displayed size went from `before` to `after` other than by a trade
+    fn on_update(&mut self, queue: &mut QueuePosition<V>, before: V, after: V);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// Trades fill the queue ahead before the order, cancellations are spread evenly across the level, and
+/// size added to the level queues behind the order
+pub struct QueueReactive;
+
+impl<V> FillModel<V> for QueueReactive
+where
+    V: DecimalType + Copy + PartialOrd + Sub<Output = V> + Mul<Output = V> + Div<Output = V>,
+{
+    fn on_trade(&mut self, queue: &mut QueuePosition<V>, size: V) -> V {
+        if size <= queue.ahead {
+            queue.ahead = queue.ahead - size;
+            return V::ZERO;
+        }
+        let left = size - queue.ahead;
+        queue.ahead = V::ZERO;
+        if left < queue.remaining {
+            left
+        } else {
+            queue.remaining
+        }
+    }
+
+    fn on_update(&mut self, queue: &mut QueuePosition<V>, before: V, after: V) {
+        if after >= before || before <= V::ZERO {
+            return;
+        }
+        queue.ahead = queue.ahead * after / before;
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        fixed,
+        sim::queue::{FillModel, QueuePosition, QueueReactive},
+    };
+
+    #[test]
+    fn test_queue_reactive() {
+        let mut model = QueueReactive;
+        let mut queue = QueuePosition::new(fixed!(10), fixed!(3));
+        assert_eq!(model.on_trade(&mut queue, fixed!(4)), fixed!(0));
+        assert_eq!(queue.ahead, fixed!(6));
+
+        // Half the level cancels, taking half the queue ahead with it, growth queues behind
+        model.on_update(&mut queue, fixed!(12), fixed!(6));
+        model.on_update(&mut queue, fixed!(6), fixed!(20));
+        assert_eq!(queue, QueuePosition::new(fixed!(3), fixed!(3)));
+
+        assert_eq!(model.on_trade(&mut queue, fixed!(5)), fixed!(2));
+        queue.remaining = fixed!(1);
+        assert_eq!(model.on_trade(&mut queue, fixed!(5)), fixed!(1));
+        assert_eq!(queue.ahead, fixed!(0));
+    }
+}
@@ -0,0 +1,338 @@
+//! A single threaded loop running event sources and timers in timestamp order.
+//!
+//! [`SimScheduler`] merges its sources with [`merge_by_timestamp`] and interleaves the callbacks
+//! registered on its [`Timers`], advancing a [`SimulatedClock`] to each one in turn. Nothing runs on
+//! another thread and every tie is broken the same way, so a backtest driven by it gives the same
+//! result on every run:
+//!
+//! - events due at the same time as a timer run before it, so the timer sees all the data up to then
+//! - events due at the same time run in source order, as for [`merge_by_timestamp`]
+//! - timers due at the same time run in the order they were set
+//!
+//! A timer set for a time that has already passed runs as soon as the events already due have run.
+//!
+//! [`merge_by_timestamp`]: crate::formats::combinators::merge_by_timestamp
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+    fmt,
+    time::Duration,
+};
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    formats::{
+        combinators::{merge_by_timestamp, Merge},
+        replay::{Clock, SimulatedClock},
+        source::{EventSource, SourceError},
+    },
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// Identifies a timer, see [`Timers::cancel`]
+pub struct TimerId(u64);
+
+type Callback<C> = Box<dyn FnMut(&mut C, &mut Timers<C>)>;
+
+struct Timer<C> {
+    /// Interval of a repeating timer
+    every: Option<Duration>,
+    callback: Callback<C>,
+}
+
+/// The timers of a [`SimScheduler`], handed to the event handler and every callback so either can set
+/// and cancel timers as the simulation runs
+pub struct Timers<C> {
+    now: Timestamp,
+    /// Deadline of every timer, ties go to the timer set first
+    deadlines: BinaryHeap<Reverse<(Timestamp, TimerId)>>,
+    timers: BTreeMap<TimerId, Timer<C>>,
+    next_id: u64,
+    /// The timer whose callback is running, cleared when it cancels itself
+    firing: Option<TimerId>,
+}
+
+impl<C> fmt::Debug for Timers<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timers").field("now", &self.now).field("pending", &self.timers.len()).finish_non_exhaustive()
+    }
+}
+
+impl<C> Timers<C> {
+    fn new(now: Timestamp) -> Self {
+        Self { now, deadlines: BinaryHeap::new(), timers: BTreeMap::new(), next_id: 0, firing: None }
+    }
+
+    #[inline]
+    #[must_use]
+    /// The simulated time
+    pub const fn now(&self) -> Timestamp {
+        self.now
+    }
+
+    #[inline]
+    #[must_use]
+    /// Timers still to run
+    pub fn pending(&self) -> usize {
+        self.timers.len()
+    }
+
+    /// Call `callback` once at `deadline`
+    pub fn at(&mut self, deadline: Timestamp, callback: impl FnMut(&mut C, &mut Self) + 'static) -> TimerId {
+        self.insert(deadline, None, Box::new(callback))
+    }
+
+    /// Call `callback` once when `delay` has passed
+    pub fn after(&mut self, delay: Duration, callback: impl FnMut(&mut C, &mut Self) + 'static) -> TimerId {
+        self.insert(self.now + delay, None, Box::new(callback))
+    }
+
+    /// Call `callback` every `interval`, starting one interval from now, until cancelled. A zero interval
+    /// is taken as one nanosecond.
+    pub fn every(&mut self, interval: Duration, callback: impl FnMut(&mut C, &mut Self) + 'static) -> TimerId {
+        let interval = interval.max(Duration::from_nanos(1));
+        self.insert(self.now + interval, Some(interval), Box::new(callback))
+    }
+
+    /// Stop a timer, returning whether it was still to run
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        if self.firing == Some(id) {
+            self.firing = None;
+            return true;
+        }
+        self.timers.remove(&id).is_some()
+    }
+
+    fn insert(&mut self, deadline: Timestamp, every: Option<Duration>, callback: Callback<C>) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.deadlines.push(Reverse((deadline.max(self.now), id)));
+        self.timers.insert(id, Timer { every, callback });
+        id
+    }
+
+    /// Deadline of the next timer still to run, skipping those cancelled
+    fn next_deadline(&mut self) -> Option<Timestamp> {
+        while let Some(&Reverse((deadline, id))) = self.deadlines.peek() {
+            if self.timers.contains_key(&id) {
+                return Some(deadline);
+            }
+            self.deadlines.pop();
+        }
+        None
+    }
+
+    fn fire(&mut self, context: &mut C) {
+        let Some(Reverse((deadline, id))) = self.deadlines.pop() else {
+            return;
+        };
+        let Some(mut timer) = self.timers.remove(&id) else {
+            return;
+        };
+        self.firing = Some(id);
+        (timer.callback)(context, self);
+        if let (Some(every), Some(_)) = (timer.every, self.firing.take()) {
+            self.deadlines.push(Reverse(((deadline + every).max(self.now), id)));
+            self.timers.insert(id, timer);
+        }
+    }
+}
+
+/// Runs the events of several sources and the callbacks of [`Timers`] on one thread in timestamp order,
+/// see the [module documentation](self)
+pub struct SimScheduler<V: DecimalType, S, C> {
+    events: Merge<V, S>,
+    /// The next event, read ahead to compare it with the next timer
+    next: Option<Event<V>>,
+    timers: Timers<C>,
+    clock: SimulatedClock,
+}
+
+impl<V: DecimalType, S, C> fmt::Debug for SimScheduler<V, S, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimScheduler").field("clock", &self.clock).field("timers", &self.timers).finish_non_exhaustive()
+    }
+}
+
+impl<V: DecimalType, S: EventSource<V>, C> SimScheduler<V, S, C> {
+    #[must_use]
+    /// Schedule the events of `sources`, each in timestamp order, with the clock starting at `start`
+    pub fn new(sources: Vec<S>, start: Timestamp) -> Self {
+        Self { events: merge_by_timestamp(sources), next: None, timers: Timers::new(start), clock: SimulatedClock::new(start) }
+    }
+
+    #[inline]
+    #[must_use]
+    /// The simulated time, that of the last event or timer run
+    pub fn now(&self) -> Timestamp {
+        self.clock.now()
+    }
+
+    #[inline]
+    /// The timers, for setting those the simulation starts with
+    pub fn timers(&mut self) -> &mut Timers<C> {
+        &mut self.timers
+    }
+
+    /// Run every event, and the timers due no later than the last one, passing each event to `on_event`.
+    /// Timers due after the last event are left pending.
+    ///
+    /// # Errors
+    /// The first error a source yields, after which calling again carries on from the next event.
+    pub fn run<F>(&mut self, context: &mut C, on_event: F) -> Result<(), SourceError>
+    where
+        F: FnMut(&mut C, &mut Timers<C>, Event<V>),
+    {
+        self.run_to(context, on_event, None)
+    }
+
+    /// Run the events and timers due no later than `deadline`, then move the clock on to it
+    ///
+    /// # Errors
+    /// The first error a source yields, after which calling again carries on from the next event.
+    pub fn run_until<F>(&mut self, deadline: Timestamp, context: &mut C, on_event: F) -> Result<(), SourceError>
+    where
+        F: FnMut(&mut C, &mut Timers<C>, Event<V>),
+    {
+        self.run_to(context, on_event, Some(deadline))?;
+        self.advance(deadline);
+        Ok(())
+    }
+
+    fn run_to<F>(&mut self, context: &mut C, mut on_event: F, deadline: Option<Timestamp>) -> Result<(), SourceError>
+    where
+        F: FnMut(&mut C, &mut Timers<C>, Event<V>),
+    {
+        loop {
+            if self.next.is_none() {
+                self.next = self.events.next().transpose()?;
+            }
+            let event_due = self.next.as_ref().map(|event| event.timestamp);
+            let timer_due = self.timers.next_deadline();
+            let run_event = match (event_due, timer_due) {
+                (Some(event), Some(timer)) => event <= timer,
+                (Some(_), None) => true,
+                // Without a deadline, only the timers the last event has made due still run
+                (None, Some(timer)) if deadline.is_some() || timer <= self.clock.now() => false,
+                _ => return Ok(()),
+            };
+            let due = if run_event { event_due } else { timer_due }.expect("the chosen item is due");
+            if deadline.is_some_and(|deadline| due > deadline) {
+                return Ok(());
+            }
+            self.advance(due);
+            if run_event {
+                let event = self.next.take().expect("the chosen event was read ahead");
+                on_event(context, &mut self.timers, event);
+            } else {
+                self.timers.fire(context);
+            }
+        }
+    }
+
+    fn advance(&mut self, to: Timestamp) {
+        self.clock.sleep_until(to);
+        self.timers.now = self.clock.now();
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::{
+            source::{EventSource, MemorySource, SourceError},
+            FormatError,
+        },
+        side::Side,
+        sim::scheduler::SimScheduler,
+        timestamp::Timestamp,
+    };
+
+    fn venue(millis: &[i64], price: FixedDecimal) -> MemorySource<FixedDecimal> {
+        let event = |ms| Event::new(EventKind::L2, Side::Buy, price, fixed!(1), Timestamp::from_millis(ms));
+        MemorySource::new(millis.iter().map(|&ms| event(ms)).collect())
+    }
+
+    #[derive(Default)]
+    struct Backtest {
+        book: BTreeOrderBook<FixedDecimal>,
+        log: Vec<(i64, String)>,
+    }
+
+    fn backtest() -> Vec<(i64, String)> {
+        let ms = Duration::from_millis;
+        let mut scheduler =
+            SimScheduler::new(vec![venue(&[0, 10, 20, 40], fixed!(100)), venue(&[10, 30], fixed!(101))], Timestamp::ZERO);
+        scheduler.timers().at(Timestamp::from_millis(10), |test: &mut Backtest, timers| {
+            let best = test.book.best_bid().map(|level| level.price.to_string()).unwrap_or_default();
+            test.log.push((timers.now().as_millis(), format!("bid {best}")));
+        });
+        let mut ticks = 0;
+        let tick = scheduler.timers().every(ms(15), move |test: &mut Backtest, timers| {
+            ticks += 1;
+            test.log.push((timers.now().as_millis(), format!("tick {ticks}")));
+        });
+
+        let mut test = Backtest::default();
+        scheduler
+            .run(&mut test, |test, timers, event| {
+                test.log.push((timers.now().as_millis(), format!("event {}", event.price)));
+                test.book.process(event);
+                if event.timestamp == Timestamp::from_millis(30) {
+                    assert!(timers.cancel(tick));
+                    timers
+                        .after(ms(5), |test: &mut Backtest, timers| test.log.push((timers.now().as_millis(), "late".to_owned())));
+                }
+            })
+            .unwrap();
+        assert_eq!((scheduler.now(), scheduler.timers().pending()), (Timestamp::from_millis(40), 0));
+        test.log
+    }
+
+    #[test]
+    fn test_runs_in_timestamp_order() {
+        let log = backtest();
+        let expected = [
+            (0, "event 100"),
+            (10, "event 100"),
+            (10, "event 101"),
+            (10, "bid 101"),
+            (15, "tick 1"),
+            (20, "event 100"),
+            (30, "event 101"),
+            (35, "late"),
+            (40, "event 100"),
+        ];
+        assert_eq!(log, expected.map(|(ms, entry)| (ms, entry.to_owned())));
+        assert_eq!(backtest(), log);
+    }
+
+    #[test]
+    fn test_run_until_and_errors() {
+        let broken = std::iter::once(Err::<Event<FixedDecimal>, SourceError>(FormatError::Io("gone".to_owned())));
+        let sources: Vec<Box<dyn EventSource<FixedDecimal>>> = vec![Box::new(venue(&[5], fixed!(100))), Box::new(broken)];
+        let mut scheduler = SimScheduler::new(sources, Timestamp::ZERO);
+        let mut fired = Vec::new();
+        scheduler.timers().every(Duration::from_millis(4), |fired: &mut Vec<i64>, timers| fired.push(timers.now().as_millis()));
+
+        let mut seen = 0;
+        assert_eq!(
+            scheduler.run_until(Timestamp::from_millis(10), &mut fired, |_, _, _| seen += 1),
+            Err(FormatError::Io("gone".to_owned()))
+        );
+        scheduler.run_until(Timestamp::from_millis(10), &mut fired, |_, _, _| seen += 1).unwrap();
+        assert_eq!((seen, scheduler.now()), (1, Timestamp::from_millis(10)));
+        assert_eq!(fired, [4, 8]);
+    }
+}
@@ -0,0 +1,219 @@
+//! Slicing a parent order over time.
+//!
+//! A [`SlicePlanner`] splits a target quantity into one child slice per interval of a horizon. Under
+//! [`Schedule::Twap`] each slice takes an even share of what is left, so a slice held back catches up
+//! in the ones after it. Under [`Schedule::Pov`] each slice takes a fixed share of the volume the
+//! interval is expected to trade, the volume a [`TradeFlow`] saw over its window scaled to the interval.
+//!
+//! A price band caps each slice at the mid plus the band for a buy, minus it for a sell, and a
+//! liquidity share caps its size at a share of what the book shows on the other side within the band.
+//! Whatever the caps or the schedule leave unplanned by the end of the horizon is the plan's shortfall.
+
+use std::{
+    iter::Sum,
+    ops::{Add, Div, Mul, Sub},
+    time::Duration,
+};
+
+use crate::{
+    decimals::decimal_type::DecimalType,
+    metrics::{flow::TradeFlow, MetricsCalculator},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule<V: DecimalType> {
+    /// Even slices across the horizon
+    Twap,
+    /// A share of the expected volume of each interval, `0.1` for a tenth
+    Pov(V),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// One child order of a [`SlicePlan`]
+pub struct Slice<V: DecimalType> {
+    /// When the interval starts
+    pub start: Timestamp,
+    pub size: V,
+    /// Worst price the slice should trade at, `None` without a band or while either side is empty
+    pub limit_price: Option<V>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlicePlan<V: DecimalType> {
+    /// One slice per interval, empty slices included
+    pub slices: Vec<Slice<V>>,
+    /// Size across the slices
+    pub planned: V,
+    /// Target quantity the slices leave unplanned
+    pub shortfall: V,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Plans the slices of a parent order, see the [module documentation](self)
+pub struct SlicePlanner<V: DecimalType> {
+    side: Side,
+    quantity: V,
+    horizon: Duration,
+    intervals: u32,
+    schedule: Schedule<V>,
+    /// Distance from the mid, in basis points, a slice may trade at
+    band: Option<V>,
+    /// Share of the size visible within the band a slice may take
+    liquidity_share: Option<V>,
+}
+
+impl<V> SlicePlanner<V>
+where
+    V: DecimalType + Copy + PartialOrd + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + Div<Output = V> + Sum,
+{
+    #[inline]
+    #[must_use]
+    /// TWAP `quantity` to `side` over `horizon` in `intervals` slices, at least one
+    pub fn new(side: Side, quantity: V, horizon: Duration, intervals: u32) -> Self {
+        Self { side, quantity, horizon, intervals: intervals.max(1), schedule: Schedule::Twap, band: None, liquidity_share: None }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn with_schedule(self, schedule: Schedule<V>) -> Self {
+        Self { schedule, ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Limit slices to `bps` basis points from the mid
+    pub const fn with_price_band(self, bps: V) -> Self {
+        Self { band: Some(bps), ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Cap slices at `share` of the size the other side shows within the band, or within the touch
+    /// without a band
+    pub const fn with_liquidity_share(self, share: V) -> Self {
+        Self { liquidity_share: Some(share), ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Time each slice covers
+    pub fn interval(&self) -> Duration {
+        self.horizon / self.intervals
+    }
+
+    /// Plan the slices from `start` with the book and the tape as they stand
+    #[must_use]
+    pub fn plan<B: MetricsCalculator<V>>(&self, start: Timestamp, book: &B, flow: &TradeFlow<V>) -> SlicePlan<V> {
+        let interval = self.interval();
+        let band = self.band.unwrap_or(V::ZERO);
+        let limit_price = book.best_bid().zip(book.best_ask()).filter(|_| self.band.is_some()).map(|(bid, ask)| {
+            let mid = (bid.price + ask.price) / V::TWO;
+            let distance = mid * band / (V::ONE_HUNDRED * V::ONE_HUNDRED);
+            match self.side {
+                Side::Buy => mid + distance,
+                Side::Sell => mid - distance,
+            }
+        });
+        let cap = self.liquidity_share.map(|share| {
+            let visible = book.liquidity_within_bps(band).map_or(V::ZERO, |liquidity| match self.side {
+                Side::Buy => liquidity.ask_size,
+                Side::Sell => liquidity.bid_size,
+            });
+            visible * share
+        });
+        let expected_volume = match flow.span().as_nanos() {
+            span if span > 0 => {
+                let (interval, span) = reduce(interval.as_nanos(), span);
+                flow.volume() * V::from_scaled(interval, 0) / V::from_scaled(span, 0)
+            }
+            _ => V::ZERO,
+        };
+
+        let (mut slices, mut left) = (Vec::with_capacity(self.intervals as usize), self.quantity);
+        for index in 0..self.intervals {
+            let wanted = match self.schedule {
+                Schedule::Twap => left / V::from_scaled(i64::from(self.intervals - index), 0),
+                Schedule::Pov(rate) => expected_volume * rate,
+            };
+            let size = [Some(left), cap].into_iter().flatten().fold(wanted, |size, cap| if cap < size { cap } else { size });
+            let size = if size > V::ZERO { size } else { V::ZERO };
+            left = left - size;
+            slices.push(Slice { start: start + interval * index, size, limit_price });
+        }
+        SlicePlan { slices, planned: self.quantity - left, shortfall: left }
+    }
+}
+
+/// The ratio `numerator / denominator` in lowest terms, rounded further until both fit in six digits,
+/// keeping nanosecond counts out of the decimal's range
+fn reduce(mut numerator: u128, mut denominator: u128) -> (i64, i64) {
+    let (mut a, mut b) = (numerator, denominator);
+    while b > 0 {
+        (a, b) = (b, a % b);
+    }
+    (numerator, denominator) = (numerator / a, denominator / a);
+    while numerator > 1_000_000 || denominator > 1_000_000 {
+        (numerator, denominator) = (numerator / 10, (denominator / 10).max(1));
+    }
+    (numerator as i64, denominator as i64)
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        metrics::flow::TradeFlow,
+        ofi::Window,
+        side::Side,
+        slicing::{Schedule, SlicePlan, SlicePlanner},
+        timestamp::Timestamp,
+    };
+
+    #[test]
+    fn test_plans_slices() {
+        let mut book = BTreeOrderBook::<FixedDecimal>::new();
+        for (side, price, size) in [
+            (Side::Buy, fixed!(99), fixed!(10)),
+            (Side::Sell, fixed!(101), fixed!(4)),
+            (Side::Sell, fixed!(102), fixed!(6)),
+            (Side::Sell, fixed!(110), fixed!(50)),
+        ] {
+            book.process(Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(1)));
+        }
+        // 60 traded over the last 60 seconds
+        let mut flow = TradeFlow::new(Window::Time(Duration::from_secs(60)));
+        for second in 1..=6 {
+            flow.on_trade(&Event::new(EventKind::Trade, Side::Sell, fixed!(101), fixed!(10), Timestamp::from_secs(second * 10)));
+        }
+        let start = Timestamp::from_secs(60);
+        let sizes = |plan: &SlicePlan<FixedDecimal>| plan.slices.iter().map(|slice| slice.size).collect::<Vec<_>>();
+
+        let twap = SlicePlanner::new(Side::Buy, fixed!(12), Duration::from_secs(40), 4).plan(start, &book, &flow);
+        assert_eq!(sizes(&twap), [fixed!(3); 4]);
+        assert_eq!((twap.slices[3].start, twap.slices[0].limit_price), (Timestamp::from_secs(90), None));
+
+        // 150 bps around the mid of 100 reaches the 4 offered at 101, half of which caps each slice
+        let capped = SlicePlanner::new(Side::Buy, fixed!(12), Duration::from_secs(40), 4)
+            .with_price_band(fixed!(150))
+            .with_liquidity_share(fixed!(0.5))
+            .plan(start, &book, &flow);
+        assert_eq!(sizes(&capped), [fixed!(2); 4]);
+        assert_eq!((capped.shortfall, capped.slices[0].limit_price), (fixed!(4), Some(fixed!(101.5))));
+
+        // A fifth of the 10 expected to trade in each 10 second interval
+        let pov = SlicePlanner::new(Side::Sell, fixed!(5), Duration::from_secs(40), 4)
+            .with_schedule(Schedule::Pov(fixed!(0.2)))
+            .plan(start, &book, &flow);
+        assert_eq!(sizes(&pov), [fixed!(2), fixed!(2), fixed!(1), fixed!(0)]);
+        assert_eq!((pov.planned, pov.shortfall), (fixed!(5), fixed!(0)));
+    }
+}
@@ -0,0 +1,294 @@
+//! Statistics over a stream of events, for vetting a feed adapter before trusting its output.
+//!
+//! [`EventStats`] counts events by kind and side, the largest jump in sequence IDs, the spacing of
+//! exchange timestamps and the share of zero sized events. [`WithStats`] collects them from whatever
+//! it wraps: every event passed to a book, or every event read from a source.
+
+use std::time::Duration;
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    event_kind::EventKind,
+    formats::source::SourceError,
+    level::Level,
+    metrics::{MetricsRequest, OrderbookMetrics},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+/// Upper bounds of the inter-arrival histogram buckets, the last bucket holds everything slower
+pub const INTER_ARRIVAL_BOUNDS: [Duration; 7] = [
+    Duration::from_micros(1),
+    Duration::from_micros(10),
+    Duration::from_micros(100),
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+];
+
+#[inline(always)]
+const fn side_index(side: Side) -> usize {
+    match side {
+        Side::Buy => 0,
+        Side::Sell => 1,
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventStats {
+    /// Events seen, indexed by `EventKind as usize` then buy and sell
+    counts: [[u64; 2]; EventKind::ALL.len()],
+    zero_size: u64,
+    /// Largest step between consecutive sequence IDs, events without one are skipped
+    max_sequence_gap: u64,
+    last_sequence_id: u64,
+    /// Counts of the time between consecutive exchange timestamps, bucketed by
+    /// [`INTER_ARRIVAL_BOUNDS`], with timestamps going backwards counted in the first bucket
+    inter_arrival: [u64; INTER_ARRIVAL_BOUNDS.len() + 1],
+    first: Option<Timestamp>,
+    last: Option<Timestamp>,
+}
+
+impl EventStats {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record<V: DecimalType + PartialEq>(&mut self, event: &Event<V>) {
+        self.counts[event.kind as usize][side_index(event.side)] += 1;
+        self.zero_size += u64::from(event.size == V::ZERO);
+        if event.sequence_id != 0 {
+            if self.last_sequence_id != 0 {
+                let gap = event.sequence_id.saturating_sub(self.last_sequence_id);
+                self.max_sequence_gap = self.max_sequence_gap.max(gap);
+            }
+            self.last_sequence_id = event.sequence_id;
+        }
+        if let Some(last) = self.last {
+            let spacing = event.timestamp.saturating_duration_since(last);
+            self.inter_arrival[INTER_ARRIVAL_BOUNDS.partition_point(|&bound| bound <= spacing)] += 1;
+        }
+        self.first.get_or_insert(event.timestamp);
+        self.last = Some(event.timestamp);
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.counts.iter().flatten().sum()
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn count(&self, kind: EventKind, side: Side) -> u64 {
+        self.counts[kind as usize][side_index(side)]
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn kind_count(&self, kind: EventKind) -> u64 {
+        let [buy, sell] = self.counts[kind as usize];
+        buy + sell
+    }
+
+    #[inline]
+    #[must_use]
+    /// Time from the first event to the last, by exchange timestamp
+    pub fn span(&self) -> Option<Duration> {
+        self.last?.duration_since(self.first?)
+    }
+
+    #[must_use]
+    /// Events of `kind` per second of [`span`](Self::span), `None` until the span is longer than zero
+    pub fn rate(&self, kind: EventKind) -> Option<f64> {
+        let secs = self.span()?.as_secs_f64();
+        (secs > 0.0).then(|| self.kind_count(kind) as f64 / secs)
+    }
+
+    #[must_use]
+    /// Events of `kind` on `side` per second of [`span`](Self::span)
+    pub fn side_rate(&self, kind: EventKind, side: Side) -> Option<f64> {
+        let secs = self.span()?.as_secs_f64();
+        (secs > 0.0).then(|| self.count(kind, side) as f64 / secs)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Largest step between consecutive sequence IDs, `1` for a feed without gaps and `0` for
+    /// duplicates
+    pub const fn max_sequence_gap(&self) -> u64 {
+        self.max_sequence_gap
+    }
+
+    #[inline]
+    #[must_use]
+    /// Counts of the time between consecutive events, the bucket `i` holding spacings below
+    /// `INTER_ARRIVAL_BOUNDS[i]` and the last bucket the rest
+    pub const fn inter_arrival(&self) -> &[u64; INTER_ARRIVAL_BOUNDS.len() + 1] {
+        &self.inter_arrival
+    }
+
+    #[must_use]
+    /// Share of events with a size of zero, `None` before any event
+    pub fn zero_size_ratio(&self) -> Option<f64> {
+        let total = self.total();
+        (total > 0).then(|| self.zero_size as f64 / total as f64)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Collects [`EventStats`] from the events passing into a book or out of a source
+pub struct WithStats<T> {
+    inner: T,
+    stats: EventStats,
+}
+
+impl<T> WithStats<T> {
+    #[inline]
+    #[must_use]
+    pub fn new(inner: T) -> Self {
+        Self { inner, stats: EventStats::new() }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn stats(&self) -> &EventStats {
+        &self.stats
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_parts(self) -> (T, EventStats) {
+        (self.inner, self.stats)
+    }
+}
+
+impl<V: DecimalType + PartialEq, B: OrderBook<V>> OrderBook<V> for WithStats<B> {
+    /// Events are counted whether or not the book applies them
+    #[inline]
+    fn process_delta(&mut self, event: Event<V>) -> Option<BookDelta<V>> {
+        self.stats.record(&event);
+        self.inner.process_delta(event)
+    }
+
+    #[inline]
+    fn best_bid(&mut self) -> Option<Level<V>> {
+        self.inner.best_bid()
+    }
+
+    #[inline]
+    fn best_ask(&mut self) -> Option<Level<V>> {
+        self.inner.best_ask()
+    }
+
+    #[inline]
+    fn levels(&self, side: Side, depth: usize) -> Vec<Level<V>> {
+        self.inner.levels(side, depth)
+    }
+
+    #[inline]
+    fn timestamp(&self) -> Timestamp {
+        self.inner.timestamp()
+    }
+
+    #[inline]
+    fn sequence_id(&self) -> u64 {
+        self.inner.sequence_id()
+    }
+
+    #[inline]
+    fn calculate_metrics_with(&self, depth: usize, request: MetricsRequest) -> OrderbookMetrics<V> {
+        self.inner.calculate_metrics_with(depth, request)
+    }
+}
+
+impl<V, S> Iterator for WithStats<S>
+where
+    V: DecimalType + PartialEq,
+    S: Iterator<Item = Result<Event<V>, SourceError>>,
+{
+    type Item = Result<Event<V>, SourceError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.inner.next()?;
+        if let Ok(event) = &event {
+            self.stats.record(event);
+        }
+        Some(event)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        formats::source::MemorySource,
+        side::Side,
+        stats::WithStats,
+        timestamp::Timestamp,
+    };
+
+    fn events() -> Vec<Event<FixedDecimal>> {
+        let event = |kind, side, size, ms, sequence| {
+            Event::new(kind, side, fixed!(100), size, Timestamp::from_millis(ms)).with_sequence_id(sequence)
+        };
+        vec![
+            event(EventKind::L2, Side::Buy, fixed!(1), 1_000, 1),
+            event(EventKind::L2, Side::Sell, fixed!(0), 1_000, 2),
+            event(EventKind::Trade, Side::Sell, fixed!(1), 1_005, 6),
+            event(EventKind::L2, Side::Buy, fixed!(2), 1_500, 6),
+            event(EventKind::L2, Side::Buy, fixed!(0), 3_000, 7),
+        ]
+    }
+
+    #[test]
+    fn test_source_stats() {
+        let mut source = WithStats::new(MemorySource::new(events()));
+        assert_eq!(source.by_ref().count(), 5);
+        let stats = source.stats();
+        assert_eq!((stats.total(), stats.kind_count(EventKind::L2), stats.count(EventKind::L2, Side::Buy)), (5, 4, 3));
+        assert_eq!(stats.count(EventKind::Trade, Side::Buy), 0);
+        assert_eq!(stats.max_sequence_gap(), 4);
+        assert_eq!(stats.inter_arrival(), &[1, 0, 0, 0, 1, 0, 1, 1]);
+        assert_eq!(stats.zero_size_ratio(), Some(0.4));
+        assert_eq!(stats.span(), Some(Duration::from_secs(2)));
+        assert_eq!(stats.rate(EventKind::L2), Some(2.0));
+        assert_eq!(stats.side_rate(EventKind::Trade, Side::Sell), Some(0.5));
+    }
+
+    #[test]
+    fn test_book_stats() {
+        let mut book = WithStats::new(BTreeOrderBook::new());
+        assert_eq!(book.stats().zero_size_ratio(), None);
+        assert_eq!(book.stats().rate(EventKind::L2), None);
+        events().into_iter().for_each(|event| book.process(event));
+        assert_eq!(book.stats().total(), 5);
+        assert_eq!(book.best_bid().map(|level| level.size), None);
+        let (_, stats) = book.into_parts();
+        assert_eq!(stats.max_sequence_gap(), 4);
+    }
+}
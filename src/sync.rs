@@ -0,0 +1,252 @@
+//! A book shared between the thread applying events and any number of reader threads.
+//!
+//! [`SharedOrderBook`] owns the book on the writer thread and, every few accepted events, publishes an
+//! immutable [`BookSnapshot`] of the top levels. [`SnapshotReader`]s on other threads `load` the latest
+//! snapshot as an `Arc`, so a reader holds a consistent view for as long as it likes while the writer
+//! moves on. The latest snapshot sits behind a lock held only to clone or replace that `Arc`, never
+//! while the book is updated or a snapshot is built.
+
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    level::Level,
+    metrics::{MetricsRequest, OrderbookMetrics},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone)]
+/// The top levels of a book as of one accepted event
+pub struct BookSnapshot<V: DecimalType> {
+    /// Bids, best first
+    pub bids: Vec<Level<V>>,
+    /// Asks, best first
+    pub asks: Vec<Level<V>>,
+    /// Timestamp of the last event applied
+    pub timestamp: Timestamp,
+    /// Sequence ID of the last event applied
+    pub sequence_id: u64,
+    /// Number of snapshots published before this one
+    pub version: u64,
+}
+
+impl<V: DecimalType + Copy> BookSnapshot<V> {
+    #[inline]
+    #[must_use]
+    pub fn best_bid(&self) -> Option<Level<V>> {
+        self.bids.first().copied()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn best_ask(&self) -> Option<Level<V>> {
+        self.asks.first().copied()
+    }
+}
+
+type Latest<V> = Arc<RwLock<Arc<BookSnapshot<V>>>>;
+
+#[derive(Debug)]
+/// Owns a book on the writer thread and publishes snapshots of it to [`SnapshotReader`]s
+pub struct SharedOrderBook<V: DecimalType, B: OrderBook<V>> {
+    book: B,
+    depth: usize,
+    publish_every: u64,
+    /// Events accepted since the last snapshot
+    unpublished: u64,
+    timestamp: Timestamp,
+    sequence_id: u64,
+    version: u64,
+    latest: Latest<V>,
+}
+
+impl<V: DecimalType + Copy, B: OrderBook<V>> SharedOrderBook<V, B> {
+    #[must_use]
+    /// Share `book`, publishing its top `depth` levels per side after every accepted event
+    pub fn new(book: B, depth: usize) -> Self {
+        let empty = BookSnapshot { bids: Vec::new(), asks: Vec::new(), timestamp: Timestamp::ZERO, sequence_id: 0, version: 0 };
+        let mut shared = Self {
+            book,
+            depth,
+            publish_every: 1,
+            unpublished: 0,
+            timestamp: Timestamp::ZERO,
+            sequence_id: 0,
+            version: 0,
+            latest: Arc::new(RwLock::new(Arc::new(empty))),
+        };
+        shared.publish();
+        shared
+    }
+
+    #[inline]
+    #[must_use]
+    /// Publish after every `events` accepted events rather than every one, trading staleness for less
+    /// copying. Call [`publish`](Self::publish) to flush a quiet book.
+    pub fn publish_every(mut self, events: u64) -> Self {
+        self.publish_every = events.max(1);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// A handle for reading snapshots from another thread
+    pub fn reader(&self) -> SnapshotReader<V> {
+        SnapshotReader { latest: Arc::clone(&self.latest) }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn inner(&self) -> &B {
+        &self.book
+    }
+
+    /// Publish a snapshot of the book as it stands
+    pub fn publish(&mut self) {
+        let snapshot = Arc::new(BookSnapshot {
+            bids: self.book.levels(Side::Buy, self.depth),
+            asks: self.book.levels(Side::Sell, self.depth),
+            timestamp: self.timestamp,
+            sequence_id: self.sequence_id,
+            version: self.version,
+        });
+        self.version += 1;
+        self.unpublished = 0;
+        *self.latest.write().unwrap_or_else(PoisonError::into_inner) = snapshot;
+    }
+}
+
+impl<V: DecimalType + Copy, B: OrderBook<V>> OrderBook<V> for SharedOrderBook<V, B> {
+    fn process_delta(&mut self, event: Event<V>) -> Option<BookDelta<V>> {
+        let delta = self.book.process_delta(event)?;
+        (self.timestamp, self.sequence_id) = (delta.timestamp, delta.sequence_id);
+        self.unpublished += 1;
+        if self.unpublished >= self.publish_every {
+            self.publish();
+        }
+        Some(delta)
+    }
+
+    #[inline]
+    fn best_bid(&mut self) -> Option<Level<V>> {
+        self.book.best_bid()
+    }
+
+    #[inline]
+    fn best_ask(&mut self) -> Option<Level<V>> {
+        self.book.best_ask()
+    }
+
+    #[inline]
+    fn levels(&self, side: Side, depth: usize) -> Vec<Level<V>> {
+        self.book.levels(side, depth)
+    }
+
+    #[inline]
+    fn timestamp(&self) -> Timestamp {
+        self.book.timestamp()
+    }
+
+    #[inline]
+    fn sequence_id(&self) -> u64 {
+        self.book.sequence_id()
+    }
+
+    #[inline]
+    fn calculate_metrics_with(&self, depth: usize, request: MetricsRequest) -> OrderbookMetrics<V> {
+        self.book.calculate_metrics_with(depth, request)
+    }
+}
+
+#[derive(Debug)]
+/// Reads the snapshots a [`SharedOrderBook`] publishes, cheap to clone and send to other threads
+pub struct SnapshotReader<V: DecimalType> {
+    latest: Latest<V>,
+}
+
+impl<V: DecimalType> Clone for SnapshotReader<V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self { latest: Arc::clone(&self.latest) }
+    }
+}
+
+impl<V: DecimalType> SnapshotReader<V> {
+    #[inline]
+    #[must_use]
+    /// The latest snapshot, which stays valid however far the writer moves on
+    pub fn load(&self) -> Arc<BookSnapshot<V>> {
+        Arc::clone(&self.latest.read().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        side::Side,
+        sync::SharedOrderBook,
+        timestamp::Timestamp,
+    };
+
+    fn bid(price: FixedDecimal, ts: i64) -> Event<FixedDecimal> {
+        Event::new(EventKind::L2, Side::Buy, price, fixed!(1), Timestamp::from_nanos(ts)).with_sequence_id(ts as u64)
+    }
+
+    #[test]
+    fn test_publishes_periodically() {
+        let mut book = SharedOrderBook::new(BTreeOrderBook::new(), 2).publish_every(2);
+        let reader = book.reader();
+        assert_eq!((reader.load().version, reader.load().best_bid().is_none()), (0, true));
+
+        book.process(bid(fixed!(100), 1));
+        let held = reader.load();
+        assert_eq!(held.version, 0);
+        book.process(bid(fixed!(101), 2));
+        book.process(bid(fixed!(102), 3));
+        let latest = reader.load();
+        assert_eq!((latest.version, latest.sequence_id, latest.bids.len()), (1, 2, 2));
+        assert_eq!(latest.best_bid().map(|level| level.price), Some(fixed!(101)));
+
+        book.publish();
+        assert_eq!(reader.load().best_bid().map(|level| level.price), Some(fixed!(102)));
+        // A snapshot a reader holds is never changed under it
+        assert!(held.bids.is_empty());
+    }
+
+    #[test]
+    fn test_readers_on_other_threads() {
+        let mut book = SharedOrderBook::new(BTreeOrderBook::<FixedDecimal>::new(), 1);
+        let reader = book.reader();
+        std::thread::scope(|scope| {
+            let readers = (0..2)
+                .map(|_| {
+                    let reader = reader.clone();
+                    scope.spawn(move || {
+                        let mut last = 0;
+                        while last < 500 {
+                            let snapshot = reader.load();
+                            assert!(snapshot.version >= last);
+                            // Each event raises the bid by one, so the snapshot matches its sequence ID
+                            let price = snapshot.best_bid().map_or(0, |level| level.price.to_f64() as u64);
+                            assert_eq!(price, snapshot.sequence_id);
+                            last = snapshot.version;
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            for ts in 1..=500 {
+                book.process(bid(FixedDecimal::from_int(ts), ts));
+            }
+            readers.into_iter().for_each(|reader| reader.join().unwrap());
+        });
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    fmt,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+/// Nanoseconds since the Unix epoch.
+///
+/// Venues publish times in seconds, milliseconds, microseconds or nanoseconds, and a bare integer
+/// does not say which. Build timestamps with the constructor matching the source unit so a book fed
+/// from several venues compares like with like. There is deliberately no conversion from a plain `i64`.
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    pub const ZERO: Self = Self(0);
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn from_nanos(nanos: i64) -> Self {
+        Self(nanos)
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn from_micros(micros: i64) -> Self {
+        Self(micros.saturating_mul(1_000))
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn from_millis(millis: i64) -> Self {
+        Self(millis.saturating_mul(1_000_000))
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn from_secs(secs: i64) -> Self {
+        Self(secs.saturating_mul(1_000_000_000))
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn now() -> Self {
+        SystemTime::now().into()
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn as_nanos(self) -> i64 {
+        self.0
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn as_micros(self) -> i64 {
+        self.0.div_euclid(1_000)
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn as_millis(self) -> i64 {
+        self.0.div_euclid(1_000_000)
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const fn as_secs(self) -> i64 {
+        self.0.div_euclid(1_000_000_000)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Time elapsed since `earlier`, `None` when `earlier` is later than `self`
+    pub const fn duration_since(self, earlier: Self) -> Option<Duration> {
+        match self.0.checked_sub(earlier.0) {
+            Some(nanos) if nanos >= 0 => Some(Duration::from_nanos(nanos as u64)),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Time elapsed since `earlier`, zero when `earlier` is later than `self`
+    pub const fn saturating_duration_since(self, earlier: Self) -> Duration {
+        match self.duration_since(earlier) {
+            Some(duration) => duration,
+            None => Duration::ZERO,
+        }
+    }
+}
+
+#[inline(always)]
+fn duration_nanos(duration: Duration) -> i64 {
+    i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX)
+}
+
+impl From<Timestamp> for i64 {
+    #[inline(always)]
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    /// Times before the epoch map to negative timestamps
+    fn from(time: SystemTime) -> Self {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => Self(duration_nanos(since)),
+            Err(err) => Self(-duration_nanos(err.duration())),
+        }
+    }
+}
+
+impl Add<Duration> for Timestamp {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, duration: Duration) -> Self {
+        Self(self.0.saturating_add(duration_nanos(duration)))
+    }
+}
+
+impl AddAssign<Duration> for Timestamp {
+    #[inline]
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for Timestamp {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, duration: Duration) -> Self {
+        Self(self.0.saturating_sub(duration_nanos(duration)))
+    }
+}
+
+impl SubAssign<Duration> for Timestamp {
+    #[inline]
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::timestamp::Timestamp;
+
+    #[test]
+    fn test_units() {
+        let ts = Timestamp::from_millis(1_700_000_000_123);
+        assert_eq!(ts, Timestamp::from_micros(1_700_000_000_123_000));
+        assert_eq!((ts.as_nanos(), ts.as_millis(), ts.as_secs()), (1_700_000_000_123_000_000, 1_700_000_000_123, 1_700_000_000));
+        assert_eq!(Timestamp::from(UNIX_EPOCH + Duration::from_millis(5)), Timestamp::from_millis(5));
+        assert_eq!(Timestamp::from_nanos(-1).as_micros(), -1);
+    }
+
+    #[test]
+    fn test_duration_math() {
+        let ts = Timestamp::from_secs(10);
+        assert_eq!(ts + Duration::from_millis(1), Timestamp::from_nanos(10_001_000_000));
+        assert_eq!(ts - Duration::from_secs(11), Timestamp::from_secs(-1));
+        assert_eq!((ts + Duration::from_micros(3)).duration_since(ts), Some(Duration::from_micros(3)));
+        assert_eq!(ts.duration_since(ts + Duration::from_nanos(1)), None);
+        assert_eq!(ts.saturating_duration_since(Timestamp::from_secs(20)), Duration::ZERO);
+    }
+}
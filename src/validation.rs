@@ -0,0 +1,172 @@
+//! Sanity checks applied to events before they reach a book.
+//!
+//! Adapters can emit garbage when an exchange changes its schema or a parser has a bug, and the books
+//! take every event at face value. [`Event::validate`] rejects the obviously wrong ones, and
+//! [`Validated`] carries the proof that an event passed so a book can be fed only checked events
+//! through [`OrderBook::process_checked`](crate::books::interface::OrderBook::process_checked).
+
+use std::{fmt, ops::Deref};
+
+use crate::{decimals::decimal_type::DecimalType, event::Event, event_kind::EventKind, timestamp::Timestamp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventError {
+    /// A level or trade at a price of zero or below
+    NonPositivePrice,
+    /// A size below zero
+    NegativeSize,
+    /// The event has no exchange timestamp
+    ZeroTimestamp,
+    /// An order level event without an order ID
+    MissingOrderId(EventKind),
+    /// A price level event carrying an order ID or priority
+    UnexpectedOrderId(EventKind),
+    /// A trade whose aggressor is on its resting side
+    AggressorOnRestingSide,
+    /// An event other than a trade or execution carrying an aggressor
+    UnexpectedAggressor(EventKind),
+}
+
+impl fmt::Display for EventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonPositivePrice => write!(f, "price is not positive"),
+            Self::NegativeSize => write!(f, "size is negative"),
+            Self::ZeroTimestamp => write!(f, "timestamp is zero"),
+            Self::MissingOrderId(kind) => write!(f, "{kind:?} event has no order ID"),
+            Self::UnexpectedOrderId(kind) => write!(f, "{kind:?} event carries an order ID"),
+            Self::AggressorOnRestingSide => write!(f, "aggressor is on the resting side"),
+            Self::UnexpectedAggressor(kind) => write!(f, "{kind:?} event carries an aggressor"),
+        }
+    }
+}
+
+impl std::error::Error for EventError {}
+
+impl<V: DecimalType + PartialOrd> Event<V> {
+    /// Check the event is one a book can apply: a positive price unless it removes a level or cancels
+    /// an order, a size of zero or more, a timestamp, and an order ID exactly when the kind refers to
+    /// an order, and for trades an aggressor, if any, opposite the resting side.
+    pub fn validate(&self) -> Result<(), EventError> {
+        if self.size < V::ZERO {
+            return Err(EventError::NegativeSize);
+        }
+        let removes = match self.kind {
+            EventKind::BBO | EventKind::L2 | EventKind::Snapshot => self.size == V::ZERO,
+            EventKind::Cancel | EventKind::Clear | EventKind::ClearSide => true,
+            EventKind::Trade | EventKind::Add | EventKind::Modify | EventKind::Execute => false,
+        };
+        if !removes && self.price <= V::ZERO {
+            return Err(EventError::NonPositivePrice);
+        }
+        if self.timestamp == Timestamp::ZERO {
+            return Err(EventError::ZeroTimestamp);
+        }
+        match (self.kind, self.aggressor.side()) {
+            (_, None) => {}
+            (EventKind::Trade | EventKind::Execute, Some(side)) if side == self.side => {
+                return Err(EventError::AggressorOnRestingSide)
+            }
+            (EventKind::Trade | EventKind::Execute, Some(_)) => {}
+            (kind, Some(_)) => return Err(EventError::UnexpectedAggressor(kind)),
+        }
+        match (self.kind.is_order_level(), self.order_id != 0 || self.priority.is_some()) {
+            (true, _) if self.order_id == 0 => Err(EventError::MissingOrderId(self.kind)),
+            (false, true) => Err(EventError::UnexpectedOrderId(self.kind)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An event that passed [`Event::validate`]
+pub struct Validated<V: DecimalType>(Event<V>);
+
+impl<V: DecimalType + PartialOrd> Validated<V> {
+    #[inline]
+    pub fn new(event: Event<V>) -> Result<Self, EventError> {
+        event.validate()?;
+        Ok(Self(event))
+    }
+}
+
+impl<V: DecimalType> Validated<V> {
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> Event<V> {
+        self.0
+    }
+}
+
+impl<V: DecimalType> Deref for Validated<V> {
+    type Target = Event<V>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<V: DecimalType + PartialOrd> TryFrom<Event<V>> for Validated<V> {
+    type Error = EventError;
+
+    #[inline]
+    fn try_from(event: Event<V>) -> Result<Self, Self::Error> {
+        Self::new(event)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        side::Side,
+        timestamp::Timestamp,
+        validation::{EventError, Validated},
+    };
+
+    #[test]
+    fn test_validate() {
+        let l2 = |price, size, ts| Event::new(EventKind::L2, Side::Buy, price, size, Timestamp::from_nanos(ts));
+        assert_eq!(l2(fixed!(100), fixed!(1), 1).validate(), Ok(()));
+        // Removing a level only needs to name it
+        assert_eq!(l2(fixed!(0), fixed!(0), 1).validate(), Ok(()));
+        assert_eq!(l2(fixed!(0), fixed!(1), 1).validate(), Err(EventError::NonPositivePrice));
+        assert_eq!(l2(fixed!(-1), fixed!(1), 1).validate(), Err(EventError::NonPositivePrice));
+        assert_eq!(l2(fixed!(100), fixed!(-1), 1).validate(), Err(EventError::NegativeSize));
+        assert_eq!(l2(fixed!(100), fixed!(1), 0).validate(), Err(EventError::ZeroTimestamp));
+        assert_eq!(l2(fixed!(100), fixed!(1), 1).with_order_id(7).validate(), Err(EventError::UnexpectedOrderId(EventKind::L2)));
+
+        let add = Event::<FixedDecimal>::new(EventKind::Add, Side::Sell, fixed!(100), fixed!(1), Timestamp::from_nanos(1));
+        assert_eq!(add.validate(), Err(EventError::MissingOrderId(EventKind::Add)));
+        assert_eq!(add.with_order_id(7).validate(), Ok(()));
+
+        let trade = Event::<FixedDecimal>::trade(Side::Buy, fixed!(100), fixed!(1), Timestamp::from_nanos(1));
+        assert_eq!(trade.validate(), Ok(()));
+        assert_eq!(Event { side: Side::Buy, ..trade }.validate(), Err(EventError::AggressorOnRestingSide));
+        let aggressor = trade.aggressor;
+        assert_eq!(
+            l2(fixed!(100), fixed!(1), 1).with_aggressor(aggressor).validate(),
+            Err(EventError::UnexpectedAggressor(EventKind::L2))
+        );
+    }
+
+    #[test]
+    fn test_checked_book() {
+        let mut book = ArrayOrderbook::<8, FixedDecimal>::new();
+        let bad = Event::new(EventKind::L2, Side::Buy, fixed!(0), fixed!(5), Timestamp::from_nanos(1));
+        assert_eq!(book.process_checked(bad), Err(EventError::NonPositivePrice));
+        assert!(book.best_bid().is_none());
+
+        let good =
+            Validated::new(Event::new(EventKind::L2, Side::Buy, fixed!(100), fixed!(5), Timestamp::from_nanos(1))).unwrap();
+        assert_eq!(good.price, fixed!(100));
+        assert!(book.process_validated(good).is_some());
+        assert_eq!(book.best_bid().map(|level| level.size), Some(fixed!(5)));
+    }
+}
@@ -0,0 +1,350 @@
+//! Write-ahead logging of a book for warm restarts.
+//!
+//! A [`Wal`] owns a book and a directory. Every event is appended to `events.wal` in the
+//! [binary layout](crate::formats::binary) before the book applies it, and every few events the whole
+//! book is written to `snapshot-<events>.bin` as a [delta recording](crate::formats::snapshots)
+//! keyframe, where `<events>` is the length of the log it covers. Snapshots are written to a temporary
+//! file and renamed into place, and the older ones removed only after that, so a crash at any point
+//! leaves a complete snapshot behind.
+//!
+//! [`Wal::recover`] rebuilds the book from the newest snapshot and the events logged after it, dropping
+//! a record cut short by the crash, so the book comes back with its levels, timestamp and sequence ID
+//! without asking the venue for a new snapshot. Appends reach the operating system straight away and
+//! survive the process dying; [`sync`](Wal::sync) forces them to disk to survive the machine.
+//!
+//! [`Wal::reconstruct_at`] answers what the book looked like at an earlier time, starting from the
+//! latest snapshot taken by then, so keeping more than the newest snapshot with
+//! [`with_snapshots_kept`](Wal::with_snapshots_kept) makes looking further back cheaper.
+//!
+//! The binary layout keeps only the kind, side, price, size, exchange timestamp and sequence ID, so
+//! events are logged without their order ID, priority, trade aggressor, instrument and local timestamp,
+//! and come back from recovery with the defaults. Level books recover exactly, books that track orders
+//! do not.
+//!
+//! A failed append is cut back off the log, and the directory is synced after a snapshot is renamed
+//! into place so the rename itself survives a power loss.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::fixed_decimal::FixedDecimal,
+    event::Event,
+    event_kind::EventKind,
+    formats::{
+        binary::{self, BinaryReplayer, RECORD_LEN},
+        snapshots::{SnapshotReplayer, SnapshotWriter},
+        source::replay_until,
+        FormatError,
+    },
+    side::Side,
+    sync::BookSnapshot,
+    timestamp::Timestamp,
+};
+
+const LOG: &str = "events.wal";
+
+/// Events covered by the snapshot file called `name`
+fn snapshot_events(name: &str) -> Option<u64> {
+    name.strip_prefix("snapshot-")?.strip_suffix(".bin")?.parse().ok()
+}
+
+#[derive(Debug)]
+/// A book whose events are logged ahead of being applied, see the [module documentation](self)
+pub struct Wal<B: OrderBook<FixedDecimal>> {
+    book: B,
+    dir: PathBuf,
+    log: File,
+    /// Records in the log
+    events: u64,
+    snapshot_interval: u64,
+    /// Snapshots kept on disk
+    keep: usize,
+    /// Log length covered by each snapshot on disk, oldest first
+    snapshots: Vec<u64>,
+}
+
+impl<B: OrderBook<FixedDecimal> + Default> Wal<B> {
+    pub const DEFAULT_SNAPSHOT_INTERVAL: u64 = 100_000;
+
+    /// Open the log in `dir`, creating it if needed, and rebuild the book it holds
+    ///
+    /// # Errors
+    /// If the directory, the log or the newest snapshot cannot be read, or the log cannot be opened for
+    /// appending.
+    pub fn recover(dir: impl AsRef<Path>) -> Result<Self, FormatError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let records = read_log(&dir)?;
+        // A record cut short by a crash was never applied, and the next append goes in its place
+        let log = OpenOptions::new().create(true).append(true).open(dir.join(LOG))?;
+        log.set_len(records.len() as u64)?;
+        let events = (records.len() / RECORD_LEN) as u64;
+
+        let snapshots = snapshots(&dir, events)?;
+        let snapshot_at = snapshots.last().copied().unwrap_or(0);
+        let mut book = B::default();
+        if snapshot_at > 0 {
+            restore(&mut book, &read_snapshot(&dir, snapshot_at)?);
+        }
+        for event in BinaryReplayer::new(&records)?.skip(snapshot_at as usize) {
+            book.process(event?);
+        }
+        Ok(Self { book, dir, log, events, snapshot_interval: Self::DEFAULT_SNAPSHOT_INTERVAL, keep: 1, snapshots })
+    }
+
+    /// The book in `dir` as it stood at `ts`, after every logged event stamped at or before it, built
+    /// from the latest snapshot taken by then without touching the log
+    ///
+    /// # Errors
+    /// If the log or a snapshot cannot be read.
+    pub fn reconstruct_at(dir: impl AsRef<Path>, ts: Timestamp) -> Result<B, FormatError> {
+        let dir = dir.as_ref();
+        let records = read_log(dir)?;
+        let mut book = B::default();
+        let mut start = 0;
+        for covered in snapshots(dir, (records.len() / RECORD_LEN) as u64)?.into_iter().rev() {
+            let snapshot = read_snapshot(dir, covered)?;
+            if snapshot.timestamp <= ts {
+                restore(&mut book, &snapshot);
+                start = covered as usize;
+                break;
+            }
+        }
+        replay_until(&mut book, BinaryReplayer::new(&records)?.skip(start), ts)?;
+        Ok(book)
+    }
+}
+
+impl<B: OrderBook<FixedDecimal>> Wal<B> {
+    #[inline]
+    #[must_use]
+    /// Snapshot the book every `events` events, at least one, trading snapshot writes for shorter
+    /// recoveries
+    pub fn with_snapshot_interval(mut self, events: u64) -> Self {
+        self.snapshot_interval = events.max(1);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// Keep the newest `snapshots` snapshots rather than only the newest, at least one, so
+    /// [`reconstruct_at`](Self::reconstruct_at) can start closer to times further back
+    pub fn with_snapshots_kept(mut self, snapshots: usize) -> Self {
+        self.keep = snapshots.max(1);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn book(&self) -> &B {
+        &self.book
+    }
+
+    #[inline]
+    #[must_use]
+    /// Events in the log
+    pub const fn events(&self) -> u64 {
+        self.events
+    }
+
+    /// Log `event`, then apply it, snapshotting the book when the interval is up
+    ///
+    /// # Errors
+    /// If the event cannot be logged, when the book is left untouched, or the snapshot cannot be written.
+    pub fn process(&mut self, event: Event<FixedDecimal>) -> Result<Option<BookDelta<FixedDecimal>>, FormatError> {
+        let mut record = [0; RECORD_LEN];
+        binary::encode_into(&event, &mut record)?;
+        if let Err(err) = self.log.write_all(&record) {
+            // A partial record would shift every record appended after it
+            let _ = self.log.set_len(self.events * RECORD_LEN as u64);
+            return Err(err.into());
+        }
+        self.events += 1;
+        let delta = self.book.process_delta(event);
+        if self.events - self.snapshots.last().copied().unwrap_or(0) >= self.snapshot_interval {
+            self.snapshot()?;
+        }
+        Ok(delta)
+    }
+
+    /// Write a snapshot of the book covering the log so far and remove those older than the ones kept
+    ///
+    /// # Errors
+    /// If the snapshot cannot be written, an older one that cannot be removed is left in place.
+    pub fn snapshot(&mut self) -> Result<(), FormatError> {
+        let snapshot = BookSnapshot {
+            bids: self.book.levels(Side::Buy, usize::MAX),
+            asks: self.book.levels(Side::Sell, usize::MAX),
+            timestamp: self.book.timestamp(),
+            sequence_id: self.book.sequence_id(),
+            version: 0,
+        };
+        let mut writer = SnapshotWriter::new(Vec::new());
+        writer.write(&snapshot)?;
+        let (temporary, path) = (self.dir.join("snapshot.tmp"), self.dir.join(format!("snapshot-{}.bin", self.events)));
+        let mut file = File::create(&temporary)?;
+        file.write_all(&writer.into_inner())?;
+        file.sync_all()?;
+        fs::rename(&temporary, &path)?;
+        sync_dir(&self.dir)?;
+        if self.snapshots.last() != Some(&self.events) {
+            self.snapshots.push(self.events);
+        }
+        while self.snapshots.len() > self.keep {
+            let oldest = self.snapshots.remove(0);
+            let _ = fs::remove_file(self.dir.join(format!("snapshot-{oldest}.bin")));
+        }
+        Ok(())
+    }
+
+    /// Force the log to disk
+    ///
+    /// # Errors
+    /// If the operating system fails to.
+    pub fn sync(&mut self) -> Result<(), FormatError> {
+        self.log.sync_data()?;
+        Ok(())
+    }
+}
+
+/// Force the entries of `dir` to disk, which directories only support on Unix
+fn sync_dir(dir: &Path) -> Result<(), FormatError> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Records of the log in `dir`, without any record cut short at the end
+fn read_log(dir: &Path) -> Result<Vec<u8>, FormatError> {
+    let mut records = fs::read(dir.join(LOG)).or_else(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        _ => Err(err),
+    })?;
+    records.truncate(records.len() - records.len() % RECORD_LEN);
+    Ok(records)
+}
+
+/// Log lengths covered by the snapshots in `dir`, oldest first, leaving out any beyond the `events` of
+/// the log
+fn snapshots(dir: &Path, events: u64) -> Result<Vec<u64>, FormatError> {
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        if let Some(covered) = entry?.file_name().to_str().and_then(snapshot_events) {
+            snapshots.push(covered);
+        }
+    }
+    snapshots.retain(|&covered| covered > 0 && covered <= events);
+    snapshots.sort_unstable();
+    Ok(snapshots)
+}
+
+fn read_snapshot(dir: &Path, covered: u64) -> Result<BookSnapshot<FixedDecimal>, FormatError> {
+    let frames = fs::read(dir.join(format!("snapshot-{covered}.bin")))?;
+    SnapshotReplayer::new(&frames)
+        .next()
+        .unwrap_or_else(|| Err(FormatError::Malformed { line: 0, reason: "empty snapshot".to_owned() }))
+}
+
+/// Load `snapshot` into an empty `book`, a clear carrying its timestamp and sequence ID followed by its
+/// levels
+fn restore<B: OrderBook<FixedDecimal>>(book: &mut B, snapshot: &BookSnapshot<FixedDecimal>) {
+    let event =
+        |kind, side, price, size| Event::new(kind, side, price, size, snapshot.timestamp).with_sequence_id(snapshot.sequence_id);
+    book.process(event(EventKind::Clear, Side::Buy, FixedDecimal::ZERO, FixedDecimal::ZERO));
+    for (side, levels) in [(Side::Buy, &snapshot.bids), (Side::Sell, &snapshot.asks)] {
+        for level in levels {
+            book.process(event(EventKind::L2, side, level.price, level.size));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::OpenOptions, io::Write};
+
+    use crate::{
+        books::{btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        side::Side,
+        timestamp::Timestamp,
+        wal::Wal,
+    };
+
+    fn event(i: i64) -> Event<FixedDecimal> {
+        let (side, price) = if i % 2 == 0 { (Side::Buy, 100 - i % 7) } else { (Side::Sell, 101 + i % 5) };
+        let size = if i % 9 == 0 { 0 } else { i % 4 + 1 };
+        Event::new(
+            EventKind::L2,
+            side,
+            FixedDecimal::from_int(price),
+            FixedDecimal::from_int(size),
+            Timestamp::from_nanos(i * 10),
+        )
+        .with_sequence_id(i as u64 + 1)
+    }
+
+    #[test]
+    fn test_recovers_after_crash() {
+        let dir = std::env::temp_dir().join(format!("freya_ob_wal_{}", std::process::id()));
+        let mut expected = BTreeOrderBook::<FixedDecimal>::new();
+        {
+            let mut wal = Wal::<BTreeOrderBook<FixedDecimal>>::recover(&dir).unwrap().with_snapshot_interval(8);
+            for i in 0..30 {
+                wal.process(event(i)).unwrap();
+                expected.process(event(i));
+            }
+            wal.sync().unwrap();
+        }
+        // The process died halfway through appending an event
+        OpenOptions::new().append(true).open(dir.join("events.wal")).unwrap().write_all(&[7; 11]).unwrap();
+        let snapshots = std::fs::read_dir(&dir).unwrap().filter_map(|entry| entry.unwrap().file_name().into_string().ok());
+        assert_eq!(snapshots.filter(|name| name.starts_with("snapshot-")).collect::<Vec<_>>(), ["snapshot-24.bin"]);
+
+        let mut wal = Wal::<BTreeOrderBook<FixedDecimal>>::recover(&dir).unwrap();
+        assert_eq!(wal.events(), 30);
+        assert_eq!(wal.book().state_hash(), expected.state_hash());
+        assert_eq!((wal.book().sequence_id(), wal.book().timestamp()), (30, expected.timestamp()));
+
+        // Appends carry on after the records kept, and survive another restart
+        wal.process(event(30)).unwrap();
+        expected.process(event(30));
+        drop(wal);
+        let wal = Wal::<BTreeOrderBook<FixedDecimal>>::recover(&dir).unwrap();
+        assert_eq!((wal.events(), wal.book().state_hash()), (31, expected.state_hash()));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_reconstructs_at_timestamp() {
+        let dir = std::env::temp_dir().join(format!("freya_ob_wal_at_{}", std::process::id()));
+        let mut wal =
+            Wal::<BTreeOrderBook<FixedDecimal>>::recover(&dir).unwrap().with_snapshot_interval(5).with_snapshots_kept(3);
+        let mut books = Vec::new();
+        let mut book = BTreeOrderBook::<FixedDecimal>::new();
+        for i in 0..40 {
+            wal.process(event(i)).unwrap();
+            book.process(event(i));
+            books.push(book.state_hash());
+        }
+        let snapshots = std::fs::read_dir(&dir).unwrap().filter(|entry| entry.as_ref().unwrap().file_name() != "events.wal");
+        assert_eq!(snapshots.count(), 3);
+
+        // Before the oldest snapshot kept, from the middle of the ones kept, between events and past the end
+        for (ts, index) in [(120, 12), (315, 31), (329, 32), (10_000, 39)] {
+            let at = Wal::<BTreeOrderBook<FixedDecimal>>::reconstruct_at(&dir, Timestamp::from_nanos(ts)).unwrap();
+            assert_eq!(at.state_hash(), books[index], "book at {ts}");
+        }
+        let before = Wal::<BTreeOrderBook<FixedDecimal>>::reconstruct_at(&dir, Timestamp::from_nanos(-1)).unwrap();
+        assert_eq!(before.state_hash(), BTreeOrderBook::<FixedDecimal>::new().state_hash());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,415 @@
+//! Top of book and level changes for async consumers.
+//!
+//! [`WatchedOrderBook`] wraps the book a processing task drives. Whenever an accepted event moves the
+//! best bid or ask it stores the new [`TopOfBook`] for every [`TopOfBookReceiver`], which awaits
+//! [`changed`](TopOfBookReceiver::changed) and, like a watch channel, only ever sees the latest
+//! value. With [`broadcast_deltas`](WatchedOrderBook::broadcast_deltas) it also queues every
+//! [`BookDelta`] for [`DeltaReceiver`]s, dropping the oldest for a receiver that falls behind.
+//!
+//! The futures only rely on [`Waker`], so they run on any executor rather than one runtime.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    books::{delta::BookDelta, interface::OrderBook},
+    decimals::decimal_type::DecimalType,
+    event::Event,
+    level::Level,
+    metrics::{MetricsRequest, OrderbookMetrics},
+    side::Side,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// The receiver fell behind and missed this many deltas, it resumes from the oldest still queued
+    Lagged(u64),
+    /// The book has been dropped and nothing more will arrive
+    Closed,
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lagged(missed) => write!(f, "receiver lagged behind by {missed} deltas"),
+            Self::Closed => write!(f, "the book has been dropped"),
+        }
+    }
+}
+
+impl std::error::Error for RecvError {}
+
+#[derive(Debug, Clone, Copy)]
+/// Best levels of a book as of one accepted event
+pub struct TopOfBook<V: DecimalType> {
+    pub bid: Option<Level<V>>,
+    pub ask: Option<Level<V>>,
+    pub timestamp: Timestamp,
+    pub sequence_id: u64,
+}
+
+impl<V: DecimalType + Copy + PartialEq> TopOfBook<V> {
+    /// Whether the best levels differ in price or size, whatever the timestamps
+    fn moved(&self, bid: Option<Level<V>>, ask: Option<Level<V>>) -> bool {
+        let same = |a: Option<Level<V>>, b: Option<Level<V>>| match (a, b) {
+            (Some(a), Some(b)) => a.price == b.price && a.size == b.size,
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        !same(self.bid, bid) || !same(self.ask, ask)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn register(wakers: &mut Vec<Waker>, cx: &Context<'_>) {
+    if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+        wakers.push(cx.waker().clone());
+    }
+}
+
+fn wake_all(wakers: &mut Vec<Waker>) {
+    wakers.drain(..).for_each(Waker::wake);
+}
+
+#[derive(Debug)]
+struct Channel<T> {
+    value: T,
+    closed: bool,
+    wakers: Vec<Waker>,
+}
+
+impl<T> Channel<T> {
+    fn shared(value: T) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self { value, closed: false, wakers: Vec::new() }))
+    }
+}
+
+#[derive(Debug)]
+struct Watched<V: DecimalType> {
+    top: TopOfBook<V>,
+    version: u64,
+}
+
+#[derive(Debug)]
+struct Queued<V: DecimalType> {
+    deltas: VecDeque<BookDelta<V>>,
+    capacity: usize,
+    /// Position of the next delta pushed, counted from the first
+    next: u64,
+}
+
+#[derive(Debug)]
+/// Publishes the top of the wrapped book, and optionally its deltas, to async receivers
+pub struct WatchedOrderBook<V: DecimalType, B: OrderBook<V>> {
+    book: B,
+    top: Arc<Mutex<Channel<Watched<V>>>>,
+    deltas: Option<Arc<Mutex<Channel<Queued<V>>>>>,
+}
+
+impl<V: DecimalType + Copy + PartialEq, B: OrderBook<V>> WatchedOrderBook<V, B> {
+    #[must_use]
+    pub fn new(book: B) -> Self {
+        let top = TopOfBook { bid: None, ask: None, timestamp: Timestamp::ZERO, sequence_id: 0 };
+        Self { book, top: Channel::shared(Watched { top, version: 0 }), deltas: None }
+    }
+
+    #[must_use]
+    /// Also queue every delta for [`DeltaReceiver`]s, up to `capacity` behind the slowest
+    pub fn broadcast_deltas(mut self, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        self.deltas = Some(Channel::shared(Queued { deltas: VecDeque::with_capacity(capacity), capacity, next: 0 }));
+        self
+    }
+
+    #[must_use]
+    /// A receiver of top of book changes, already up to date with the current top
+    pub fn subscribe_top(&self) -> TopOfBookReceiver<V> {
+        let seen = lock(&self.top).value.version;
+        TopOfBookReceiver { channel: Arc::clone(&self.top), seen }
+    }
+
+    #[must_use]
+    /// A receiver of the deltas applied from now on, `None` unless deltas are broadcast
+    pub fn subscribe_deltas(&self) -> Option<DeltaReceiver<V>> {
+        let deltas = self.deltas.as_ref()?;
+        let next = lock(deltas).value.next;
+        Some(DeltaReceiver { channel: Arc::clone(deltas), next })
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn inner(&self) -> &B {
+        &self.book
+    }
+}
+
+impl<V: DecimalType + Copy + PartialEq, B: OrderBook<V>> OrderBook<V> for WatchedOrderBook<V, B> {
+    fn process_delta(&mut self, event: Event<V>) -> Option<BookDelta<V>> {
+        let delta = self.book.process_delta(event)?;
+        if let Some(deltas) = &self.deltas {
+            let mut channel = lock(deltas);
+            let queued = &mut channel.value;
+            if queued.deltas.len() == queued.capacity {
+                queued.deltas.pop_front();
+            }
+            queued.deltas.push_back(delta);
+            queued.next += 1;
+            wake_all(&mut channel.wakers);
+        }
+        // Reading the touch through `best_bid` would clear a flag like `ArrayOrderbook::has_moved`
+        // before the owner of the book sees it
+        let (bid, ask) = (self.book.levels(Side::Buy, 1).pop(), self.book.levels(Side::Sell, 1).pop());
+        let mut channel = lock(&self.top);
+        if channel.value.top.moved(bid, ask) {
+            channel.value.top = TopOfBook { bid, ask, timestamp: delta.timestamp, sequence_id: delta.sequence_id };
+            channel.value.version += 1;
+            wake_all(&mut channel.wakers);
+        }
+        Some(delta)
+    }
+
+    #[inline]
+    fn best_bid(&mut self) -> Option<Level<V>> {
+        self.book.best_bid()
+    }
+
+    #[inline]
+    fn best_ask(&mut self) -> Option<Level<V>> {
+        self.book.best_ask()
+    }
+
+    #[inline]
+    fn levels(&self, side: Side, depth: usize) -> Vec<Level<V>> {
+        self.book.levels(side, depth)
+    }
+
+    #[inline]
+    fn timestamp(&self) -> Timestamp {
+        self.book.timestamp()
+    }
+
+    #[inline]
+    fn sequence_id(&self) -> u64 {
+        self.book.sequence_id()
+    }
+
+    #[inline]
+    fn calculate_metrics_with(&self, depth: usize, request: MetricsRequest) -> OrderbookMetrics<V> {
+        self.book.calculate_metrics_with(depth, request)
+    }
+}
+
+impl<V: DecimalType, B: OrderBook<V>> Drop for WatchedOrderBook<V, B> {
+    fn drop(&mut self) {
+        let mut top = lock(&self.top);
+        top.closed = true;
+        wake_all(&mut top.wakers);
+        if let Some(deltas) = &self.deltas {
+            let mut deltas = lock(deltas);
+            deltas.closed = true;
+            wake_all(&mut deltas.wakers);
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Awaits changes to the top of a [`WatchedOrderBook`], cheap to clone for another task
+pub struct TopOfBookReceiver<V: DecimalType> {
+    channel: Arc<Mutex<Channel<Watched<V>>>>,
+    /// Version of the last top returned
+    seen: u64,
+}
+
+impl<V: DecimalType> Clone for TopOfBookReceiver<V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self { channel: Arc::clone(&self.channel), seen: self.seen }
+    }
+}
+
+impl<V: DecimalType + Copy> TopOfBookReceiver<V> {
+    #[must_use]
+    /// The latest top, without marking it seen
+    pub fn borrow(&self) -> TopOfBook<V> {
+        lock(&self.channel).value.top
+    }
+
+    /// Wait for a top this receiver has not yet returned. Changes in between are skipped, only the
+    /// latest is returned.
+    pub fn changed(&mut self) -> impl Future<Output = Result<TopOfBook<V>, RecvError>> + '_ {
+        Next(move |cx: &mut Context<'_>| {
+            let mut channel = lock(&self.channel);
+            if channel.value.version != self.seen {
+                self.seen = channel.value.version;
+                return Poll::Ready(Ok(channel.value.top));
+            }
+            if channel.closed {
+                return Poll::Ready(Err(RecvError::Closed));
+            }
+            register(&mut channel.wakers, cx);
+            Poll::Pending
+        })
+    }
+}
+
+#[derive(Debug)]
+/// Awaits the deltas a [`WatchedOrderBook`] applies, in order
+pub struct DeltaReceiver<V: DecimalType> {
+    channel: Arc<Mutex<Channel<Queued<V>>>>,
+    next: u64,
+}
+
+impl<V: DecimalType> Clone for DeltaReceiver<V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self { channel: Arc::clone(&self.channel), next: self.next }
+    }
+}
+
+impl<V: DecimalType + Copy> DeltaReceiver<V> {
+    /// Wait for the next delta
+    ///
+    /// # Errors
+    /// [`RecvError::Lagged`] once when deltas were dropped before this receiver read them, and
+    /// [`RecvError::Closed`] once the book is dropped and every queued delta read.
+    pub fn recv(&mut self) -> impl Future<Output = Result<BookDelta<V>, RecvError>> + '_ {
+        Next(move |cx: &mut Context<'_>| {
+            let mut channel = lock(&self.channel);
+            let queued = &channel.value;
+            let oldest = queued.next - queued.deltas.len() as u64;
+            if self.next < oldest {
+                let missed = oldest - self.next;
+                self.next = oldest;
+                return Poll::Ready(Err(RecvError::Lagged(missed)));
+            }
+            if let Some(delta) = queued.deltas.get((self.next - oldest) as usize) {
+                self.next += 1;
+                return Poll::Ready(Ok(*delta));
+            }
+            if channel.closed {
+                return Poll::Ready(Err(RecvError::Closed));
+            }
+            register(&mut channel.wakers, cx);
+            Poll::Pending
+        })
+    }
+}
+
+/// A future polling its closure until it is ready
+struct Next<F>(F);
+
+impl<F: FnMut(&mut Context<'_>) -> Poll<T> + Unpin, T> Future for Next<F> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        (self.0)(cx)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixed_decimal")]
+mod tests {
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::Arc,
+        task::{Context, Poll, Wake},
+        thread::{self, Thread},
+    };
+
+    use crate::{
+        books::{array_orderbook::ArrayOrderbook, btree_orderbook::BTreeOrderBook, interface::OrderBook},
+        decimals::fixed_decimal::FixedDecimal,
+        event::Event,
+        event_kind::EventKind,
+        fixed,
+        side::Side,
+        timestamp::Timestamp,
+        watch::{RecvError, WatchedOrderBook},
+    };
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<T>(future: impl Future<Output = T>) -> T {
+        let waker = Arc::new(Unpark(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = pin!(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(value) => return value,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    fn level(side: Side, price: FixedDecimal, size: FixedDecimal, ts: i64) -> Event<FixedDecimal> {
+        Event::new(EventKind::L2, side, price, size, Timestamp::from_nanos(ts)).with_sequence_id(ts as u64)
+    }
+
+    #[test]
+    fn test_watch_top() {
+        let mut book = WatchedOrderBook::new(BTreeOrderBook::<FixedDecimal>::new());
+        let mut top = book.subscribe_top();
+        let pricer = thread::spawn(move || {
+            let mut bids = Vec::new();
+            while let Ok(top) = block_on(top.changed()) {
+                bids.push(top.bid.map(|level| level.price));
+            }
+            bids
+        });
+        book.process(level(Side::Buy, fixed!(100), fixed!(1), 1));
+        let mut late = book.subscribe_top();
+        // Behind the best bid, so the top does not move
+        book.process(level(Side::Buy, fixed!(99), fixed!(1), 2));
+        book.process(level(Side::Sell, fixed!(101), fixed!(1), 3));
+        assert_eq!(late.borrow().sequence_id, 3);
+        assert_eq!(block_on(late.changed()).map(|top| top.ask.map(|level| level.price)), Ok(Some(fixed!(101))));
+        drop(book);
+        assert_eq!(block_on(late.changed()).err(), Some(RecvError::Closed));
+        // The pricer may have skipped to the latest top
+        let bids = pricer.join().unwrap();
+        assert!(!bids.is_empty() && bids.iter().all(|&bid| bid == Some(fixed!(100))));
+    }
+
+    #[test]
+    fn test_leaves_has_moved() {
+        let mut book = WatchedOrderBook::new(ArrayOrderbook::<4, FixedDecimal>::new());
+        let top = book.subscribe_top();
+        book.process(level(Side::Buy, fixed!(100), fixed!(1), 1));
+        book.process(level(Side::Buy, fixed!(100), fixed!(0), 2));
+        assert_eq!(top.borrow().sequence_id, 2);
+        assert!(book.inner().has_moved);
+    }
+
+    #[test]
+    fn test_broadcast_deltas() {
+        let mut book = WatchedOrderBook::new(BTreeOrderBook::<FixedDecimal>::new()).broadcast_deltas(2);
+        assert!(WatchedOrderBook::new(BTreeOrderBook::<FixedDecimal>::new()).subscribe_deltas().is_none());
+        let mut risk = book.subscribe_deltas().unwrap();
+        for ts in 1..=3 {
+            book.process(level(Side::Buy, FixedDecimal::from_int(ts), fixed!(1), ts));
+        }
+        assert_eq!(block_on(risk.recv()), Err(RecvError::Lagged(1)));
+        assert_eq!(block_on(risk.recv()).map(|delta| delta.sequence_id), Ok(2));
+        let mut ui = risk.clone();
+        assert_eq!(block_on(risk.recv()).map(|delta| delta.sequence_id), Ok(3));
+        drop(book);
+        assert_eq!(block_on(risk.recv()), Err(RecvError::Closed));
+        assert_eq!(block_on(ui.recv()).map(|delta| delta.price), Ok(fixed!(3)));
+    }
+}